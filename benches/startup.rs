@@ -0,0 +1,68 @@
+//! Measures the cold-start time of representative subcommands.
+//!
+//! ```console
+//! $ cargo bench --bench startup
+//! $ cargo bench --bench startup -- --save-baseline before
+//! $ cargo bench --bench startup -- --baseline before
+//! ```
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::env;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn startup(c: &mut Criterion) {
+    let cargo_bikecase = Path::new(env!("CARGO_BIN_EXE_cargo-bikecase"));
+    let bikecase = Path::new(env!("CARGO_BIN_EXE_bikecase"));
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    let home = tempfile::Builder::new()
+        .prefix("bikecase-bench-")
+        .tempdir()
+        .unwrap();
+
+    let cases: &[(&str, &Path, &[&str])] = &[
+        ("bikecase --help", bikecase, &["--help"]),
+        ("bikecase --print-config", bikecase, &["--print-config"]),
+        (
+            "cargo bikecase --help",
+            cargo_bikecase,
+            &["bikecase", "--help"],
+        ),
+        (
+            "cargo bikecase export --help",
+            cargo_bikecase,
+            &["bikecase", "export", "--help"],
+        ),
+        (
+            "cargo bikecase export",
+            cargo_bikecase,
+            &["bikecase", "export", "--color", "never"],
+        ),
+    ];
+
+    let mut group = c.benchmark_group("startup");
+    group.sample_size(20);
+
+    for (name, program, args) in cases {
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                Command::new(program)
+                    .args(*args)
+                    .current_dir(manifest_dir)
+                    .env("HOME", home.path())
+                    .env("BIKECASE_CONFIG_DIR", home.path())
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, startup);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let git_dir = Path::new(&manifest_dir).join(".git");
+
+    // Emitting a nonexistent path would make this script rerun on every build.
+    for path in &["HEAD", "refs", "packed-refs"] {
+        let path = git_dir.join(path);
+        if path.exists() {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    if let Some(commit) = git_commit(&manifest_dir) {
+        println!("cargo:rustc-env=BIKECASE_GIT_COMMIT={}", commit);
+    }
+    println!("cargo:rustc-env=BIKECASE_BUILD_DATE={}", build_date());
+    println!(
+        "cargo:rustc-env=BIKECASE_TARGET={}",
+        env::var("TARGET").unwrap(),
+    );
+}
+
+fn git_commit(manifest_dir: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=9", "HEAD"])
+        .current_dir(manifest_dir)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let commit = String::from_utf8(output.stdout).ok()?;
+    Some(commit.trim().to_owned()).filter(|s| !s.is_empty())
+}
+
+/// `YYYY-MM-DD` of `$SOURCE_DATE_EPOCH` or now, for reproducible builds.
+fn build_date() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as _)
+                .unwrap_or(0)
+        });
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = secs.div_euclid(86400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
@@ -0,0 +1,249 @@
+use crate::cargo_args::CargoArgs;
+use crate::ui::Spinner;
+use crate::workspace;
+
+use anyhow::{bail, Context as _};
+use duct::Expression;
+use itertools::Itertools as _;
+use serde::Deserialize;
+
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Read as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::{str, thread};
+
+/// Maps `--message-format` of `bikecase` to ones for `cargo build`, so that the artifact messages
+/// are printed to stdout while the diagnostics are rendered as usual.
+///
+/// Returns the values and whether the user asked for the JSON messages.
+pub(crate) fn message_format(message_format: &[String]) -> (String, bool) {
+    let mut values = vec!["json-render-diagnostics"];
+    let mut json = false;
+    for format in message_format {
+        match &*format.to_lowercase() {
+            "short" => values.push("json-diagnostic-short"),
+            "json" => json = true,
+            _ => {}
+        }
+    }
+    if json {
+        values = vec!["json"];
+    }
+    (values.join(","), json)
+}
+
+/// Runs `cargo build` with `--message-format json..` and returns the executable built for the
+/// package at `manifest_path`.
+///
+/// The executable is the example `example` if it is set, or else the `bin` target `bin`, or the
+/// only one.
+///
+/// If `forward_messages` is `true`, the JSON messages are printed to stdout as `cargo run` does.
+///
+/// stderr of `cargo build` is forwarded while being kept, so that a failure caused by
+/// `package.rust-version` comes with a hint.
+pub(crate) fn build(
+    expr: Expression,
+    manifest_path: &Path,
+    bin: Option<&str>,
+    example: Option<&str>,
+    forward_messages: bool,
+    home_dir: Option<&Path>,
+) -> anyhow::Result<PathBuf> {
+    let (mut stderr_reader, stderr_writer) = os_pipe::pipe()?;
+    let tee = thread::spawn(move || -> io::Result<_> {
+        let mut kept = vec![];
+        let mut buf = [0; 4096];
+        loop {
+            let n = stderr_reader.read(&mut buf)?;
+            if n == 0 {
+                break Ok(kept);
+            }
+            io::stderr().write_all(&buf[..n])?;
+            kept.extend_from_slice(&buf[..n]);
+        }
+    });
+    let output = expr
+        .stderr_file(stderr_writer)
+        .stdout_capture()
+        .unchecked()
+        .run();
+    let stderr = tee.join().unwrap_or_else(|_| Ok(vec![]))?;
+    let output = output?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        match crate::msrv::hint(&stderr, manifest_path, home_dir) {
+            Some(hint) => bail!("`cargo build` failed ({})\n\n{}", output.status, hint),
+            None => bail!("`cargo build` failed ({})", output.status),
+        }
+    }
+    let stdout = str::from_utf8(&output.stdout)?;
+
+    if forward_messages {
+        let mut out = io::stdout();
+        out.write_all(stdout.as_ref())?;
+        out.flush()?;
+    }
+
+    let mut executables = vec![];
+    for line in stdout.lines() {
+        // Skip messages that we are not interested in, and lines printed by build scripts.
+        let message = match serde_json::from_str::<Message>(line) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if message.reason != "compiler-artifact"
+            || message.manifest_path.as_deref() != Some(manifest_path)
+        {
+            continue;
+        }
+        if let (Some(target), Some(executable)) = (message.target, message.executable) {
+            let selected = match example {
+                Some(example) => {
+                    target.kind.iter().any(|k| k == "example") && example == target.name
+                }
+                None => {
+                    target.kind.iter().any(|k| k == "bin") && bin.is_none_or(|b| b == target.name)
+                }
+            };
+            if selected {
+                executables.push((target.name, executable));
+            }
+        }
+    }
+
+    match &*executables {
+        [] => bail!(
+            "could not find the executable in the output of `cargo build`{}",
+            example
+                .or(bin)
+                .map(|b| format!(" (`{}`)", b))
+                .unwrap_or_default(),
+        ),
+        [(_, executable)] => Ok(executable.clone()),
+        _ => Err(anyhow::Error::msg(format!(
+            "available binaries: [{}]",
+            executables.iter().map(|(name, _)| name).format(", "),
+        )))
+        .with_context(|| "could not determine which binary to run. Use `--bin`"),
+    }
+}
+
+#[derive(Deserialize)]
+struct Message {
+    reason: String,
+    #[serde(default)]
+    manifest_path: Option<PathBuf>,
+    #[serde(default)]
+    target: Option<MessageTarget>,
+    #[serde(default)]
+    executable: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct MessageTarget {
+    name: String,
+    kind: Vec<String>,
+}
+
+/// Returns the dynamic library search path that `cargo run` sets for `executable`, i.e.
+/// `target/<profile>/deps` and `target/<profile>` prepended to the existing one.
+pub(crate) fn dylib_search_path(executable: &Path) -> anyhow::Result<(&'static str, OsString)> {
+    let name = if cfg!(windows) {
+        "PATH"
+    } else if cfg!(target_os = "macos") {
+        "DYLD_FALLBACK_LIBRARY_PATH"
+    } else {
+        "LD_LIBRARY_PATH"
+    };
+
+    let mut profile_dir = executable.parent().expect("should be a file");
+    if profile_dir.ends_with("examples") {
+        profile_dir = profile_dir
+            .parent()
+            .expect("should be in `target/<profile>`");
+    }
+    let mut paths = vec![profile_dir.join("deps"), profile_dir.to_owned()];
+    match env::var_os(name) {
+        Some(existing) => paths.extend(env::split_paths(&existing)),
+        // Same as `cargo run`, which follows the default of dyld.
+        None if cfg!(target_os = "macos") => {
+            paths.extend(dirs::home_dir().map(|h| h.join("lib")));
+            paths.extend(vec!["/usr/local/lib".into(), "/usr/lib".into()]);
+        }
+        None => {}
+    }
+    let value = env::join_paths(paths).with_context(|| {
+        format!(
+            "could not construct `${}` for {}",
+            name,
+            executable.display()
+        )
+    })?;
+    Ok((name, value))
+}
+
+/// Runs `cargo bloat --release --message-format json` on the package `package_name` in the
+/// workspace at `workspace_root`, and returns the top `n` functions, or crates if `crates`.
+pub(crate) fn bloat(
+    workspace_root: &Path,
+    package_name: &str,
+    bin: Option<&str>,
+    crates: bool,
+    n: usize,
+) -> anyhow::Result<Bloat> {
+    let args = CargoArgs::new("bloat")
+        .option("-p", Some(package_name))
+        .option("--manifest-path", Some(workspace_root.join("Cargo.toml")))
+        .flag("--release", true)
+        .option("--bin", bin)
+        .flag("--crates", crates)
+        .option("-n", Some(n.to_string()))
+        .option("--message-format", Some("json"));
+    let cmd = crate::process::cmd(workspace::cargo_exe()?, args)
+        .dir(workspace_root)
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked();
+    let output = {
+        let _spinner = Spinner::new("Running `cargo bloat`");
+        cmd.run()?
+    };
+    serde_json::from_slice(&output.stdout).with_context(|| {
+        format!(
+            "could not parse the output of `cargo bloat`. Is `cargo-bloat` installed?\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end(),
+        )
+    })
+}
+
+/// The output of `cargo bloat --message-format json`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct Bloat {
+    pub(crate) file_size: u64,
+    pub(crate) text_section_size: u64,
+    /// Empty with `--crates`.
+    #[serde(default)]
+    pub(crate) functions: Vec<BloatFunction>,
+    /// Empty without `--crates`.
+    #[serde(default)]
+    pub(crate) crates: Vec<BloatCrate>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct BloatFunction {
+    /// `None` if `cargo bloat` could not tell.
+    #[serde(rename = "crate")]
+    pub(crate) krate: Option<String>,
+    pub(crate) name: String,
+    pub(crate) size: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct BloatCrate {
+    pub(crate) name: String,
+    pub(crate) size: u64,
+}
@@ -0,0 +1,18 @@
+use bikecase::{Bikecase, Context};
+
+use structopt::StructOpt as _;
+
+use std::env;
+
+fn main() {
+    let color = bikecase::ColorChoice::Auto;
+    // `cargo eval ..` → `cargo-eval eval ..`
+    let args = env::args_os().skip(1).skip_while(|a| a == "eval");
+    let result = bikecase::translate_cargo_script_args(args).and_then(|args| {
+        let opt = Bikecase::from_iter(args);
+        Context::new().and_then(|ctx| bikecase::bikecase(opt, ctx))
+    });
+    if let Err(err) = result {
+        bikecase::exit_with_error(err, color);
+    }
+}
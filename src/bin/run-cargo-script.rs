@@ -0,0 +1,16 @@
+use bikecase::{Bikecase, Context};
+
+use structopt::StructOpt as _;
+
+use std::env;
+
+fn main() {
+    let color = bikecase::ColorChoice::Auto;
+    let result = bikecase::translate_cargo_script_args(env::args_os().skip(1)).and_then(|args| {
+        let opt = Bikecase::from_iter(args);
+        Context::new().and_then(|ctx| bikecase::bikecase(opt, ctx))
+    });
+    if let Err(err) = result {
+        bikecase::exit_with_error(err, color);
+    }
+}
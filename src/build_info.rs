@@ -0,0 +1,58 @@
+//! How the binary was built, for `cargo bikecase version`.
+
+use serde::Serialize;
+
+use std::io::{self, Write};
+
+/// The cargo features of this crate.
+static FEATURES: &[(&str, bool)] = &[("shims", cfg!(feature = "shims"))];
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct BuildInfo {
+    pub(crate) version: &'static str,
+    /// `None` if built outside of the Git repository.
+    pub(crate) commit_hash: Option<&'static str>,
+    pub(crate) build_date: &'static str,
+    pub(crate) features: Vec<&'static str>,
+    pub(crate) target: &'static str,
+    /// `cargo --version` at runtime.
+    pub(crate) cargo: Option<String>,
+    /// `rustc --version` at runtime.
+    pub(crate) rustc: Option<String>,
+}
+
+impl BuildInfo {
+    pub(crate) fn detect() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            commit_hash: option_env!("BIKECASE_GIT_COMMIT"),
+            build_date: env!("BIKECASE_BUILD_DATE"),
+            features: FEATURES
+                .iter()
+                .filter(|&&(_, enabled)| enabled)
+                .map(|&(name, _)| name)
+                .collect(),
+            target: env!("BIKECASE_TARGET"),
+            cargo: crate::workspace::cargo_version(),
+            rustc: crate::workspace::rustc_version(None),
+        }
+    }
+
+    /// Writes in the style of `rustc -vV`.
+    pub(crate) fn write_text(&self, mut wtr: impl Write) -> io::Result<()> {
+        let or_unknown = |s: Option<&str>| s.unwrap_or("unknown").to_owned();
+        writeln!(wtr, "bikecase {}", self.version)?;
+        writeln!(wtr, "commit-hash: {}", or_unknown(self.commit_hash))?;
+        writeln!(wtr, "build-date: {}", self.build_date)?;
+        if self.features.is_empty() {
+            writeln!(wtr, "features: none")?;
+        } else {
+            writeln!(wtr, "features: {}", self.features.join(", "))?;
+        }
+        writeln!(wtr, "target: {}", self.target)?;
+        writeln!(wtr, "cargo: {}", or_unknown(self.cargo.as_deref()))?;
+        writeln!(wtr, "rustc: {}", or_unknown(self.rustc.as_deref()))?;
+        wtr.flush()
+    }
+}
@@ -0,0 +1,218 @@
+use itertools::Itertools as _;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub(crate) fn run_state_key(
+    script: &str,
+    script_path: Option<&Path>,
+    manifest_path: &Path,
+    bin: Option<&str>,
+    package_name: Option<&str>,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(script);
+    for param in &[
+        script_path.map(Path::to_string_lossy).unwrap_or_default(),
+        manifest_path.to_string_lossy(),
+        bin.unwrap_or_default().into(),
+        package_name.unwrap_or_default().into(),
+    ] {
+        hasher.update([0]);
+        hasher.update(param.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Identifies the build configurations that produce different executables.
+///
+/// `toolchain` is the output of `rustc --version`. The environment variables that affect builds
+/// are included as well.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn build_key(
+    profile: &str,
+    target: Option<&Path>,
+    features: &[String],
+    all_features: bool,
+    no_default_features: bool,
+    example: Option<&str>,
+    rustflags: Option<&str>,
+    toolchain: Option<&str>,
+) -> String {
+    let mut features = features
+        .iter()
+        .flat_map(|f| f.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|f| !f.is_empty())
+        .collect::<Vec<_>>();
+    features.sort_unstable();
+    features.dedup();
+
+    let mut key = format!("profile={}", profile);
+    if let Some(target) = target {
+        key += &format!(" target={}", target.display());
+    }
+    if !features.is_empty() {
+        key += &format!(" features={}", features.join(","));
+    }
+    if all_features {
+        key += " all-features";
+    }
+    if no_default_features {
+        key += " no-default-features";
+    }
+    if let Some(example) = example {
+        key += &format!(" example={}", example);
+    }
+    if let Some(rustflags) = rustflags {
+        key += &format!(" rustflags={}", rustflags);
+    }
+    if let Some(toolchain) = toolchain {
+        key += &format!(" toolchain={}", toolchain);
+    }
+    let env = build_env();
+    if !env.is_empty() {
+        let env = env.iter().map(|(k, v)| format!("{}={}\0", k, v)).join("");
+        key += &format!(" env={}", &sha256(&env)[..16]);
+    }
+    return key;
+
+    /// Environment variables other than `RUSTFLAGS` that change what `cargo build` produces.
+    fn build_env() -> Vec<(String, String)> {
+        static NAMES: &[&str] = &[
+            "CARGO_ENCODED_RUSTFLAGS",
+            "RUSTC",
+            "RUSTC_WRAPPER",
+            "RUSTC_WORKSPACE_WRAPPER",
+            "RUSTUP_TOOLCHAIN",
+        ];
+        static PREFIXES: &[&str] = &["CARGO_BUILD_", "CARGO_PROFILE_", "CARGO_TARGET_"];
+
+        env::vars_os()
+            .flat_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)))
+            .filter(|(k, _)| NAMES.contains(&&**k) || PREFIXES.iter().any(|p| k.starts_with(p)))
+            .sorted()
+            .collect()
+    }
+}
+
+/// Whether `executable` is newer than every file in `inputs`, which may be directories.
+/// Nonexistent inputs are ignored.
+pub(crate) fn is_fresh(executable: &Path, inputs: &[PathBuf]) -> bool {
+    let built_at = match executable.metadata().and_then(|m| m.modified()) {
+        Ok(built_at) => built_at,
+        Err(_) => return false,
+    };
+    return inputs
+        .iter()
+        .all(|input| !is_modified_since(input, built_at));
+
+    fn is_modified_since(path: &Path, time: SystemTime) -> bool {
+        let metadata = match path.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => return false,
+        };
+        if metadata.is_dir() {
+            match std::fs::read_dir(path) {
+                Ok(entries) => entries
+                    .flatten()
+                    .any(|entry| is_modified_since(&entry.path(), time)),
+                Err(_) => true,
+            }
+        } else {
+            metadata.modified().map_or(true, |modified| modified > time)
+        }
+    }
+}
+
+/// Records that the program of the member at `manifest_path` is run now, for `ls --sort last-run`.
+///
+/// This is best-effort, and not reported as a file write.
+pub(crate) fn record_run(cache_dir: &Path, manifest_path: &Path) {
+    let dir = cache_dir.join("last-run");
+    let _ = std::fs::create_dir_all(&dir)
+        .and_then(|()| std::fs::write(dir.join(sha256(&manifest_path.to_string_lossy())), ""));
+}
+
+/// The last time [`record_run`] was called for the member at `manifest_path`.
+pub(crate) fn last_run(cache_dir: &Path, manifest_path: &Path) -> Option<SystemTime> {
+    let path = cache_dir
+        .join("last-run")
+        .join(sha256(&manifest_path.to_string_lossy()));
+    path.metadata().ok()?.modified().ok()
+}
+
+pub(crate) fn sha256(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Synchronization state of a script that has been run.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct RunState {
+    pub(crate) package: String,
+    pub(crate) manifest_path: PathBuf,
+    pub(crate) bin_path: PathBuf,
+    /// The `bin` target to pass to `cargo build --bin`.
+    #[serde(default)]
+    pub(crate) bin_target: Option<String>,
+    pub(crate) cargo_toml_sha256: String,
+    /// Executables reported by `cargo build`, keyed by [`build_key`].
+    #[serde(default)]
+    pub(crate) executables: BTreeMap<String, PathBuf>,
+}
+
+impl RunState {
+    /// Returns the state only if the member still holds exactly what `script` would be synchronized to.
+    pub(crate) fn load(cache_dir: &Path, key: &str, script: &str) -> Option<Self> {
+        let path = cache_dir.join("run-state").join(key).with_extension("json");
+        let this = serde_json::from_str::<Self>(&std::fs::read_to_string(&path).ok()?).ok()?;
+
+        let up_to_date = std::fs::read_to_string(&this.bin_path).ok()? == script
+            && sha256(&std::fs::read_to_string(&this.manifest_path).ok()?)
+                == this.cargo_toml_sha256;
+
+        if up_to_date {
+            info!("`{}` is up to date (cached)", this.package);
+            Some(this)
+        } else {
+            debug!("Outdated: {}", path.display());
+            None
+        }
+    }
+
+    /// Finds the most recently built executable of the `bin` target `bin_target` of the member at
+    /// `manifest_path` for `build_key`, among the states of all the scripts run so far.
+    pub(crate) fn find_latest_executable(
+        cache_dir: &Path,
+        manifest_path: &Path,
+        bin_target: Option<&str>,
+        build_key: &str,
+    ) -> Option<PathBuf> {
+        std::fs::read_dir(cache_dir.join("run-state"))
+            .ok()?
+            .flatten()
+            .flat_map(|entry| {
+                let json = std::fs::read_to_string(entry.path()).ok()?;
+                serde_json::from_str::<Self>(&json).ok()
+            })
+            .filter(|this| {
+                this.manifest_path == manifest_path && this.bin_target.as_deref() == bin_target
+            })
+            .flat_map(|mut this| this.executables.remove(build_key))
+            .flat_map(|path| Some((path.metadata().ok()?.modified().ok()?, path)))
+            .max()
+            .map(|(_, path)| path)
+    }
+
+    pub(crate) fn save(&self, cache_dir: &Path, key: &str) -> anyhow::Result<()> {
+        let dir = cache_dir.join("run-state");
+        crate::fs::create_dir_all(&dir, false)?;
+        let json = serde_json::to_string(self).expect("should not fail");
+        crate::fs::write(dir.join(key).with_extension("json"), json, false)
+    }
+}
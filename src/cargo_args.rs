@@ -0,0 +1,221 @@
+use std::ffi::OsString;
+use std::iter;
+use std::path::Path;
+
+/// Command line arguments for a `cargo` subcommand.
+///
+/// The arguments are kept in the order they are added, except the ones added with
+/// [`passthrough`], which always come last after `--`.
+///
+/// [`passthrough`]: Self::passthrough
+#[derive(Debug)]
+pub(crate) struct CargoArgs {
+    args: Vec<OsString>,
+    passthrough: Option<Vec<OsString>>,
+}
+
+impl CargoArgs {
+    /// Starts with `subcommand` (e.g. `build`).
+    pub(crate) fn new(subcommand: &str) -> Self {
+        Self {
+            args: vec![subcommand.into()],
+            passthrough: None,
+        }
+    }
+
+    /// Starts with `+<toolchain>` and `subcommand` (e.g. `+nightly miri run`), for the `cargo`
+    /// proxy of rustup.
+    pub(crate) fn with_toolchain(toolchain: &str, subcommand: &[&str]) -> Self {
+        Self {
+            args: iter::once(format!("+{}", toolchain).into())
+                .chain(subcommand.iter().map(Into::into))
+                .collect(),
+            passthrough: None,
+        }
+    }
+
+    /// Adds `-p <spec> --manifest-path <manifest_path> --color <color>`, which every spawn site
+    /// passes.
+    pub(crate) fn package(
+        self,
+        spec: impl Into<OsString>,
+        manifest_path: &Path,
+        color: crate::ColorChoice,
+    ) -> Self {
+        self.option("-p", Some(spec))
+            .option("--manifest-path", Some(manifest_path))
+            .option("--color", Some(<&str>::from(color)))
+    }
+
+    /// Adds `name` if `enabled`.
+    pub(crate) fn flag(mut self, name: &str, enabled: bool) -> Self {
+        if enabled {
+            self.args.push(name.into());
+        }
+        self
+    }
+
+    /// Adds `name value` if `value` is `Some`.
+    pub(crate) fn option(mut self, name: &str, value: Option<impl Into<OsString>>) -> Self {
+        if let Some(value) = value {
+            self.args.push(name.into());
+            self.args.push(value.into());
+        }
+        self
+    }
+
+    /// Adds `name value` for each of `values`.
+    pub(crate) fn options<T: Into<OsString>>(
+        mut self,
+        name: &str,
+        values: impl IntoIterator<Item = T>,
+    ) -> Self {
+        for value in values {
+            self.args.push(name.into());
+            self.args.push(value.into());
+        }
+        self
+    }
+
+    /// Adds `-` followed by `short` repeated `n` times (e.g. `-vv`), if `n > 0`.
+    pub(crate) fn occurrences(mut self, short: char, n: u32) -> Self {
+        if n > 0 {
+            let mut arg = "-".to_owned();
+            arg.extend((0..n).map(|_| short));
+            self.args.push(arg.into());
+        }
+        self
+    }
+
+    /// Adds arguments for the program that `cargo` runs. `--` is put before them even if `args`
+    /// is empty.
+    pub(crate) fn passthrough<T: Into<OsString>>(
+        mut self,
+        args: impl IntoIterator<Item = T>,
+    ) -> Self {
+        self.passthrough
+            .get_or_insert_with(Vec::new)
+            .extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<OsString> {
+        let Self {
+            mut args,
+            passthrough,
+        } = self;
+        if let Some(passthrough) = passthrough {
+            args.push("--".into());
+            args.extend(passthrough);
+        }
+        args
+    }
+}
+
+impl IntoIterator for CargoArgs {
+    type Item = OsString;
+    type IntoIter = std::vec::IntoIter<OsString>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CargoArgs;
+
+    use std::ffi::OsString;
+    use std::path::Path;
+
+    fn strs(args: CargoArgs) -> Vec<String> {
+        args.into_iter().map(|a| a.into_string().unwrap()).collect()
+    }
+
+    #[test]
+    fn flags() {
+        let args = CargoArgs::new("build")
+            .flag("--release", true)
+            .flag("--locked", false)
+            .flag("--offline", true);
+        assert_eq!(strs(args), ["build", "--release", "--offline"]);
+    }
+
+    #[test]
+    fn occurrences() {
+        assert_eq!(strs(CargoArgs::new("run").occurrences('v', 0)), ["run"]);
+        assert_eq!(
+            strs(CargoArgs::new("run").occurrences('v', 1)),
+            ["run", "-v"]
+        );
+        assert_eq!(
+            strs(CargoArgs::new("run").occurrences('v', 3)),
+            ["run", "-vvv"],
+        );
+    }
+
+    #[test]
+    fn options() {
+        let args = CargoArgs::new("build")
+            .option("--bin", Some("hello"))
+            .option("--example", None::<&str>)
+            .options("--features", vec!["a", "b c"])
+            .options("--config", Vec::<String>::new())
+            .package(
+                "hello@0.1.0",
+                Path::new("ws/Cargo.toml"),
+                crate::ColorChoice::Never,
+            );
+        assert_eq!(
+            strs(args),
+            [
+                "build",
+                "--bin",
+                "hello",
+                "--features",
+                "a",
+                "--features",
+                "b c",
+                "-p",
+                "hello@0.1.0",
+                "--manifest-path",
+                "ws/Cargo.toml",
+                "--color",
+                "never",
+            ],
+        );
+    }
+
+    #[test]
+    fn with_toolchain() {
+        let args = CargoArgs::with_toolchain("nightly", &["miri", "run"]).flag("-q", true);
+        assert_eq!(strs(args), ["+nightly", "miri", "run", "-q"]);
+    }
+
+    #[test]
+    fn passthrough_comes_last() {
+        let args = CargoArgs::new("run")
+            .passthrough(vec!["--flag", "x"])
+            .flag("--release", true)
+            .passthrough(vec![OsString::from("-v")])
+            .option("--bin", Some("hello"));
+        assert_eq!(
+            strs(args),
+            [
+                "run",
+                "--release",
+                "--bin",
+                "hello",
+                "--",
+                "--flag",
+                "x",
+                "-v"
+            ],
+        );
+
+        let args = CargoArgs::new("test").passthrough(Vec::<String>::new());
+        assert_eq!(strs(args), ["test", "--"]);
+
+        assert_eq!(strs(CargoArgs::new("check")), ["check"]);
+    }
+}
@@ -1,8 +1,8 @@
 use crate::workspace;
 
-use anyhow::{anyhow, Context as _};
+use anyhow::{anyhow, bail, Context as _};
 use indexmap::{indexmap, IndexMap};
-use log::info;
+use log::{info, warn};
 use maplit::btreemap;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
@@ -10,32 +10,170 @@ use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::io;
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
+use std::{env, fmt};
 
+/// The default path of the config file. The first existing one of:
+///
+/// 1. `$BIKECASE_CONFIG_DIR/bikecase.toml`
+/// 2. `$XDG_CONFIG_HOME/bikecase/bikecase.toml` (`~/.config/bikecase/bikecase.toml` if
+///    `$XDG_CONFIG_HOME` is not set)
+/// 3. `<platform config dir>/bikecase/bikecase.toml`
+///
+/// If none exists, 1 is used if `$BIKECASE_CONFIG_DIR` is set, 2 if `$XDG_CONFIG_HOME` is set,
+/// and 3 otherwise. [`LEGACY_PATH`] is moved there on the first load.
 pub(crate) static PATH: Lazy<String> = Lazy::new(|| {
-    dirs::config_dir()
-        .and_then(|d| d.join("bikecase.toml").into_os_string().into_string().ok())
+    discover()
+        .and_then(|p| p.into_os_string().into_string().ok())
         .unwrap_or_else(|| "bikecase.toml".to_owned())
 });
 
+/// `<platform config dir>/bikecase.toml`, where the config was before the discovery order.
+static LEGACY_PATH: Lazy<Option<PathBuf>> =
+    Lazy::new(|| Some(dirs::config_dir()?.join("bikecase.toml")));
+
+fn discover() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("BIKECASE_CONFIG_DIR").filter(|d| !d.is_empty()) {
+        return Some(Path::new(&dir).join("bikecase.toml"));
+    }
+
+    // Relative paths are invalid in `$XDG_CONFIG_HOME`.
+    let xdg_config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|d| d.is_absolute());
+    let xdg = xdg_config_home
+        .clone()
+        .or_else(|| Some(dirs::home_dir()?.join(".config")))
+        .map(|d| d.join("bikecase").join("bikecase.toml"));
+    let platform = dirs::config_dir().map(|d| d.join("bikecase").join("bikecase.toml"));
+
+    if let Some(found) = xdg.iter().chain(&platform).find(|p| p.exists()) {
+        return Some(found.clone());
+    }
+    if xdg_config_home.is_some() {
+        xdg
+    } else {
+        platform
+    }
+}
+
+/// Moves [`LEGACY_PATH`] to `path` if `path` is the default and does not exist yet.
+///
+/// Returns the path to read the config from, which is the legacy one if `dry_run`.
+fn migrate_legacy(path: &Path, dry_run: bool) -> anyhow::Result<Option<PathBuf>> {
+    let legacy = match &*LEGACY_PATH {
+        Some(legacy) if path == Path::new(&*PATH) && legacy != path && legacy.exists() => legacy,
+        _ => return Ok(None),
+    };
+    info!(
+        "Moving the config from {} to {}",
+        legacy.display(),
+        path.display(),
+    );
+    if let Some(parent) = path.parent() {
+        crate::fs::create_dir_all(parent, dry_run)?;
+    }
+    crate::fs::copy(legacy, path, dry_run)?;
+    crate::fs::remove_file(legacy, dry_run)?;
+    Ok(Some(if dry_run {
+        legacy.clone()
+    } else {
+        path.to_owned()
+    }))
+}
+
+/// The config loaded once per invocation, shared by the code paths of a command through
+/// [`Context`].
+///
+/// [`Context`]: crate::Context
+#[derive(Default, Debug)]
+pub struct ConfigCache(Option<BikecaseConfig>);
+
+impl ConfigCache {
+    pub(crate) fn load_or_create(
+        &mut self,
+        path: &Path,
+        home_dir: Option<&Path>,
+        data_local_dir: Option<&Path>,
+        dry_run: bool,
+    ) -> anyhow::Result<&mut BikecaseConfig> {
+        self.load_or_create_with(path, home_dir, data_local_dir, dry_run, false)
+    }
+
+    /// Same as [`load_or_create`], but fails instead of creating the config, the default
+    /// workspace, and the template package if `no_auto_create`.
+    ///
+    /// Only the first call reads the file or creates the defaults. The later calls for the same
+    /// `path` return the loaded config, including the changes made to it.
+    ///
+    /// [`load_or_create`]: Self::load_or_create
+    pub(crate) fn load_or_create_with(
+        &mut self,
+        path: &Path,
+        home_dir: Option<&Path>,
+        data_local_dir: Option<&Path>,
+        dry_run: bool,
+        no_auto_create: bool,
+    ) -> anyhow::Result<&mut BikecaseConfig> {
+        match &mut self.0 {
+            Some(config) if config.path == path => {
+                config.no_auto_create |= no_auto_create;
+            }
+            cache => {
+                let config = BikecaseConfig::load_or_create(
+                    path,
+                    home_dir,
+                    data_local_dir,
+                    dry_run,
+                    no_auto_create,
+                )?;
+                crate::http::set_user_agent_suffix(config.content.user_agent_suffix.as_deref());
+                crate::ui::configure(&config.content.ui)
+                    .with_context(|| format!("invalid config: {}", config.path.display()))?;
+                *cache = Some(config);
+            }
+        }
+        Ok(self.0.as_mut().expect("should be loaded"))
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct BikecaseConfig {
     content: BikecaseConfigContent,
     path: PathBuf,
+    no_auto_create: bool,
 }
 
 impl BikecaseConfig {
-    pub(crate) fn load_or_create(
+    fn load_or_create(
         path: &Path,
         home_dir: Option<&Path>,
         data_local_dir: Option<&Path>,
         dry_run: bool,
+        no_auto_create: bool,
     ) -> anyhow::Result<Self> {
         let path = path.to_owned();
-        if path.exists() {
-            let content = toml::from_str(&crate::fs::read(&path)?)
-                .with_context(|| format!("failed to parse the TOML file at {}", path.display()))?;
-            Ok(Self { content, path })
+        let existing = if path.exists() {
+            Some(path.clone())
+        } else {
+            migrate_legacy(&path, dry_run)?
+        };
+        if let Some(existing) = existing {
+            let content = toml::from_str(&crate::fs::read(&existing)?).with_context(|| {
+                format!("failed to parse the TOML file at {}", existing.display())
+            })?;
+            Ok(Self {
+                content,
+                path,
+                no_auto_create,
+            })
+        } else if no_auto_create {
+            bail!(
+                "{} does not exist. Write one by hand, or run once without `--no-auto-create` to \
+                 create the default config, workspace, and template package",
+                path.display(),
+            );
         } else {
             let data_local_dir =
                 data_local_dir.with_context(|| "could not find the local data directory")?;
@@ -67,15 +205,38 @@ impl BikecaseConfig {
                     }),
                     default_workspace: Some(default_workspace.clone()),
                     template_package: Some(template_package),
+                    derive_package_names: false,
+                    clone_path_template: None,
+                    check_for_updates: false,
+                    disable_self_update: false,
+                    pull_policy: None,
+                    auto_create: true,
+                    mode: None,
+                    install_dir: None,
+                    profile_preset: None,
+                    user_agent_suffix: None,
+                    editor: None,
+                    ui: Default::default(),
+                    template_vars: btreemap!(),
+                    path_dependencies: btreemap!(),
                     workspaces: indexmap!(default_workspace => BikecaseConfigWorkspace {
                         gist_ids: btreemap!(),
+                        ..Default::default()
                     }),
                 },
                 path,
+                no_auto_create,
             };
             this.save(dry_run)?;
             if !Path::new(&default_workspace_expanded).exists() {
-                workspace::create_workspace(default_workspace_expanded, dry_run)?;
+                workspace::create_workspace(
+                    default_workspace_expanded,
+                    None,
+                    None,
+                    None,
+                    None,
+                    dry_run,
+                )?;
             }
             if !Path::new(&template_package_expanded).exists() {
                 crate::process::run(
@@ -138,6 +299,157 @@ fn main() {
     pub(crate) fn path(&self) -> &Path {
         &self.path
     }
+
+    /// Renders the config as it takes effect, for `--print-config`.
+    ///
+    /// `~` and environment variables in the paths are expanded, and `auto-create` reflects
+    /// `--no-auto-create`. The GitHub token is shown only by where it is read from, and the values
+    /// of `env` are redacted as in `--record` bundles.
+    pub(crate) fn effective(&self, home_dir: Option<&Path>) -> anyhow::Result<String> {
+        let expand = |path: &TildePath| path.expand(home_dir).into_owned();
+
+        let mut content = toml::Value::try_from(&self.content)?;
+        let table = content.as_table_mut().expect("should be a table");
+        table.insert(
+            "auto-create".to_owned(),
+            (self.content.auto_create && !self.no_auto_create).into(),
+        );
+        for (key, path) in &[
+            ("default-workspace", &self.content.default_workspace),
+            ("template-package", &self.content.template_package),
+            ("install-dir", &self.content.install_dir),
+        ] {
+            if let Some(path) = path {
+                table.insert((*key).to_owned(), expand(path).into());
+            }
+        }
+        if let Some(BikecaseConfigGithubToken::File { path }) = &self.content.github_token {
+            let github_token = table
+                .get_mut("github-token")
+                .and_then(toml::Value::as_table_mut)
+                .expect("should be a table");
+            github_token.insert("path".to_owned(), expand(path).into());
+        }
+        if let Some(workspaces) = table
+            .get_mut("workspaces")
+            .and_then(toml::Value::as_table_mut)
+        {
+            *workspaces = std::mem::take(workspaces)
+                .into_iter()
+                .map(|(path, workspace)| (expand(&TildePath(path)), workspace))
+                .collect();
+        }
+        crate::record::redact_env(&mut content);
+
+        Ok(format!(
+            "# {}\n{}",
+            self.path.display(),
+            toml::to_string_pretty(&content)?,
+        ))
+    }
+
+    /// Returns the gist IDs for `workspace_root`, from [`WorkspaceFile`] if it exists.
+    pub(crate) fn gist_ids(
+        &self,
+        workspace_root: &Path,
+        home_dir: Option<&Path>,
+    ) -> anyhow::Result<Cow<'_, BTreeMap<String, GistIdEntry>>> {
+        if let Some((_, WorkspaceFile { gist_ids, .. })) = WorkspaceFile::load(workspace_root)? {
+            return Ok(Cow::Owned(gist_ids));
+        }
+        Ok(self
+            .content
+            .workspace(workspace_root, home_dir)
+            .map(|w| Cow::Borrowed(&w.gist_ids))
+            .unwrap_or_default())
+    }
+
+    /// Same as [`gist_ids`], but for modifying them. Call [`GistIdsMut::save`] and then
+    /// [`save`] to persist the changes.
+    ///
+    /// [`gist_ids`]: Self::gist_ids
+    /// [`save`]: Self::save
+    pub(crate) fn gist_ids_mut(
+        &mut self,
+        workspace_root: &Path,
+        home_dir: Option<&Path>,
+    ) -> anyhow::Result<GistIdsMut<'_>> {
+        if let Some((path, file)) = WorkspaceFile::load(workspace_root)? {
+            return Ok(GistIdsMut::WorkspaceFile(path, file));
+        }
+        let workspace = self
+            .content
+            .workspace_or_default(workspace_root, home_dir)?;
+        Ok(GistIdsMut::Config(workspace))
+    }
+
+    /// Resolves `gist_id` if it is an `alias` in `gist-ids` of any workspace. Otherwise it is
+    /// returned as it is.
+    pub(crate) fn resolve_gist_id(
+        &self,
+        gist_id: &str,
+        home_dir: Option<&Path>,
+    ) -> anyhow::Result<String> {
+        let mut workspace_roots = self
+            .content
+            .workspaces
+            .keys()
+            .chain(&self.content.default_workspace)
+            .map(|p| PathBuf::from(&*p.expand(home_dir)))
+            .collect::<Vec<_>>();
+        workspace_roots.dedup();
+
+        let mut found = None::<(String, String)>;
+        for workspace_root in workspace_roots {
+            for (package, entry) in &*self.gist_ids(&workspace_root, home_dir)? {
+                if entry.alias.as_deref() != Some(gist_id) {
+                    continue;
+                }
+                match &found {
+                    Some((_, id)) if *id != entry.id => bail!(
+                        "ambiguous gist alias {:?}: `{}` and `{}`",
+                        gist_id,
+                        id,
+                        entry.id,
+                    ),
+                    _ => found = Some((package.clone(), entry.id.clone())),
+                }
+            }
+        }
+        Ok(match found {
+            Some((package, id)) => {
+                info!(
+                    "Resolved the alias {:?} to `{}` (`{}`)",
+                    gist_id, id, package
+                );
+                id
+            }
+            None => gist_id.to_owned(),
+        })
+    }
+
+    /// Fails with instructions if `workspace_root` does not exist and may not be created.
+    ///
+    /// Returns whether the workspace should be created.
+    pub(crate) fn should_create_workspace(&self, workspace_root: &Path) -> anyhow::Result<bool> {
+        if workspace_root.exists() {
+            return Ok(false);
+        }
+        if self.no_auto_create || !self.content.auto_create {
+            bail!(
+                "{} does not exist and auto-creation is disabled{}. Create it with `cargo \
+                 bikecase init-workspace {}`",
+                workspace_root.display(),
+                if self.no_auto_create {
+                    " by `--no-auto-create`"
+                } else {
+                    " by `auto-create = false`"
+                },
+                workspace_root.display(),
+            );
+        }
+        Ok(true)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -147,6 +459,53 @@ pub(crate) struct BikecaseConfigContent {
     pub(crate) default_workspace: Option<TildePath>,
     #[serde(default)]
     pub(crate) template_package: Option<TildePath>,
+    /// Suffix the package names of scripts run by `bikecase` with the hashes of their paths, so
+    /// that scripts sharing an embedded `package.name` do not overwrite each other.
+    #[serde(default)]
+    pub(crate) derive_package_names: bool,
+    /// Where `gist clone` creates packages, relative to the workspace root. See
+    /// `RetrievedGist::expand_path_template` for the placeholders.
+    #[serde(default)]
+    pub(crate) clone_path_template: Option<String>,
+    /// Check for a new release at most once a day when running scripts.
+    #[serde(default)]
+    pub(crate) check_for_updates: bool,
+    /// Refuse `self update`, e.g. for installations managed by package managers.
+    #[serde(default)]
+    pub(crate) disable_self_update: bool,
+    /// The default of `--theirs`/`--ours`/`--backup-local` for `gist pull`.
+    #[serde(default)]
+    pub(crate) pull_policy: Option<PullPolicy>,
+    /// Create missing workspaces when running scripts and cloning gists. `--no-auto-create`
+    /// disables this per command.
+    #[serde(default = "default_auto_create")]
+    pub(crate) auto_create: bool,
+    /// The default of `--mode` for `bikecase`.
+    #[serde(default)]
+    pub(crate) mode: Option<crate::Mode>,
+    /// Where `bikecase --install` copies executables. Defaults to `$CARGO_HOME/bin`.
+    #[serde(default)]
+    pub(crate) install_dir: Option<TildePath>,
+    /// The preset of `[profile.dev]` written into the workspaces created automatically.
+    #[serde(default)]
+    pub(crate) profile_preset: Option<crate::ProfilePreset>,
+    /// Appended to `User-Agent` of the requests to GitHub and crates.io, e.g. for proxies that
+    /// require one.
+    #[serde(default)]
+    pub(crate) user_agent_suffix: Option<String>,
+    /// The command `open` launches instead of `$VISUAL`/`$EDITOR`. See
+    /// `process::open_in_editor` for the placeholders.
+    #[serde(default)]
+    pub(crate) editor: Option<String>,
+    /// Values for the `.hbs` files in the template package. `new --var` overrides them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) template_vars: BTreeMap<String, String>,
+    /// Colors and glyphs for the terminal.
+    #[serde(default, skip_serializing_if = "crate::ui::UiConfig::is_default")]
+    pub(crate) ui: crate::ui::UiConfig,
+    /// What `export` and `gist push` replace `path` dependencies with, by the dependency names.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) path_dependencies: BTreeMap<String, BikecaseConfigPathDependency>,
     #[serde(default)]
     pub(crate) github_token: Option<BikecaseConfigGithubToken>,
     #[serde(default)]
@@ -165,6 +524,174 @@ impl BikecaseConfigContent {
             .map(|(_, w)| w)
     }
 
+    pub(crate) fn export_profile(
+        &self,
+        home_dir: Option<&Path>,
+    ) -> anyhow::Result<BikecaseConfigProfile> {
+        let mut workspaces = BTreeMap::new();
+        for (path, workspace) in &self.workspaces {
+            let alias = self.alias(path, workspace, home_dir)?;
+            let profile_workspace = BikecaseConfigProfileWorkspace {
+                path: path.clone(),
+                gist_ids: workspace.gist_ids.clone(),
+            };
+            if workspaces
+                .insert(alias.clone(), profile_workspace)
+                .is_some()
+            {
+                bail!(
+                    "duplicate workspace alias {:?}. Set `alias` to distinguish them",
+                    alias,
+                );
+            }
+        }
+
+        Ok(BikecaseConfigProfile {
+            default_workspace: self.default_workspace.clone(),
+            template_package: self.template_package.clone(),
+            derive_package_names: self.derive_package_names,
+            clone_path_template: self.clone_path_template.clone(),
+            github_token: self.github_token.clone(),
+            workspaces,
+        })
+    }
+
+    /// Merges `profile` into `self`. On conflicts, the local values are kept unless `overwrite`.
+    ///
+    /// Returns the keys of the local workspaces that the workspaces in `profile` were merged into.
+    pub(crate) fn import_profile(
+        &mut self,
+        profile: BikecaseConfigProfile,
+        overwrite: bool,
+        home_dir: Option<&Path>,
+    ) -> anyhow::Result<Vec<TildePath>> {
+        let BikecaseConfigProfile {
+            default_workspace,
+            template_package,
+            derive_package_names,
+            clone_path_template,
+            github_token,
+            workspaces,
+        } = profile;
+
+        merge(
+            "default-workspace",
+            &mut self.default_workspace,
+            default_workspace,
+            overwrite,
+        );
+        merge(
+            "template-package",
+            &mut self.template_package,
+            template_package,
+            overwrite,
+        );
+        merge(
+            "clone-path-template",
+            &mut self.clone_path_template,
+            clone_path_template,
+            overwrite,
+        );
+        merge(
+            "github-token",
+            &mut self.github_token,
+            github_token,
+            overwrite,
+        );
+        let mut local_derive_package_names = Some(self.derive_package_names);
+        merge(
+            "derive-package-names",
+            &mut local_derive_package_names,
+            Some(derive_package_names),
+            overwrite,
+        );
+        self.derive_package_names = local_derive_package_names.unwrap_or_default();
+
+        let mut keys = vec![];
+        for (alias, profile_workspace) in workspaces {
+            let mut key = None;
+            for (path, workspace) in &self.workspaces {
+                if self.alias(path, workspace, home_dir)? == alias {
+                    key = Some(path.clone());
+                    break;
+                }
+            }
+
+            let key = key.unwrap_or_else(|| {
+                info!(
+                    "`workspaces.{:?}`: added as {:?}",
+                    profile_workspace.path.to_string(),
+                    alias,
+                );
+                profile_workspace.path.clone()
+            });
+            let is_default = self.default_workspace.as_ref() == Some(&key);
+            let derived_alias = derive_alias(&key, is_default, home_dir)?;
+
+            let workspace = self.workspaces.entry(key.clone()).or_default();
+            if workspace.alias.is_none() && derived_alias != alias {
+                workspace.alias = Some(alias.clone());
+            }
+
+            for (package, gist_id) in profile_workspace.gist_ids {
+                let name = format!("workspaces.{:?}.gist-ids.{:?}", key.to_string(), package);
+                let mut local = workspace.gist_ids.get(&package).cloned();
+                merge(&name, &mut local, Some(gist_id), overwrite);
+                if let Some(local) = local {
+                    workspace.gist_ids.insert(package, local);
+                }
+            }
+            keys.push(key);
+        }
+        return Ok(keys);
+
+        fn merge<T: PartialEq + Serialize>(
+            name: &str,
+            local: &mut Option<T>,
+            imported: Option<T>,
+            overwrite: bool,
+        ) {
+            let show = |v: &T| serde_json::to_string(v).expect("should not fail");
+            match (&*local, imported) {
+                (_, None) => {}
+                (None, Some(imported)) => {
+                    info!("`{}`: None → {}", name, show(&imported));
+                    *local = Some(imported);
+                }
+                (Some(l), Some(imported)) if *l == imported => {}
+                (Some(l), Some(imported)) => {
+                    if overwrite {
+                        info!("`{}`: {} → {}", name, show(l), show(&imported));
+                        *local = Some(imported);
+                    } else {
+                        warn!(
+                            "`{}`: keeping {} instead of {}. Enable `--overwrite` to replace it",
+                            name,
+                            show(l),
+                            show(&imported),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn alias(
+        &self,
+        path: &TildePath,
+        workspace: &BikecaseConfigWorkspace,
+        home_dir: Option<&Path>,
+    ) -> anyhow::Result<String> {
+        match &workspace.alias {
+            Some(alias) => Ok(alias.clone()),
+            None => derive_alias(
+                path,
+                self.default_workspace.as_ref() == Some(path),
+                home_dir,
+            ),
+        }
+    }
+
     pub(crate) fn workspace_or_default(
         &mut self,
         workspace_root: &Path,
@@ -186,7 +713,27 @@ impl BikecaseConfigContent {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+fn derive_alias(
+    path: &TildePath,
+    is_default: bool,
+    home_dir: Option<&Path>,
+) -> anyhow::Result<String> {
+    if is_default {
+        return Ok("default".to_owned());
+    }
+    let expanded = path.expand(home_dir);
+    Path::new(&*expanded)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .map(ToOwned::to_owned)
+        .with_context(|| format!("could not derive an alias from {:?}", expanded))
+}
+
+fn default_auto_create() -> bool {
+    true
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
 #[serde(tag = "kind")]
 pub(crate) enum BikecaseConfigGithubToken {
     File { path: TildePath },
@@ -214,11 +761,361 @@ impl BikecaseConfigGithubToken {
     }
 }
 
+/// `path-dependencies.<name>`: a published version or a Git repository of a `path` dependency.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct BikecaseConfigPathDependency {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) git: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rev: Option<String>,
+}
+
+impl BikecaseConfigPathDependency {
+    /// The fields to set in place of `path`.
+    pub(crate) fn fields(&self) -> Vec<(&'static str, &str)> {
+        let Self {
+            version,
+            git,
+            branch,
+            tag,
+            rev,
+        } = self;
+        [
+            ("version", version),
+            ("git", git),
+            ("branch", branch),
+            ("tag", tag),
+            ("rev", rev),
+        ]
+        .iter()
+        .filter_map(|&(k, v)| Some((k, v.as_deref()?)))
+        .collect()
+    }
+}
+
 #[derive(Deserialize, Serialize, Default, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct BikecaseConfigWorkspace {
+    /// Name to identify the workspace in profiles, defaults to `default` for `default-workspace`
+    /// and the directory name for the others.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) alias: Option<String>,
+    #[serde(default, serialize_with = "toml::ser::tables_last")]
+    pub(crate) gist_ids: BTreeMap<String, GistIdEntry>,
+    /// Gist IDs of the members moved by `cargo bikecase archive`.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        serialize_with = "toml::ser::tables_last"
+    )]
+    pub(crate) archived_gist_ids: BTreeMap<String, GistIdEntry>,
+    /// Environment variables for the programs run in the workspace.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub(crate) env: IndexMap<String, BikecaseConfigEnvValue>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) packages: BTreeMap<String, BikecaseConfigPackage>,
+    /// The default of `--require-tests` for `gist push`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) push_require_tests: bool,
+    /// The default of `--if-clean` for `gist push`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) push_if_clean: bool,
+}
+
+impl BikecaseConfigWorkspace {
+    /// Resolves `env` and `packages.<package>.env`. The latter takes precedence.
+    pub(crate) fn env(
+        &self,
+        package: &str,
+        home_dir: Option<&Path>,
+    ) -> anyhow::Result<IndexMap<String, String>> {
+        let package_env = self.packages.get(package).map(|p| &p.env);
+        self.env
+            .iter()
+            .chain(package_env.into_iter().flatten())
+            .map(|(name, value)| {
+                let value = value
+                    .resolve(home_dir)
+                    .with_context(|| format!("could not resolve `env.{}`", name))?;
+                Ok((name.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// A value of `gist-ids`: a gist ID, or a table of it and names for humans.
+///
+/// ```toml
+/// [workspaces."~/src/bikecase".gist-ids]
+/// hello = "0123456789abcdef0123456789abcdef"
+/// mandelbrot = { id = "fedcba9876543210fedcba9876543210", alias = "mandelbrot" }
+/// ```
+#[derive(Deserialize, Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(from = "GistIdEntryRepr", into = "GistIdEntryRepr")]
+pub(crate) struct GistIdEntry {
+    pub(crate) id: String,
+    /// Accepted in place of the ID by `gist clone`, `gist clone-all`, and `gist:<ALIAS>`.
+    pub(crate) alias: Option<String>,
+    pub(crate) url: Option<String>,
+}
+
+impl Deref for GistIdEntry {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.id
+    }
+}
+
+impl fmt::Display for GistIdEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.id, f)
+    }
+}
+
+impl From<String> for GistIdEntry {
+    fn from(id: String) -> Self {
+        Self {
+            id,
+            alias: None,
+            url: None,
+        }
+    }
+}
+
+/// Plain strings are kept as they are, for the configs written before aliases.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum GistIdEntryRepr {
+    Id(String),
+    Table {
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        alias: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+    },
+}
+
+impl From<GistIdEntryRepr> for GistIdEntry {
+    fn from(repr: GistIdEntryRepr) -> Self {
+        match repr {
+            GistIdEntryRepr::Id(id) => id.into(),
+            GistIdEntryRepr::Table { id, alias, url } => Self { id, alias, url },
+        }
+    }
+}
+
+impl From<GistIdEntry> for GistIdEntryRepr {
+    fn from(entry: GistIdEntry) -> Self {
+        match entry {
+            GistIdEntry {
+                id,
+                alias: None,
+                url: None,
+            } => Self::Id(id),
+            GistIdEntry { id, alias, url } => Self::Table { id, alias, url },
+        }
+    }
+}
+
+/// `bikecase-workspace.toml` at the root of a workspace.
+///
+/// Unlike the config, this file is meant to be committed with the workspace so that the gist
+/// mappings are shared. It must not contain secrets. When it exists, it is used instead of
+/// `workspaces.<workspace>.gist-ids` in the config.
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct WorkspaceFile {
+    #[serde(default, serialize_with = "toml::ser::tables_last")]
+    pub(crate) gist_ids: BTreeMap<String, GistIdEntry>,
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        serialize_with = "toml::ser::tables_last"
+    )]
+    pub(crate) archived_gist_ids: BTreeMap<String, GistIdEntry>,
+}
+
+impl WorkspaceFile {
+    pub(crate) const FILE_NAME: &'static str = "bikecase-workspace.toml";
+
+    fn load(workspace_root: &Path) -> anyhow::Result<Option<(PathBuf, Self)>> {
+        let path = workspace_root.join(Self::FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let this = crate::fs::read_toml(&path)?;
+        Ok(Some((path, this)))
+    }
+}
+
+/// Gist IDs borrowed from [`BikecaseConfig`] or loaded from [`WorkspaceFile`].
+pub(crate) enum GistIdsMut<'a> {
+    WorkspaceFile(PathBuf, WorkspaceFile),
+    Config(&'a mut BikecaseConfigWorkspace),
+}
+
+impl GistIdsMut<'_> {
+    /// Writes [`WorkspaceFile`] if the IDs were loaded from it. Does nothing otherwise.
+    pub(crate) fn save(&self, dry_run: bool) -> anyhow::Result<()> {
+        if let Self::WorkspaceFile(path, file) = self {
+            let content = toml::to_string_pretty(file).expect("should not fail");
+            crate::fs::write(path, content, dry_run)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the gist ID of `package` from `gist-ids` to `archived-gist-ids` if `archived`, and
+    /// back otherwise. Gist commands only look at the former.
+    ///
+    /// Returns the moved ID.
+    pub(crate) fn set_archived(&mut self, package: &str, archived: bool) -> Option<String> {
+        let (gist_ids, archived_gist_ids) = match self {
+            Self::WorkspaceFile(_, file) => (&mut file.gist_ids, &mut file.archived_gist_ids),
+            Self::Config(workspace) => (&mut workspace.gist_ids, &mut workspace.archived_gist_ids),
+        };
+        let (from, to) = if archived {
+            (gist_ids, archived_gist_ids)
+        } else {
+            (archived_gist_ids, gist_ids)
+        };
+        let gist_id = from.remove(package)?;
+        to.insert(package.to_owned(), gist_id.clone());
+        Some(gist_id.id)
+    }
+}
+
+impl Deref for GistIdsMut<'_> {
+    type Target = BTreeMap<String, GistIdEntry>;
+
+    fn deref(&self) -> &BTreeMap<String, GistIdEntry> {
+        match self {
+            Self::WorkspaceFile(_, file) => &file.gist_ids,
+            Self::Config(workspace) => &workspace.gist_ids,
+        }
+    }
+}
+
+impl DerefMut for GistIdsMut<'_> {
+    fn deref_mut(&mut self) -> &mut BTreeMap<String, GistIdEntry> {
+        match self {
+            Self::WorkspaceFile(_, file) => &mut file.gist_ids,
+            Self::Config(workspace) => &mut workspace.gist_ids,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct BikecaseConfigPackage {
+    /// Defaults for `cargo build` written by `cargo bikecase set`, overridden by the command line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) features: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target: Option<String>,
+    /// Used unless `$RUSTFLAGS` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rustflags: Option<String>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub(crate) env: IndexMap<String, BikecaseConfigEnvValue>,
+}
+
+/// A string with `$NAME`/`${NAME}` interpolated from the environment of bikecase, or a reference
+/// to a secret.
+///
+/// ```toml
+/// DATABASE_URL = "postgres://${USER}@localhost/db"
+/// API_KEY = { kind = "File", path = "~/.config/api-key" }
+/// ```
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+pub(crate) enum BikecaseConfigEnvValue {
+    Interpolated(String),
+    Secret(BikecaseConfigSecret),
+}
+
+impl BikecaseConfigEnvValue {
+    fn resolve(&self, home_dir: Option<&Path>) -> anyhow::Result<String> {
+        match self {
+            Self::Interpolated(value) => Ok(shellexpand::env(value)?.into_owned()),
+            Self::Secret(BikecaseConfigSecret::File { path }) => {
+                let content = crate::fs::read(&*path.expand(home_dir))?;
+                Ok(content.trim_end_matches(&['\n', '\r'][..]).to_owned())
+            }
+        }
+    }
+}
+
+/// What `gist pull` does with local files that differ from the gist.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum PullPolicy {
+    /// Overwrite them, leaving `*.orig` backups.
+    Theirs,
+    /// Keep them.
+    Ours,
+    /// Overwrite them, stashing them under `.bikecase/backups/<timestamp>/`.
+    BackupLocal,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(tag = "kind")]
+pub(crate) enum BikecaseConfigSecret {
+    File { path: TildePath },
+}
+
+/// A portable form of [`BikecaseConfigContent`] for `config export`/`import`.
+///
+/// Workspaces are keyed by their aliases instead of their paths.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct BikecaseConfigProfile {
+    #[serde(default)]
+    default_workspace: Option<TildePath>,
+    #[serde(default)]
+    template_package: Option<TildePath>,
+    #[serde(default)]
+    derive_package_names: bool,
+    #[serde(default)]
+    clone_path_template: Option<String>,
     #[serde(default)]
-    pub(crate) gist_ids: BTreeMap<String, String>,
+    github_token: Option<BikecaseConfigGithubToken>,
+    #[serde(default)]
+    workspaces: BTreeMap<String, BikecaseConfigProfileWorkspace>,
+}
+
+impl BikecaseConfigProfile {
+    /// Parses either a profile or a whole config file.
+    pub(crate) fn parse_profile_or_config(
+        content: &str,
+        home_dir: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        match toml::from_str::<Self>(content) {
+            Ok(profile) => Ok(profile),
+            Err(err) => match toml::from_str::<BikecaseConfigContent>(content) {
+                Ok(config) => config.export_profile(home_dir),
+                Err(_) => Err(err.into()),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct BikecaseConfigProfileWorkspace {
+    path: TildePath,
+    #[serde(default, serialize_with = "toml::ser::tables_last")]
+    gist_ids: BTreeMap<String, GistIdEntry>,
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Hash, Clone)]
@@ -235,7 +1132,14 @@ impl TildePath {
         })
     }
 
+    /// Expands `~` and environment variables. Undefined variables are left as they are.
     pub(crate) fn expand(&self, home_dir: Option<&Path>) -> Cow<'_, str> {
-        shellexpand::tilde_with_context(&self.0, || home_dir)
+        shellexpand::full_with_context_no_errors(&self.0, || home_dir, |var| env::var(var).ok())
+    }
+}
+
+impl fmt::Display for TildePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
     }
 }
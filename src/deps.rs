@@ -0,0 +1,342 @@
+use crate::spec::PackageIdSpec;
+use crate::ui::Spinner;
+use crate::workspace;
+
+use anyhow::{bail, ensure, Context as _};
+use cargo_metadata::{Metadata, Package, PackageId};
+use itertools::Itertools as _;
+use log::{info, warn};
+use serde::Deserialize;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::OsString;
+
+/// Prints the direct dependencies of `package_name` and enforces `deny`.
+///
+/// Only the direct dependencies from crates.io are checked for yanked versions.
+pub(crate) fn report(
+    metadata: &Metadata,
+    package_name: &str,
+    deny: &[crate::Deny],
+    offline: bool,
+) -> anyhow::Result<()> {
+    let (root, nodes, all) = dependencies(metadata, package_name)?;
+
+    let direct = nodes
+        .get(&root.id)
+        .into_iter()
+        .flat_map(|n| &n.dependencies)
+        .map(|id| &metadata[id])
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect::<Vec<_>>();
+
+    info!(
+        "`{}`: {} direct dependencies, {} crates in total",
+        package_name,
+        direct.len(),
+        all.len(),
+    );
+    for package in &direct {
+        if is_git(package) {
+            warn!("    {} {} (git)", package.name, package.version);
+        } else if is_crates_io(package) && !offline && is_yanked(package) {
+            warn!("    {} {} (yanked)", package.name, package.version);
+        } else {
+            info!("    {} {}", package.name, package.version);
+        }
+    }
+
+    let git_deps = all
+        .iter()
+        .map(|id| &metadata[id])
+        .filter(|p| is_git(p))
+        .collect::<Vec<_>>();
+    for package in &git_deps {
+        if let Some(source) = &package.source {
+            warn!(
+                "Git dependency: {} {} ({})",
+                package.name, package.version, source
+            );
+        }
+    }
+
+    if deny.iter().any(|d| matches!(d, crate::Deny::GitDeps)) && !git_deps.is_empty() {
+        bail!(
+            "denied git dependencies: [{}]",
+            git_deps.iter().map(|p| &p.name).format(", "),
+        );
+    }
+    Ok(())
+}
+
+/// Returns the direct dependencies of `package_name` through which packages matching `spec` are
+/// pulled in, including the ones matching `spec` themselves.
+pub(crate) fn why<'a>(
+    metadata: &'a Metadata,
+    package_name: &str,
+    spec: &PackageIdSpec,
+) -> anyhow::Result<Vec<&'a Package>> {
+    let (root, nodes, all) = dependencies(metadata, package_name)?;
+    if !all.iter().any(|id| spec.matches(&metadata[id])) {
+        bail!("`{}` does not depend on `{}`", package_name, spec);
+    }
+
+    let culprits = nodes
+        .get(&root.id)
+        .into_iter()
+        .flat_map(|n| &n.dependencies)
+        .filter(|&direct| {
+            let mut visited = BTreeSet::new();
+            let mut stack = vec![direct];
+            while let Some(id) = stack.pop() {
+                if spec.matches(&metadata[id]) {
+                    return true;
+                }
+                if visited.insert(id) {
+                    stack.extend(nodes.get(id).into_iter().flat_map(|n| &n.dependencies));
+                }
+            }
+            false
+        })
+        .map(|id| &metadata[id])
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect();
+    Ok(culprits)
+}
+
+type Nodes<'a> = HashMap<&'a PackageId, &'a cargo_metadata::Node>;
+
+/// Returns the workspace member `package_name`, the nodes of the resolve graph, and all of the
+/// packages that the member depends on.
+fn dependencies<'a>(
+    metadata: &'a Metadata,
+    package_name: &str,
+) -> anyhow::Result<(&'a Package, Nodes<'a>, BTreeSet<&'a PackageId>)> {
+    let resolve = metadata
+        .resolve
+        .as_ref()
+        .with_context(|| "`resolve` is null")?;
+    let nodes = resolve
+        .nodes
+        .iter()
+        .map(|n| (&n.id, n))
+        .collect::<HashMap<_, _>>();
+
+    let root = metadata
+        .packages
+        .iter()
+        .find(|p| p.name == package_name && metadata.workspace_members.contains(&p.id))
+        .with_context(|| format!("`{}` is not a workspace member", package_name))?;
+
+    let mut all = BTreeSet::<&PackageId>::new();
+    let mut stack = vec![&root.id];
+    while let Some(id) = stack.pop() {
+        for dep in nodes.get(id).into_iter().flat_map(|n| &n.dependencies) {
+            if all.insert(dep) {
+                stack.push(dep);
+            }
+        }
+    }
+    Ok((root, nodes, all))
+}
+
+/// Runs `cargo audit` on the lockfile of the workspace and returns the findings about
+/// `package_name` and its dependencies. The other members are not considered.
+pub(crate) fn audit(
+    metadata: &Metadata,
+    package_name: &str,
+    ignore: &[String],
+) -> anyhow::Result<Vec<AuditFinding>> {
+    let (root, _, all) = dependencies(metadata, package_name)?;
+    let packages = all
+        .iter()
+        .chain(Some(&&root.id))
+        .map(|id| (metadata[id].name.clone(), metadata[id].version.to_string()))
+        .collect::<BTreeSet<_>>();
+
+    let mut args = vec![
+        OsString::from("audit"),
+        "--json".into(),
+        "--file".into(),
+        metadata.workspace_root.join("Cargo.lock").into(),
+    ];
+    for id in ignore {
+        args.push("--ignore".into());
+        args.push(id.into());
+    }
+    let cmd = crate::process::cmd(workspace::cargo_exe()?, args)
+        .dir(&metadata.workspace_root)
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked();
+    let output = {
+        let _spinner = Spinner::new("Running `cargo audit`");
+        cmd.run()?
+    };
+    let report = serde_json::from_slice::<AuditReport>(&output.stdout).with_context(|| {
+        format!(
+            "could not parse the output of `cargo audit`. Is `cargo-audit` installed?\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end(),
+        )
+    })?;
+
+    let vulnerabilities = report
+        .vulnerabilities
+        .list
+        .into_iter()
+        .map(|v| ("vulnerability".to_owned(), v));
+    let warnings = report
+        .warnings
+        .into_iter()
+        .flat_map(|(kind, ws)| ws.into_iter().map(move |w| (kind.clone(), w)));
+
+    Ok(vulnerabilities
+        .chain(warnings)
+        .filter(|(_, AuditEntry { package, .. })| {
+            packages.contains(&(package.name.clone(), package.version.clone()))
+        })
+        .map(|(kind, AuditEntry { advisory, package })| AuditFinding {
+            id: advisory.as_ref().map(|a| a.id.clone()),
+            title: advisory.map(|a| a.title),
+            kind,
+            name: package.name,
+            version: package.version,
+        })
+        .collect())
+}
+
+#[derive(Debug)]
+pub(crate) struct AuditFinding {
+    /// `None` for yanked crates.
+    pub(crate) id: Option<String>,
+    /// `vulnerability`, or the kind of the warning (`unmaintained`, `unsound`, `yanked`, ...).
+    pub(crate) kind: String,
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) title: Option<String>,
+}
+
+impl AuditFinding {
+    pub(crate) fn is_vulnerability(&self) -> bool {
+        self.kind == "vulnerability"
+    }
+}
+
+#[derive(Deserialize)]
+struct AuditReport {
+    vulnerabilities: AuditVulnerabilities,
+    #[serde(default)]
+    warnings: BTreeMap<String, Vec<AuditEntry>>,
+}
+
+#[derive(Deserialize)]
+struct AuditVulnerabilities {
+    list: Vec<AuditEntry>,
+}
+
+#[derive(Deserialize)]
+struct AuditEntry {
+    advisory: Option<AuditAdvisory>,
+    package: AuditPackage,
+}
+
+#[derive(Deserialize)]
+struct AuditAdvisory {
+    id: String,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct AuditPackage {
+    name: String,
+    version: String,
+}
+
+fn is_git(package: &Package) -> bool {
+    package
+        .source
+        .as_ref()
+        .is_some_and(|s| s.to_string().starts_with("git+"))
+}
+
+fn is_crates_io(package: &Package) -> bool {
+    package
+        .source
+        .as_ref()
+        .is_some_and(|s| s.is_crates_io() || s.to_string() == "sparse+https://index.crates.io/")
+}
+
+fn is_yanked(package: &Package) -> bool {
+    return match retrieve(package) {
+        Ok(yanked) => yanked,
+        Err(err) => {
+            warn!(
+                "Could not check whether {} {} is yanked: {}",
+                package.name, package.version, err,
+            );
+            false
+        }
+    };
+
+    fn retrieve(package: &Package) -> anyhow::Result<bool> {
+        let name = package.name.to_lowercase();
+        let path = match name.len() {
+            1 => format!("1/{}", name),
+            2 => format!("2/{}", name),
+            3 => format!("3/{}/{}", &name[..1], name),
+            _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+        };
+        let url = format!("https://index.crates.io/{}", path);
+
+        let res = crate::http::request("GET", &url).call();
+        crate::http::raise_synthetic_error(&res)?;
+        ensure!(res.status() == 200, "{}: {}", url, res.status_line());
+
+        let version = package.version.to_string();
+        for line in res.into_string()?.lines() {
+            let IndexEntry { vers, yanked } = serde_json::from_str(line)?;
+            if vers == version {
+                return Ok(yanked);
+            }
+        }
+        Ok(false)
+    }
+
+    #[derive(Deserialize)]
+    struct IndexEntry {
+        vers: String,
+        yanked: bool,
+    }
+}
+
+/// Annotates the direct dependencies in the output of `cargo tree` with where they are declared:
+/// `(embedded)` for the embedded manifest and `(workspace)` for `workspace.dependencies`.
+pub(crate) fn annotate_tree(tree: &str, inherited: &BTreeMap<(String, String), bool>) -> String {
+    let mut kind = "dependencies";
+    let mut ret = "".to_owned();
+    for line in tree.lines() {
+        ret += line;
+        match line {
+            "[dev-dependencies]" => kind = "dev-dependencies",
+            "[build-dependencies]" => kind = "build-dependencies",
+            _ => {
+                if let Some(rest) = line
+                    .strip_prefix("├── ")
+                    .or_else(|| line.strip_prefix("└── "))
+                {
+                    let name = rest.split(' ').next().unwrap_or_default();
+                    match inherited.get(&(kind.to_owned(), name.to_owned())) {
+                        Some(true) => ret += " (workspace)",
+                        Some(false) => ret += " (embedded)",
+                        None => {}
+                    }
+                } else if !line.starts_with(['│', ' ']) {
+                    // The root package, or the blank line between the roots.
+                    kind = "dependencies";
+                }
+            }
+        }
+        ret += "\n";
+    }
+    ret
+}
@@ -0,0 +1,245 @@
+use log::{info, log, Level};
+use once_cell::sync::Lazy;
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, RwLock};
+
+static SINK: Lazy<RwLock<Arc<dyn EventSink>>> =
+    Lazy::new(|| RwLock::new(Arc::new(LogSink::default())));
+
+/// Receives the progress of operations.
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: &Event);
+}
+
+impl<F: Fn(&Event) + Send + Sync> EventSink for F {
+    fn emit(&self, event: &Event) {
+        self(event);
+    }
+}
+
+impl EventSink for mpsc::Sender<Event> {
+    fn emit(&self, event: &Event) {
+        // The receiver may have been dropped. That should not stop the operation.
+        let _ = self.send(event.clone());
+    }
+}
+
+/// Replaces the sink for the whole process. The default is [`LogSink`].
+pub fn set_event_sink(sink: impl EventSink + 'static) {
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(sink);
+}
+
+pub(crate) fn emit(event: Event) {
+    let sink = SINK.read().unwrap_or_else(|e| e.into_inner()).clone();
+    sink.emit(&event);
+}
+
+/// Emits [`Event::Message`] with [`Level::Info`].
+macro_rules! info_event {
+    ($($arg:tt)*) => {
+        crate::event::emit(crate::event::Event::Message {
+            level: log::Level::Info,
+            message: format!($($arg)*),
+        })
+    };
+}
+
+/// Emits [`Event::Message`] with [`Level::Warn`].
+macro_rules! warn_event {
+    ($($arg:tt)*) => {
+        crate::event::emit(crate::event::Event::Message {
+            level: log::Level::Warn,
+            message: format!($($arg)*),
+        })
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A file was written.
+    FileWritten { path: PathBuf, dry_run: bool },
+    /// A file was copied.
+    FileCopied {
+        src: PathBuf,
+        dst: PathBuf,
+        dry_run: bool,
+    },
+    /// A file or a directory was moved.
+    FileMoved {
+        src: PathBuf,
+        dst: PathBuf,
+        dry_run: bool,
+    },
+    /// A file or a directory was removed.
+    FileRemoved { path: PathBuf, dry_run: bool },
+    /// A symlink was created at `dst`.
+    SymlinkCreated {
+        src: PathBuf,
+        dst: PathBuf,
+        dry_run: bool,
+    },
+    /// A workspace was created.
+    WorkspaceCreated {
+        workspace_root: PathBuf,
+        dry_run: bool,
+    },
+    /// `member` was added to `workspace.<field>` (`members`, `exclude`, or `default-members`).
+    MemberAdded {
+        member: String,
+        field: &'static str,
+        dry_run: bool,
+    },
+    /// `member` was removed from `workspace.<field>`.
+    MemberRemoved {
+        member: String,
+        field: &'static str,
+        dry_run: bool,
+    },
+    /// An HTTP request is being sent.
+    HttpRequest {
+        method: &'static str,
+        url: String,
+        dry_run: bool,
+    },
+    /// An HTTP response was received.
+    HttpResponse { status: u16, status_text: String },
+    /// A process such as `cargo` is being spawned.
+    ProcessSpawned {
+        program: OsString,
+        args: Vec<OsString>,
+        dry_run: bool,
+    },
+    /// `name` is going to change from `orig` to `edit`.
+    Diff {
+        name: String,
+        orig: String,
+        edit: String,
+    },
+    /// Any other message.
+    Message { level: Level, message: String },
+    /// The command finished.
+    Finished { success: bool },
+}
+
+/// Logs the events with `log`. This is what the CLI uses.
+///
+/// The paths, URLs, and programs in the events are attached to the records as key-values.
+#[derive(Debug, Clone, Copy)]
+pub struct LogSink {
+    pub str_width: fn(&str) -> usize,
+}
+
+impl Default for LogSink {
+    fn default() -> Self {
+        Self {
+            str_width: crate::str_width,
+        }
+    }
+}
+
+impl EventSink for LogSink {
+    fn emit(&self, event: &Event) {
+        let dry_run_prefix = |dry_run: bool| if dry_run { "[dry-run] " } else { "" };
+
+        match event {
+            Event::FileWritten { path, dry_run } => info!(
+                path:% = path.display(), dry_run;
+                "{}Wrote {}",
+                dry_run_prefix(*dry_run),
+                path.display(),
+            ),
+            Event::FileCopied { src, dst, dry_run } => info!(
+                src:% = src.display(), dst:% = dst.display(), dry_run;
+                "{}Copied {} to {}",
+                dry_run_prefix(*dry_run),
+                src.display(),
+                dst.display(),
+            ),
+            Event::FileMoved { src, dst, dry_run } => info!(
+                src:% = src.display(), dst:% = dst.display(), dry_run;
+                "{}Moved {} to {}",
+                dry_run_prefix(*dry_run),
+                src.display(),
+                dst.display(),
+            ),
+            Event::FileRemoved { path, dry_run } => info!(
+                path:% = path.display(), dry_run;
+                "{}Removed {}",
+                dry_run_prefix(*dry_run),
+                path.display(),
+            ),
+            Event::SymlinkCreated { src, dst, dry_run } => info!(
+                src:% = src.display(), dst:% = dst.display(), dry_run;
+                "{}Created a symlink {} → {}",
+                dry_run_prefix(*dry_run),
+                dst.display(),
+                src.display(),
+            ),
+            Event::WorkspaceCreated {
+                workspace_root,
+                dry_run,
+            } => info!(
+                workspace_root:% = workspace_root.display(), dry_run;
+                "{}Created a new workspace: {}",
+                dry_run_prefix(*dry_run),
+                workspace_root.display(),
+            ),
+            Event::MemberAdded {
+                member,
+                field,
+                dry_run,
+            } => info!(
+                member = member.as_str(), field, dry_run;
+                "Added {:?} to `workspace.{}`",
+                member,
+                field,
+            ),
+            Event::MemberRemoved {
+                member,
+                field,
+                dry_run,
+            } => info!(
+                member = member.as_str(), field, dry_run;
+                "Removed {:?} from `workspace.{}`",
+                member,
+                field,
+            ),
+            Event::HttpRequest {
+                method,
+                url,
+                dry_run,
+            } => info!(
+                method, url = url.as_str(), dry_run;
+                "{}{} {}",
+                dry_run_prefix(*dry_run),
+                method,
+                url,
+            ),
+            Event::HttpResponse {
+                status,
+                status_text,
+            } => info!(status; "{} {}", status, status_text),
+            Event::ProcessSpawned {
+                program,
+                args,
+                dry_run,
+            } => info!(
+                program:% = program.to_string_lossy(), dry_run;
+                "{}Running `{}{}`",
+                dry_run_prefix(*dry_run),
+                shell_escape::escape(program.to_string_lossy()),
+                args.iter()
+                    .map(|arg| format!(" {}", arg.to_string_lossy()))
+                    .collect::<String>(),
+            ),
+            Event::Diff { name, orig, edit } => {
+                crate::ui::info_diff(orig, edit, name, self.str_width);
+            }
+            Event::Message { level, message } => log!(*level, "{}", message),
+            Event::Finished { .. } => {}
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use crate::event::Event;
+
 use anyhow::{anyhow, Context as _};
-use log::info;
+use ignore::{Walk, WalkBuilder};
 use serde::de::DeserializeOwned;
 
 use std::io;
@@ -28,13 +30,26 @@ pub(crate) fn read_toml_with_raw<P: AsRef<Path>, T: DeserializeOwned>(
     Ok((string, value))
 }
 
-pub(crate) fn read_toml_edit(path: impl AsRef<Path>) -> anyhow::Result<toml_edit::Document> {
+pub(crate) fn read_toml_edit(path: impl AsRef<Path>) -> anyhow::Result<toml_edit::DocumentMut> {
     let path = path.as_ref();
     read(path)?
         .parse()
         .with_context(|| format!("failed to parse the TOML file at {}", path.display()))
 }
 
+/// Walks `dir` honoring `.gitignore`s and `.bikecaseignore`s.
+pub(crate) fn walk(dir: impl AsRef<Path>) -> Walk {
+    walk_builder(dir).build()
+}
+
+pub(crate) fn walk_builder(dir: impl AsRef<Path>) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(dir);
+    builder
+        .hidden(false)
+        .add_custom_ignore_filename(".bikecaseignore");
+    builder
+}
+
 pub(crate) fn write(
     path: impl AsRef<Path>,
     contents: impl AsRef<[u8]>,
@@ -45,11 +60,10 @@ pub(crate) fn write(
         std::fs::write(path, contents)
             .with_context(|| format!("failed to write {}", path.display()))?;
     }
-    info!(
-        "{}Wrote {}",
-        if dry_run { "[dry-run] " } else { "" },
-        path.display(),
-    );
+    crate::event::emit(Event::FileWritten {
+        path: path.to_owned(),
+        dry_run,
+    });
     Ok(())
 }
 
@@ -64,15 +78,42 @@ pub(crate) fn copy(
             format!("failed to copy `{}` to `{}`", src.display(), dst.display())
         })?;
     }
-    info!(
-        "{}Copied {} to {}",
-        if dry_run { "[dry-run] " } else { "" },
-        src.display(),
-        dst.display(),
-    );
+    crate::event::emit(Event::FileCopied {
+        src: src.to_owned(),
+        dst: dst.to_owned(),
+        dry_run,
+    });
     Ok(())
 }
 
+pub(crate) fn rename(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    if !dry_run {
+        std::fs::rename(src, dst).with_context(|| {
+            format!("failed to move `{}` to `{}`", src.display(), dst.display())
+        })?;
+    }
+    crate::event::emit(Event::FileMoved {
+        src: src.to_owned(),
+        dst: dst.to_owned(),
+        dry_run,
+    });
+    Ok(())
+}
+
+/// Creates `path` and its parent directories for writing.
+pub(crate) fn create(path: impl AsRef<Path>) -> anyhow::Result<std::fs::File> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent, false)?;
+    }
+    std::fs::File::create(path).with_context(|| format!("failed to create `{}`", path.display()))
+}
+
 pub(crate) fn create_dir_all(path: impl AsRef<Path>, dry_run: bool) -> anyhow::Result<()> {
     let path = path.as_ref();
     if !dry_run {
@@ -82,16 +123,88 @@ pub(crate) fn create_dir_all(path: impl AsRef<Path>, dry_run: bool) -> anyhow::R
     Ok(())
 }
 
+pub(crate) fn remove_file(path: impl AsRef<Path>, dry_run: bool) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if !dry_run {
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove `{}`", path.display()))?;
+    }
+    crate::event::emit(Event::FileRemoved {
+        path: path.to_owned(),
+        dry_run,
+    });
+    Ok(())
+}
+
 pub(crate) fn remove_dir_all(path: impl AsRef<Path>, dry_run: bool) -> anyhow::Result<()> {
     let path = path.as_ref();
     if !dry_run {
         remove_dir_all::remove_dir_all(path)
             .with_context(|| format!("failed to remove `{}`", path.display()))?;
     }
-    info!(
-        "{}Removed {}",
-        if dry_run { "[dry-run] " } else { "" },
-        path.display(),
-    );
+    crate::event::emit(Event::FileRemoved {
+        path: path.to_owned(),
+        dry_run,
+    });
+    Ok(())
+}
+
+pub(crate) fn symlink_dir(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    if !dry_run {
+        #[cfg(unix)]
+        let result = std::os::unix::fs::symlink(src, dst);
+        #[cfg(windows)]
+        let result = std::os::windows::fs::symlink_dir(src, dst);
+        result.with_context(|| {
+            format!(
+                "failed to create a symlink `{}` → `{}`",
+                dst.display(),
+                src.display(),
+            )
+        })?;
+    }
+    crate::event::emit(Event::SymlinkCreated {
+        src: src.to_owned(),
+        dst: dst.to_owned(),
+        dry_run,
+    });
+    Ok(())
+}
+
+/// Creates a symlink `dst` → `src`. On Windows, whether `src` is a directory is decided by
+/// resolving it from the parent of `dst`.
+pub(crate) fn symlink(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    if !dry_run {
+        #[cfg(unix)]
+        let result = std::os::unix::fs::symlink(src, dst);
+        #[cfg(windows)]
+        let result = if dst.parent().unwrap_or(dst).join(src).is_dir() {
+            std::os::windows::fs::symlink_dir(src, dst)
+        } else {
+            std::os::windows::fs::symlink_file(src, dst)
+        };
+        result.with_context(|| {
+            format!(
+                "failed to create a symlink `{}` → `{}`",
+                dst.display(),
+                src.display(),
+            )
+        })?;
+    }
+    crate::event::emit(Event::SymlinkCreated {
+        src: src.to_owned(),
+        dst: dst.to_owned(),
+        dry_run,
+    });
     Ok(())
 }
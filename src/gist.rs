@@ -1,70 +1,457 @@
-use crate::logger;
+use crate::config::GistIdEntry;
+use crate::event;
 
-use anyhow::{anyhow, bail, ensure};
+use anyhow::{anyhow, bail, ensure, Context as _};
 use indexmap::IndexMap;
 use itertools::Itertools as _;
-use log::info;
-use serde::Deserialize;
+use log::warn;
+use once_cell::sync::Lazy;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use ureq::Response;
 use url::Url;
 
-use std::collections::btree_map;
-use std::path::Path;
+use std::cell::Cell;
+use std::collections::{btree_map, BTreeMap};
+use std::env;
+use std::io::{self, Read as _, Write as _};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use std::vec;
+
+/// The gist file `--changelog` maintains.
+const CHANGELOG: &str = "CHANGELOG.md";
+
+/// The default of `--max-size`, for the commands without it.
+pub(crate) const DEFAULT_MAX_SIZE: u64 = 10 << 20;
 
 pub(crate) fn retrieve_rust_code(gist_id: &str) -> anyhow::Result<(String, String)> {
-    let url = "https://api.github.com/gists/"
-        .parse::<Url>()
-        .unwrap()
-        .join(&gist_id)?;
-
-    info!("GET: {}", url);
-    let res = ureq::get(url.as_ref()).set("User-Agent", USER_AGENT).call();
-    raise_synthetic_error(&res)?;
-    info!("{} {}", res.status(), res.status_text());
-    ensure!(res.status() == 200, "expected 200");
+    let RetrievedGist {
+        description,
+        script,
+        ..
+    } = retrieve(gist_id, DEFAULT_MAX_SIZE)?;
+    Ok((script, description))
+}
 
-    let Gist { files, description } = serde_json::from_str(&res.into_string()?)?;
+/// Retrieves the files of a gist.
+///
+/// The main script is the only Rust file whose name does not contain `__` and that is not listed in
+/// `package.metadata.bikecase.gist.extra-files` of another one. The other files are mapped to
+/// paths relative to the package by reading `__` as `/` (e.g. `tests__smoke.rs` →
+/// `tests/smoke.rs`). A file named `<package>.Cargo.lock` is the lockfile pushed with
+/// `--with-lockfile`.
+///
+/// Files too large to be included in the API response are downloaded from their raw URLs, up to
+/// `max_size` bytes each.
+pub(crate) fn retrieve(gist_id: &str, max_size: u64) -> anyhow::Result<RetrievedGist> {
+    retrieve_revision(gist_id, None, max_size)
+}
 
-    let file = files
-        .values()
-        .filter(|GistFile { filename, .. }| {
-            [Some("rs".as_ref()), Some("crs".as_ref())].contains(&Path::new(&filename).extension())
+/// [`retrieve`] at `revision`, or the latest one if `None`.
+pub(crate) fn retrieve_revision(
+    gist_id: &str,
+    revision: Option<&str>,
+    max_size: u64,
+) -> anyhow::Result<RetrievedGist> {
+    let Gist {
+        files,
+        description,
+        owner,
+        created_at,
+        ..
+    } = get(gist_id, revision, max_size)?;
+
+    let (lockfiles, files) = files
+        .into_iter()
+        .map(|(_, file)| file)
+        .partition::<Vec<_>, _>(|GistFile { filename, .. }| filename.ends_with(".Cargo.lock"));
+
+    if lockfiles.len() > 1 {
+        bail!(
+            "multiple lockfiles: [{}]",
+            lockfiles
+                .iter()
+                .format_with(", ", |GistFile { filename, .. }, f| f(filename)),
+        );
+    }
+    let lockfile = lockfiles.into_iter().next().map(|file| file.content);
+
+    let (mut scripts, mut extra_files) =
+        files
+            .into_iter()
+            .partition::<Vec<_>, _>(|GistFile { filename, .. }| {
+                !filename.contains("__")
+                    && [Some("rs".as_ref()), Some("crs".as_ref())]
+                        .contains(&Path::new(&filename).extension())
+            });
+
+    if scripts.len() > 1 {
+        let declared = scripts
+            .iter()
+            .flat_map(|GistFile { content, .. }| {
+                crate::rust::extract_cargo_lang_code(content, || "")
+                    .and_then(|manifest| crate::workspace::gist_extra_files(&manifest))
+                    .unwrap_or_default()
+            })
+            .map(|path| filename(&path))
+            .collect::<Vec<_>>();
+        let (declared, rest) = scripts
+            .into_iter()
+            .partition::<Vec<_>, _>(|GistFile { filename, .. }| declared.contains(filename));
+        scripts = rest;
+        extra_files.extend(declared);
+    }
+
+    let script = scripts.into_iter().exactly_one().map_err(|err| {
+        let mut err = err.peekable();
+        if err.peek().is_some() {
+            anyhow!(
+                "multiple Rust files: [{}]",
+                err.format_with(", ", |GistFile { filename, .. }, f| f(&filename)),
+            )
+        } else {
+            anyhow!("no Rust files found")
+        }
+    })?;
+
+    let extra_files = extra_files
+        .into_iter()
+        .map(|file| {
+            let path = file.filename.split("__").collect::<PathBuf>();
+            if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+                bail!("invalid filename: {:?}", file.filename);
+            }
+            Ok((path, file.content))
         })
-        .exactly_one()
-        .map_err(|err| {
-            let mut err = err.peekable();
-            if err.peek().is_some() {
-                anyhow!(
-                    "multiple Rust files: [{}]",
-                    err.format_with(", ", |GistFile { filename, .. }, f| f(&filename)),
-                )
-            } else {
-                anyhow!("no Rust files found")
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(RetrievedGist {
+        description: description.unwrap_or_default(),
+        owner: owner.map(|o| o.login),
+        created_at,
+        script_filename: script.filename,
+        script: script.content,
+        extra_files,
+        lockfile,
+    })
+}
+
+/// Retrieves the files of a gist as they are, by their names.
+pub(crate) fn retrieve_files(
+    gist_id: &str,
+    max_size: u64,
+) -> anyhow::Result<IndexMap<String, String>> {
+    let Gist { files, .. } = get(gist_id, None, max_size)?;
+    Ok(files
+        .into_iter()
+        .map(|(_, file)| (file.filename, file.content))
+        .collect())
+}
+
+fn get(gist_id: &str, revision: Option<&str>, max_size: u64) -> anyhow::Result<Gist> {
+    let url = match revision {
+        Some(revision) => gists_url().join(&format!("{}/{}", gist_id, revision))?,
+        None => gists_url().join(gist_id)?,
+    };
+
+    request("GET", url.as_ref(), false);
+    let res = crate::http::request("GET", url.as_ref()).call();
+    crate::http::raise_synthetic_error(&res)?;
+    response(&res);
+    ensure!(res.status() == 200, "expected 200");
+
+    let mut gist = parse_response::<Gist>(res)?;
+    for file in gist.files.values_mut().filter(|f| f.truncated) {
+        let raw_url = file
+            .raw_url
+            .as_deref()
+            .with_context(|| format!("{} is truncated", file.filename))?;
+        file.content = download_raw(&file.filename, raw_url, max_size)?;
+        file.truncated = false;
+    }
+    Ok(gist)
+}
+
+/// Downloads the content of a truncated file from `raw_url`.
+///
+/// Interrupted downloads are resumed with `Range`, or restarted if the server ignores it.
+fn download_raw(filename: &str, raw_url: &str, max_size: u64) -> anyhow::Result<String> {
+    const ATTEMPTS: u32 = 5;
+
+    let too_large = |size: u64| {
+        anyhow!(
+            "{} is larger than `--max-size` ({} > {} bytes)",
+            filename,
+            size,
+            max_size,
+        )
+    };
+
+    let mut content = vec![];
+    let mut total = None;
+    let progress = Progress::new(filename);
+
+    for attempt in 1..=ATTEMPTS {
+        let received = content.len();
+
+        request("GET", raw_url, false);
+        let mut req = crate::http::request("GET", raw_url);
+        if received > 0 {
+            req.set("Range", &format!("bytes={}-", received));
+        }
+        let res = req.call();
+        if let Err(err) = crate::http::raise_synthetic_error(&res) {
+            if attempt == ATTEMPTS {
+                return Err(err.context(format!("failed to download {}", filename)));
             }
-        })?;
+            warn!("{:#} ({}/{})", err, attempt, ATTEMPTS);
+            thread::sleep(Duration::from_secs(attempt.into()));
+            continue;
+        }
+        response(&res);
+
+        let size = match res.status() {
+            200 => {
+                content.clear();
+                res.header("Content-Length").and_then(|s| s.parse().ok())
+            }
+            206 => res
+                .header("Content-Range")
+                .and_then(|s| s.rsplit('/').next())
+                .and_then(|s| s.parse().ok()),
+            status => bail!("expected 200 or 206, got {}", status),
+        };
+        if let Some(size) = size {
+            if size > max_size {
+                return Err(too_large(size));
+            }
+            total = Some(size);
+        }
 
-    if file.truncated {
-        bail!("{} is truncated", file.filename);
+        let mut reader = res.into_reader();
+        let mut buf = [0; 8192];
+        let result = loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => break Err(e),
+            };
+            content.extend_from_slice(&buf[..n]);
+            if content.len() as u64 > max_size {
+                return Err(too_large(content.len() as u64));
+            }
+            progress.update(content.len() as u64, total);
+        };
+
+        match (result, total) {
+            (Ok(()), Some(total)) if content.len() as u64 >= total => break,
+            (Ok(()), None) => break,
+            (Ok(()), Some(total)) => warn!(
+                "the connection was closed at {}/{} bytes ({}/{})",
+                content.len(),
+                total,
+                attempt,
+                ATTEMPTS,
+            ),
+            (Err(err), _) => warn!("{} ({}/{})", err, attempt, ATTEMPTS),
+        }
+        if attempt == ATTEMPTS {
+            bail!("failed to download {}", filename);
+        }
+        if content.len() == received {
+            thread::sleep(Duration::from_secs(attempt.into()));
+        }
+    }
+    String::from_utf8(content).with_context(|| format!("{} is not valid UTF-8", filename))
+}
+
+/// A progress bar on stderr, shown only when stderr is a TTY. The line is ended on drop.
+struct Progress<'a> {
+    filename: &'a str,
+    enabled: bool,
+    shown: Cell<bool>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(filename: &'a str) -> Self {
+        Self {
+            filename,
+            enabled: atty::is(atty::Stream::Stderr),
+            shown: Cell::new(false),
+        }
     }
 
-    return Ok((file.content.clone(), description));
+    fn update(&self, received: u64, total: Option<u64>) {
+        const WIDTH: u64 = 30;
 
-    #[derive(Deserialize)]
-    struct Gist {
-        files: IndexMap<String, GistFile>,
-        description: String,
+        if !self.enabled {
+            return;
+        }
+        let mut stderr = io::stderr();
+        let _ = match total {
+            Some(total) if total > 0 => {
+                let filled = (WIDTH * received.min(total) / total) as usize;
+                write!(
+                    stderr,
+                    "\r{} [{}{}] {}/{} KiB",
+                    self.filename,
+                    "#".repeat(filled),
+                    "-".repeat(WIDTH as usize - filled),
+                    received >> 10,
+                    total >> 10,
+                )
+            }
+            _ => write!(stderr, "\r{} {} KiB", self.filename, received >> 10),
+        };
+        let _ = stderr.flush();
+        self.shown.set(true);
     }
+}
 
-    #[derive(Deserialize, Debug)]
-    struct GistFile {
-        filename: String,
-        truncated: bool,
-        content: String,
+impl Drop for Progress<'_> {
+    fn drop(&mut self) {
+        if self.shown.get() {
+            eprintln!();
+        }
     }
 }
 
-pub(crate) fn push(opts: PushOptions<'_>) -> anyhow::Result<()> {
+// The fields not used are not declared, and the ones that may be `null` or missing are `Option`s
+// or `#[serde(default)]`.
+
+#[derive(Deserialize)]
+struct Gist {
+    files: IndexMap<String, GistFile>,
+    /// `null` for gists without descriptions.
+    description: Option<String>,
+    #[serde(default)]
+    public: bool,
+    html_url: String,
+    owner: Option<GistOwner>,
+    #[serde(default)]
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct GistOwner {
+    login: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GistFile {
+    filename: String,
+    #[serde(default)]
+    truncated: bool,
+    /// Missing for large files, which are `truncated`.
+    #[serde(default)]
+    content: String,
+    raw_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct RetrievedGist {
+    pub(crate) description: String,
+    /// `None` for anonymous gists.
+    pub(crate) owner: Option<String>,
+    /// e.g. `2020-04-01T12:34:56Z`
+    pub(crate) created_at: String,
+    pub(crate) script_filename: String,
+    pub(crate) script: String,
+    pub(crate) extra_files: BTreeMap<PathBuf, String>,
+    /// The content of `<package>.Cargo.lock`.
+    pub(crate) lockfile: Option<String>,
+}
+
+impl RetrievedGist {
+    /// Expands `clone-path-template` into a relative path.
+    ///
+    /// | Placeholder       | Value                                                 |
+    /// | ----------------- | ----------------------------------------------------- |
+    /// | `{package}`       | The package name                                      |
+    /// | `{gist_id}`       | The gist ID                                           |
+    /// | `{gist_id_short}` | The first 7 characters of the gist ID                 |
+    /// | `{owner}`         | The login name of the owner, or `anonymous`           |
+    /// | `{description}`   | The description with `/`, `\` and controls as `_`     |
+    /// | `{date}`          | The creation date (`YYYY-MM-DD`)                      |
+    /// | `{year}`          | The creation year                                     |
+    /// | `{month}`         | The creation month (`MM`)                             |
+    ///
+    /// `{{` and `}}` are `{` and `}`.
+    pub(crate) fn expand_path_template(
+        &self,
+        template: &str,
+        gist_id: &str,
+        package: &str,
+    ) -> anyhow::Result<PathBuf> {
+        let date = self.created_at.get(..10).unwrap_or_default();
+        let description = self
+            .description
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect::<String>();
+        let description = description.trim();
+
+        let mut expanded = "".to_owned();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    expanded.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    expanded.push('}');
+                }
+                '{' => {
+                    let mut name = "".to_owned();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("`clone-path-template`: unclosed `{{`: {:?}", template),
+                        }
+                    }
+                    expanded += match &*name {
+                        "package" => package,
+                        "gist_id" => gist_id,
+                        "gist_id_short" => gist_id.get(..7).unwrap_or(gist_id),
+                        "owner" => self.owner.as_deref().unwrap_or("anonymous"),
+                        "description" if description.is_empty() => "untitled",
+                        "description" => description,
+                        "date" => date,
+                        "year" => date.get(..4).unwrap_or_default(),
+                        "month" => date.get(5..7).unwrap_or_default(),
+                        _ => bail!("`clone-path-template`: unknown placeholder `{{{}}}`", name),
+                    };
+                }
+                '}' => bail!("`clone-path-template`: unmatched `}}`: {:?}", template),
+                c => expanded.push(c),
+            }
+        }
+
+        let path = PathBuf::from(expanded);
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            bail!(
+                "`clone-path-template` should expand to a relative path without `..`: {:?}",
+                path,
+            );
+        }
+        Ok(path)
+    }
+}
+
+/// Pushes the script. Returns `None` if there is nothing to push or `dry_run` is enabled.
+pub(crate) fn push(opts: PushOptions<'_>) -> anyhow::Result<Option<PushOutcome>> {
     let PushOptions {
         github_token,
         mut gist_id,
@@ -74,17 +461,51 @@ pub(crate) fn push(opts: PushOptions<'_>) -> anyhow::Result<()> {
         set_upstream,
         private,
         description,
+        auto_description,
+        lockfile,
+        extra_files,
+        changelog,
         dry_run,
-        str_width,
     } = opts;
 
+    if changelog.is_some() && extra_files.contains_key(Path::new(CHANGELOG)) {
+        bail!(
+            "`{}` is one of `extra-files` of `{}`. Edit it instead of using `--changelog`",
+            CHANGELOG,
+            package,
+        );
+    }
+
     let state = if let btree_map::Entry::Occupied(gist_id) = &mut gist_id {
         let gist_id = gist_id.get();
-        let (remote_code, remote_description) = retrieve_rust_code(gist_id)?;
-        if remote_code == local && description.map_or(true, |d| d == remote_description) {
+        let RetrievedGist {
+            script: remote_code,
+            script_filename: remote_filename,
+            description: remote_description,
+            lockfile: remote_lockfile,
+            extra_files: remote_extra_files,
+            ..
+        } = retrieve(gist_id, DEFAULT_MAX_SIZE)?;
+        let description =
+            description.or_else(|| auto_description.filter(|_| remote_description.is_empty()));
+        if remote_code == local
+            && description.is_none_or(|d| d == remote_description)
+            && lockfile.is_none_or(|l| remote_lockfile.as_deref() == Some(l))
+            && extra_files
+                .iter()
+                .all(|(path, content)| remote_extra_files.get(path) == Some(content))
+        {
             State::UpToDate
         } else {
-            State::Forward(gist_id, remote_code, remote_description)
+            State::Forward(
+                gist_id,
+                remote_code,
+                remote_filename,
+                remote_description,
+                remote_lockfile,
+                remote_extra_files,
+                description,
+            )
         }
     } else {
         State::NotExist
@@ -92,140 +513,519 @@ pub(crate) fn push(opts: PushOptions<'_>) -> anyhow::Result<()> {
 
     return match state {
         State::UpToDate => {
-            info!("Up to date");
-            Ok(())
+            info_event!("Up to date");
+            if changelog.is_some() {
+                warn!("Nothing to push. The changelog entry was not added");
+            }
+            Ok(None)
         }
-        State::Forward(gist_id, remote_code, remote_description) => {
-            let url = "https://api.github.com/gists/"
-                .parse::<Url>()
-                .unwrap()
-                .join(gist_id)?;
+        State::Forward(
+            gist_id,
+            remote_code,
+            remote_filename,
+            remote_description,
+            remote_lockfile,
+            remote_extra_files,
+            description,
+        ) => {
+            let url = gists_url().join(gist_id)?;
 
             if dry_run {
-                info!("[dry-run] PATCH {}", url);
+                request("PATCH", url.as_ref(), true);
+                Ok(None)
             } else {
                 let description = description.unwrap_or(&remote_description);
-                let filename = format!("{}.rs", package);
+                // Keep the name, so that the script is updated in place instead of added.
+                let filename = remote_filename;
+                let lockfile_name = format!("{}.Cargo.lock", package);
+                let extra_files = &with_changelog(
+                    extra_files,
+                    changelog,
+                    remote_extra_files.get(Path::new(CHANGELOG)),
+                );
 
                 let payload = json!({
                     "description": description,
-                    "files": {
-                        &filename: {
-                            "content": local
-                        }
-                    }
+                    "files": files(&filename, local, &lockfile_name, lockfile, extra_files),
                 });
 
-                info!("PATCH {}", url);
-                let res = ureq::patch(url.as_ref())
+                request("PATCH", url.as_ref(), false);
+                let res = crate::http::request("PATCH", url.as_ref())
                     .set("Authorization", &format!("token {}", github_token))
-                    .set("User-Agent", USER_AGENT)
                     .send_json(payload);
-                raise_synthetic_error(&res)?;
-                info!("{} {}", res.status(), res.status_text());
+                crate::http::raise_synthetic_error(&res)?;
+                response(&res);
                 ensure!(res.status() == 200, "expected 200");
-                serde_json::from_str::<serde_json::Value>(&res.into_string()?)?;
+                let res = parse_response::<PushResponse>(res)?;
 
-                info!("Updated `{}`", gist_id);
-                logger::info_diff(&remote_description, description, "<description>", str_width);
-                logger::info_diff(&remote_code, local, filename, str_width);
+                info_event!("Updated `{}`", gist_id);
+                diff("<description>", &remote_description, description);
+                diff(&filename, &remote_code, local);
+                if let Some(lockfile) = lockfile {
+                    let remote_lockfile = remote_lockfile.as_deref().unwrap_or_default();
+                    diff(&lockfile_name, remote_lockfile, lockfile);
+                }
+                for (path, content) in extra_files {
+                    let remote = remote_extra_files.get(path).map(|s| &**s);
+                    diff(&self::filename(path), remote.unwrap_or_default(), content);
+                }
+                Ok(Some(res.into()))
             }
-            Ok(())
         }
         State::NotExist => {
-            static URL: &str = "https://api.github.com/gists";
+            let url = GITHUB_API_URL.join("gists").unwrap();
 
             if !set_upstream {
                 bail!("to create a new gist, enable `--set-upstream`");
             } else if dry_run {
-                info!("[dry-run] POST {}", URL);
-                Ok(())
+                request("POST", url.as_ref(), true);
+                Ok(None)
             } else {
                 let filename = format!("{}.rs", package);
-                let description = description.unwrap_or_default();
+                let lockfile_name = format!("{}.Cargo.lock", package);
+                let description = description.or(auto_description).unwrap_or_default();
+                let extra_files = &with_changelog(extra_files, changelog, None);
 
                 let payload = json!({
-                    "files": {
-                        &filename: {
-                            "content": local
-                        }
-                    },
+                    "files": files(&filename, local, &lockfile_name, lockfile, extra_files),
                     "description": description,
                     "public": !private
                 });
 
-                info!("POST {}", URL);
-                let res = ureq::post(URL)
+                request("POST", url.as_ref(), false);
+                let res = crate::http::request("POST", url.as_ref())
                     .set("Authorization", &format!("token {}", github_token))
-                    .set("User-Agent", USER_AGENT)
                     .send_json(payload);
-                raise_synthetic_error(&res)?;
-                info!("{} {}", res.status(), res.status_text());
+                crate::http::raise_synthetic_error(&res)?;
+                response(&res);
                 ensure!(res.status() == 201, "expected 201");
-                let CreateGist { id } = serde_json::from_str(&res.into_string()?)?;
-                info!("Created `{}`", id);
-                logger::info_diff("", description, "<description>", str_width);
-                logger::info_diff("", local, filename, str_width);
-                info!(
+                let res = parse_response::<PushResponse>(res)?;
+                let id = res.id.clone();
+                info_event!("Created `{}`", id);
+                diff("<description>", "", description);
+                diff(&filename, "", local);
+                if let Some(lockfile) = lockfile {
+                    diff(&lockfile_name, "", lockfile);
+                }
+                for (path, content) in extra_files {
+                    diff(&self::filename(path), "", content);
+                }
+                info_event!(
                     "`workspaces.{:?}.gist_ids.{:?}`: None → Some({:?})",
-                    workspace_root, package, id,
+                    workspace_root,
+                    package,
+                    id,
                 );
-                gist_id.or_insert(id);
-                Ok(())
+                gist_id.or_insert(id.into());
+                Ok(Some(res.into()))
             }
         }
     };
 
+    /// `extra_files` and `CHANGELOG.md` with `entry` appended to `remote`.
+    fn with_changelog(
+        extra_files: &BTreeMap<PathBuf, String>,
+        entry: Option<&str>,
+        remote: Option<&String>,
+    ) -> BTreeMap<PathBuf, String> {
+        let mut extra_files = extra_files.clone();
+        if let Some(entry) = entry {
+            let now = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+            let mut content = match remote {
+                Some(remote) => format!("{}\n", remote.trim_end()),
+                None => "# Changelog\n\n".to_owned(),
+            };
+            content += &format!("- {}: {}\n", &now[..10], entry);
+            extra_files.insert(CHANGELOG.into(), content);
+        }
+        extra_files
+    }
+
+    fn files(
+        filename: &str,
+        code: &str,
+        lockfile_name: &str,
+        lockfile: Option<&str>,
+        extra_files: &BTreeMap<PathBuf, String>,
+    ) -> serde_json::Value {
+        let mut files = serde_json::Map::new();
+        files.insert(filename.to_owned(), json!({ "content": code }));
+        if let Some(lockfile) = lockfile {
+            files.insert(lockfile_name.to_owned(), json!({ "content": lockfile }));
+        }
+        for (path, content) in extra_files {
+            files.insert(self::filename(path), json!({ "content": content }));
+        }
+        files.into()
+    }
+
     enum State<'a> {
         UpToDate,
-        Forward(&'a str, String, String),
+        Forward(
+            &'a str,
+            String,
+            String,
+            String,
+            Option<String>,
+            BTreeMap<PathBuf, String>,
+            Option<&'a str>,
+        ),
         NotExist,
     }
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct PushOutcome {
+    pub(crate) html_url: String,
+    pub(crate) raw_urls: IndexMap<String, String>,
+    pub(crate) revision: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PushResponse {
+    id: String,
+    html_url: String,
+    #[serde(default)]
+    files: IndexMap<String, PushResponseFile>,
+    #[serde(default)]
+    history: Vec<PushResponseHistory>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PushResponseFile {
+    raw_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct PushResponseHistory {
+    version: String,
+}
+
+impl From<PushResponse> for PushOutcome {
+    fn from(res: PushResponse) -> Self {
+        Self {
+            html_url: res.html_url,
+            raw_urls: res
+                .files
+                .into_iter()
+                .map(|(name, file)| (name, file.raw_url))
+                .collect(),
+            revision: res.history.into_iter().next().map(|h| h.version),
+        }
+    }
+}
+
+/// Recreates the gist as public or secret, since the API cannot change the visibility of an
+/// existing gist. The files and the description are copied. Comments, stars, forks, and the
+/// revisions are not.
+///
+/// Returns the ID of the new gist, or `None` if the gist already has the visibility or `dry_run`
+/// is enabled.
+pub(crate) fn republish(
+    github_token: &str,
+    gist_id: &str,
+    public: bool,
+    delete_old: bool,
+    dry_run: bool,
+) -> anyhow::Result<Option<(String, PushOutcome)>> {
+    let visibility = if public { "public" } else { "secret" };
+
+    let old = get(gist_id, None, DEFAULT_MAX_SIZE)?;
+    if old.public == public {
+        info_event!("`{}` is already {}", gist_id, visibility);
+        return Ok(None);
+    }
+
+    let url = GITHUB_API_URL.join("gists").unwrap();
+    if dry_run {
+        request("POST", url.as_ref(), true);
+        if delete_old {
+            request("DELETE", gists_url().join(gist_id)?.as_ref(), true);
+        }
+        return Ok(None);
+    }
+
+    let files = old
+        .files
+        .values()
+        .map(|file| (file.filename.clone(), json!({ "content": file.content })))
+        .collect::<serde_json::Map<_, _>>();
+    let payload = json!({
+        "files": files,
+        "description": old.description.unwrap_or_default(),
+        "public": public,
+    });
+
+    request("POST", url.as_ref(), false);
+    let res = crate::http::request("POST", url.as_ref())
+        .set("Authorization", &format!("token {}", github_token))
+        .send_json(payload);
+    crate::http::raise_synthetic_error(&res)?;
+    response(&res);
+    ensure!(res.status() == 201, "expected 201");
+    let res = parse_response::<PushResponse>(res)?;
+    let new_id = res.id.clone();
+    let outcome = PushOutcome::from(res);
+    info_event!("Created `{}` as a {} gist", new_id, visibility);
+    warn_event!(
+        "The URL changes from {} to {}. Comments, stars, forks, and revisions are not carried over",
+        old.html_url,
+        outcome.html_url,
+    );
+
+    if delete_old {
+        let url = gists_url().join(gist_id)?;
+        request("DELETE", url.as_ref(), false);
+        let res = crate::http::request("DELETE", url.as_ref())
+            .set("Authorization", &format!("token {}", github_token))
+            .call();
+        crate::http::raise_synthetic_error(&res)?;
+        response(&res);
+        ensure!(res.status() == 204, "expected 204");
+        info_event!("Deleted `{}`", gist_id);
+    } else {
+        info_event!(
+            "`{}` is left as it is. Delete it on GitHub or use `--delete-old`",
+            gist_id,
+        );
+    }
+    Ok(Some((new_id, outcome)))
+}
+
+pub(crate) fn list_comments(gist_id: &str) -> anyhow::Result<Vec<GistComment>> {
+    let url = gists_url().join(&format!("{}/comments", gist_id))?;
+    Pages::new(url, None).collect()
+}
+
+pub(crate) fn post_comment(
+    github_token: &str,
+    gist_id: &str,
+    body: &str,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let url = gists_url().join(&format!("{}/comments", gist_id))?;
+
+    if dry_run {
+        request("POST", url.as_ref(), true);
+        return Ok(());
+    }
+
+    request("POST", url.as_ref(), false);
+    let res = crate::http::request("POST", url.as_ref())
+        .set("Authorization", &format!("token {}", github_token))
+        .send_json(json!({ "body": body }));
+    crate::http::raise_synthetic_error(&res)?;
+    response(&res);
+    ensure!(res.status() == 201, "expected 201");
+    let GistComment { id, .. } = parse_response(res)?;
+    info_event!("Created comment `{}` on `{}`", id, gist_id);
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct GistComment {
+    pub(crate) id: u64,
+    pub(crate) user: Option<GistCommentUser>,
+    #[serde(default)]
+    pub(crate) created_at: String,
+    #[serde(default)]
+    pub(crate) body: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct GistCommentUser {
+    pub(crate) login: String,
+}
+
+/// Lists the gists of the authenticated user, most recently updated first.
+///
+/// The pages are retrieved lazily, so `.take(n)` stops requesting once `n` gists are yielded.
+pub(crate) fn list(
+    github_token: &str,
+    since: Option<SystemTime>,
+) -> anyhow::Result<Pages<GistSummary>> {
+    let mut url = GITHUB_API_URL.join("gists")?;
+    if let Some(since) = since {
+        let since = humantime::format_rfc3339_seconds(since).to_string();
+        url.query_pairs_mut().append_pair("since", &since);
+    }
+    Ok(Pages::new(url, Some(github_token)))
+}
 
-    #[derive(Deserialize, Debug)]
-    struct CreateGist {
-        id: String,
+#[derive(Deserialize, Debug)]
+pub(crate) struct GistSummary {
+    pub(crate) id: String,
+    pub(crate) description: Option<String>,
+    #[serde(default)]
+    pub(crate) public: bool,
+    #[serde(default)]
+    pub(crate) updated_at: String,
+    #[serde(default)]
+    pub(crate) files: IndexMap<String, IgnoredAny>,
+}
+
+/// Items of a paginated endpoint, following the `rel="next"` URLs in the `Link` headers.
+pub(crate) struct Pages<T> {
+    next: Option<Url>,
+    github_token: Option<String>,
+    items: vec::IntoIter<T>,
+}
+
+impl<T: DeserializeOwned> Pages<T> {
+    fn new(mut url: Url, github_token: Option<&str>) -> Self {
+        url.query_pairs_mut().append_pair("per_page", "100");
+        Self {
+            next: Some(url),
+            github_token: github_token.map(ToOwned::to_owned),
+            items: vec![].into_iter(),
+        }
     }
 
-    #[derive(Deserialize, Debug)]
-    struct Gist {
-        files: IndexMap<String, GistFile>,
+    fn fetch(&mut self, url: &Url) -> anyhow::Result<Vec<T>> {
+        request("GET", url.as_ref(), false);
+        let mut req = crate::http::request("GET", url.as_ref());
+        if let Some(github_token) = &self.github_token {
+            req.set("Authorization", &format!("token {}", github_token));
+        }
+        let res = req.call();
+        crate::http::raise_synthetic_error(&res)?;
+        response(&res);
+        ensure!(res.status() == 200, "expected 200");
+
+        self.next = res.header("Link").and_then(next_link);
+        parse_response(res)
     }
+}
+
+impl<T: DeserializeOwned> Iterator for Pages<T> {
+    type Item = anyhow::Result<T>;
 
-    #[derive(Deserialize, Debug)]
-    struct GistFile {
-        filename: String,
-        truncated: bool,
-        content: String,
+    fn next(&mut self) -> Option<anyhow::Result<T>> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+            let url = self.next.take()?;
+            match self.fetch(&url) {
+                Ok(items) => self.items = items.into_iter(),
+                Err(err) => return Some(Err(err)),
+            }
+        }
     }
 }
 
+/// Extracts the `rel="next"` URL from a `Link` header.
+///
+/// `<https://api.github.com/gists?page=2>; rel="next", <https://api.github.com/gists?page=5>; rel="last"`
+fn next_link(link: &str) -> Option<Url> {
+    link.split(',').find_map(|link| {
+        let mut params = link.split(';').map(str::trim);
+        let url = params.next()?.strip_prefix('<')?.strip_suffix('>')?;
+        if params.any(|param| param == r#"rel="next""#) {
+            url.parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Renders Markdown as plain text for terminals.
+pub(crate) fn render_markdown(markdown: &str) -> String {
+    let mut rendered = "".to_owned();
+    let mut list_depth = 0;
+
+    for event in Parser::new_ext(markdown, Options::all()) {
+        match event {
+            Event::Start(Tag::Item) => {
+                rendered += &"  ".repeat(list_depth.max(1) - 1);
+                rendered += "- ";
+            }
+            Event::Start(Tag::List(_)) => list_depth += 1,
+            Event::End(Tag::List(_)) => list_depth -= 1,
+            Event::End(Tag::Paragraph) | Event::End(Tag::Heading(_)) => rendered += "\n\n",
+            Event::End(Tag::CodeBlock(_)) => rendered += "\n",
+            Event::End(Tag::Item) | Event::SoftBreak | Event::HardBreak | Event::Rule => {
+                rendered += "\n"
+            }
+            Event::Text(text) | Event::Html(text) => rendered += &text,
+            Event::Code(code) => rendered += &format!("`{}`", code),
+            _ => {}
+        }
+    }
+
+    rendered.trim_end().to_owned()
+}
+
 pub(crate) struct PushOptions<'a> {
     pub(crate) github_token: &'a str,
-    pub(crate) gist_id: btree_map::Entry<'a, String, String>,
+    pub(crate) gist_id: btree_map::Entry<'a, String, GistIdEntry>,
     pub(crate) code: &'a str,
     pub(crate) workspace_root: &'a Path,
     pub(crate) package: &'a str,
     pub(crate) set_upstream: bool,
     pub(crate) private: bool,
     pub(crate) description: Option<&'a str>,
+    /// Used when `description` is `None` and the gist has no description.
+    pub(crate) auto_description: Option<&'a str>,
+    /// The subset of the workspace's `Cargo.lock` for the package.
+    pub(crate) lockfile: Option<&'a str>,
+    /// `package.metadata.bikecase.gist.extra-files` and their contents.
+    pub(crate) extra_files: &'a BTreeMap<PathBuf, String>,
+    /// An entry to append to `CHANGELOG.md` of the gist, which is created if missing.
+    pub(crate) changelog: Option<&'a str>,
     pub(crate) dry_run: bool,
-    pub(crate) str_width: fn(&str) -> usize,
 }
 
-static USER_AGENT: &str = "bikecase <https://github.com/qryxip/bikecase>";
+/// The name of the gist file for `path` relative to the package, with `/` as `__`.
+fn filename(path: &Path) -> String {
+    path.iter().map(|s| s.to_string_lossy()).join("__")
+}
+
+fn request(method: &'static str, url: &str, dry_run: bool) {
+    event::emit(event::Event::HttpRequest {
+        method,
+        url: url.to_owned(),
+        dry_run,
+    });
+}
 
-fn raise_synthetic_error(res: &Response) -> anyhow::Result<()> {
-    if let Some(err) = res.synthetic_error() {
-        let mut err = err as &dyn std::error::Error;
-        let mut displays = vec![err.to_string()];
-        while let Some(source) = err.source() {
-            displays.push(source.to_string());
-            err = source;
+fn response(res: &Response) {
+    event::emit(event::Event::HttpResponse {
+        status: res.status(),
+        status_text: res.status_text().to_owned(),
+    });
+}
+
+/// Parses the body of `res`, quoting the beginning of it on failure.
+fn parse_response<T: DeserializeOwned>(res: Response) -> anyhow::Result<T> {
+    const MAX_CHARS: usize = 200;
+
+    let body = res.into_string()?;
+    serde_json::from_str(&body).with_context(|| {
+        let mut snippet = body.chars().take(MAX_CHARS).collect::<String>();
+        if body.chars().nth(MAX_CHARS).is_some() {
+            snippet += "…";
         }
-        let mut displays = displays.into_iter().rev();
-        let cause = anyhow!("{}", displays.next().unwrap());
-        return Err(displays.fold(cause, |err, display| err.context(display)));
-    }
-    Ok(())
+        format!("failed to parse the response: {}", snippet)
+    })
+}
+
+fn diff(name: &str, orig: &str, edit: &str) {
+    event::emit(event::Event::Diff {
+        name: name.to_owned(),
+        orig: orig.to_owned(),
+        edit: edit.to_owned(),
+    });
+}
+
+/// `https://api.github.com/`. Can be overridden with `$BIKECASE_GITHUB_API_URL` for testing.
+static GITHUB_API_URL: Lazy<Url> = Lazy::new(|| {
+    env::var("BIKECASE_GITHUB_API_URL")
+        .ok()
+        .and_then(|url| url.parse().ok())
+        .unwrap_or_else(|| "https://api.github.com/".parse().unwrap())
+});
+
+fn gists_url() -> Url {
+    GITHUB_API_URL.join("gists/").unwrap()
 }
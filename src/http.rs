@@ -0,0 +1,80 @@
+//! The HTTP client for the GitHub API and the crates.io index.
+//!
+//! Requests made in a process share one [`Agent`], so that the connections are kept alive between
+//! the requests of e.g. `gist clone-all`.
+
+use anyhow::anyhow;
+use log::warn;
+use once_cell::sync::Lazy;
+use ureq::{Agent, Proxy, Request, Response};
+
+use std::env;
+use std::sync::RwLock;
+
+static AGENT: Lazy<Agent> = Lazy::new(|| Agent::new().build());
+
+/// `$HTTPS_PROXY`, used for `https://` URLs.
+static HTTPS_PROXY: Lazy<Option<Proxy>> = Lazy::new(|| {
+    let proxy = ["HTTPS_PROXY", "https_proxy"]
+        .iter()
+        .flat_map(env::var)
+        .find(|p| !p.is_empty())?;
+    match Proxy::new(&proxy) {
+        Ok(proxy) => Some(proxy),
+        Err(err) => {
+            warn!("Ignoring `$HTTPS_PROXY`: {}", err);
+            None
+        }
+    }
+});
+
+static USER_AGENT_SUFFIX: RwLock<Option<String>> = RwLock::new(None);
+
+static USER_AGENT: &str = "bikecase <https://github.com/qryxip/bikecase>";
+
+const CONNECT_TIMEOUT_MILLIS: u64 = 10_000;
+const READ_TIMEOUT_MILLIS: u64 = 60_000;
+const WRITE_TIMEOUT_MILLIS: u64 = 60_000;
+
+/// Appends `suffix` to `User-Agent` of the requests made after this. Set from `user-agent-suffix`
+/// in the config.
+pub(crate) fn set_user_agent_suffix(suffix: Option<&str>) {
+    *USER_AGENT_SUFFIX.write().unwrap_or_else(|e| e.into_inner()) = suffix.map(ToOwned::to_owned);
+}
+
+fn user_agent() -> String {
+    match &*USER_AGENT_SUFFIX.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(suffix) => format!("{} {}", USER_AGENT, suffix),
+        None => USER_AGENT.to_owned(),
+    }
+}
+
+/// Starts a request with `User-Agent`, the timeouts, and the proxy set.
+pub(crate) fn request(method: &str, url: &str) -> Request {
+    let mut req = AGENT.request(method, url);
+    req.set("User-Agent", &user_agent())
+        .timeout_connect(CONNECT_TIMEOUT_MILLIS)
+        .timeout_read(READ_TIMEOUT_MILLIS)
+        .timeout_write(WRITE_TIMEOUT_MILLIS);
+    if let (true, Some(proxy)) = (url.starts_with("https://"), &*HTTPS_PROXY) {
+        req.set_proxy(proxy.clone());
+    }
+    req
+}
+
+/// Converts the error that `ureq` stores in `res` into an `anyhow::Error` with the sources as
+/// contexts.
+pub(crate) fn raise_synthetic_error(res: &Response) -> anyhow::Result<()> {
+    if let Some(err) = res.synthetic_error() {
+        let mut err = err as &dyn std::error::Error;
+        let mut displays = vec![err.to_string()];
+        while let Some(source) = err.source() {
+            displays.push(source.to_string());
+            err = source;
+        }
+        let mut displays = displays.into_iter().rev();
+        let cause = anyhow!("{}", displays.next().unwrap());
+        return Err(displays.fold(cause, |err, display| err.context(display)));
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use anyhow::{bail, Context as _};
+use cargo_metadata::{DependencyKind, Metadata, Node, Package, PackageId, Target};
+use serde_json::json;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+pub(crate) fn vscode_settings(prev: Option<&str>, manifest_path: &Path) -> anyhow::Result<String> {
+    let manifest_path = manifest_path
+        .to_str()
+        .with_context(|| format!("{:?} is not valid UTF-8 path", manifest_path))?;
+
+    let mut settings = match prev {
+        Some(prev) => serde_json::from_str::<serde_json::Value>(prev).with_context(|| {
+            "failed to parse the existing settings (comments are not supported)"
+        })?,
+        None => json!({}),
+    };
+
+    let settings_obj = match settings.as_object_mut() {
+        Some(settings_obj) => settings_obj,
+        None => bail!("the settings must be a JSON object"),
+    };
+
+    let linked_projects = settings_obj
+        .entry("rust-analyzer.linkedProjects")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .with_context(|| "`rust-analyzer.linkedProjects` must be an array")?;
+
+    if !linked_projects.iter().any(|p| p == manifest_path) {
+        linked_projects.push(manifest_path.into());
+    }
+
+    let mut settings = serde_json::to_string_pretty(&settings)?;
+    settings += "\n";
+    Ok(settings)
+}
+
+/// Generates a `rust-project.json` with the targets of `member` and the libraries they depend on,
+/// for editors that do not load Cargo workspaces.
+///
+/// `metadata` must have `resolve`. Build scripts are not run, and procedural macros are not
+/// expanded.
+pub(crate) fn rust_project(
+    metadata: &Metadata,
+    member: &Package,
+    sysroot: Option<&Path>,
+) -> anyhow::Result<String> {
+    let resolve = metadata
+        .resolve
+        .as_ref()
+        .with_context(|| "`resolve` is missing in the metadata")?;
+
+    let mut project = RustProject {
+        metadata,
+        packages: metadata.packages.iter().map(|p| (&p.id, p)).collect(),
+        nodes: resolve.nodes.iter().map(|n| (&n.id, n)).collect(),
+        libs: HashMap::new(),
+        crates: vec![],
+    };
+
+    let lib = project.lib(&member.id)?;
+    for target in &member.targets {
+        if is_lib(target) {
+            continue;
+        }
+        let dev = !target.kind.iter().any(|k| k == "bin");
+        let mut deps = project.deps(&member.id, dev)?;
+        if let Some(lib) = lib {
+            deps.insert(
+                0,
+                json!({ "crate": lib, "name": target_crate_name(&member.targets) }),
+            );
+        }
+        project.push(member, target, deps);
+    }
+
+    let mut rust_project = json!({ "crates": project.crates });
+    if let Some(sysroot) = sysroot {
+        rust_project["sysroot"] = json!(sysroot);
+    }
+    let mut rust_project = serde_json::to_string_pretty(&rust_project)?;
+    rust_project += "\n";
+    return Ok(rust_project);
+
+    struct RustProject<'a> {
+        metadata: &'a Metadata,
+        packages: HashMap<&'a PackageId, &'a Package>,
+        nodes: HashMap<&'a PackageId, &'a Node>,
+        /// Indices of the library crates in `crates`.
+        libs: HashMap<&'a PackageId, Option<usize>>,
+        crates: Vec<serde_json::Value>,
+    }
+
+    impl<'a> RustProject<'a> {
+        /// Adds the library of `id` after its dependencies, and returns its index.
+        fn lib(&mut self, id: &'a PackageId) -> anyhow::Result<Option<usize>> {
+            if let Some(&index) = self.libs.get(id) {
+                return Ok(index);
+            }
+            let package = self.package(id)?;
+            let index = match package.targets.iter().find(|t| is_lib(t)) {
+                Some(target) => {
+                    let deps = self.deps(id, false)?;
+                    Some(self.push(package, target, deps))
+                }
+                None => None,
+            };
+            self.libs.insert(id, index);
+            Ok(index)
+        }
+
+        /// Adds the libraries that `id` depends on, and returns them as `deps` of
+        /// `rust-project.json`.
+        fn deps(&mut self, id: &'a PackageId, dev: bool) -> anyhow::Result<Vec<serde_json::Value>> {
+            let node = *self
+                .nodes
+                .get(id)
+                .with_context(|| format!("`{}` is not in `resolve`", id))?;
+            let mut deps = vec![];
+            for dep in &node.deps {
+                let is_used = dep.dep_kinds.is_empty()
+                    || dep.dep_kinds.iter().any(|k| match k.kind {
+                        DependencyKind::Normal => true,
+                        DependencyKind::Development => dev,
+                        _ => false,
+                    });
+                if is_used {
+                    if let Some(index) = self.lib(&dep.pkg)? {
+                        deps.push(json!({ "crate": index, "name": dep.name }));
+                    }
+                }
+            }
+            Ok(deps)
+        }
+
+        fn push(
+            &mut self,
+            package: &Package,
+            target: &Target,
+            deps: Vec<serde_json::Value>,
+        ) -> usize {
+            let cfg = self
+                .nodes
+                .get(&package.id)
+                .into_iter()
+                .flat_map(|n| &n.features)
+                .map(|f| format!("feature={:?}", f))
+                .collect::<Vec<_>>();
+            self.crates.push(json!({
+                "display_name": target.name,
+                "root_module": target.src_path,
+                "edition": target.edition,
+                "deps": deps,
+                "cfg": cfg,
+                "is_workspace_member": self.metadata.workspace_members.contains(&package.id),
+                "is_proc_macro": target.kind.iter().any(|k| k == "proc-macro"),
+            }));
+            self.crates.len() - 1
+        }
+
+        fn package(&self, id: &PackageId) -> anyhow::Result<&'a Package> {
+            self.packages
+                .get(id)
+                .copied()
+                .with_context(|| format!("`{}` is not in `packages`", id))
+        }
+    }
+
+    fn is_lib(target: &Target) -> bool {
+        target
+            .kind
+            .iter()
+            .any(|k| ["lib", "rlib", "dylib", "proc-macro"].contains(&&**k))
+    }
+
+    fn target_crate_name(targets: &[Target]) -> String {
+        targets
+            .iter()
+            .find(|t| is_lib(t))
+            .map(|t| t.name.replace('-', "_"))
+            .unwrap_or_default()
+    }
+}
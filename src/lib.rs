@@ -1,35 +1,72 @@
 #![warn(rust_2018_idioms)]
 
+mod artifact;
+mod build_info;
+mod cache;
+mod cargo_args;
 mod config;
+mod deps;
+#[macro_use]
+mod event;
 mod fs;
 mod gist;
+mod http;
+mod ide;
+mod lockfile;
 mod logger;
+mod msrv;
+mod naming;
 mod process;
+mod record;
 mod rust;
+mod shim;
+mod snapshot;
+mod source;
+mod spec;
+mod template;
+mod trust;
+mod ui;
+mod update;
+mod watch;
 mod workspace;
 
-use crate::config::{BikecaseConfig, BikecaseConfigWorkspace};
+pub use crate::config::ConfigCache;
+pub use crate::event::{set_event_sink, Event, EventSink, LogSink};
+pub use crate::shim::translate_cargo_script_args;
+pub use crate::source::{Script, ScriptSource};
+pub use crate::ui::str_width;
+
+use crate::build_info::BuildInfo;
+use crate::cache::RunState;
+use crate::cargo_args::CargoArgs;
+use crate::config::{BikecaseConfigProfile, PullPolicy};
 use crate::gist::PushOptions;
+use crate::snapshot::{Snapshot, SnapshotDiffKind};
+use crate::spec::PackageIdSpec;
+use crate::ui::{Cell, PrintOptions, Table};
 use crate::workspace::{MetadataExt as _, PackageExt as _};
 
-use anyhow::{anyhow, bail, Context as _};
-use cargo_metadata::Metadata;
+use anyhow::{bail, Context as _};
+use cargo_metadata::{Metadata, Package};
 use derivative::Derivative;
 use env_logger::fmt::WriteStyle;
-use ignore::WalkBuilder;
+use indexmap::IndexMap;
 use itertools::Itertools as _;
-use log::{info, warn};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
 use structopt::clap::AppSettings;
 use structopt::StructOpt;
 use strum::{EnumString, EnumVariantNames, IntoStaticStr, VariantNames as _};
 use termcolor::{BufferedStandardStream, ColorSpec, WriteColor as _};
-use unicode_width::UnicodeWidthStr;
 
-use std::convert::TryInto as _;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::io::{self, Read as _, Stdout, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicUsize};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
 pub fn exit_with_error(error: anyhow::Error, color: crate::ColorChoice) -> ! {
     let mut color = termcolor::ColorChoice::from(color);
@@ -40,7 +77,7 @@ pub fn exit_with_error(error: anyhow::Error, color: crate::ColorChoice) -> ! {
 
     let _ = stderr.set_color(
         ColorSpec::new()
-            .set_fg(Some(termcolor::Color::Red))
+            .set_fg(Some(ui::theme().error))
             .set_bold(true)
             .set_reset(false),
     );
@@ -53,13 +90,299 @@ pub fn exit_with_error(error: anyhow::Error, color: crate::ColorChoice) -> ! {
     }
 
     let _ = stderr.flush();
+    if error.is::<crate::process::TimedOut>() {
+        std::process::exit(124);
+    }
     std::process::exit(101);
 }
 
-pub fn bikecase<W: Sized, I: FnOnce() -> io::Result<String>, P: Sized>(
+pub fn bikecase<
+    W: Write,
+    I: FnOnce() -> io::Result<String>,
+    P: FnMut(&str) -> io::Result<String>,
+>(
+    opt: Bikecase,
+    ctx: Context<W, I, P>,
+) -> anyhow::Result<()> {
+    let result = run(opt, ctx);
+    event::emit(Event::Finished {
+        success: result.is_ok(),
+    });
+    result
+}
+
+fn run<W: Write, I: FnOnce() -> io::Result<String>, P: FnMut(&str) -> io::Result<String>>(
     opt: Bikecase,
     ctx: Context<W, I, P>,
 ) -> anyhow::Result<()> {
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        mut stdout,
+        read_input,
+        mut read_password,
+        init_logger,
+        init_json_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    if opt.json_logs {
+        init_json_logger();
+    } else {
+        init_logger(opt.color);
+    }
+    ui::set_ascii(opt.ascii);
+
+    if opt.print_config {
+        let config = config_cache.load_or_create_with(
+            &opt.config,
+            home_dir.as_deref(),
+            data_local_dir.as_deref(),
+            true,
+            opt.no_auto_create,
+        )?;
+        write!(stdout, "{}", config.effective(home_dir.as_deref())?)?;
+        return stdout.flush().map_err(Into::into);
+    }
+
+    if opt.pipe {
+        return run_pipeline(
+            opt,
+            cwd,
+            home_dir,
+            data_local_dir,
+            read_input,
+            &mut read_password,
+            &mut config_cache,
+        );
+    }
+
+    let watch = opt.watch || opt.restart_on_change;
+    let watched_script = match ScriptSource::parse(opt.file.as_deref(), &cwd)? {
+        ScriptSource::File(path) => Some(path),
+        _ if watch => bail!("`--watch` requires FILE to be a local file"),
+        _ => None,
+    };
+    let grace_period = if opt.restart_on_change {
+        Some(opt.grace_period.unwrap_or(Duration::from_secs(5)))
+    } else {
+        None
+    };
+    let timeout = opt.timeout;
+    let limits = crate::process::Limits {
+        memory: opt.max_memory,
+        cpu_seconds: opt.max_cpu_seconds,
+    };
+    let prefix_output = opt.prefix_output;
+    let timestamps = opt.timestamps;
+    let capture = opt.capture.clone().map(|p| p.map(|p| cwd.join(p)));
+    let capture_stderr = opt.capture_stderr;
+
+    let mut read_input = Some(read_input);
+    let mut package_dir = None::<PathBuf>;
+    let mut snapshot = None::<watch::Snapshot>;
+    loop {
+        let result = prepare(
+            opt.clone(),
+            cwd.clone(),
+            home_dir.clone(),
+            data_local_dir.clone(),
+            read_input.take(),
+            &mut read_password,
+            &mut config_cache,
+            &mut stdout,
+        );
+        let result = result.and_then(|prepared| {
+            let Prepared {
+                expr,
+                package,
+                package_dir: dir,
+            } = match prepared {
+                Some(prepared) => prepared,
+                None => return Ok(None),
+            };
+            package_dir = Some(dir.clone());
+
+            let watched = watched_script
+                .clone()
+                .filter(|_| watch)
+                .map(|script| watch::Watched::new(script, Some(dir.clone())));
+            // Taken before running, so that changes made while the program runs are not missed.
+            snapshot = watched.as_ref().map(watch::Watched::snapshot);
+            let has_changed = || match (&watched, &snapshot) {
+                (Some(watched), Some(snapshot)) => watched.has_changed(snapshot),
+                _ => false,
+            };
+
+            let prefix_output = if prefix_output {
+                Some(crate::process::PrefixOutput {
+                    name: &package,
+                    timestamps,
+                })
+            } else {
+                None
+            };
+            let capture = capture.clone().map(|path| {
+                path.unwrap_or_else(|| {
+                    // `:` is not allowed in file names on Windows.
+                    let now = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+                    dir.join("captures")
+                        .join(format!("{}.log", now.replace(':', "-")))
+                })
+            });
+            let result = crate::process::run_child(
+                expr,
+                timeout,
+                limits,
+                prefix_output,
+                capture.as_deref().map(|path| crate::process::Capture {
+                    path,
+                    stderr: capture_stderr,
+                }),
+                watched.as_ref().map(|_| crate::process::Interrupt {
+                    poll: &has_changed,
+                    interval: watch::INTERVAL,
+                    grace_period,
+                }),
+            );
+            if let Some(capture) = &capture {
+                info_event!("Wrote the output to {}", capture.display());
+            }
+            result.map(|()| Some(()))
+        });
+
+        let script = match (&watched_script, watch) {
+            (Some(script), true) => script,
+            _ => return result.map(drop),
+        };
+        let watched = watch::Watched::new(script.clone(), package_dir.clone());
+        match result {
+            Err(err) if err.is::<crate::process::Interrupted>() => {
+                info!("Detected changes. Stopped the program");
+                watch::settle();
+            }
+            result => {
+                if let Err(err) = result {
+                    error!("{:#}", err);
+                }
+                let snapshot = snapshot.take().unwrap_or_else(|| watched.snapshot());
+                info!("Watching {} for changes", script.display());
+                watched.wait_for_change(&snapshot);
+            }
+        }
+        if !script.exists() {
+            info!("{} has been removed. Stopping", script.display());
+            return Ok(());
+        }
+    }
+}
+
+/// Prepares the stages of `--pipe`, then runs them connected stdout to stdin.
+fn run_pipeline(
+    opt: Bikecase,
+    cwd: PathBuf,
+    home_dir: Option<PathBuf>,
+    data_local_dir: Option<PathBuf>,
+    read_input: impl FnOnce() -> io::Result<String>,
+    read_tty: &mut impl FnMut(&str) -> io::Result<String>,
+    config_cache: &mut ConfigCache,
+) -> anyhow::Result<()> {
+    let timeout = opt.timeout;
+    let limits = crate::process::Limits {
+        memory: opt.max_memory,
+        cpu_seconds: opt.max_cpu_seconds,
+    };
+    let stages = opt
+        .file
+        .iter()
+        .map(|file| file.as_os_str())
+        .chain(opt.args.iter().map(OsString::as_os_str))
+        .map(parse_pipe_stage)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut read_input = Some(read_input);
+    let mut pipeline = None::<duct::Expression>;
+    for (i, (file, args)) in stages.into_iter().enumerate() {
+        let opt = Bikecase {
+            file: Some(file),
+            args,
+            stdin_data: opt.stdin_data.clone().filter(|_| i == 0),
+            pipe: false,
+            ..opt.clone()
+        };
+        let prepared = prepare(
+            opt,
+            cwd.clone(),
+            home_dir.clone(),
+            data_local_dir.clone(),
+            read_input.take(),
+            read_tty,
+            config_cache,
+            &mut io::sink(),
+        )?;
+        if let Some(Prepared { expr, .. }) = prepared {
+            pipeline = Some(match pipeline {
+                Some(pipeline) => pipeline.pipe(expr),
+                None => expr,
+            });
+        }
+    }
+
+    if let Some(pipeline) = pipeline {
+        crate::process::run_child(pipeline, timeout, limits, None, None, None)?;
+    }
+    return Ok(());
+
+    /// `a.rs:--flag --name 'a b'` → (`a.rs`, [`--flag`, `--name`, `a b`])
+    ///
+    /// The arguments are split as the shell does. The first `.rs:` ends the path, so the
+    /// arguments may contain `.rs:`.
+    fn parse_pipe_stage(stage: &OsStr) -> anyhow::Result<(PathBuf, Vec<OsString>)> {
+        let stage = stage
+            .to_str()
+            .with_context(|| format!("{:?} is not valid UTF-8", stage))?;
+        Ok(match stage.find(".rs:") {
+            Some(i) => {
+                let args = shlex::split(&stage[i + 4..])
+                    .with_context(|| format!("could not split the arguments of {:?}", stage))?;
+                (
+                    stage[..i + 3].into(),
+                    args.into_iter().map(Into::into).collect(),
+                )
+            }
+            None => (stage.into(), vec![]),
+        })
+    }
+}
+
+struct Prepared {
+    expr: duct::Expression,
+    package: String,
+    package_dir: PathBuf,
+}
+
+/// Adds the script to the workspace and builds it. Returns the command to run the program (or
+/// `cargo test` for `test`), or `None` if `dry_run`, `--list-targets`, `--install`, or the mode is
+/// `build` or `check`.
+#[allow(clippy::too_many_arguments)]
+fn prepare(
+    opt: Bikecase,
+    cwd: PathBuf,
+    home_dir: Option<PathBuf>,
+    data_local_dir: Option<PathBuf>,
+    read_input: Option<impl FnOnce() -> io::Result<String>>,
+    read_tty: &mut impl FnMut(&str) -> io::Result<String>,
+    config_cache: &mut ConfigCache,
+    stdout: &mut impl Write,
+) -> anyhow::Result<Option<Prepared>> {
+    let recorded_args = opt
+        .record
+        .as_ref()
+        .map(|_| serde_json::to_value(&opt))
+        .transpose()?;
+
     let Bikecase {
         jobs,
         release,
@@ -73,45 +396,157 @@ pub fn bikecase<W: Sized, I: FnOnce() -> io::Result<String>, P: Sized>(
         frozen,
         locked,
         offline,
+        ignore_rust_version,
+        mode,
+        miri,
+        miri_flags,
+        test,
+        check,
         bin,
+        example,
+        list_targets,
+        package_name,
         manifest_path,
         config,
         color,
+        json_logs: _,
+        ascii: _,
+        timeout: _,
+        max_memory: _,
+        max_cpu_seconds: _,
+        prefix_output,
+        timestamps: _,
+        capture: _,
+        capture_stderr: _,
+        locked_deps_report,
+        deny,
+        arg_file,
+        record,
+        record_args,
+        trust,
+        no_input,
+        skip_build,
+        install,
+        watch: _,
+        restart_on_change: _,
+        grace_period: _,
+        dry_run,
+        no_auto_create,
+        print_config: _,
+        pipe: _,
+        stdin_data,
         file,
         args,
     } = opt;
 
-    let Context {
-        cwd,
-        home_dir,
-        data_local_dir,
-        read_input,
-        init_logger,
-        ..
-    } = ctx;
-
-    init_logger(color);
-
-    let script = file
-        .map(|p| crate::fs::read(cwd.join(p.strip_prefix(".").unwrap_or(&p))))
-        .unwrap_or_else(|| read_input().map_err(Into::into))?;
+    let mut read_input = read_input;
+    let mut source = ScriptSource::parse(file.as_deref(), &cwd)?;
+    if let ScriptSource::Gist { id, .. } = &mut source {
+        *id = config_cache
+            .load_or_create_with(
+                &config,
+                home_dir.as_deref(),
+                data_local_dir.as_deref(),
+                dry_run,
+                no_auto_create,
+            )?
+            .resolve_gist_id(id, home_dir.as_deref())?;
+    }
+    let Script {
+        code: script,
+        path: file,
+        file_name,
+    } = source.resolve(&mut read_input)?;
+
+    if !dry_run {
+        trust::check(
+            &source,
+            &script,
+            data_local_dir.as_deref(),
+            trust,
+            no_input,
+            read_tty,
+        )?;
+    }
 
-    let cargo_toml =
-        rust::extract_cargo_lang_code(&script, || "could not find the `cargo` code block")?;
+    let (stdin_bytes, stdin_path) = match stdin_data {
+        Some(stdin_data) if stdin_data == Path::new("-") => {
+            let read_input = read_input.with_context(|| {
+                "`--stdin-data -` cannot be used when the script is read from stdin"
+            })?;
+            (Some(read_input()?), None)
+        }
+        Some(stdin_data) => {
+            let stdin_data = cwd.join(stdin_data);
+            if !stdin_data.is_file() {
+                bail!("{} is not a file", stdin_data.display());
+            }
+            (None, Some(stdin_data))
+        }
+        None => (None, None),
+    };
 
-    let config = BikecaseConfig::load_or_create(
+    let script_dir = file
+        .as_ref()
+        .and_then(|p| p.parent())
+        .unwrap_or(&cwd)
+        .to_owned();
+
+    let arg_file = arg_file
+        .map(|arg_file| crate::fs::read(cwd.join(arg_file)))
+        .transpose()?;
+    let file_args = arg_file
+        .iter()
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter(|l| !l.is_empty())
+                .map(OsString::from)
+        })
+        .collect::<Vec<_>>();
+
+    let config = config_cache.load_or_create_with(
         &config,
         home_dir.as_deref(),
         data_local_dir.as_deref(),
-        false,
+        dry_run,
+        no_auto_create,
     )?;
 
+    if config.content().check_for_updates && !(offline || frozen) {
+        update::check(data_local_dir.as_deref());
+    }
+
+    let mode = if skip_build || install {
+        Mode::Run
+    } else if test {
+        Mode::Test
+    } else if check {
+        Mode::Check
+    } else {
+        mode.or(config.content().mode).unwrap_or(Mode::Run)
+    };
+
     let (workspace_root, manifest_path) = if let Some(manifest_path) = manifest_path {
         let manifest_path = cwd.join(manifest_path.strip_prefix(".").unwrap_or(&manifest_path));
         if !manifest_path.ends_with("Cargo.toml") {
             bail!("the manifest-path must be a path to a Cargo.toml file");
         }
-        let workspace_root = manifest_path.parent().expect("should not empty").to_owned();
+        let member_dir = manifest_path.parent().expect("should not empty");
+        // A member's manifest stands for the workspace it belongs to, as with `cargo`.
+        let workspace_root = if manifest_path.exists() {
+            workspace::workspace_root(&manifest_path, color, &cwd)?
+        } else {
+            member_dir.to_owned()
+        };
+        if workspace_root != member_dir {
+            info_event!(
+                "{} is a member of the workspace at {}",
+                manifest_path.display(),
+                workspace_root.display(),
+            );
+        }
+        let manifest_path = workspace_root.join("Cargo.toml");
         (workspace_root, manifest_path)
     } else if let Some(workspace_root) = &config.content().default_workspace {
         let workspace_root = PathBuf::from(workspace_root.expand(home_dir.as_deref()).into_owned());
@@ -124,82 +559,473 @@ pub fn bikecase<W: Sized, I: FnOnce() -> io::Result<String>, P: Sized>(
         );
     };
 
-    if !workspace_root.exists() {
-        workspace::create_workspace(workspace_root, false)?;
+    if let (Some(record), Some(recorded_args)) = (record, recorded_args) {
+        let workspace_manifest = workspace_root.join("Cargo.toml");
+        let bundle = record::Bundle {
+            info: record::BundleInfo::new(recorded_args, &workspace_root, record_args),
+            script: script.clone(),
+            arg_file: arg_file.filter(|_| record_args),
+            config: record::redact_config(config.content(), &workspace_root, home_dir.as_deref())?,
+            manifest: if workspace_manifest.exists() {
+                Some(crate::fs::read(workspace_manifest)?)
+            } else {
+                None
+            },
+        };
+        let record = cwd.join(record);
+        bundle.write(&record)?;
+        info!("Recorded to {}", record.display());
     }
 
-    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
-    workspace::raise_unless_virtual(&metadata.workspace_root)?;
-    let package_name =
-        workspace::add_member(&metadata, &cargo_toml, &script, bin.as_deref(), false)?;
-
-    let program = workspace::cargo_exe()?;
-    let mut program_args = vec![
-        "run".into(),
-        "-p".into(),
-        package_name.into(),
-        "--manifest-path".into(),
-        manifest_path.into_os_string(),
-    ];
+    // Miri builds are not cached, since they do not produce executables.
+    let cache_dir = data_local_dir
+        .map(|d| d.join("bikecase").join("cache"))
+        .filter(|_| !miri);
+    let run_state_key = cache::run_state_key(
+        &script,
+        file.as_deref(),
+        &manifest_path,
+        bin.as_deref(),
+        package_name.as_deref(),
+    );
 
-    macro_rules! add {
-        () => {};
-        ($(,)+ $($rest:tt)*) => {
-            add!($($rest)*)
-        };
-        ($var:ident => Flag($opt:literal) $($rest:tt)*) => {
-            if $var {
-                program_args.push($opt.into());
-            }
-            add!($($rest)*);
-        };
-        ($var:ident => Single($opt:literal, $f:expr) $($rest:tt)*) => {
-            if let Some(var) = $var {
-                program_args.push($opt.into());
-                program_args.push(apply($f, var));
-            }
-            add!($($rest)*);
-        };
-        ($var:ident => Multiple($opt:literal, $f:expr) $($rest:tt)*) => {
-            for var in $var {
-                program_args.push($opt.into());
-                program_args.push(apply($f, var));
+    let (package, member_manifest_path, bin_target, run_state) = if let Some(run_state) = cache_dir
+        .as_ref()
+        .and_then(|cache_dir| RunState::load(cache_dir, &run_state_key, &script))
+    {
+        (
+            run_state.package.clone(),
+            run_state.manifest_path.clone(),
+            run_state.bin_target.clone(),
+            Some(run_state),
+        )
+    } else {
+        let cargo_toml =
+            rust::extract_cargo_lang_code(&script, || "could not find the `cargo` code block")?;
+        let mut cargo_toml =
+            workspace::name_after_file(&workspace_root, &cargo_toml, file_name.as_deref())?;
+
+        let package_name = match (package_name, &file) {
+            (Some(package_name), _) => Some(package_name),
+            (None, Some(file)) if config.content().derive_package_names => {
+                let name = workspace::package_name(&cargo_toml)?;
+                let file = file.canonicalize().unwrap_or_else(|_| file.clone());
+                let hash = cache::sha256(&file.to_string_lossy());
+                Some(format!("{}-{}", name, &hash[..8]))
             }
-            add!($($rest)*);
+            (None, _) => None,
         };
-        ($var:ident => Occurrences($c:literal) $($rest:tt)*) => {
-            if $var > 0 {
-                let n = $var.try_into().unwrap_or_else(|_| usize::max_value());
-                program_args.push(format!("-{}", itertools::repeat_n($c, n).format("")).into());
+        if let Some(package_name) = package_name {
+            let mut doc = cargo_toml
+                .parse::<toml_edit::DocumentMut>()
+                .with_context(|| "failed to parse the manifest")?;
+            workspace::modify_package_name(&mut doc, &package_name)?;
+            cargo_toml = doc.to_string();
+        }
+
+        if config.should_create_workspace(&workspace_root)? {
+            workspace::create_workspace(
+                &workspace_root,
+                None,
+                None,
+                None,
+                config.content().profile_preset,
+                dry_run,
+            )?;
+            if dry_run {
+                info_event!(
+                    "[dry-run] Adding the script to {}",
+                    workspace_root.display()
+                );
+                return Ok(None);
             }
-            add!($($rest)*);
         }
+
+        let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+        workspace::raise_unless_virtual(&metadata.workspace_root)?;
+        let (package, member_manifest_path, bin_path, bin_target) = workspace::add_member(
+            &metadata,
+            &cargo_toml,
+            &script,
+            bin.as_deref(),
+            false,
+            dry_run,
+        )?;
+
+        if let (Some(cache_dir), false) = (&cache_dir, dry_run) {
+            let run_state = RunState {
+                package: package.clone(),
+                manifest_path: member_manifest_path.clone(),
+                bin_path,
+                bin_target: bin_target.clone(),
+                cargo_toml_sha256: cache::sha256(&cargo_toml),
+                executables: BTreeMap::new(),
+            };
+            run_state.save(cache_dir, &run_state_key)?;
+            (package, member_manifest_path, bin_target, Some(run_state))
+        } else {
+            (package, member_manifest_path, bin_target, None)
+        }
+    };
+
+    workspace::touch_default_member(
+        &workspace_root,
+        member_manifest_path
+            .parent()
+            .expect("should end with \"Cargo.toml\""),
+        dry_run,
+    )?;
+
+    if list_targets {
+        let metadata = workspace::cargo_metadata_no_deps(&member_manifest_path, color, &cwd)?;
+        let targets = metadata
+            .packages
+            .iter()
+            .find(|p| p.manifest_path == member_manifest_path)
+            .with_context(|| format!("could not find `{}` in the metadata", package))?
+            .targets
+            .iter()
+            .flat_map(|t| t.kind.iter().map(move |k| (k, &t.name)))
+            .filter(|(k, _)| *k == "bin" || *k == "example")
+            .sorted();
+        for (kind, name) in targets {
+            writeln!(stdout, "{} {}", kind, name)?;
+        }
+        stdout.flush()?;
+        return Ok(None);
+    }
+
+    if locked_deps_report || !deny.is_empty() {
+        let flags = [
+            (frozen, "--frozen"),
+            (locked, "--locked"),
+            (offline, "--offline"),
+        ]
+        .iter()
+        .filter(|&&(p, _)| p)
+        .map(|&(_, f)| f)
+        .collect::<Vec<_>>();
+        let metadata = workspace::cargo_metadata(&manifest_path, color, &flags, &cwd)?;
+        deps::report(&metadata, &package, &deny, offline || frozen)?;
     }
 
-    add! {
-        jobs                => Single("--jobs", |j| j.to_string().into()),
-        bin                 => Single("--bin", Into::into),
-        release             => Flag("--release"),
-        profile             => Single("--profile", Into::into),
-        features            => Multiple("--features", Into::into),
-        all_features        => Flag("--all-features"),
-        no_default_features => Flag("--no-default-features"),
-        target              => Single("--target", Into::into),
-        message_format      => Multiple("--message-format", Into::into),
-        verbose             => Occurrences('v'),
-        frozen              => Flag("--frozen"),
-        locked              => Flag("--locked"),
-        offline             => Flag("--offline"),
+    let release = release || (install && profile.is_none());
+    let package_defaults = config
+        .content()
+        .workspace(&workspace_root, home_dir.as_deref())
+        .and_then(|w| w.packages.get(&package));
+    let profile = profile.or_else(|| {
+        package_defaults
+            .and_then(|d| d.profile.clone())
+            .filter(|_| !release)
+    });
+    let features = package_defaults
+        .into_iter()
+        .flat_map(|d| d.features.iter().cloned())
+        .chain(features)
+        .collect::<Vec<_>>();
+    let target =
+        target.or_else(|| package_defaults.and_then(|d| d.target.as_ref().map(Into::into)));
+    let rustflags = package_defaults
+        .and_then(|d| d.rustflags.clone())
+        .filter(|_| env::var_os("RUSTFLAGS").is_none());
+
+    let build_key = cache::build_key(
+        profile
+            .as_deref()
+            .unwrap_or(if release { "release" } else { "dev" }),
+        target.as_deref(),
+        &features,
+        all_features,
+        no_default_features,
+        example.as_deref(),
+        rustflags
+            .clone()
+            .or_else(|| env::var("RUSTFLAGS").ok())
+            .as_deref(),
+        // Only for the cache, which a toolchain update invalidates.
+        cache_dir
+            .as_ref()
+            .filter(|_| !dry_run)
+            .and_then(|_| workspace::rustc_version(Some(&workspace_root)))
+            .as_deref(),
+    );
+    let (message_format, forward_messages, cargo_color) = if mode == Mode::Run && !miri {
+        let (message_format, forward_messages) = artifact::message_format(&message_format);
+        // stderr of `cargo build` is piped to be kept, so `auto` has to be decided here.
+        let cargo_color = match color {
+            crate::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => {
+                crate::ColorChoice::Always
+            }
+            crate::ColorChoice::Auto => crate::ColorChoice::Never,
+            color => color,
+        };
+        (vec![message_format], forward_messages, cargo_color)
+    } else {
+        let message_format = message_format.iter().map(|f| f.to_lowercase()).collect();
+        (message_format, false, color)
+    };
+    let bin = bin
+        .or_else(|| bin_target.clone())
+        .filter(|_| example.is_none());
+    let bin_name = bin.clone();
+
+    let args = file_args
+        .into_iter()
+        .chain(args)
+        .map(|arg| expand_placeholders(arg, &script_dir, &package))
+        .collect::<Vec<_>>();
+
+    let (subcommand, program_args) = if miri {
+        (
+            "miri run",
+            CargoArgs::with_toolchain("nightly", &["miri", "run"]),
+        )
+    } else {
+        let subcommand = match mode {
+            Mode::Run | Mode::Build => "build",
+            Mode::Check => "check",
+            Mode::Test => "test",
+        };
+        (subcommand, CargoArgs::new(subcommand))
+    };
+    let program_args = program_args
+        .package(&package, &manifest_path, cargo_color)
+        .options("--message-format", message_format)
+        .option("--jobs", jobs.map(|j| j.to_string()))
+        .option("--bin", bin)
+        .option("--example", example.clone())
+        .flag("--release", release)
+        .option("--profile", profile)
+        .options("--features", features)
+        .flag("--all-features", all_features)
+        .flag("--no-default-features", no_default_features)
+        .option("--target", target)
+        .occurrences('v', verbose)
+        .flag("--frozen", frozen)
+        .flag("--locked", locked)
+        .flag("--offline", offline)
+        .flag("--ignore-rust-version", ignore_rust_version);
+
+    if rustflags.is_some() {
+        info_event!("Setting `RUSTFLAGS` for `cargo {}`", subcommand);
     }
 
-    program_args.push("--".into());
-    program_args.extend(args);
+    let mut expr = if miri {
+        crate::process::run(
+            "rustup",
+            &["component", "add", "miri", "--toolchain", "nightly"],
+            dry_run,
+        )?;
+        // `$CARGO` may be the `cargo` of a toolchain, which does not accept `+nightly`.
+        let program_args = program_args.passthrough(args);
+        if dry_run {
+            crate::process::run("cargo", program_args, true)?;
+            return Ok(None);
+        }
+        let mut expr = crate::process::cmd("cargo", program_args);
+        if let Some(rustflags) = rustflags {
+            expr = expr.env("RUSTFLAGS", rustflags);
+        }
+        if let Some(miri_flags) = miri_flags {
+            info!("Setting `MIRIFLAGS`");
+            expr = expr.env("MIRIFLAGS", miri_flags);
+        }
+        expr
+    } else if mode == Mode::Test {
+        let program = workspace::cargo_exe()?;
+        let program_args = program_args.passthrough(args);
+        if dry_run {
+            crate::process::run(program, program_args, true)?;
+            return Ok(None);
+        }
+        let mut expr = crate::process::cmd(program, program_args);
+        if let Some(rustflags) = rustflags {
+            expr = expr.env("RUSTFLAGS", rustflags);
+        }
+        expr
+    } else {
+        let executable = if skip_build {
+            let executable = cache_dir
+                .as_ref()
+                .and_then(|cache_dir| {
+                    RunState::find_latest_executable(
+                        cache_dir,
+                        &member_manifest_path,
+                        bin_target.as_deref(),
+                        &build_key,
+                    )
+                })
+                .with_context(|| {
+                    format!(
+                        "`{}` has not been built with these options yet. Run without \
+                         `--skip-build` first",
+                        package,
+                    )
+                })?;
+            if dry_run {
+                info_event!("[dry-run] Running {}", executable.display());
+                return Ok(None);
+            }
+            if !install {
+                info_event!("Skipping the build. Running {}", executable.display());
+            }
+            executable
+        } else {
+            let program = workspace::cargo_exe()?;
+            if dry_run {
+                crate::process::run(program, program_args, true)?;
+                return Ok(None);
+            }
+
+            // `run_state` has executables only if it has been loaded, i.e. the script and the
+            // manifest are unchanged.
+            let cached_executable = run_state
+                .as_ref()
+                .filter(|_| mode == Mode::Run && !forward_messages && verbose == 0)
+                .and_then(|run_state| run_state.executables.get(&build_key))
+                .filter(|executable| {
+                    let member_dir = member_manifest_path
+                        .parent()
+                        .expect("should end with \"Cargo.toml\"");
+                    let inputs = [
+                        member_dir.join("Cargo.toml"),
+                        member_dir.join("build.rs"),
+                        member_dir.join("src"),
+                        member_dir.join("examples"),
+                        workspace_root.join("Cargo.toml"),
+                        workspace_root.join("Cargo.lock"),
+                        workspace_root.join(".cargo"),
+                        workspace_root.join("rust-toolchain"),
+                        workspace_root.join("rust-toolchain.toml"),
+                    ];
+                    let path_dependencies =
+                        workspace::path_dependency_dirs(&member_manifest_path, &workspace_root);
+                    cache::is_fresh(executable, &[&inputs[..], &path_dependencies].concat())
+                });
+            if let Some(executable) = cached_executable {
+                info!("Using the cached executable {}", executable.display());
+                executable.clone()
+            } else {
+                let mut build = crate::process::cmd(program, program_args);
+                if let Some(rustflags) = rustflags {
+                    build = build.env("RUSTFLAGS", rustflags);
+                }
+                if mode != Mode::Run {
+                    let status = build.unchecked().run()?.status;
+                    if !status.success() {
+                        bail!("`cargo {}` failed ({})", subcommand, status);
+                    }
+                    return Ok(None);
+                }
+                let executable = artifact::build(
+                    build,
+                    &member_manifest_path,
+                    bin_name.as_deref(),
+                    example.as_deref(),
+                    forward_messages,
+                    home_dir.as_deref(),
+                )?;
+
+                if let (Some(cache_dir), Some(mut run_state)) = (&cache_dir, run_state) {
+                    if run_state.executables.get(&build_key) != Some(&executable) {
+                        run_state.executables.insert(build_key, executable.clone());
+                        run_state.save(cache_dir, &run_state_key)?;
+                    }
+                }
+                executable
+            }
+        };
 
-    crate::process::cmd(program, program_args).run()?;
-    return Ok(());
+        if install {
+            let install_dir = match &config.content().install_dir {
+                Some(install_dir) => {
+                    PathBuf::from(install_dir.expand(home_dir.as_deref()).into_owned())
+                }
+                None => env::var_os("CARGO_HOME")
+                    .map(PathBuf::from)
+                    .or_else(|| home_dir.map(|h| h.join(".cargo")))
+                    .with_context(|| {
+                        "could not determine `$CARGO_HOME`. Set `install-dir` in the config"
+                    })?
+                    .join("bin"),
+            };
+            let dst = install_dir.join(
+                executable
+                    .file_name()
+                    .expect("should be a path to an executable"),
+            );
+            crate::fs::create_dir_all(&install_dir, false)?;
+            crate::fs::copy(&executable, &dst, false)?;
+            info_event!("Installed {} as {}", package, dst.display());
+            return Ok(None);
+        }
+        let (dylib_path_name, dylib_path) = artifact::dylib_search_path(&executable)?;
+        let mut expr = crate::process::cmd(&executable, args).env(dylib_path_name, dylib_path);
+        if let Ok(cargo) = workspace::cargo_exe() {
+            expr = expr.env("CARGO", cargo);
+        }
+        if example.is_none() {
+            if let Some(bin_name) = executable.file_stem() {
+                expr = expr.env("CARGO_BIN_NAME", bin_name);
+            }
+        }
+        expr
+    };
+
+    let env = config
+        .content()
+        .workspace(&workspace_root, home_dir.as_deref())
+        .map(|w| w.env(&package, home_dir.as_deref()))
+        .transpose()?
+        .unwrap_or_default();
 
-    fn apply<T, F: FnOnce(T) -> OsString>(f: F, arg: T) -> OsString {
-        f(arg)
+    let package_dir = member_manifest_path
+        .parent()
+        .expect("should end with \"Cargo.toml\"")
+        .to_owned();
+    for (name, value) in workspace::package_env(&member_manifest_path, &workspace_root)? {
+        expr = expr.env(name, value);
+    }
+    // With `--prefix-output`, the program writes to pipes instead of the terminal, so `auto` has to
+    // be decided here.
+    let colorize = match color {
+        crate::ColorChoice::Auto if prefix_output => Some(atty::is(atty::Stream::Stdout)),
+        crate::ColorChoice::Auto => None,
+        crate::ColorChoice::Always => Some(true),
+        crate::ColorChoice::Never => Some(false),
+    };
+    match colorize {
+        Some(true) => expr = expr.env("CLICOLOR_FORCE", "1").env_remove("NO_COLOR"),
+        Some(false) => expr = expr.env("NO_COLOR", "1").env_remove("CLICOLOR_FORCE"),
+        None => {}
+    }
+    for (name, value) in env {
+        info!("Setting `{}`", name);
+        expr = expr.env(name, value);
+    }
+    if let Some(stdin_bytes) = stdin_bytes {
+        expr = expr.stdin_bytes(stdin_bytes);
+    } else if let Some(stdin_path) = stdin_path {
+        expr = expr.stdin_path(stdin_path);
+    }
+    if let Some(cache_dir) = &cache_dir {
+        cache::record_run(cache_dir, &member_manifest_path);
+    }
+    return Ok(Some(Prepared {
+        expr,
+        package,
+        package_dir,
+    }));
+
+    fn expand_placeholders(arg: OsString, script_dir: &Path, package_name: &str) -> OsString {
+        match arg.into_string() {
+            Ok(arg) => arg
+                .replace("{script_dir}", &script_dir.to_string_lossy())
+                .replace("{package}", package_name)
+                .into(),
+            Err(arg) => arg,
+        }
     }
 }
 
@@ -211,20 +1037,71 @@ pub fn cargo_bikecase<
     opt: CargoBikecase,
     ctx: Context<W, I, P>,
 ) -> anyhow::Result<()> {
-    match opt {
+    let result = match opt {
         CargoBikecase::InitWorkspace(opt) => cargo_bikecase_init_workspace(opt, ctx),
+        CargoBikecase::OptimizeWorkspace(opt) => cargo_bikecase_optimize_workspace(opt, ctx),
         CargoBikecase::New(opt) => cargo_bikecase_new(opt, ctx),
         CargoBikecase::Rm(opt) => cargo_bikecase_rm(opt, ctx),
         CargoBikecase::Include(opt) => cargo_bikecase_include(opt, ctx),
         CargoBikecase::Exclude(opt) => cargo_bikecase_exclude(opt, ctx),
+        CargoBikecase::Archive(opt) => cargo_bikecase_archive(opt, ctx),
+        CargoBikecase::Unarchive(opt) => cargo_bikecase_unarchive(opt, ctx),
         CargoBikecase::Import(opt) => cargo_bikecase_import(opt, ctx),
         CargoBikecase::Export(opt) => cargo_bikecase_export(opt, ctx),
+        CargoBikecase::Verify(opt) => cargo_bikecase_verify(opt, ctx),
+        CargoBikecase::Readme(opt) => cargo_bikecase_readme(opt, ctx),
+        CargoBikecase::Set(opt) => cargo_bikecase_set(opt, ctx),
+        CargoBikecase::Ls(opt) => cargo_bikecase_ls(opt, ctx),
+        CargoBikecase::Ide(opt) => cargo_bikecase_ide(opt, ctx),
+        CargoBikecase::Open(opt) => cargo_bikecase_open(opt, ctx),
+        CargoBikecase::Tree(opt) => cargo_bikecase_tree(opt, ctx),
+        CargoBikecase::Why(opt) => cargo_bikecase_why(opt, ctx),
+        CargoBikecase::Audit(opt) => cargo_bikecase_audit(opt, ctx),
+        CargoBikecase::Size(opt) => cargo_bikecase_size(opt, ctx),
         CargoBikecase::Gist(opt) => match opt {
             CargoBikecaseGist::Clone(opt) => cargo_bikecase_gist_clone(opt, ctx),
+            CargoBikecaseGist::CloneAll(opt) => cargo_bikecase_gist_clone_all(opt, ctx),
             CargoBikecaseGist::Pull(opt) => cargo_bikecase_gist_pull(opt, ctx),
             CargoBikecaseGist::Push(opt) => cargo_bikecase_gist_push(opt, ctx),
+            CargoBikecaseGist::Status(opt) => cargo_bikecase_gist_status(opt, ctx),
+            CargoBikecaseGist::List(opt) => cargo_bikecase_gist_list(opt, ctx),
+            CargoBikecaseGist::Comments(opt) => cargo_bikecase_gist_comments(opt, ctx),
+            CargoBikecaseGist::Comment(opt) => cargo_bikecase_gist_comment(opt, ctx),
+            CargoBikecaseGist::Publish(opt) => cargo_bikecase_gist_publish(opt, true, ctx),
+            CargoBikecaseGist::Unpublish(opt) => cargo_bikecase_gist_publish(opt, false, ctx),
         },
-    }
+        CargoBikecase::Config(opt) => match opt {
+            CargoBikecaseConfig::Export(opt) => cargo_bikecase_config_export(opt, ctx),
+            CargoBikecaseConfig::Import(opt) => cargo_bikecase_config_import(opt, ctx),
+        },
+        CargoBikecase::DefaultMembers(opt) => match opt {
+            CargoBikecaseDefaultMembers::Add(opt) => cargo_bikecase_default_members_add(opt, ctx),
+            CargoBikecaseDefaultMembers::Rm(opt) => cargo_bikecase_default_members_rm(opt, ctx),
+            CargoBikecaseDefaultMembers::List(opt) => cargo_bikecase_default_members_list(opt, ctx),
+        },
+        CargoBikecase::Trust(opt) => match opt {
+            CargoBikecaseTrust::List(opt) => cargo_bikecase_trust_list(opt, ctx),
+            CargoBikecaseTrust::Revoke(opt) => cargo_bikecase_trust_revoke(opt, ctx),
+        },
+        CargoBikecase::Snapshot(opt) => match opt {
+            CargoBikecaseSnapshot::Create(opt) => cargo_bikecase_snapshot_create(opt, ctx),
+            CargoBikecaseSnapshot::Restore(opt) => cargo_bikecase_snapshot_restore(opt, ctx),
+            CargoBikecaseSnapshot::Diff(opt) => cargo_bikecase_snapshot_diff(opt, ctx),
+        },
+        CargoBikecase::Filter(opt) => match opt {
+            CargoBikecaseFilter::Clean(opt) => cargo_bikecase_filter_clean(opt, ctx),
+            CargoBikecaseFilter::Smudge(opt) => cargo_bikecase_filter_smudge(opt, ctx),
+        },
+        CargoBikecase::Replay(opt) => cargo_bikecase_replay(opt, ctx),
+        CargoBikecase::Itself(opt) => match opt {
+            CargoBikecaseSelf::Update(opt) => cargo_bikecase_self_update(opt, ctx),
+        },
+        CargoBikecase::Version(opt) => cargo_bikecase_version(opt, ctx),
+    };
+    event::emit(Event::Finished {
+        success: result.is_ok(),
+    });
+    result
 }
 
 fn cargo_bikecase_init_workspace(
@@ -233,7 +1110,41 @@ fn cargo_bikecase_init_workspace(
 ) -> anyhow::Result<()> {
     let CargoBikecaseInitWorkspace {
         color,
+        ascii,
+        dry_run,
+        default_members,
+        resolver,
+        edition,
+        profile_preset,
+        path,
+    } = opt;
+
+    let Context {
+        cwd, init_logger, ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    workspace::create_workspace(
+        cwd.join(path.strip_prefix(".").unwrap_or(&path)),
+        default_members,
+        resolver.as_deref(),
+        edition.as_deref(),
+        profile_preset,
+        dry_run,
+    )
+}
+
+fn cargo_bikecase_optimize_workspace(
+    opt: CargoBikecaseOptimizeWorkspace,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseOptimizeWorkspace {
+        color,
+        ascii,
         dry_run,
+        preset,
         path,
     } = opt;
 
@@ -242,8 +1153,13 @@ fn cargo_bikecase_init_workspace(
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
-    workspace::create_workspace(cwd.join(path.strip_prefix(".").unwrap_or(&path)), dry_run)
+    workspace::write_profile_preset(
+        &cwd.join(path.strip_prefix(".").unwrap_or(&path)),
+        preset,
+        dry_run,
+    )
 }
 
 fn cargo_bikecase_new(
@@ -253,9 +1169,14 @@ fn cargo_bikecase_new(
     let CargoBikecaseNew {
         manifest_path,
         color,
+        ascii,
         name,
         dry_run,
+        strict,
         config,
+        follow_symlinks,
+        from_script,
+        var,
         path,
     } = opt;
 
@@ -264,10 +1185,12 @@ fn cargo_bikecase_new(
         home_dir,
         data_local_dir,
         init_logger,
+        mut config_cache,
         ..
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
     let Metadata { workspace_root, .. } =
@@ -275,7 +1198,19 @@ fn cargo_bikecase_new(
 
     let path = cwd.join(path.strip_prefix(".").unwrap_or(&path));
 
-    let config = BikecaseConfig::load_or_create(
+    let from_script = from_script
+        .map(|from_script| -> anyhow::Result<_> {
+            let script = crate::fs::read(cwd.join(from_script))?;
+            let (main_rs, cargo_toml) = rust::replace_cargo_lang_code_with_default(&script)?;
+            let cargo_toml =
+                workspace::inherit_workspace_dependencies(&workspace_root, &cargo_toml)?
+                    .parse::<toml_edit::DocumentMut>()
+                    .with_context(|| "failed to parse the embedded manifest")?;
+            Ok((main_rs, cargo_toml))
+        })
+        .transpose()?;
+
+    let config = config_cache.load_or_create(
         &config,
         home_dir.as_deref(),
         data_local_dir.as_deref(),
@@ -289,39 +1224,131 @@ fn cargo_bikecase_new(
         .with_context(|| format!("missing `template-package`: {}", config.path().display()))?
         .expand(home_dir.as_deref());
     let template_package = Path::new(&*template_package);
+    if !template_package.exists() {
+        bail!(
+            "the template package does not exist: {}. Create one with `cargo new`, or fix \
+             `template-package` in {}",
+            template_package.display(),
+            config.path().display(),
+        );
+    }
 
-    for entry in WalkBuilder::new(template_package).hidden(false).build() {
-        match entry {
-            Ok(entry) => {
-                let from = entry.path();
-                if !(from.is_dir()
-                    || from == template_package.join("Cargo.toml")
-                    || from.starts_with(template_package.join(".git")))
-                {
-                    let to = path.join(from.strip_prefix(template_package)?);
-                    if let Some(parent) = to.parent() {
-                        if !parent.exists() {
-                            crate::fs::create_dir_all(parent, dry_run)?;
-                        }
-                    }
-                    crate::fs::copy(from, to, dry_run)?;
+    let (name, original_filename) = if let Some(name) = name {
+        (name, None)
+    } else {
+        let dir_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_str()
+            .with_context(|| format!("the file name of `{}` is not valid UTF-8", path.display()))?;
+        let name = naming::sanitize(dir_name);
+        let original_filename = Some(dir_name).filter(|&dir_name| dir_name != name);
+        (name, original_filename)
+    };
+
+    let mut vars = config.content().template_vars.clone();
+    vars.insert("name".to_owned(), name.clone());
+    vars.extend(var);
+
+    let walk = crate::fs::walk_builder(template_package)
+        .follow_links(follow_symlinks)
+        .build();
+
+    for entry in walk {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                warn!("{}", err);
+                continue;
+            }
+        };
+        let from = entry.path();
+        let file_type = match entry.file_type() {
+            Some(file_type) => file_type,
+            None => continue,
+        };
+        if file_type.is_dir()
+            || from == template_package.join("Cargo.toml")
+            || from.starts_with(template_package.join(".git"))
+        {
+            continue;
+        }
+
+        let rendered =
+            if file_type.is_file() && from.extension() == Some(template::EXTENSION.as_ref()) {
+                let rendered = template::render(&crate::fs::read(from)?, &vars)
+                    .with_context(|| format!("failed to render {}", from.display()))?;
+                if rendered.trim().is_empty() {
+                    info!("Skipping {} (rendered empty)", from.display());
+                    continue;
                 }
+                Some(rendered)
+            } else {
+                None
+            };
+
+        let to = path.join(from.strip_prefix(template_package)?);
+        let to = if rendered.is_some() {
+            to.with_extension("")
+        } else {
+            to
+        };
+        if let Some(parent) = to.parent() {
+            if !parent.exists() {
+                crate::fs::create_dir_all(parent, dry_run)?;
             }
-            Err(err) => warn!("{}", err),
+        }
+
+        if file_type.is_symlink() {
+            let link = std::fs::read_link(from)
+                .with_context(|| format!("failed to read the symlink {}", from.display()))?;
+            // Absolute links into the template are redirected to the new package.
+            let link = match link.strip_prefix(template_package) {
+                Ok(rel) => path.join(rel),
+                Err(_) => link,
+            };
+            crate::fs::symlink(link, to, dry_run)?;
+        } else if let Some(rendered) = rendered {
+            crate::fs::write(to, rendered, dry_run)?;
+        } else if file_type.is_file() {
+            crate::fs::copy(from, to, dry_run)?;
+        } else {
+            warn!("Skipping {} (not a regular file)", from.display());
         }
     }
 
     let mut cargo_toml = crate::fs::read_toml_edit(template_package.join("Cargo.toml"))?;
-    let new_package_name = name.as_deref().map(Ok).unwrap_or_else(|| {
-        path.file_name()
-            .unwrap_or_default()
-            .to_str()
-            .with_context(|| format!("the file name of `{}` is not valid UTF-8", path.display()))
-    })?;
-    workspace::modify_package_name(&mut cargo_toml, new_package_name)?;
+    workspace::modify_package_name(&mut cargo_toml, &name)?;
+    if let Some(original_filename) = original_filename {
+        workspace::set_original_filename(&mut cargo_toml, original_filename)?;
+    }
+    if let Some((_, script_cargo_toml)) = &from_script {
+        workspace::merge_dependencies(&mut cargo_toml, script_cargo_toml)?;
+    }
     crate::fs::write(path.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
 
-    workspace::modify_members(&workspace_root, Some(&path), None, None, None, dry_run)
+    if let Some((main_rs, _)) = from_script {
+        let main_rs_path = path.join("src").join("main.rs");
+        // Do not write through a symlink copied from the template.
+        if main_rs_path
+            .symlink_metadata()
+            .is_ok_and(|m| m.file_type().is_symlink())
+        {
+            crate::fs::remove_file(&main_rs_path, dry_run)?;
+        }
+        crate::fs::create_dir_all(path.join("src"), dry_run)?;
+        crate::fs::write(main_rs_path, main_rs, dry_run)?;
+    }
+
+    workspace::modify_members(
+        &workspace_root,
+        Some(&path),
+        None,
+        None,
+        None,
+        strict,
+        dry_run,
+    )
 }
 
 fn cargo_bikecase_rm(
@@ -331,8 +1358,11 @@ fn cargo_bikecase_rm(
     let CargoBikecaseRm {
         manifest_path,
         color,
+        ascii,
         dry_run,
-        spec,
+        strict,
+        force,
+        specs,
     } = opt;
 
     let Context {
@@ -340,39 +1370,77 @@ fn cargo_bikecase_rm(
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
     let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
-    let package = metadata.query_for_member(&manifest_path, Some(&spec))?;
-    let dir = package
-        .manifest_path
-        .parent()
-        .expect("`manifest_path` should end with \"Cargo.toml\"");
 
-    if cwd.starts_with(dir) {
-        bail!("aborted due to CWD");
+    let mut packages = Vec::<&cargo_metadata::Package>::new();
+    for spec in &specs {
+        let package = metadata.query_for_member(&manifest_path, Some(spec))?;
+        if !packages.iter().any(|p| p.id == package.id) {
+            packages.push(package);
+        }
     }
 
-    workspace::modify_members(
-        &metadata.workspace_root,
-        None,
-        None,
-        Some(dir),
-        Some(dir),
-        dry_run,
-    )?;
+    let (packages, broken) = workspace::plan_removal(&metadata, &packages);
+    if !broken.is_empty() {
+        let msgs = broken
+            .iter()
+            .map(|(dependent, dependency)| {
+                format!("`{}` depends on `{}`", dependent.name, dependency.name)
+            })
+            .collect::<Vec<_>>();
+        if !force {
+            bail!(
+                "{}. Remove them together, or use `--force` to leave the workspace unbuildable",
+                msgs.join(", "),
+            );
+        }
+        for msg in msgs {
+            warn!("{}", msg);
+        }
+    }
 
-    crate::fs::remove_dir_all(dir, dry_run)
-}
+    let dirs = packages
+        .iter()
+        .map(|p| {
+            p.manifest_path
+                .parent()
+                .expect("`manifest_path` should end with \"Cargo.toml\"")
+        })
+        .collect::<Vec<_>>();
 
-fn cargo_bikecase_include(
-    opt: CargoBikecaseInclude,
-    ctx: Context<impl Sized, impl Sized, impl Sized>,
+    if dirs.iter().any(|dir| cwd.starts_with(dir)) {
+        bail!("aborted due to CWD");
+    }
+
+    for dir in dirs {
+        workspace::modify_members(
+            &metadata.workspace_root,
+            None,
+            None,
+            Some(dir),
+            Some(dir),
+            strict,
+            dry_run,
+        )?;
+        crate::fs::remove_dir_all(dir, dry_run)?;
+    }
+    Ok(())
+}
+
+fn cargo_bikecase_include(
+    opt: CargoBikecaseInclude,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
 ) -> anyhow::Result<()> {
     let CargoBikecaseInclude {
         manifest_path,
         color,
+        ascii,
         dry_run,
+        strict,
+        scriptify,
         path,
     } = opt;
 
@@ -381,20 +1449,39 @@ fn cargo_bikecase_include(
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
     let Metadata { workspace_root, .. } =
         workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
     let path = cwd.join(path);
 
+    let scriptified = if scriptify {
+        Some(workspace::scriptify(&path)?)
+    } else {
+        None
+    };
+
     workspace::modify_members(
         &workspace_root,
         Some(&*path),
         None,
         None,
         Some(&*path),
+        strict,
         dry_run,
-    )
+    )?;
+
+    if let Some((main_rs, code)) = scriptified {
+        let orig = crate::fs::read(&main_rs)?;
+        crate::fs::write(&main_rs, &code, dry_run)?;
+        event::emit(Event::Diff {
+            name: main_rs.display().to_string(),
+            orig,
+            edit: code,
+        });
+    }
+    Ok(())
 }
 
 fn cargo_bikecase_exclude(
@@ -404,7 +1491,9 @@ fn cargo_bikecase_exclude(
     let CargoBikecaseExclude {
         manifest_path,
         color,
+        ascii,
         dry_run,
+        strict,
         path,
     } = opt;
 
@@ -413,6 +1502,7 @@ fn cargo_bikecase_exclude(
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
     let Metadata { workspace_root, .. } =
@@ -425,10 +1515,169 @@ fn cargo_bikecase_exclude(
         Some(&*path),
         Some(&*path),
         None,
+        strict,
         dry_run,
     )
 }
 
+fn cargo_bikecase_archive(
+    opt: CargoBikecaseArchive,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseArchive {
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        strict,
+        force,
+        config,
+        spec,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, Some(&spec))?;
+
+    let (_, broken) = workspace::plan_removal(&metadata, &[package]);
+    if !broken.is_empty() {
+        let msgs = broken
+            .iter()
+            .map(|(dependent, dependency)| {
+                format!("`{}` depends on `{}`", dependent.name, dependency.name)
+            })
+            .collect::<Vec<_>>();
+        if !force {
+            bail!(
+                "{}. Archive them first, or use `--force` to leave the workspace unbuildable",
+                msgs.join(", "),
+            );
+        }
+        for msg in msgs {
+            warn!("{}", msg);
+        }
+    }
+
+    let src = package
+        .manifest_path
+        .parent()
+        .expect("`manifest_path` should end with \"Cargo.toml\"");
+    if cwd.starts_with(src) {
+        bail!("aborted due to CWD");
+    }
+    let archive_dir = metadata.workspace_root.join(workspace::ARCHIVE_DIR);
+    let dst = archive_dir.join(src.file_name().with_context(|| "empty path")?);
+    if dst.exists() {
+        bail!("{} already exists", dst.display());
+    }
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+
+    workspace::modify_members(
+        &metadata.workspace_root,
+        None,
+        Some(&dst),
+        Some(src),
+        None,
+        strict,
+        dry_run,
+    )?;
+    crate::fs::create_dir_all(&archive_dir, dry_run)?;
+    crate::fs::rename(src, &dst, dry_run)?;
+
+    let mut gist_ids = config.gist_ids_mut(&metadata.workspace_root, home_dir.as_deref())?;
+    if let Some(gist_id) = gist_ids.set_archived(&package.name, true) {
+        info!(
+            "`gist_ids.{:?}` -> `archived_gist_ids.{:?}`: {:?}",
+            package.name, package.name, gist_id,
+        );
+    }
+    gist_ids.save(dry_run)?;
+    config.save(dry_run)
+}
+
+fn cargo_bikecase_unarchive(
+    opt: CargoBikecaseUnarchive,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseUnarchive {
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        strict,
+        config,
+        name,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let Metadata { workspace_root, .. } =
+        workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    let src = workspace::archived_package_dir(&workspace_root, &name)?;
+    let dst = workspace_root.join(src.file_name().with_context(|| "empty path")?);
+    if dst.exists() {
+        bail!("{} already exists", dst.display());
+    }
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+
+    workspace::modify_members(
+        &workspace_root,
+        Some(&dst),
+        None,
+        None,
+        Some(&src),
+        strict,
+        dry_run,
+    )?;
+    crate::fs::rename(&src, &dst, dry_run)?;
+
+    let mut gist_ids = config.gist_ids_mut(&workspace_root, home_dir.as_deref())?;
+    if let Some(gist_id) = gist_ids.set_archived(&name, false) {
+        info!(
+            "`archived_gist_ids.{:?}` -> `gist_ids.{:?}`: {:?}",
+            name, name, gist_id,
+        );
+    }
+    gist_ids.save(dry_run)?;
+    config.save(dry_run)
+}
+
 fn cargo_bikecase_import(
     opt: CargoBikecaseImport,
     ctx: Context<impl Sized, impl FnOnce() -> io::Result<String>, impl Sized>,
@@ -436,7 +1685,12 @@ fn cargo_bikecase_import(
     let CargoBikecaseImport {
         manifest_path,
         color,
+        ascii,
         dry_run,
+        diff,
+        diff_threshold,
+        split_modules,
+        strict,
         path,
         file,
     } = opt;
@@ -445,27 +1699,84 @@ fn cargo_bikecase_import(
         cwd,
         read_input,
         init_logger,
-        str_width,
         ..
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
+    ui::set_diff_mode(diff, diff_threshold);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
-    let Metadata { workspace_root, .. } =
-        workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let workspace_root = workspace::workspace_root(&manifest_path, color, &cwd)?;
 
-    let content = file
-        .as_ref()
-        .map(crate::fs::read)
-        .unwrap_or_else(move || read_input().map_err(Into::into))?;
+    if let Some(dir) = file.as_ref().map(|f| cwd.join(f)).filter(|f| f.is_dir()) {
+        if path.is_some() {
+            bail!("`--path` cannot be used when importing a directory");
+        }
+        // The members, and the scripts exported from them, may be in `dir`.
+        let canonical_workspace_root = workspace_root
+            .canonicalize()
+            .unwrap_or_else(|_| workspace_root.clone());
+        let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+        let mut package_names = metadata
+            .packages
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<BTreeSet<_>>();
+        for entry in crate::fs::walk(&dir) {
+            let entry = entry?;
+            let script = entry.path();
+            if !(script.is_file() && script.extension() == Some("rs".as_ref())) {
+                continue;
+            }
+            if script
+                .canonicalize()
+                .is_ok_and(|s| s.starts_with(&canonical_workspace_root))
+            {
+                info!("Skipping {} (in the workspace)", script.display());
+                continue;
+            }
+            let content = crate::fs::read(script)?;
+            if rust::extract_cargo_lang_code(&content, || "").is_err() {
+                info!("Skipping {} (no `cargo` code block)", script.display());
+                continue;
+            }
+            let file_name = script.file_name().and_then(OsStr::to_str);
+            let package_name =
+                workspace::script_package_name(&workspace_root, &content, file_name)?;
+            if !package_names.insert(package_name.clone()) {
+                warn_event!(
+                    "Skipping {} (`{}` already exists)",
+                    script.display(),
+                    package_name,
+                );
+                continue;
+            }
+            workspace::import_script(
+                &workspace_root,
+                &content,
+                split_modules,
+                strict,
+                dry_run,
+                file_name,
+                |name| Ok(workspace_root.join(name)),
+            )?;
+        }
+        return Ok(());
+    }
+
+    let Script {
+        code, file_name, ..
+    } = ScriptSource::parse(file.as_deref(), &cwd)?.resolve(&mut Some(read_input))?;
 
     workspace::import_script(
         &workspace_root,
-        &content,
+        &code,
+        split_modules,
+        strict,
         dry_run,
-        str_width,
-        |package_name| cwd.join(path.unwrap_or_else(|| workspace_root.join(package_name))),
+        file_name.as_deref(),
+        |package_name| Ok(cwd.join(path.unwrap_or_else(|| workspace_root.join(package_name)))),
     )
     .map(drop)
 }
@@ -478,161 +1789,389 @@ fn cargo_bikecase_export(
         package,
         manifest_path,
         color,
+        ascii,
+        bin,
+        prune_manifest,
+        check,
+        emit_fence,
+        inline_path_deps,
+        config,
     } = opt;
 
     let Context {
         cwd,
-        mut stdout,
+        home_dir,
+        data_local_dir,
+        stdout,
         init_logger,
+        str_width,
+        mut config_cache,
         ..
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
-    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
-    let (src_path, cargo_toml) = metadata
-        .query_for_member(&manifest_path, package.as_deref())?
-        .find_default_bin()?;
-    let (code, _) =
-        rust::replace_cargo_lang_code(&crate::fs::read(src_path)?, &cargo_toml, || {
-            anyhow!(
-                "could not find the `cargo` code block: {}",
-                src_path.display(),
-            )
-        })?;
+    let from_manifests = if bin.is_none() && !prune_manifest {
+        workspace::export_script_from_manifests(&cwd.join(&manifest_path), package.as_deref())?
+    } else {
+        None
+    };
+    let mut exported = match from_manifests {
+        Some(exported) => exported,
+        None => {
+            let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+            metadata
+                .query_for_member(&manifest_path, package.as_deref())?
+                .export_bin_script(&metadata.workspace_root, bin.as_deref(), prune_manifest)?
+        }
+    };
 
-    stdout.write_all(code.as_ref())?;
-    stdout.flush().map_err(Into::into)
+    exported.resolve_path_dependencies(inline_path_deps, || {
+        let config = config_cache.load_or_create(
+            &config,
+            home_dir.as_deref(),
+            data_local_dir.as_deref(),
+            false,
+        )?;
+        Ok(config.content().path_dependencies.clone())
+    })?;
+
+    if let Some(fence) = &emit_fence {
+        if !rust::is_manifest_fence(fence) {
+            warn!(
+                "bikecase does not read ```{} blocks as manifests. Add a `cargo` tag to read the script back",
+                fence,
+            );
+        }
+    }
+
+    if let Some(check) = check {
+        let code = exported.to_code(emit_fence.as_deref())?;
+        let check = cwd.join(check);
+        if !is_up_to_date(&check, &code, str_width)? {
+            bail!("{} differs from the exported script", check.display());
+        }
+        return Ok(());
+    }
+
+    exported.write(emit_fence.as_deref(), stdout)
 }
 
-fn cargo_bikecase_gist_clone(
-    opt: CargoBikecaseGistClone,
-    ctx: Context<impl Sized, impl Sized, impl Sized>,
+fn cargo_bikecase_verify(
+    opt: CargoBikecaseVerify,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
 ) -> anyhow::Result<()> {
-    let CargoBikecaseGistClone {
+    let CargoBikecaseVerify {
         manifest_path,
         color,
-        dry_run,
-        path,
-        config,
-        gist_id,
+        ascii,
+        test,
+        filter,
+        jobs,
+        mapping,
     } = opt;
 
     let Context {
         cwd,
-        home_dir,
-        data_local_dir,
+        stdout,
         init_logger,
         str_width,
         ..
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
-    let Metadata { workspace_root, .. } =
-        workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
 
-    let mut config = BikecaseConfig::load_or_create(
-        &config,
-        home_dir.as_deref(),
-        data_local_dir.as_deref(),
-        dry_run,
-    )?;
-    let gist_ids = &mut config
-        .content_mut()
-        .workspace_or_default(&workspace_root, home_dir.as_deref())?
-        .gist_ids;
+    let mapping = match mapping {
+        Some(mapping) => cwd.join(mapping),
+        None => {
+            let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+            return verify_members(
+                &metadata, &filter, test, jobs, color, &cwd, stdout, str_width,
+            );
+        }
+    };
+    let metadata = once_cell::unsync::OnceCell::new();
+
+    let base = mapping.parent().expect("should not be empty");
+    let mapping = crate::fs::read_toml::<_, IndexMap<String, PathBuf>>(&mapping)?;
+
+    let mut num_drifted = 0;
+    for (package, script) in mapping {
+        let exported = match workspace::export_script_from_manifests(
+            &cwd.join(&manifest_path),
+            Some(&package),
+        )? {
+            Some(exported) => exported,
+            None => {
+                let metadata = metadata.get_or_try_init(|| {
+                    workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)
+                })?;
+                metadata
+                    .query_for_member(&manifest_path, Some(&package))?
+                    .export_bin_script(&metadata.workspace_root, None, false)?
+            }
+        };
+        let code = exported.to_code(None)?;
+        if is_up_to_date(&base.join(script), &code, str_width)? {
+            info!("`{}` is up to date", package);
+        } else {
+            warn!("`{}` has drifted", package);
+            num_drifted += 1;
+        }
+    }
 
-    let (script, _) = gist::retrieve_rust_code(&gist_id)?;
-    let package_name = workspace::import_script(
-        &workspace_root,
-        &script,
-        dry_run,
-        str_width,
-        |package_name| cwd.join(path.unwrap_or_else(|| workspace_root.join(package_name))),
-    )?;
-    let old_gist_id = gist_ids.get(&package_name).cloned();
-    info!(
-        "`gist_ids.{:?}`: {:?} -> {:?}",
-        package_name, old_gist_id, gist_id,
-    );
-    gist_ids.insert(package_name, gist_id);
-    config.save(dry_run)?;
+    if num_drifted > 0 {
+        bail!(
+            "{} script(s) differ from the workspace members",
+            num_drifted
+        );
+    }
     Ok(())
 }
 
-fn cargo_bikecase_gist_pull(
-    opt: CargoBikecaseGistPull,
+/// Runs `cargo check` (and `cargo test` if `test`) for each member matching `filter`, `jobs` members
+/// at a time, and prints the results as a table.
+#[allow(clippy::too_many_arguments)]
+fn verify_members(
+    metadata: &Metadata,
+    filter: &[String],
+    test: bool,
+    jobs: Option<u32>,
+    color: crate::ColorChoice,
+    cwd: &Path,
+    stdout: impl Write,
+    str_width: fn(&str) -> usize,
+) -> anyhow::Result<()> {
+    let filter = filter
+        .iter()
+        .map(|glob| globset::Glob::new(glob).with_context(|| format!("invalid glob: {:?}", glob)))
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .fold(globset::GlobSetBuilder::new(), |mut builder, glob| {
+            builder.add(glob);
+            builder
+        })
+        .build()?;
+
+    let members = metadata
+        .workspace_members
+        .iter()
+        .map(|id| &metadata[id])
+        .filter(|p| filter.is_empty() || filter.is_match(&p.name))
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect::<Vec<_>>();
+    if members.is_empty() {
+        if filter.is_empty() {
+            info!("No members to verify");
+            return Ok(());
+        }
+        bail!("no members match `--filter`");
+    }
+
+    let cargo_exe = workspace::cargo_exe()?;
+    let jobs = jobs
+        .map(|n| n as usize)
+        .or_else(|| thread::available_parallelism().ok().map(Into::into))
+        .unwrap_or(1)
+        .clamp(1, members.len());
+
+    // `Ok(())` or `Err(output)` for each step. `cargo test` is skipped if `cargo check` fails.
+    let run = |package: &Package, subcommand: &str| -> Result<(), String> {
+        let args = CargoArgs::new(subcommand)
+            .package(&package.name, &package.manifest_path, color)
+            .into_vec();
+        let output = crate::process::cmd(&cargo_exe, &args)
+            .dir(cwd)
+            .stderr_to_stdout()
+            .stdout_capture()
+            .unchecked()
+            .run()
+            .map_err(|e| e.to_string())?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+    };
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let i = next.fetch_add(1, atomic::Ordering::Relaxed);
+                        let package = match members.get(i) {
+                            Some(package) => package,
+                            None => break results,
+                        };
+                        let check = run(package, "check");
+                        let test = if test && check.is_ok() {
+                            Some(run(package, "test"))
+                        } else {
+                            None
+                        };
+                        results.push((i, check, test));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("should not panic"))
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|&(i, _, _)| i);
+
+    let cell = |result: Option<&Result<(), String>>| match result {
+        Some(Ok(())) => Cell::new("ok").fg(ui::theme().ok),
+        Some(Err(_)) => Cell::new("FAILED").fg(ui::theme().error),
+        None => Cell::new("-"),
+    };
+    let mut table = Table::new(if test {
+        &["NAME", "CHECK", "TEST"]
+    } else {
+        &["NAME", "CHECK"]
+    });
+    let mut num_failed = 0;
+    for (i, check, test_result) in &results {
+        let package = members[*i];
+        for (subcommand, result) in &[("check", Some(check)), ("test", test_result.as_ref())] {
+            if let Some(Err(output)) = result {
+                warn!(
+                    "`cargo {}` failed for `{}`:\n{}",
+                    subcommand,
+                    package.name,
+                    output.trim_end(),
+                );
+            }
+        }
+        if check.is_err() || matches!(test_result, Some(Err(_))) {
+            num_failed += 1;
+        }
+        let mut row = vec![Cell::new(&package.name), cell(Some(check))];
+        if test {
+            row.push(cell(test_result.as_ref()));
+        }
+        table.push_row(row);
+    }
+    table.print(stdout, PrintOptions::for_stdout(color, false, str_width))?;
+
+    if num_failed > 0 {
+        bail!("{} of {} member(s) failed", num_failed, results.len());
+    }
+    Ok(())
+}
+
+fn is_up_to_date(script: &Path, code: &str, str_width: fn(&str) -> usize) -> anyhow::Result<bool> {
+    let prev = if script.exists() {
+        crate::fs::read(script)?
+    } else {
+        "".to_owned()
+    };
+    if prev == code {
+        Ok(true)
+    } else {
+        ui::info_diff(&prev, code, script.display(), str_width);
+        Ok(false)
+    }
+}
+
+fn cargo_bikecase_readme(
+    opt: CargoBikecaseReadme,
     ctx: Context<impl Sized, impl Sized, impl Sized>,
 ) -> anyhow::Result<()> {
-    let CargoBikecaseGistPull {
+    let CargoBikecaseReadme {
         package,
         manifest_path,
         color,
+        ascii,
         dry_run,
-        config,
+        check,
+        template,
     } = opt;
 
     let Context {
         cwd,
-        home_dir,
-        data_local_dir,
         init_logger,
         str_width,
         ..
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
     let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
     let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+    let (src_path, _) = package.find_default_bin()?;
+    let package_dir = package.manifest_path.parent().expect("should not be empty");
 
-    let config = BikecaseConfig::load_or_create(
-        &config,
-        home_dir.as_deref(),
-        data_local_dir.as_deref(),
-        dry_run,
-    )?;
-    let gist_id = config
-        .content()
-        .workspace(&metadata.workspace_root, home_dir.as_deref())
-        .and_then(|BikecaseConfigWorkspace { gist_ids, .. }| gist_ids.get(&package.name))
-        .with_context(|| format!("could not find the `gist_id` for {:?}", package.name))?;
+    let template = match template {
+        Some(template) => crate::fs::read(cwd.join(template))?,
+        None if package_dir.join("README.tpl").exists() => {
+            crate::fs::read(package_dir.join("README.tpl"))?
+        }
+        None => DEFAULT_TEMPLATE.to_owned(),
+    };
 
-    let (pulled_code, _) = gist::retrieve_rust_code(gist_id)?;
-    let (pulled_code, pulled_cargo_toml) =
-        rust::replace_cargo_lang_code_with_default(&pulled_code)?;
-    let (src_path, prev_cargo_toml) = package.find_default_bin()?;
+    let mut vars = BTreeMap::new();
+    vars.insert("crate".to_owned(), package.name.clone());
+    vars.insert("version".to_owned(), package.version.to_string());
+    vars.extend(package.license.clone().map(|l| ("license".to_owned(), l)));
+    vars.insert(
+        "readme".to_owned(),
+        rust::module_doc(&crate::fs::read(src_path)?)?,
+    );
+    let readme = template::render(&template, &vars)
+        .with_context(|| "failed to render the template for `README.md`")?;
 
-    for (path, orig, edit) in &[
-        (src_path, crate::fs::read(src_path)?, pulled_code),
-        (&package.manifest_path, prev_cargo_toml, pulled_cargo_toml),
-    ] {
-        if orig == edit {
-            info!("No changes: {}", path.display());
-        } else {
-            logger::info_diff(orig, edit, path.display(), str_width);
-            crate::fs::write(&path, edit, dry_run)?;
+    let path = package_dir.join("README.md");
+    if check {
+        if !is_up_to_date(&path, &readme, str_width)? {
+            bail!("{} is not up to date", path.display());
         }
+        return Ok(());
     }
-    Ok(())
+
+    let orig = if path.exists() {
+        crate::fs::read(&path)?
+    } else {
+        "".to_owned()
+    };
+    crate::fs::write(&path, &readme, dry_run)?;
+    event::emit(Event::Diff {
+        name: path.display().to_string(),
+        orig,
+        edit: readme,
+    });
+    return Ok(());
+
+    static DEFAULT_TEMPLATE: &str =
+        "# {{crate}}\n\n{{readme}}{{#if license}}\nLicense: {{license}}\n{{/if}}";
 }
 
-fn cargo_bikecase_gist_push(
-    opt: CargoBikecaseGistPush,
-    ctx: Context<impl Sized, impl Sized, impl FnMut(&str) -> io::Result<String>>,
+fn cargo_bikecase_set(
+    opt: CargoBikecaseSet,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
 ) -> anyhow::Result<()> {
-    let CargoBikecaseGistPush {
+    let CargoBikecaseSet {
         package,
         manifest_path,
         color,
+        ascii,
         dry_run,
-        set_upstream,
-        private,
-        description,
+        clear,
+        release,
+        profile,
+        features,
+        target,
+        rustflags,
         config,
     } = opt;
 
@@ -640,137 +2179,4007 @@ fn cargo_bikecase_gist_push(
         cwd,
         home_dir,
         data_local_dir,
-        read_password,
         init_logger,
-        str_width,
+        mut config_cache,
         ..
     } = ctx;
 
     init_logger(color);
+    ui::set_ascii(ascii);
 
     let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
     let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
-
     let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
 
-    let mut config = BikecaseConfig::load_or_create(
+    let config = config_cache.load_or_create(
         &config,
         home_dir.as_deref(),
         data_local_dir.as_deref(),
         dry_run,
     )?;
 
-    let github_token = config
-        .content()
+    let defaults = config
+        .content_mut()
+        .workspace_or_default(&metadata.workspace_root, home_dir.as_deref())?
+        .packages
+        .entry(package.name.clone())
+        .or_default();
+
+    let profile = if release {
+        Some("release".to_owned())
+    } else {
+        profile
+    };
+
+    macro_rules! set {
+        ($field:ident, $value:expr) => {
+            let value = $value;
+            if defaults.$field != value {
+                info!(
+                    "`packages.{:?}.{}`: {:?} -> {:?}",
+                    package.name,
+                    stringify!($field),
+                    defaults.$field,
+                    value,
+                );
+                defaults.$field = value;
+            }
+        };
+    }
+
+    set!(
+        profile,
+        profile.or_else(|| defaults.profile.clone().filter(|_| !clear))
+    );
+    set!(
+        features,
+        if features.is_empty() && !clear {
+            defaults.features.clone()
+        } else {
+            features
+        }
+    );
+    set!(
+        target,
+        target.or_else(|| defaults.target.clone().filter(|_| !clear))
+    );
+    set!(
+        rustflags,
+        rustflags.or_else(|| defaults.rustflags.clone().filter(|_| !clear))
+    );
+
+    config.save(dry_run)
+}
+
+fn cargo_bikecase_ls(
+    opt: CargoBikecaseLs,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseLs {
+        manifest_path,
+        color,
+        ascii,
+        no_header,
+        sort,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        stdout,
+        init_logger,
+        str_width,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        false,
+    )?;
+    let gist_ids = config.gist_ids(&metadata.workspace_root, home_dir.as_deref())?;
+
+    let mut table = Table::new(&["NAME", "VERSION", "PATH", "GIST"]);
+    let cache_dir = data_local_dir.map(|d| d.join("bikecase").join("cache"));
+    for package in ui::sort_listing(
+        &metadata.packages,
+        sort,
+        |p| &p.name,
+        |p| {
+            p.manifest_path
+                .parent()
+                .expect("`manifest_path` should end with \"Cargo.toml\"")
+        },
+        |p| cache::last_run(cache_dir.as_deref()?, &p.manifest_path),
+    ) {
+        let dir = package
+            .manifest_path
+            .parent()
+            .expect("`manifest_path` should end with \"Cargo.toml\"");
+        let dir = dir.strip_prefix(&metadata.workspace_root).unwrap_or(dir);
+        let gist_id = gist_ids.get(&package.name);
+
+        table.push_row(vec![
+            Cell::new(&package.name),
+            Cell::new(package.version.to_string()),
+            Cell::new(dir.to_string_lossy()),
+            Cell::new(gist_id.map_or("-", |e| &e.id)),
+        ]);
+    }
+    table
+        .print(
+            stdout,
+            PrintOptions::for_stdout(color, no_header, str_width),
+        )
+        .map_err(Into::into)
+}
+
+fn cargo_bikecase_ide(
+    opt: CargoBikecaseIde,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseIde {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        print,
+        rust_project,
+        dir,
+        link,
+    } = opt;
+
+    let Context {
+        cwd,
+        mut stdout,
+        init_logger,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = if rust_project {
+        workspace::cargo_metadata(&manifest_path, color, &[], &cwd)?
+    } else {
+        workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?
+    };
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let dir = dir.map_or_else(|| cwd.clone(), |dir| cwd.join(dir));
+    let path = if rust_project {
+        dir.join("rust-project.json")
+    } else {
+        dir.join(".vscode").join("settings.json")
+    };
+
+    let prev = if !print && path.exists() {
+        Some(crate::fs::read(&path)?)
+    } else {
+        None
+    };
+    let content = if rust_project {
+        let sysroot = workspace::rustc_sysroot(&metadata.workspace_root);
+        ide::rust_project(&metadata, package, sysroot.as_deref())?
+    } else {
+        ide::vscode_settings(prev.as_deref(), &package.manifest_path)?
+    };
+
+    if print {
+        stdout.write_all(content.as_ref())?;
+        stdout.flush()?;
+    } else if prev.as_deref() == Some(&content) {
+        info!("{} is up to date", path.display());
+    } else {
+        crate::fs::create_dir_all(path.parent().expect("should not empty"), dry_run)?;
+        crate::fs::write(&path, content, dry_run)?;
+    }
+
+    if let Some(link) = link {
+        let link = cwd.join(link);
+        if link.exists() {
+            bail!("{} already exists", link.display());
+        }
+        let package_dir = package
+            .manifest_path
+            .parent()
+            .expect("`manifest_path` should end with \"Cargo.toml\"");
+        crate::fs::symlink_dir(package_dir, link, dry_run)?;
+    }
+    Ok(())
+}
+
+fn cargo_bikecase_open(
+    opt: CargoBikecaseOpen,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseOpen {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        dir,
+        print,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        mut stdout,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let package_dir = package
+        .manifest_path
+        .parent()
+        .expect("`manifest_path` should end with \"Cargo.toml\"");
+    let file = package
+        .find_default_bin()
+        .ok()
+        .map(|(src_path, _)| src_path);
+    let target = match file {
+        Some(file) if !dir => file,
+        _ => package_dir,
+    };
+
+    let editor = if print {
+        None
+    } else {
+        let config = config_cache.load_or_create(
+            &config,
+            home_dir.as_deref(),
+            data_local_dir.as_deref(),
+            false,
+        )?;
+        config.content().editor.clone().or_else(|| {
+            ["VISUAL", "EDITOR"]
+                .iter()
+                .flat_map(env::var)
+                .find(|e| !e.trim().is_empty())
+        })
+    };
+
+    match editor {
+        Some(editor) => crate::process::open_in_editor(&editor, package_dir, file, target),
+        None => {
+            if !print {
+                info!("No editor is configured. Set `editor`, `$VISUAL`, or `$EDITOR`");
+            }
+            writeln!(stdout, "{}", target.display())?;
+            stdout.flush().map_err(Into::into)
+        }
+    }
+}
+
+fn cargo_bikecase_tree(
+    opt: CargoBikecaseTree,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseTree {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        duplicates,
+        invert,
+        edges,
+        embedded,
+    } = opt;
+
+    let Context {
+        cwd,
+        mut stdout,
+        init_logger,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let args = CargoArgs::new("tree")
+        .package(
+            format!("{}@{}", package.name, package.version),
+            &metadata.workspace_root.join("Cargo.toml"),
+            color,
+        )
+        .flag("--duplicates", duplicates)
+        .option("--invert", invert)
+        .options("--edges", edges);
+    let cmd = crate::process::cmd(workspace::cargo_exe()?, args).dir(&metadata.workspace_root);
+
+    if !embedded {
+        cmd.run()?;
+        return Ok(());
+    }
+
+    let output = cmd.stdout_capture().unchecked().run()?;
+    if !output.status.success() {
+        bail!("`cargo tree` failed ({})", output.status);
+    }
+    let inherited = workspace::inherited_dependencies(
+        &crate::fs::read_toml_edit(&package.manifest_path)?,
+        &crate::fs::read_toml_edit(metadata.workspace_root.join("Cargo.toml"))?,
+    );
+    let tree = deps::annotate_tree(str::from_utf8(&output.stdout)?, &inherited);
+    stdout.write_all(tree.as_ref())?;
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_why(
+    opt: CargoBikecaseWhy,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseWhy {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        offline,
+        spec,
+    } = opt;
+
+    let Context {
+        cwd,
+        mut stdout,
+        init_logger,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+    let spec = spec.parse::<PackageIdSpec>()?;
+
+    let args = CargoArgs::new("tree")
+        .package(
+            format!("{}@{}", package.name, package.version),
+            &metadata.workspace_root.join("Cargo.toml"),
+            color,
+        )
+        .option("--invert", Some(spec.to_string()))
+        .flag("--offline", offline);
+    let output = crate::process::cmd(workspace::cargo_exe()?, args)
+        .dir(&metadata.workspace_root)
+        .stdout_capture()
+        .run()?;
+    stdout.write_all(&output.stdout)?;
+
+    let flags = if offline { &["--offline"][..] } else { &[] };
+    let resolved = workspace::cargo_metadata(&manifest_path, color, flags, &cwd)?;
+    let culprits = deps::why(&resolved, &package.name, &spec)?;
+
+    let cargo_toml = crate::fs::read(&package.manifest_path)?;
+    let lines = workspace::dependency_lines(&cargo_toml)?;
+    let cargo_toml = cargo_toml.lines().collect::<Vec<_>>();
+
+    // Point to the manifest in the order of the lines. The ones not found there come last.
+    let (found, not_found) = culprits
+        .into_iter()
+        .partition::<Vec<_>, _>(|c| lines.contains_key(&c.name));
+    let found = found.iter().flat_map(|c| &lines[&c.name]).sorted().dedup();
+
+    writeln!(stdout)?;
+    writeln!(stdout, "`{}` is pulled in by:", spec)?;
+    for &line in found {
+        writeln!(
+            stdout,
+            "    {}:{}: {}",
+            package.manifest_path.display(),
+            line,
+            cargo_toml[line - 1].trim(),
+        )?;
+    }
+    for culprit in not_found {
+        writeln!(stdout, "    {} v{}", culprit.name, culprit.version)?;
+    }
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_audit(
+    opt: CargoBikecaseAudit,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseAudit {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        offline,
+        ignore_rust_version,
+        ignore,
+        no_header,
+        then_run,
+        args,
+    } = opt;
+
+    let Context {
+        cwd,
+        mut stdout,
+        init_logger,
+        str_width,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata
+        .query_for_member(&manifest_path, package.as_deref())?
+        .name
+        .clone();
+
+    // Resolving the dependencies updates `Cargo.lock` for the embedded manifest.
+    let flags = if offline { &["--offline"][..] } else { &[] };
+    let metadata = workspace::cargo_metadata(&manifest_path, color, flags, &cwd)?;
+    let findings = deps::audit(&metadata, &package, &ignore)?;
+
+    let num_vulnerabilities = findings.iter().filter(|f| f.is_vulnerability()).count();
+    if findings.is_empty() {
+        info!("`{}`: no advisories found", package);
+    } else {
+        let mut table = Table::new(&["ID", "KIND", "CRATE", "TITLE"]);
+        for finding in &findings {
+            let kind = Cell::new(&finding.kind);
+            table.push_row(vec![
+                Cell::new(finding.id.as_deref().unwrap_or("-")),
+                if finding.is_vulnerability() {
+                    kind.fg(ui::theme().error)
+                } else {
+                    kind.fg(ui::theme().warn)
+                },
+                Cell::new(format!("{} {}", finding.name, finding.version)),
+                Cell::new(finding.title.as_deref().unwrap_or("-")),
+            ]);
+        }
+        table.print(
+            &mut stdout,
+            PrintOptions::for_stdout(color, no_header, str_width),
+        )?;
+        stdout.flush()?;
+    }
+
+    if num_vulnerabilities > 0 {
+        bail!(
+            "`{}`: {} vulnerable dependencies found",
+            package,
+            num_vulnerabilities,
+        );
+    }
+
+    if then_run {
+        let run_args = CargoArgs::new("run")
+            .package(package, &metadata.workspace_root.join("Cargo.toml"), color)
+            .flag("--offline", offline)
+            .flag("--ignore-rust-version", ignore_rust_version)
+            .passthrough(args);
+        crate::process::cmd(workspace::cargo_exe()?, run_args)
+            .dir(&cwd)
+            .run()?;
+    }
+    Ok(())
+}
+
+fn cargo_bikecase_size(
+    opt: CargoBikecaseSize,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    /// Inherits `release`, so that `--strip` does not overwrite the unstripped binary.
+    const STRIPPED_PROFILE: &str = "bikecase-stripped";
+
+    let CargoBikecaseSize {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        offline,
+        bin,
+        strip,
+        bloat,
+        crates,
+        n,
+        no_header,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        mut stdout,
+        init_logger,
+        str_width,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let build = |profile: &str, config: &[String]| -> anyhow::Result<_> {
+        let args = CargoArgs::new("build")
+            .package(
+                format!("{}@{}", package.name, package.version),
+                &metadata.workspace_root.join("Cargo.toml"),
+                color,
+            )
+            .option("--message-format", Some("json-render-diagnostics"))
+            .options("--config", config)
+            .option("--profile", Some(profile))
+            .option("--bin", bin.as_deref())
+            .flag("--offline", offline);
+        let expr = crate::process::cmd(workspace::cargo_exe()?, args).dir(&metadata.workspace_root);
+        let executable = artifact::build(
+            expr,
+            &package.manifest_path,
+            bin.as_deref(),
+            None,
+            false,
+            home_dir.as_deref(),
+        )?;
+        let size = std::fs::metadata(&executable)
+            .with_context(|| format!("could not read `{}`", executable.display()))?
+            .len();
+        Ok((executable, size))
+    };
+
+    let (executable, size) = build("release", &[])?;
+    let stripped_size = if strip {
+        let config = [
+            format!("profile.{}.inherits=\"release\"", STRIPPED_PROFILE),
+            format!("profile.{}.strip=true", STRIPPED_PROFILE),
+        ];
+        let (_, stripped_size) = build(STRIPPED_PROFILE, &config)?;
+        Some(stripped_size)
+    } else {
+        None
+    };
+
+    let mut header = vec!["BINARY", "SIZE"];
+    if strip {
+        header.push("STRIPPED");
+    }
+    let mut table = Table::new(&header);
+    let mut row = vec![
+        Cell::new(
+            executable
+                .strip_prefix(&metadata.workspace_root)
+                .unwrap_or(&executable)
+                .to_string_lossy(),
+        ),
+        Cell::new(format_size(size)),
+    ];
+    if let Some(stripped_size) = stripped_size {
+        row.push(Cell::new(format!(
+            "{} ({})",
+            format_size(stripped_size),
+            percentage(stripped_size as f64 - size as f64, size),
+        )));
+    }
+    table.push_row(row);
+    table.print(
+        &mut stdout,
+        PrintOptions::for_stdout(color, no_header, str_width),
+    )?;
+
+    if bloat {
+        let bloat = artifact::bloat(
+            &metadata.workspace_root,
+            &package.name,
+            bin.as_deref(),
+            crates,
+            n,
+        )?;
+
+        let mut table = if crates {
+            Table::new(&["FILE", ".TEXT", "SIZE", "CRATE"])
+        } else {
+            Table::new(&["FILE", ".TEXT", "SIZE", "CRATE", "NAME"])
+        };
+        let rows = bloat
+            .crates
+            .iter()
+            .map(|c| (c.size, Some(&*c.name), None))
+            .chain(
+                bloat
+                    .functions
+                    .iter()
+                    .map(|f| (f.size, f.krate.as_deref(), Some(&*f.name))),
+            );
+        for (size, krate, name) in rows {
+            let mut row = vec![
+                Cell::new(percentage(size as f64, bloat.file_size)),
+                Cell::new(percentage(size as f64, bloat.text_section_size)),
+                Cell::new(format_size(size)),
+                Cell::new(krate.unwrap_or("?")),
+            ];
+            if let Some(name) = name {
+                row.push(Cell::new(name));
+            }
+            table.push_row(row);
+        }
+        writeln!(stdout)?;
+        table.print(
+            &mut stdout,
+            PrintOptions::for_stdout(color, no_header, str_width),
+        )?;
+    }
+    return stdout.flush().map_err(Into::into);
+
+    fn percentage(numer: f64, denom: u64) -> String {
+        format!("{:.1}%", 100.0 * numer / denom.max(1) as f64)
+    }
+}
+
+fn cargo_bikecase_gist_clone(
+    opt: CargoBikecaseGistClone,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistClone {
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        strict,
+        path,
+        max_size,
+        config,
+        gist_id,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let Metadata { workspace_root, .. } =
+        workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+    let gist_id = config.resolve_gist_id(&gist_id, home_dir.as_deref())?;
+    let clone_path_template = config.content().clone_path_template.clone();
+    let package_name = clone_gist(
+        &workspace_root,
+        &gist_id,
+        path.map(|p| cwd.join(p)),
+        clone_path_template.as_deref(),
+        max_size,
+        strict,
+        dry_run,
+    )?;
+
+    let mut gist_ids = config.gist_ids_mut(&workspace_root, home_dir.as_deref())?;
+    let old_gist_id = gist_ids.get(&package_name).map(|e| e.id.clone());
+    info!(
+        "`gist_ids.{:?}`: {:?} -> {:?}",
+        package_name, old_gist_id, gist_id,
+    );
+    if old_gist_id.as_ref() != Some(&gist_id) {
+        gist_ids.insert(package_name, gist_id.into());
+    }
+    gist_ids.save(dry_run)?;
+    config.save(dry_run)?;
+    Ok(())
+}
+
+fn cargo_bikecase_gist_clone_all(
+    opt: CargoBikecaseGistCloneAll,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistCloneAll {
+        color,
+        ascii,
+        dry_run,
+        strict,
+        overwrite,
+        no_auto_create,
+        file,
+        max_size,
+        config,
+        gist_id,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let config = config_cache.load_or_create_with(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+        no_auto_create,
+    )?;
+    let gist_id = config.resolve_gist_id(&gist_id, home_dir.as_deref())?;
+
+    let files = gist::retrieve_files(&gist_id, max_size)?;
+    let (file_name, content) = match &file {
+        Some(file) => files
+            .get_full(file)
+            .map(|(_, k, v)| (k, v))
+            .with_context(|| format!("`{}` not found in the gist", file))?,
+        None => files
+            .get_full("bikecase.toml")
+            .map(|(_, k, v)| (k, v))
+            .or_else(|| files.iter().exactly_one().ok())
+            .with_context(|| "the gist has multiple files. Specify one with `--file`")?,
+    };
+
+    // `(workspace root, [(package name, gist ID)])`
+    let mut targets = vec![];
+    if file_name.ends_with(".toml") {
+        let profile = BikecaseConfigProfile::parse_profile_or_config(content, home_dir.as_deref())
+            .with_context(|| format!("failed to parse `{}`", file_name))?;
+        let keys = config
+            .content_mut()
+            .import_profile(profile, overwrite, home_dir.as_deref())?;
+        for key in keys {
+            let gist_ids = config.content().workspaces[&key]
+                .gist_ids
+                .iter()
+                .map(|(package, gist_id)| (Some(package.clone()), gist_id.clone()))
+                .collect::<Vec<_>>();
+            targets.push((PathBuf::from(&*key.expand(home_dir.as_deref())), gist_ids));
+        }
+    } else {
+        let default_workspace = config
+            .content()
+            .default_workspace
+            .as_ref()
+            .with_context(|| {
+                format!(
+                    "`default-workspace` is not set: {}",
+                    config.path().display()
+                )
+            })?;
+        let gist_ids = content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !(l.is_empty() || l.starts_with('#')))
+            .map(|gist_id| (None, gist_id.to_owned().into()))
+            .collect();
+        targets.push((
+            PathBuf::from(&*default_workspace.expand(home_dir.as_deref())),
+            gist_ids,
+        ));
+    }
+
+    if let Some(default_workspace) = &config.content().default_workspace {
+        let default_workspace = PathBuf::from(&*default_workspace.expand(home_dir.as_deref()));
+        if !targets.iter().any(|(root, _)| *root == default_workspace) {
+            targets.push((default_workspace, vec![]));
+        }
+    }
+
+    let clone_path_template = config.content().clone_path_template.clone();
+    let mut num_failures = 0;
+    for (workspace_root, gist_ids) in targets {
+        if config.should_create_workspace(&workspace_root)? {
+            workspace::create_workspace(
+                &workspace_root,
+                None,
+                None,
+                None,
+                config.content().profile_preset,
+                dry_run,
+            )?;
+            if dry_run {
+                for (_, gist_id) in gist_ids {
+                    info!("[dry-run] Cloning {}", gist_id);
+                }
+                continue;
+            }
+        }
+
+        let metadata =
+            workspace::cargo_metadata_no_deps(&workspace_root.join("Cargo.toml"), color, &cwd)?;
+        let mut gist_ids_mut = config.gist_ids_mut(&workspace_root, home_dir.as_deref())?;
+        let cloned = gist_ids_mut.clone();
+
+        for (package_name, gist_id) in gist_ids {
+            let package_name = package_name.or_else(|| {
+                cloned
+                    .iter()
+                    .find(|(_, entry)| entry.id == gist_id.id)
+                    .map(|(name, _)| name.clone())
+            });
+            if let Some(package_name) = &package_name {
+                if metadata.packages.iter().any(|p| p.name == *package_name) {
+                    info!("`{}` already exists. Skipping {}", package_name, gist_id);
+                    continue;
+                }
+            }
+
+            match clone_gist(
+                &workspace_root,
+                &gist_id,
+                None,
+                clone_path_template.as_deref(),
+                max_size,
+                strict,
+                dry_run,
+            ) {
+                Ok(package_name) => {
+                    gist_ids_mut.insert(package_name, gist_id);
+                }
+                Err(err) => {
+                    warn!("Failed to clone {}: {:#}", gist_id, err);
+                    num_failures += 1;
+                }
+            }
+        }
+        gist_ids_mut.save(dry_run)?;
+    }
+
+    config.save(dry_run)?;
+    if num_failures > 0 {
+        bail!("failed to clone {} gist(s)", num_failures);
+    }
+    Ok(())
+}
+
+/// Clones a gist as a new member of `workspace_root`, and returns the package name.
+///
+/// If the gist has a lockfile, the locked versions are added to the workspace's `Cargo.lock`.
+fn clone_gist(
+    workspace_root: &Path,
+    gist_id: &str,
+    path: Option<PathBuf>,
+    clone_path_template: Option<&str>,
+    max_size: u64,
+    strict: bool,
+    dry_run: bool,
+) -> anyhow::Result<String> {
+    let gist = gist::retrieve(gist_id, max_size)?;
+    let script = if rust::extract_cargo_lang_code(&gist.script, || "").is_ok() {
+        gist.script.clone()
+    } else {
+        workspace::synthesize_manifest(
+            workspace_root,
+            &gist.script,
+            &gist.script_filename,
+            &gist.description,
+        )?
+    };
+    let mut package_dir = None;
+    let package_name = workspace::import_script(
+        workspace_root,
+        &script,
+        false,
+        strict,
+        dry_run,
+        Some(&gist.script_filename),
+        |package_name| {
+            let path = match (path, clone_path_template) {
+                (Some(path), _) => path,
+                (None, Some(template)) => workspace_root.join(gist.expand_path_template(
+                    template,
+                    gist_id,
+                    package_name,
+                )?),
+                (None, None) => workspace_root.join(package_name),
+            };
+            package_dir = Some(path.clone());
+            Ok(path)
+        },
+    )?;
+    let package_dir = package_dir.expect("should have been set");
+    for (rel, content) in &gist.extra_files {
+        let path = package_dir.join(rel);
+        crate::fs::create_dir_all(path.parent().expect("should not be empty"), dry_run)?;
+        crate::fs::write(&path, content, dry_run)?;
+        event::emit(Event::Diff {
+            name: path.display().to_string(),
+            orig: "".to_owned(),
+            edit: content.clone(),
+        });
+    }
+    if let Some(lockfile) = &gist.lockfile {
+        lockfile::restore(workspace_root, lockfile, dry_run)?;
+    }
+    Ok(package_name)
+}
+
+fn cargo_bikecase_gist_pull(
+    opt: CargoBikecaseGistPull,
+    ctx: Context<impl Sized, impl Sized, impl FnMut(&str) -> io::Result<String>>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistPull {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        diff,
+        diff_threshold,
+        delete,
+        yes,
+        no_input,
+        theirs,
+        ours,
+        backup_local,
+        max_size,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        mut read_password,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+    ui::set_diff_mode(diff, diff_threshold);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+    let gist_ids = config.gist_ids(&metadata.workspace_root, home_dir.as_deref())?;
+    let gist_id = gist_ids
+        .get(&package.name)
+        .with_context(|| format!("could not find the `gist_id` for {:?}", package.name))?;
+
+    let policy = match (theirs, ours, backup_local) {
+        (true, _, _) => Some(PullPolicy::Theirs),
+        (_, true, _) => Some(PullPolicy::Ours),
+        (_, _, true) => Some(PullPolicy::BackupLocal),
+        _ => config.content().pull_policy,
+    };
+
+    let gist::RetrievedGist {
+        script: pulled_code,
+        extra_files,
+        lockfile: pulled_lockfile,
+        ..
+    } = gist::retrieve(gist_id, max_size)?;
+    let (src_path, prev_cargo_toml) = package.find_default_bin()?;
+    let (pulled_code, pulled_cargo_toml) = if rust::extract_cargo_lang_code(&pulled_code, || "")
+        .is_err()
+        && workspace::is_synthetic_manifest(&prev_cargo_toml)
+    {
+        // The gist has not been pushed since it was cloned.
+        let pulled_code = rust::insert_cargo_lang_code(&pulled_code, rust::DEFAULT_MANIFEST)?;
+        (pulled_code, prev_cargo_toml.clone())
+    } else {
+        let (pulled_code, pulled_cargo_toml) =
+            rust::replace_cargo_lang_code_with_default(&pulled_code)?;
+        let pulled_cargo_toml = workspace::inherit_workspace_dependencies(
+            &metadata.workspace_root,
+            &pulled_cargo_toml,
+        )?;
+        (pulled_code, pulled_cargo_toml)
+    };
+
+    let package_dir = package
+        .manifest_path
+        .parent()
+        .expect("`manifest_path` should end with \"Cargo.toml\"");
+
+    let backup_dir = package_dir
+        .join(".bikecase")
+        .join("backups")
+        .join(backup_timestamp());
+    let backup = |path: &Path| -> anyhow::Result<()> {
+        let dst = backup_dir.join(path.strip_prefix(package_dir)?);
+        crate::fs::create_dir_all(dst.parent().expect("should not be empty"), dry_run)?;
+        crate::fs::copy(path, dst, dry_run)
+    };
+
+    // Files in `tests`, `examples`, and `benches` that are not in the gist.
+    let mut extraneous = vec![];
+    for dir in &["tests", "examples", "benches"] {
+        let dir = package_dir.join(dir);
+        if !dir.exists() {
+            continue;
+        }
+        for entry in crate::fs::walk(&dir) {
+            let entry = entry?;
+            let path = entry.path();
+            if path.is_file()
+                && path.extension() != Some("orig".as_ref())
+                && !extra_files.contains_key(path.strip_prefix(package_dir)?)
+            {
+                extraneous.push(entry.into_path());
+            }
+        }
+    }
+
+    // Confirmed before writing anything, so that declining leaves the package as it is.
+    if delete && !extraneous.is_empty() && !yes && !dry_run {
+        let not_confirmed = || {
+            format!(
+                "`--delete` would delete {} file(s). Run with `--yes` to confirm",
+                extraneous.len(),
+            )
+        };
+        if no_input {
+            bail!("{}", not_confirmed());
+        }
+        info_event!("These files are not in the gist:");
+        for path in &extraneous {
+            info_event!("  {}", path.display());
+        }
+        let answer = read_password("Delete them? [y/N] ").with_context(not_confirmed)?;
+        if !["y", "yes"].contains(&&*answer.trim().to_lowercase()) {
+            bail!("{}", not_confirmed());
+        }
+    }
+
+    let mut num_kept = 0;
+    let mut pull = |path: &Path, orig: Option<&str>, edit: &str| -> anyhow::Result<()> {
+        if orig == Some(edit) {
+            info_event!("No changes: {}", path.display());
+            return Ok(());
+        }
+        if orig.is_some() {
+            match policy {
+                Some(PullPolicy::Ours) => {
+                    warn_event!("Kept {} (differs from the gist)", path.display());
+                    num_kept += 1;
+                    return Ok(());
+                }
+                Some(PullPolicy::Theirs) => {
+                    let mut orig_path = path.as_os_str().to_owned();
+                    orig_path.push(".orig");
+                    crate::fs::copy(path, orig_path, dry_run)?;
+                }
+                Some(PullPolicy::BackupLocal) => backup(path)?,
+                None => {}
+            }
+        }
+        event::emit(Event::Diff {
+            name: path.display().to_string(),
+            orig: orig.unwrap_or_default().to_owned(),
+            edit: edit.to_owned(),
+        });
+        if let Some(parent) = path.parent() {
+            if !parent.exists() {
+                crate::fs::create_dir_all(parent, dry_run)?;
+            }
+        }
+        crate::fs::write(path, edit, dry_run)
+    };
+
+    pull(src_path, Some(&crate::fs::read(src_path)?), &pulled_code)?;
+    pull(
+        &package.manifest_path,
+        Some(&prev_cargo_toml),
+        &pulled_cargo_toml,
+    )?;
+
+    for (rel, edit) in &extra_files {
+        let path = package_dir.join(rel);
+        let orig = if path.exists() {
+            Some(crate::fs::read(&path)?)
+        } else {
+            None
+        };
+        pull(&path, orig.as_deref(), edit)?;
+    }
+
+    for path in &extraneous {
+        if delete {
+            if policy == Some(PullPolicy::BackupLocal) {
+                backup(path)?;
+            }
+            crate::fs::remove_file(path, dry_run)?;
+        } else {
+            warn_event!(
+                "{} is not in the gist. Enable `--delete` to delete it",
+                path.display(),
+            );
+        }
+    }
+
+    if let Some(pulled_lockfile) = &pulled_lockfile {
+        lockfile::restore(&metadata.workspace_root, pulled_lockfile, dry_run)?;
+    }
+
+    if num_kept > 0 {
+        warn_event!(
+            "Kept {} local file(s). Enable `--theirs` or `--backup-local` to overwrite them",
+            num_kept,
+        );
+    }
+    return Ok(());
+
+    /// e.g. `20200401T123456Z`
+    fn backup_timestamp() -> String {
+        humantime::format_rfc3339_seconds(SystemTime::now())
+            .to_string()
+            .replace(&['-', ':'][..], "")
+    }
+}
+
+fn cargo_bikecase_gist_push(
+    opt: CargoBikecaseGistPush,
+    ctx: Context<impl Write, impl Sized, impl FnMut(&str) -> io::Result<String>>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistPush {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        diff,
+        diff_threshold,
+        set_upstream,
+        private,
+        description,
+        no_auto_description,
+        with_lockfile,
+        require_tests,
+        if_clean,
+        open,
+        inline_path_deps,
+        changelog,
+        json,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        mut stdout,
+        read_password,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+    ui::set_diff_mode(diff, diff_threshold);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+
+    let workspace = config
+        .content()
+        .workspace(&metadata.workspace_root, home_dir.as_deref());
+    let package_dir = package
+        .manifest_path
+        .parent()
+        .expect("`manifest_path` should end with \"Cargo.toml\"");
+
+    if if_clean || workspace.is_some_and(|w| w.push_if_clean) {
+        workspace::ensure_clean(package_dir)?;
+    }
+
+    if require_tests || workspace.is_some_and(|w| w.push_require_tests) {
+        let cargo_exe = workspace::cargo_exe()?;
+        let args = CargoArgs::new("test")
+            .package(&package.name, &package.manifest_path, color)
+            .into_vec();
+        if dry_run {
+            crate::process::run(&cargo_exe, &args, true)?;
+        } else {
+            crate::process::cmd(&cargo_exe, &args)
+                .dir(&cwd)
+                .stdout_to_stderr()
+                .run()
+                .with_context(|| format!("the tests of `{}` failed. Not pushing", package.name))?;
+        }
+    }
+
+    let mut exported = package.export_bin_script(&metadata.workspace_root, None, false)?;
+    exported.resolve_path_dependencies(inline_path_deps, || {
+        Ok(config.content().path_dependencies.clone())
+    })?;
+    let code = exported.to_code(None)?;
+
+    let github_token = config
+        .content()
+        .github_token
+        .as_ref()
+        .with_context(|| "missing `github-token`")?
+        .load_or_ask(dry_run, home_dir.as_deref(), read_password)?;
+
+    let mut gist_ids = config.gist_ids_mut(&metadata.workspace_root, home_dir.as_deref())?;
+    let gist_id = gist_ids.entry(package.name.clone());
+
+    let auto_description = if description.is_none() && !no_auto_description {
+        rust::doc_summary(&code, 100)?
+    } else {
+        None
+    };
+    let lockfile = if with_lockfile {
+        Some(lockfile::extract(&metadata.workspace_root, &package.name)?)
+    } else {
+        None
+    };
+    let extra_files = package.read_gist_extra_files()?;
+
+    let outcome = gist::push(PushOptions {
+        github_token: &github_token,
+        gist_id,
+        code: &code,
+        workspace_root: &metadata.workspace_root,
+        package: &package.name,
+        set_upstream,
+        private,
+        description: description.as_deref(),
+        auto_description: auto_description.as_deref(),
+        lockfile: lockfile.as_deref(),
+        extra_files: &extra_files,
+        changelog: changelog.as_deref(),
+        dry_run,
+    })?;
+    gist_ids.save(dry_run)?;
+    config.save(dry_run)?;
+
+    if let Some(outcome) = outcome {
+        if json {
+            writeln!(stdout, "{}", serde_json::to_string(&outcome)?)?;
+        } else {
+            writeln!(stdout, "URL: {}", outcome.html_url)?;
+            if let Some(revision) = &outcome.revision {
+                writeln!(stdout, "Revision: {}", revision)?;
+            }
+            for (filename, raw_url) in &outcome.raw_urls {
+                writeln!(stdout, "Raw URL ({}): {}", filename, raw_url)?;
+            }
+        }
+        stdout.flush()?;
+
+        if open {
+            crate::process::open_in_browser(&outcome.html_url)?;
+        }
+    } else if open {
+        info!("Nothing was pushed. Not opening the gist");
+    }
+    Ok(())
+}
+
+fn cargo_bikecase_gist_status(
+    opt: CargoBikecaseGistStatus,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistStatus {
+        manifest_path,
+        color,
+        ascii,
+        no_header,
+        sort,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        stdout,
+        init_logger,
+        str_width,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        false,
+    )?;
+    let gist_ids = config.gist_ids(&metadata.workspace_root, home_dir.as_deref())?;
+
+    let mut table = Table::new(&["NAME", "GIST", "STATUS"]);
+    let cache_dir = data_local_dir.map(|d| d.join("bikecase").join("cache"));
+    for package in ui::sort_listing(
+        &metadata.packages,
+        sort,
+        |p| &p.name,
+        |p| {
+            p.manifest_path
+                .parent()
+                .expect("`manifest_path` should end with \"Cargo.toml\"")
+        },
+        |p| cache::last_run(cache_dir.as_deref()?, &p.manifest_path),
+    ) {
+        let gist_id = gist_ids.get(&package.name);
+
+        let status = if let Some(gist_id) = gist_id {
+            let mut local = vec![];
+            let local = package
+                .export_script(&metadata.workspace_root, &mut local)
+                .and_then(|()| String::from_utf8(local).map_err(Into::into));
+            let remote = gist::retrieve_rust_code(gist_id);
+            match (local, remote) {
+                (Ok(local), Ok((remote, _))) if local == remote => {
+                    Cell::new("up to date").fg(ui::theme().ok)
+                }
+                (Ok(_), Ok(_)) => Cell::new("modified").fg(ui::theme().warn),
+                (Err(err), _) | (_, Err(err)) => {
+                    warn!("{}: {}", package.name, err);
+                    Cell::new("error").fg(ui::theme().error)
+                }
+            }
+        } else {
+            Cell::new("not linked")
+        };
+
+        table.push_row(vec![
+            Cell::new(&package.name),
+            Cell::new(gist_id.map_or("-", |e| &e.id)),
+            status,
+        ]);
+    }
+    table
+        .print(
+            stdout,
+            PrintOptions::for_stdout(color, no_header, str_width),
+        )
+        .map_err(Into::into)
+}
+
+fn cargo_bikecase_gist_list(
+    opt: CargoBikecaseGistList,
+    ctx: Context<impl Write, impl Sized, impl FnMut(&str) -> io::Result<String>>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistList {
+        color,
+        ascii,
+        limit,
+        since,
+        no_header,
+        config,
+    } = opt;
+
+    let Context {
+        home_dir,
+        data_local_dir,
+        stdout,
+        read_password,
+        init_logger,
+        str_width,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        false,
+    )?;
+
+    let github_token = config
+        .content()
+        .github_token
+        .as_ref()
+        .with_context(|| "missing `github-token`")?
+        .load_or_ask(false, home_dir.as_deref(), read_password)?;
+
+    let mut table = Table::new(&["ID", "UPDATED", "FILES", "DESCRIPTION"]);
+    for gist in gist::list(&github_token, since)?.take(limit.unwrap_or(usize::MAX)) {
+        let gist = gist?;
+        let id = Cell::new(&gist.id);
+        table.push_row(vec![
+            if gist.public {
+                id
+            } else {
+                id.fg(ui::theme().accent)
+            },
+            Cell::new(&gist.updated_at),
+            Cell::new(gist.files.keys().join(", ")),
+            Cell::new(gist.description.unwrap_or_default()),
+        ]);
+    }
+    table
+        .print(
+            stdout,
+            PrintOptions::for_stdout(color, no_header, str_width),
+        )
+        .map_err(Into::into)
+}
+
+fn cargo_bikecase_gist_comments(
+    opt: CargoBikecaseGistComments,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistComments {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        mut stdout,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        false,
+    )?;
+    let gist_ids = config.gist_ids(&metadata.workspace_root, home_dir.as_deref())?;
+    let gist_id = gist_ids
+        .get(&package.name)
+        .with_context(|| format!("could not find the `gist_id` for {:?}", package.name))?;
+
+    for comment in gist::list_comments(gist_id)? {
+        let author = comment.user.map_or_else(|| "ghost".to_owned(), |u| u.login);
+        writeln!(stdout, "@{} ({}):", author, comment.created_at)?;
+        for line in gist::render_markdown(&comment.body).lines() {
+            writeln!(stdout, "    {}", line)?;
+        }
+        writeln!(stdout)?;
+    }
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_gist_comment(
+    opt: CargoBikecaseGistComment,
+    ctx: Context<impl Sized, impl Sized, impl FnMut(&str) -> io::Result<String>>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistComment {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        message,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        read_password,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+
+    let github_token = config
+        .content()
+        .github_token
+        .as_ref()
+        .with_context(|| "missing `github-token`")?
+        .load_or_ask(dry_run, home_dir.as_deref(), read_password)?;
+
+    let gist_ids = config.gist_ids(&metadata.workspace_root, home_dir.as_deref())?;
+    let gist_id = gist_ids
+        .get(&package.name)
+        .with_context(|| format!("could not find the `gist_id` for {:?}", package.name))?;
+
+    gist::post_comment(&github_token, gist_id, &message, dry_run)
+}
+
+fn cargo_bikecase_gist_publish(
+    opt: CargoBikecaseGistPublish,
+    public: bool,
+    ctx: Context<impl Write, impl Sized, impl FnMut(&str) -> io::Result<String>>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseGistPublish {
+        package,
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        delete_old,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        mut stdout,
+        read_password,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, package.as_deref())?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+
+    let github_token = config
+        .content()
         .github_token
         .as_ref()
         .with_context(|| "missing `github-token`")?
         .load_or_ask(dry_run, home_dir.as_deref(), read_password)?;
 
-    let gist_id = config
-        .content_mut()
-        .workspace_or_default(&metadata.workspace_root, home_dir.as_deref())?
-        .gist_ids
-        .entry(package.name.clone());
-
-    let (src_path, cargo_toml) = package.find_default_bin()?;
-    let (code, _) =
-        rust::replace_cargo_lang_code(&crate::fs::read(src_path)?, &cargo_toml, || {
-            anyhow!(
-                "could not find the `cargo` code block: {}",
-                src_path.display(),
-            )
-        })?;
+    let mut gist_ids = config.gist_ids_mut(&metadata.workspace_root, home_dir.as_deref())?;
+    let old_id = gist_ids
+        .get(&package.name)
+        .map(|e| e.id.clone())
+        .with_context(|| format!("could not find the `gist_id` for {:?}", package.name))?;
+
+    let (new_id, outcome) =
+        match gist::republish(&github_token, &old_id, public, delete_old, dry_run)? {
+            Some(republished) => republished,
+            None => return Ok(()),
+        };
+    info_event!(
+        "`workspaces.{:?}.gist_ids.{:?}`: Some({:?}) → Some({:?})",
+        metadata.workspace_root,
+        package.name,
+        old_id,
+        new_id,
+    );
+    gist_ids
+        .entry(package.name.clone())
+        .and_modify(|entry| {
+            entry.id = new_id.clone();
+            entry.url = None;
+        })
+        .or_insert_with(|| new_id.into());
+    gist_ids.save(dry_run)?;
+    config.save(dry_run)?;
+
+    writeln!(stdout, "URL: {}", outcome.html_url)?;
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_config_export(
+    opt: CargoBikecaseConfigExport,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseConfigExport {
+        color,
+        ascii,
+        config,
+    } = opt;
+
+    let Context {
+        home_dir,
+        data_local_dir,
+        mut stdout,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        false,
+    )?;
+    let profile = config.content().export_profile(home_dir.as_deref())?;
+
+    stdout.write_all(toml::to_string_pretty(&profile)?.as_ref())?;
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_config_import(
+    opt: CargoBikecaseConfigImport,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseConfigImport {
+        color,
+        ascii,
+        dry_run,
+        overwrite,
+        config,
+        profile,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let profile = crate::fs::read_toml(cwd.join(profile))?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+    config
+        .content_mut()
+        .import_profile(profile, overwrite, home_dir.as_deref())?;
+    config.save(dry_run)
+}
+
+fn cargo_bikecase_default_members_add(
+    opt: CargoBikecaseDefaultMembersAdd,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseDefaultMembersAdd {
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        spec,
+    } = opt;
+
+    let Context {
+        cwd, init_logger, ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, Some(&spec))?;
+    let dir = package
+        .manifest_path
+        .parent()
+        .expect("`manifest_path` should end with \"Cargo.toml\"");
+
+    workspace::modify_default_members(&metadata.workspace_root, Some(dir), None, dry_run)
+}
+
+fn cargo_bikecase_default_members_rm(
+    opt: CargoBikecaseDefaultMembersRm,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseDefaultMembersRm {
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        spec,
+    } = opt;
+
+    let Context {
+        cwd, init_logger, ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+    let package = metadata.query_for_member(&manifest_path, Some(&spec))?;
+    let dir = package
+        .manifest_path
+        .parent()
+        .expect("`manifest_path` should end with \"Cargo.toml\"");
+
+    workspace::modify_default_members(&metadata.workspace_root, None, Some(dir), dry_run)
+}
+
+fn cargo_bikecase_default_members_list(
+    opt: CargoBikecaseDefaultMembersList,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseDefaultMembersList {
+        manifest_path,
+        color,
+        ascii,
+    } = opt;
+
+    let Context {
+        cwd,
+        mut stdout,
+        init_logger,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    for default_member in workspace::default_members(&metadata.workspace_root)? {
+        writeln!(stdout, "{}", default_member)?;
+    }
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_trust_list(
+    opt: CargoBikecaseTrustList,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseTrustList {
+        color,
+        ascii,
+        no_header,
+    } = opt;
+
+    let Context {
+        data_local_dir,
+        stdout,
+        init_logger,
+        str_width,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let store = trust::TrustStore::load(data_local_dir.as_deref())?;
+    let mut table = Table::new(&["SOURCE", "SHA256", "TRUSTED AT"]);
+    for entry in store.entries() {
+        table.push_row(vec![
+            Cell::new(&entry.source),
+            Cell::new(&entry.sha256[..12.min(entry.sha256.len())]),
+            Cell::new(&entry.trusted_at),
+        ]);
+    }
+    table
+        .print(
+            stdout,
+            PrintOptions::for_stdout(color, no_header, str_width),
+        )
+        .map_err(Into::into)
+}
+
+fn cargo_bikecase_trust_revoke(
+    opt: CargoBikecaseTrustRevoke,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseTrustRevoke {
+        color,
+        ascii,
+        dry_run,
+        source,
+    } = opt;
+
+    let Context {
+        cwd,
+        data_local_dir,
+        init_logger,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let key = ScriptSource::parse(Some(source.as_ref()), &cwd)?
+        .remote_key()
+        .with_context(|| format!("{:?} is not a URL or a gist", source))?;
+    let mut store = trust::TrustStore::load(data_local_dir.as_deref())?;
+    match store.revoke(&key) {
+        0 => bail!("{} is not trusted", key),
+        n => info_event!("Revoked {} ({} revision(s))", key, n),
+    }
+    store.save(dry_run)
+}
+
+fn cargo_bikecase_snapshot_create(
+    opt: CargoBikecaseSnapshotCreate,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseSnapshotCreate {
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        config,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        mut stdout,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+    let config = config
+        .content()
+        .workspace(&metadata.workspace_root, home_dir.as_deref());
+
+    let dir = snapshot::dir(data_local_dir.as_deref(), &metadata.workspace_root)?;
+    let path = Snapshot::collect(&metadata, config)?.write(&dir, dry_run)?;
+
+    writeln!(stdout, "{}", path.display())?;
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_snapshot_restore(
+    opt: CargoBikecaseSnapshotRestore,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseSnapshotRestore {
+        manifest_path,
+        color,
+        ascii,
+        dry_run,
+        config,
+        snapshot,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+
+    let dir = snapshot::dir(data_local_dir.as_deref(), &metadata.workspace_root)?;
+    let snapshot = Snapshot::read(&snapshot::find(&dir, snapshot.as_deref(), &cwd)?)?;
+
+    snapshot.restore_files(&metadata.workspace_root, dry_run)?;
+
+    if let Some(config_workspace) = snapshot.config()? {
+        *config
+            .content_mut()
+            .workspace_or_default(&metadata.workspace_root, home_dir.as_deref())? =
+            config_workspace;
+        config.save(dry_run)?;
+    }
+    Ok(())
+}
+
+fn cargo_bikecase_snapshot_diff(
+    opt: CargoBikecaseSnapshotDiff,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseSnapshotDiff {
+        manifest_path,
+        color,
+        ascii,
+        config,
+        snapshot,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        mut stdout,
+        init_logger,
+        str_width,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let metadata = workspace::cargo_metadata_no_deps(&manifest_path, color, &cwd)?;
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        false,
+    )?;
+    let config = config
+        .content()
+        .workspace(&metadata.workspace_root, home_dir.as_deref());
+
+    let dir = snapshot::dir(data_local_dir.as_deref(), &metadata.workspace_root)?;
+    let snapshot = Snapshot::read(&snapshot::find(&dir, snapshot.as_deref(), &cwd)?)?;
+    let current = Snapshot::collect(&metadata, config)?;
+
+    for diff in snapshot.diff(&current) {
+        let mark = match diff.kind {
+            SnapshotDiffKind::Added => 'A',
+            SnapshotDiffKind::Removed => 'D',
+            SnapshotDiffKind::Modified => 'M',
+        };
+        writeln!(stdout, "{} {}", mark, diff.path.display())?;
+
+        if let (Some(from), Some(to)) = (diff.from, diff.to) {
+            if let (Ok(from), Ok(to)) = (std::str::from_utf8(from), std::str::from_utf8(to)) {
+                ui::info_diff(from, to, diff.path.display(), str_width);
+            }
+        }
+    }
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_filter_clean(
+    opt: CargoBikecaseFilterClean,
+    ctx: Context<impl Write, impl FnOnce() -> io::Result<String>, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseFilterClean {
+        manifest_path,
+        color,
+    } = opt;
+    cargo_bikecase_filter(manifest_path, color, ctx, workspace::clean_script)
+}
+
+fn cargo_bikecase_filter_smudge(
+    opt: CargoBikecaseFilterSmudge,
+    ctx: Context<impl Write, impl FnOnce() -> io::Result<String>, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseFilterSmudge {
+        manifest_path,
+        color,
+    } = opt;
+    cargo_bikecase_filter(manifest_path, color, ctx, workspace::smudge_script)
+}
+
+fn cargo_bikecase_filter(
+    manifest_path: Option<PathBuf>,
+    color: crate::ColorChoice,
+    ctx: Context<impl Write, impl FnOnce() -> io::Result<String>, impl Sized>,
+    filter: fn(&Path, &str) -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    let Context {
+        cwd,
+        mut stdout,
+        read_input,
+        init_logger,
+        ..
+    } = ctx;
+
+    init_logger(color);
+
+    let manifest_path = workspace::manifest_path(manifest_path.as_deref(), &cwd)?;
+    let workspace_root = workspace::workspace_root(&manifest_path, color, &cwd)?;
+
+    let script = filter(&workspace_root, &read_input()?)?;
+    stdout.write_all(script.as_ref())?;
+    stdout.flush().map_err(Into::into)
+}
+
+fn cargo_bikecase_replay<
+    W: Write,
+    I: FnOnce() -> io::Result<String>,
+    P: FnMut(&str) -> io::Result<String>,
+>(
+    opt: CargoBikecaseReplay,
+    ctx: Context<W, I, P>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseReplay {
+        color,
+        ascii,
+        dry_run,
+        bundle,
+        args,
+    } = opt;
+
+    let Context {
+        cwd,
+        home_dir,
+        data_local_dir,
+        stdout,
+        read_input,
+        read_password,
+        init_logger,
+        init_json_logger: _,
+        str_width,
+        config_cache: _,
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let bundle = cwd.join(bundle);
+    let record::Bundle {
+        info,
+        script,
+        arg_file,
+        config,
+        manifest,
+    } = record::Bundle::read(&bundle)?;
+
+    info!(
+        "Recorded with bikecase {} ({}, {}-{})",
+        info.bikecase_version,
+        info.cargo_version
+            .as_deref()
+            .unwrap_or("unknown cargo version"),
+        info.arch,
+        info.os,
+    );
+    if info.bikecase_version != env!("CARGO_PKG_VERSION") {
+        warn!(
+            "The bundle was recorded with bikecase {}, but this is {}",
+            info.bikecase_version,
+            env!("CARGO_PKG_VERSION"),
+        );
+    }
+
+    let dir = data_local_dir
+        .as_deref()
+        .with_context(|| "could not find the local data directory")?
+        .join("bikecase")
+        .join("replays")
+        .join(&cache::sha256(&bundle.to_string_lossy())[..16]);
+    if dir.exists() {
+        crate::fs::remove_dir_all(&dir, false)?;
+    }
+    crate::fs::create_dir_all(dir.join("workspace"), false)?;
+
+    let mut opt = serde_json::from_value::<Bikecase>(info.args.clone())
+        .with_context(|| format!("{}: invalid arguments", bundle.display()))?;
+    if !args.is_empty() {
+        opt.args = args;
+    } else if info.args_omitted {
+        warn_event!("The program arguments were not recorded. Pass them after `--` if needed");
+    }
+
+    crate::fs::write(dir.join("script.rs"), script, false)?;
+    opt.file = Some(dir.join("script.rs"));
+
+    let workspace_root = dir.join("workspace");
+    let config = record::relocate_config(&config, &info.workspace_root, &workspace_root)?;
+    crate::fs::write(dir.join("config.toml"), config, false)?;
+    opt.config = dir.join("config.toml");
+
+    opt.arg_file = if let Some(arg_file) = arg_file {
+        crate::fs::write(dir.join("args.txt"), arg_file, false)?;
+        Some(dir.join("args.txt"))
+    } else {
+        None
+    };
+
+    let manifest_path = workspace_root.join("Cargo.toml");
+    if let Some(manifest) = manifest {
+        crate::fs::write(&manifest_path, record::isolate_manifest(&manifest)?, false)?;
+    } else {
+        crate::fs::remove_dir_all(&workspace_root, false)?;
+    }
+    opt.manifest_path = Some(manifest_path);
+
+    opt.record = None;
+    opt.dry_run |= dry_run;
+    opt.color = color;
+
+    run(
+        opt,
+        Context {
+            cwd: dir,
+            home_dir,
+            data_local_dir,
+            stdout,
+            read_input,
+            read_password,
+            init_logger: |_| (),
+            init_json_logger: || (),
+            str_width,
+            config_cache: ConfigCache::default(),
+        },
+    )
+}
+
+fn cargo_bikecase_self_update(
+    opt: CargoBikecaseSelfUpdate,
+    ctx: Context<impl Sized, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseSelfUpdate {
+        color,
+        ascii,
+        offline,
+        dry_run,
+        config,
+    } = opt;
+
+    let Context {
+        home_dir,
+        data_local_dir,
+        init_logger,
+        mut config_cache,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    if offline {
+        bail!("cannot update with `--offline`");
+    }
+
+    let config = config_cache.load_or_create(
+        &config,
+        home_dir.as_deref(),
+        data_local_dir.as_deref(),
+        dry_run,
+    )?;
+    if config.content().disable_self_update {
+        bail!(
+            "`self update` is disabled by `disable-self-update` in {}",
+            config.path().display(),
+        );
+    }
+
+    update::self_update(dry_run)
+}
+
+fn cargo_bikecase_version(
+    opt: CargoBikecaseVersion,
+    ctx: Context<impl Write, impl Sized, impl Sized>,
+) -> anyhow::Result<()> {
+    let CargoBikecaseVersion { color, ascii, json } = opt;
+
+    let Context {
+        mut stdout,
+        init_logger,
+        ..
+    } = ctx;
+
+    init_logger(color);
+    ui::set_ascii(ascii);
+
+    let info = BuildInfo::detect();
+    if json {
+        writeln!(stdout, "{}", serde_json::to_string(&info)?)?;
+        stdout.flush()?;
+    } else {
+        info.write_text(stdout)?;
+    }
+    Ok(())
+}
+
+#[derive(StructOpt, Deserialize, Serialize, Clone, Debug)]
+#[structopt(
+    author,
+    about,
+    settings(&[AppSettings::DeriveDisplayOrder, AppSettings::UnifiedHelpMessage])
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Bikecase {
+    /// [cargo] Number of parallel jobs, defaults to # of CPUs
+    #[structopt(long, value_name("N"))]
+    pub jobs: Option<u32>,
+
+    /// [cargo] Build artifacts in release mode, with optimizations
+    #[structopt(long)]
+    pub release: bool,
+
+    /// [cargo] Build artifacts with the specified profile
+    #[structopt(long, value_name("PROFILE-NAME"))]
+    pub profile: Option<String>,
+
+    /// [cargo] Space-separated list of features to activate
+    #[structopt(long, value_name("FEATURES"), min_values(1))]
+    pub features: Vec<String>,
+
+    /// [cargo] Activate all available features
+    #[structopt(long)]
+    pub all_features: bool,
+
+    /// [cargo] Do not activate the `default` feature
+    #[structopt(long)]
+    pub no_default_features: bool,
+
+    /// [cargo] Build for the target triple
+    #[structopt(long, value_name("TRIPLE"))]
+    pub target: Option<PathBuf>,
+
+    /// [cargo] Error format
+    #[structopt(
+        long,
+        value_name("FMT"),
+        case_insensitive(true),
+        possible_values(&["human", "json", "short"]),
+        default_value("human")
+    )]
+    pub message_format: Vec<String>,
+
+    /// [cargo] Use verbose output (-vv very verbose/build.rs output)
+    #[structopt(short, long, parse(from_occurrences))]
+    pub verbose: u32,
+
+    /// [cargo] Require Cargo.lock and cache are up to date
+    #[structopt(long)]
+    pub frozen: bool,
+
+    /// [cargo] Require Cargo.lock is up to date
+    #[structopt(long)]
+    pub locked: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// [cargo] Ignore `rust-version` specification in packages
+    #[structopt(long)]
+    #[serde(default)]
+    pub ignore_rust_version: bool,
+
+    /// What to do with the script. `build` and `check` stop after `cargo build`/`cargo check`.
+    /// `test` runs `cargo test` instead of the program. Defaults to `mode` in the config, or `run`
+    #[structopt(long, value_name("MODE"), possible_values(crate::Mode::VARIANTS))]
+    #[serde(default)]
+    pub mode: Option<crate::Mode>,
+
+    /// Run the program under Miri with `cargo +nightly miri run`, adding the `miri` component with
+    /// rustup first. The build cache is not used
+    #[structopt(long, conflicts_with_all(&["mode", "list-targets"]))]
+    #[serde(default)]
+    pub miri: bool,
+
+    /// Run the unit tests of the script with `cargo test` instead of the program, passing ARGS to
+    /// the test harness. Same as `--mode test`
+    #[structopt(
+        long,
+        conflicts_with_all(&["mode", "miri", "list-targets", "skip-build", "install"])
+    )]
+    #[serde(default)]
+    pub test: bool,
+
+    /// Only check the script with `cargo check` without running it. Same as `--mode check`
+    #[structopt(
+        long,
+        conflicts_with_all(&["mode", "miri", "list-targets", "skip-build", "install", "test"])
+    )]
+    #[serde(default)]
+    pub check: bool,
+
+    /// `MIRIFLAGS` for `--miri` (e.g. `-Zmiri-disable-isolation`)
+    #[structopt(long, value_name("FLAGS"), requires("miri"), allow_hyphen_values(true))]
+    #[serde(default)]
+    pub miri_flags: Option<String>,
+
+    /// Save the script as src/bin/<NAME>.rs instead of src/main.rs
+    #[structopt(long, value_name("NAME"))]
+    pub bin: Option<String>,
+
+    /// [cargo] Run the example <NAME> of the package instead of the binary
+    #[structopt(long, value_name("NAME"))]
+    #[serde(default)]
+    pub example: Option<String>,
+
+    /// List the `bin` and `example` targets of the package as `<KIND> <NAME>` lines and exit
+    #[structopt(long, conflicts_with("pipe"))]
+    #[serde(default)]
+    pub list_targets: bool,
+
+    /// Override `package.name` of the embedded manifest
+    #[structopt(long, value_name("NAME"))]
+    pub package_name: Option<String>,
+
+    /// Path to the virtual manifest, or to the manifest of a member of it
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+
+    /// Coloring. Forwarded to `cargo build`, and to the program as `CLICOLOR_FORCE`/`NO_COLOR`
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    #[serde(default)]
+    pub ascii: bool,
+
+    /// Write logs to stderr as JSON lines. `BIKECASE_LOG_FORMAT=json` does the same when stderr is
+    /// not a TTY
+    #[structopt(long)]
+    #[serde(default)]
+    pub json_logs: bool,
+
+    /// Kill the program after <DURATION> (e.g. `30s`, `5m`) and exit with 124
+    #[structopt(long, value_name("DURATION"), parse(try_from_str = humantime::parse_duration))]
+    pub timeout: Option<Duration>,
+
+    /// Limit the address space of the program to <SIZE> (e.g. `512M`, `2G`). Uses a Job Object on
+    /// Windows
+    #[structopt(long, value_name("SIZE"), parse(try_from_str = parse_size))]
+    pub max_memory: Option<u64>,
+
+    /// Limit the CPU time of the program to <SECONDS>. Uses a Job Object on Windows
+    #[structopt(long, value_name("SECONDS"))]
+    pub max_cpu_seconds: Option<u64>,
+
+    /// Pipe stdout and stderr of the program through bikecase, prefixing each line with the
+    /// package name and the stream
+    #[structopt(long)]
+    pub prefix_output: bool,
+
+    /// Prefix each line with a timestamp as well. Requires `--prefix-output`
+    #[structopt(long, requires("prefix-output"))]
+    pub timestamps: bool,
+
+    /// Write stdout of the program to <PATH> as well, defaults to
+    /// `<package>/captures/<timestamp>.log`
+    #[structopt(long, value_name("PATH"), require_equals(true))]
+    #[serde(default)]
+    pub capture: Option<Option<PathBuf>>,
+
+    /// Write stderr to the file of `--capture` as well
+    #[structopt(long, requires("capture"))]
+    #[serde(default)]
+    pub capture_stderr: bool,
+
+    /// Print the resolved dependencies of the package before building
+    #[structopt(long)]
+    pub locked_deps_report: bool,
+
+    /// Refuse to build if the dependencies violate <POLICY>. Implies `--locked-deps-report`
+    #[structopt(
+        long,
+        value_name("POLICY"),
+        possible_values(crate::Deny::VARIANTS),
+        number_of_values(1)
+    )]
+    pub deny: Vec<crate::Deny>,
+
+    /// Read additional arguments for the compiled program from <PATH>, one per line. Empty lines
+    /// are skipped
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub arg_file: Option<PathBuf>,
+
+    /// Save the script, the config, the workspace manifest, and the options to <PATH> as a
+    /// zstd-compressed tarball (`.tar.zst`) for `cargo bikecase replay`. The values of `env` in the
+    /// config are redacted, and the program arguments are omitted unless `--record-args`
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub record: Option<PathBuf>,
+
+    /// Include ARGS and the content of `--arg-file` in the bundle of `--record`
+    #[structopt(long, requires("record"))]
+    #[serde(default)]
+    pub record_args: bool,
+
+    /// Trust the script fetched from a URL or a gist without prompting, and remember it in
+    /// `<data local dir>/bikecase/trust.json`
+    #[structopt(long)]
+    #[serde(default)]
+    pub trust: bool,
+
+    /// Fail instead of prompting, e.g. when the script fetched from a URL or a gist is not trusted
+    #[structopt(long)]
+    #[serde(default)]
+    pub no_input: bool,
+
+    /// Run the executable built last for the package and the build options without `cargo build`,
+    /// even if the script has changed since
+    #[structopt(
+        short("S"),
+        long,
+        conflicts_with_all(&["mode", "miri", "list-targets", "pipe"])
+    )]
+    #[serde(default)]
+    pub skip_build: bool,
+
+    /// Build the program in release mode (unless `--profile` is given) and copy the executable to
+    /// `install-dir` in the config, or `$CARGO_HOME/bin`, instead of running it
+    #[structopt(long, conflicts_with_all(&["mode", "miri", "list-targets", "pipe"]))]
+    #[serde(default)]
+    pub install: bool,
+
+    /// Run the script again whenever it or its package changes, until the script is removed. The
+    /// program is killed if it is still running. FILE has to be a local file
+    #[structopt(
+        long,
+        requires("file"),
+        conflicts_with_all(&["pipe", "list-targets", "install", "skip-build", "dry-run", "record"])
+    )]
+    #[serde(default)]
+    pub watch: bool,
+
+    /// Same as `--watch`, but stop the program with SIGTERM and kill it only if it does not exit
+    /// within `--grace-period`, for servers. The program is killed right away on Windows
+    #[structopt(
+        long,
+        requires("file"),
+        conflicts_with_all(&[
+            "watch",
+            "pipe",
+            "list-targets",
+            "install",
+            "skip-build",
+            "dry-run",
+            "record",
+        ])
+    )]
+    #[serde(default)]
+    pub restart_on_change: bool,
+
+    /// How long `--restart-on-change` waits for the program to exit [default: 5s]
+    #[structopt(
+        long,
+        value_name("DURATION"),
+        parse(try_from_str = humantime::parse_duration),
+        requires("restart-on-change")
+    )]
+    pub grace_period: Option<Duration>,
+
+    /// Dry run. Print the `cargo build` command instead of building and running the program
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of creating the config, the workspace, or the template package
+    #[structopt(long)]
+    #[serde(default)]
+    pub no_auto_create: bool,
+
+    /// Print the effective config with `~` and environment variables expanded, and exit. The
+    /// GitHub token is shown only by its path
+    #[structopt(long)]
+    #[serde(default)]
+    pub print_config: bool,
+
+    /// Run FILE and ARGS (after `--`) as scripts connected stdout to stdin, after building all
+    /// of them. `a.rs:--flag --name 'a b'` passes arguments to a stage, quoted as in the shell
+    #[structopt(
+        long,
+        requires("file"),
+        conflicts_with_all(&["prefix-output", "arg-file", "record", "package-name"])
+    )]
+    #[serde(default)]
+    pub pipe: bool,
+
+    /// Feed <PATH> to stdin of the program. `-` reads stdin of bikecase, which requires FILE
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub stdin_data: Option<PathBuf>,
+
+    /// Path to the script, an `http(s)://` URL, or `gist:<ID>[@<REVISION>]`. Read from stdin if
+    /// omitted
+    #[structopt(parse(try_from_os_str = crate::parse_path))]
+    pub file: Option<PathBuf>,
+
+    /// Arguments for the compiled program. `{script_dir}` and `{package}` are expanded
+    #[structopt(parse(from_os_str), raw(true))]
+    pub args: Vec<OsString>,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(
+    author,
+    about,
+    global_settings(&[AppSettings::DeriveDisplayOrder, AppSettings::UnifiedHelpMessage])
+)]
+pub enum Cargo {
+    #[structopt(author, about)]
+    Bikecase(CargoBikecase),
+}
+
+#[derive(StructOpt, Debug)]
+pub enum CargoBikecase {
+    /// Create a new workspace in an existing directory
+    #[structopt(author)]
+    InitWorkspace(CargoBikecaseInitWorkspace),
+
+    /// Write a preset of `[profile.dev]` into the manifest of a workspace
+    #[structopt(author)]
+    OptimizeWorkspace(CargoBikecaseOptimizeWorkspace),
+
+    /// Create a new workspace member from a template
+    #[structopt(author)]
+    New(CargoBikecaseNew),
+
+    /// Remove a workspace member
+    #[structopt(author)]
+    Rm(CargoBikecaseRm),
+
+    /// Include a package in the workspace
+    #[structopt(author)]
+    Include(CargoBikecaseInclude),
+
+    /// Exclude a package from the workspace
+    #[structopt(author)]
+    Exclude(CargoBikecaseExclude),
+
+    /// Move a workspace member to `archive/` and exclude it from the workspace
+    #[structopt(author)]
+    Archive(CargoBikecaseArchive),
+
+    /// Move an archived package back to the workspace
+    #[structopt(author)]
+    Unarchive(CargoBikecaseUnarchive),
+
+    /// Import a script as a package (in the same format as `cargo-script`)
+    #[structopt(author)]
+    Import(CargoBikecaseImport),
+
+    /// Export a package as a script (in the same format as `cargo-script`)
+    #[structopt(author)]
+    Export(CargoBikecaseExport),
+
+    /// Check that the workspace members build, or that scripts are in sync with them
+    #[structopt(author)]
+    Verify(CargoBikecaseVerify),
+
+    /// Generate `README.md` of a package from the module doc of the script
+    #[structopt(author)]
+    Readme(CargoBikecaseReadme),
+
+    /// Set the default flags for building a package with `bikecase`
+    #[structopt(author)]
+    Set(CargoBikecaseSet),
+
+    /// List the workspace members
+    #[structopt(author)]
+    Ls(CargoBikecaseLs),
+
+    /// Generate editor integration files for a package
+    #[structopt(author)]
+    Ide(CargoBikecaseIde),
+
+    /// Open a package in the editor
+    #[structopt(author)]
+    Open(CargoBikecaseOpen),
+
+    /// Display the dependency tree of a package with `cargo tree`
+    #[structopt(author)]
+    Tree(CargoBikecaseTree),
+
+    /// Explain why a package depends on a crate, pointing to the entries in its manifest
+    #[structopt(author)]
+    Why(CargoBikecaseWhy),
+
+    /// Check the dependencies of a package for security advisories with `cargo audit`
+    #[structopt(author)]
+    Audit(CargoBikecaseAudit),
+
+    /// Build a package in release mode and report the size of the binary
+    #[structopt(author)]
+    Size(CargoBikecaseSize),
+
+    /// Gist
+    #[structopt(author)]
+    Gist(CargoBikecaseGist),
+
+    /// Manage the scripts from URLs and gists trusted to run
+    #[structopt(author)]
+    Trust(CargoBikecaseTrust),
+
+    /// Export or import a portable config profile
+    #[structopt(author)]
+    Config(CargoBikecaseConfig),
+
+    /// Manage `workspace.default-members`
+    #[structopt(author)]
+    DefaultMembers(CargoBikecaseDefaultMembers),
+
+    /// Snapshot
+    #[structopt(author)]
+    Snapshot(CargoBikecaseSnapshot),
+
+    /// Filter scripts for `git` (`filter.<driver>.clean`/`smudge` in `.gitattributes`)
+    #[structopt(author)]
+    Filter(CargoBikecaseFilter),
+
+    /// Run `bikecase` again with a bundle saved by `bikecase --record`
+    #[structopt(author)]
+    Replay(CargoBikecaseReplay),
+
+    /// Manage the bikecase installation
+    #[structopt(author, name = "self")]
+    Itself(CargoBikecaseSelf),
+
+    /// Print the version and how the binary was built
+    #[structopt(author)]
+    Version(CargoBikecaseVersion),
+}
+
+impl CargoBikecase {
+    pub fn color(&self) -> crate::ColorChoice {
+        match *self {
+            CargoBikecase::InitWorkspace(CargoBikecaseInitWorkspace { color, .. })
+            | CargoBikecase::OptimizeWorkspace(CargoBikecaseOptimizeWorkspace { color, .. })
+            | CargoBikecase::New(CargoBikecaseNew { color, .. })
+            | CargoBikecase::Rm(CargoBikecaseRm { color, .. })
+            | CargoBikecase::Include(CargoBikecaseInclude { color, .. })
+            | CargoBikecase::Exclude(CargoBikecaseExclude { color, .. })
+            | CargoBikecase::Archive(CargoBikecaseArchive { color, .. })
+            | CargoBikecase::Unarchive(CargoBikecaseUnarchive { color, .. })
+            | CargoBikecase::Import(CargoBikecaseImport { color, .. })
+            | CargoBikecase::Export(CargoBikecaseExport { color, .. })
+            | CargoBikecase::Verify(CargoBikecaseVerify { color, .. })
+            | CargoBikecase::Readme(CargoBikecaseReadme { color, .. })
+            | CargoBikecase::Set(CargoBikecaseSet { color, .. })
+            | CargoBikecase::Ls(CargoBikecaseLs { color, .. })
+            | CargoBikecase::Ide(CargoBikecaseIde { color, .. })
+            | CargoBikecase::Open(CargoBikecaseOpen { color, .. })
+            | CargoBikecase::Tree(CargoBikecaseTree { color, .. })
+            | CargoBikecase::Why(CargoBikecaseWhy { color, .. })
+            | CargoBikecase::Audit(CargoBikecaseAudit { color, .. })
+            | CargoBikecase::Size(CargoBikecaseSize { color, .. })
+            | CargoBikecase::Gist(CargoBikecaseGist::Clone(CargoBikecaseGistClone {
+                color, ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::CloneAll(CargoBikecaseGistCloneAll {
+                color,
+                ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::Pull(CargoBikecaseGistPull {
+                color, ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::Push(CargoBikecaseGistPush {
+                color, ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::Status(CargoBikecaseGistStatus {
+                color,
+                ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::List(CargoBikecaseGistList {
+                color, ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::Comments(CargoBikecaseGistComments {
+                color,
+                ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::Comment(CargoBikecaseGistComment {
+                color,
+                ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::Publish(CargoBikecaseGistPublish {
+                color,
+                ..
+            }))
+            | CargoBikecase::Gist(CargoBikecaseGist::Unpublish(CargoBikecaseGistPublish {
+                color,
+                ..
+            }))
+            | CargoBikecase::Config(CargoBikecaseConfig::Export(CargoBikecaseConfigExport {
+                color,
+                ..
+            }))
+            | CargoBikecase::Config(CargoBikecaseConfig::Import(CargoBikecaseConfigImport {
+                color,
+                ..
+            }))
+            | CargoBikecase::DefaultMembers(CargoBikecaseDefaultMembers::Add(
+                CargoBikecaseDefaultMembersAdd { color, .. },
+            ))
+            | CargoBikecase::DefaultMembers(CargoBikecaseDefaultMembers::Rm(
+                CargoBikecaseDefaultMembersRm { color, .. },
+            ))
+            | CargoBikecase::DefaultMembers(CargoBikecaseDefaultMembers::List(
+                CargoBikecaseDefaultMembersList { color, .. },
+            ))
+            | CargoBikecase::Trust(CargoBikecaseTrust::List(CargoBikecaseTrustList {
+                color,
+                ..
+            }))
+            | CargoBikecase::Trust(CargoBikecaseTrust::Revoke(CargoBikecaseTrustRevoke {
+                color,
+                ..
+            }))
+            | CargoBikecase::Snapshot(CargoBikecaseSnapshot::Create(
+                CargoBikecaseSnapshotCreate { color, .. },
+            ))
+            | CargoBikecase::Snapshot(CargoBikecaseSnapshot::Restore(
+                CargoBikecaseSnapshotRestore { color, .. },
+            ))
+            | CargoBikecase::Snapshot(CargoBikecaseSnapshot::Diff(CargoBikecaseSnapshotDiff {
+                color,
+                ..
+            }))
+            | CargoBikecase::Filter(CargoBikecaseFilter::Clean(CargoBikecaseFilterClean {
+                color,
+                ..
+            }))
+            | CargoBikecase::Filter(CargoBikecaseFilter::Smudge(CargoBikecaseFilterSmudge {
+                color,
+                ..
+            }))
+            | CargoBikecase::Replay(CargoBikecaseReplay { color, .. })
+            | CargoBikecase::Itself(CargoBikecaseSelf::Update(CargoBikecaseSelfUpdate {
+                color,
+                ..
+            }))
+            | CargoBikecase::Version(CargoBikecaseVersion { color, .. }) => color,
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseInitWorkspace {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Maintain `workspace.default-members` with the <N> most recently used members
+    #[structopt(long, value_name("N"))]
+    pub default_members: Option<usize>,
+
+    /// `workspace.resolver`. Defaults to the latest one that the installed `cargo` supports
+    #[structopt(long, value_name("VERSION"), possible_values(&["1", "2", "3"]))]
+    pub resolver: Option<String>,
+
+    /// [cargo] Edition to set for the generated project, written as `workspace.package.edition`.
+    /// Defaults to the latest one that the installed `cargo` supports
+    #[structopt(
+        long,
+        value_name("YEAR"),
+        possible_values(&["2015", "2018", "2021", "2024"])
+    )]
+    pub edition: Option<String>,
+
+    /// Write a preset of `[profile.dev]` for iterating on scripts. See `optimize-workspace`
+    #[structopt(
+        long,
+        value_name("PRESET"),
+        possible_values(crate::ProfilePreset::VARIANTS)
+    )]
+    pub profile_preset: Option<crate::ProfilePreset>,
+
+    /// [cargo] Directory
+    #[structopt(default_value("."), parse(try_from_os_str = crate::parse_path))]
+    pub path: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseOptimizeWorkspace {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// `fast-compile` drops the debug info. `fast-run` optimizes the program a little and the
+    /// dependencies fully. `balanced` keeps limited debug info split off, and optimizes the
+    /// dependencies
+    #[structopt(
+        long,
+        value_name("PRESET"),
+        possible_values(crate::ProfilePreset::VARIANTS),
+        default_value("balanced")
+    )]
+    pub preset: crate::ProfilePreset,
+
+    /// Root directory of the workspace
+    #[structopt(default_value("."), parse(try_from_os_str = crate::parse_path))]
+    pub path: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseNew {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Set the resulting package name, defaults to the directory name
+    #[structopt(long, value_name("NAME"))]
+    pub name: Option<String>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+
+    /// Copy the targets of symlinks in the template instead of recreating the symlinks
+    #[structopt(long)]
+    pub follow_symlinks: bool,
+
+    /// Replace `src/main.rs` with the code of <FILE>, and merge the dependencies in its embedded
+    /// manifest into the template's
+    #[structopt(long, value_name("FILE"), parse(try_from_os_str = crate::parse_path))]
+    pub from_script: Option<PathBuf>,
+
+    /// Set a value for the `.hbs` files in the template. <KEY> alone sets `true`
+    #[structopt(
+        long,
+        value_name("KEY=VALUE"),
+        number_of_values(1),
+        parse(try_from_str = crate::template::parse_var)
+    )]
+    pub var: Vec<(String, String)>,
+
+    /// [cargo] Directory
+    #[structopt(parse(try_from_os_str = crate::parse_path))]
+    pub path: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseRm {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Remove the packages even if remaining members depend on them
+    #[structopt(long)]
+    pub force: bool,
+
+    /// Packages to remove. Dependents are removed before their dependencies
+    #[structopt(required(true), min_values(1))]
+    pub specs: Vec<String>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseInclude {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Embed `Cargo.toml` into `src/main.rs` as a `cargo` code block, so that the package can be
+    /// exported as a script
+    #[structopt(long)]
+    pub scriptify: bool,
+
+    /// Path to the Cargo package to include
+    pub path: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseExclude {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Path to the Cargo package to exclude
+    pub path: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseArchive {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Archive the package even if remaining members depend on it
+    #[structopt(long)]
+    pub force: bool,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+
+    /// Package to archive
+    pub spec: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseUnarchive {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+
+    /// Name of the archived package
+    pub name: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseImport {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// How to print diffs. `auto` prints only the numbers of changed lines for the diffs longer
+    /// than `--diff-threshold`
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::DiffMode::VARIANTS),
+        default_value("auto")
+    )]
+    pub diff: crate::DiffMode,
+
+    /// Number of changed lines above which `--diff auto` prints the numbers only
+    #[structopt(long, value_name("LINES"), default_value("200"))]
+    pub diff_threshold: usize,
+
+    /// Split the top-level `mod x { ... }` blocks into `src/x.rs`. `export` re-inlines them
+    #[structopt(long)]
+    pub split_modules: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Path to create the package, defaults to `<workspace-root>/<package-name>`
+    #[structopt(long, parse(try_from_os_str = crate::parse_path))]
+    pub path: Option<PathBuf>,
+
+    /// Path to the script, a directory to import the scripts in recursively, an `http(s)://` URL,
+    /// or `gist:<ID>[@<REVISION>]`. Read from stdin if omitted
+    #[structopt(parse(try_from_os_str = crate::parse_path))]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseExport {
+    /// [cargo] Package with the target to export
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// [cargo] Export the `bin` target <NAME> instead of the default one
+    #[structopt(long, value_name("NAME"))]
+    pub bin: Option<String>,
+
+    /// Remove `[[bin]]` and the features and optional dependencies that the `bin` target does not
+    /// need from the manifest. Its `required-features` are added to the default features
+    #[structopt(long)]
+    pub prune_manifest: bool,
+
+    /// Compare the exported script with <PATH> instead of printing it, and fail if they differ
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub check: Option<PathBuf>,
+
+    /// Info string of the manifest block to write (e.g. `cargo`, `toml,cargo`)
+    #[structopt(long, value_name("INFO"))]
+    pub emit_fence: Option<String>,
+
+    /// Bundle the libraries of `path` dependencies without `path-dependencies.<name>` in the
+    /// config into the script as modules
+    #[structopt(long)]
+    pub inline_path_deps: bool,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseVerify {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Run `cargo test` after `cargo check`
+    #[structopt(long, conflicts_with("mapping"))]
+    pub test: bool,
+
+    /// Verify only the members whose names match GLOB
+    #[structopt(
+        long,
+        value_name("GLOB"),
+        number_of_values(1),
+        conflicts_with("mapping")
+    )]
+    pub filter: Vec<String>,
+
+    /// Number of members verified in parallel, defaults to # of CPUs
+    #[structopt(short, long, value_name("N"), conflicts_with("mapping"))]
+    pub jobs: Option<u32>,
+
+    /// TOML file mapping package names to script paths (relative to the file). Checks that the
+    /// scripts are in sync with the members instead of building them
+    #[structopt(parse(try_from_os_str = crate::parse_path))]
+    pub mapping: Option<PathBuf>,
+}
+
+/// The template is rendered as the `.hbs` files in the template package are, with `{{crate}}`,
+/// `{{version}}`, `{{license}}`, and `{{readme}}`.
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseReadme {
+    /// [cargo] Package to generate `README.md` of
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail if `README.md` is not up to date, instead of writing it
+    #[structopt(long)]
+    pub check: bool,
+
+    /// Template, defaults to `README.tpl` in the package if any
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub template: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseSet {
+    /// [cargo] Package to set the defaults for
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Remove the current defaults before setting the given ones
+    #[structopt(long)]
+    pub clear: bool,
+
+    /// Build in release mode by default
+    #[structopt(long, conflicts_with("profile"))]
+    pub release: bool,
+
+    /// Build with the profile by default
+    #[structopt(long, value_name("PROFILE-NAME"))]
+    pub profile: Option<String>,
+
+    /// Features to activate in addition to `--features` of `bikecase`
+    #[structopt(long, value_name("FEATURES"), min_values(1))]
+    pub features: Vec<String>,
+
+    /// Build for the target triple by default
+    #[structopt(long, value_name("TRIPLE"))]
+    pub target: Option<String>,
+
+    /// `RUSTFLAGS` for `cargo build`, unless it is already set
+    #[structopt(long, value_name("FLAGS"), allow_hyphen_values(true))]
+    pub rustflags: Option<String>,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseLs {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Do not print the header
+    #[structopt(long)]
+    pub no_header: bool,
+
+    /// Order of the rows. `modified`, `size`, and `last-run` are the newest/largest first
+    #[structopt(
+        long,
+        value_name("KEY"),
+        possible_values(crate::SortKey::VARIANTS),
+        default_value("name")
+    )]
+    pub sort: crate::SortKey,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseIde {
+    /// [cargo] Package to generate the files for
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Print the file to stdout instead of writing it
+    #[structopt(long)]
+    pub print: bool,
+
+    /// Generate `rust-project.json` instead of `.vscode/settings.json`, for editors without Cargo
+    /// support. Build scripts and procedural macros are not supported
+    #[structopt(long)]
+    pub rust_project: bool,
+
+    /// Directory to write the file in, defaults to the current directory
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub dir: Option<PathBuf>,
+
+    /// Create a symlink at <PATH> pointing to the package directory
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub link: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseOpen {
+    /// [cargo] Package to open
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Open the package directory instead of the default `bin` file
+    #[structopt(long)]
+    pub dir: bool,
+
+    /// Print the path instead of launching the editor
+    #[structopt(long)]
+    pub print: bool,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseTree {
+    /// [cargo] Package to display the tree for
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// [cargo] Show only dependencies which come in multiple versions
+    #[structopt(short, long)]
+    pub duplicates: bool,
+
+    /// [cargo] Invert the tree direction and focus on the given package
+    #[structopt(short, long, value_name("SPEC"))]
+    pub invert: Option<String>,
+
+    /// [cargo] The kinds of dependencies to display (e.g. `normal`, `dev`, `no-build`)
+    #[structopt(short, long, value_name("KINDS"), number_of_values(1))]
+    pub edges: Vec<String>,
+
+    /// Annotate the direct dependencies with whether they are declared in the embedded manifest or
+    /// inherited from `workspace.dependencies`
+    #[structopt(long, conflicts_with_all(&["duplicates", "invert"]))]
+    pub embedded: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseWhy {
+    /// [cargo] Package to explain
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Crate to explain (e.g. `openssl`, `openssl@0.10`)
+    pub spec: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseAudit {
+    /// [cargo] Package to audit
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// [cargo] Ignore `rust-version` specification in packages. Requires `--then-run`
+    #[structopt(long, requires("then-run"))]
+    pub ignore_rust_version: bool,
+
+    /// Advisory to ignore (e.g. `RUSTSEC-2020-0071`)
+    #[structopt(long, value_name("ID"), number_of_values(1))]
+    pub ignore: Vec<String>,
+
+    /// Do not print the header
+    #[structopt(long)]
+    pub no_header: bool,
+
+    /// Run the package with `cargo run` if no vulnerabilities are found
+    #[structopt(long)]
+    pub then_run: bool,
+
+    /// Arguments for the program. Requires `--then-run`
+    #[structopt(parse(from_os_str), raw(true), requires("then-run"))]
+    pub args: Vec<OsString>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseSize {
+    /// [cargo] Package to measure
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// [cargo] Measure the `bin` target <NAME> instead of the default one
+    #[structopt(long, value_name("NAME"))]
+    pub bin: Option<String>,
+
+    /// Also build a stripped binary and compare the sizes
+    #[structopt(long)]
+    pub strip: bool,
+
+    /// Show the largest functions with `cargo bloat`
+    #[structopt(long)]
+    pub bloat: bool,
+
+    /// Show the largest crates instead of functions. Requires `--bloat`
+    #[structopt(long, requires("bloat"))]
+    pub crates: bool,
+
+    /// Number of the functions or crates to show with `--bloat`
+    #[structopt(short, value_name("N"), default_value("10"))]
+    pub n: usize,
+
+    /// Do not print the header
+    #[structopt(long)]
+    pub no_header: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum CargoBikecaseGist {
+    /// Clone a script from Gist
+    #[structopt(author)]
+    Clone(CargoBikecaseGistClone),
+
+    /// Merge a config (or a list of gist IDs) stored in a gist and clone every gist in it
+    #[structopt(author)]
+    CloneAll(CargoBikecaseGistCloneAll),
+
+    /// Pull a script from Gist
+    #[structopt(author)]
+    Pull(CargoBikecaseGistPull),
+
+    /// Pull a script to Gist
+    #[structopt(author)]
+    Push(CargoBikecaseGistPush),
+
+    /// Show whether the workspace members are in sync with their gists
+    #[structopt(author)]
+    Status(CargoBikecaseGistStatus),
+
+    /// List your gists
+    #[structopt(author)]
+    List(CargoBikecaseGistList),
+
+    /// Show the comments on the gist
+    #[structopt(author)]
+    Comments(CargoBikecaseGistComments),
+
+    /// Post a comment on the gist
+    #[structopt(author)]
+    Comment(CargoBikecaseGistComment),
+
+    /// Make the gist public by recreating it, since GitHub cannot change the visibility
+    #[structopt(author)]
+    Publish(CargoBikecaseGistPublish),
+
+    /// Make the gist secret by recreating it, since GitHub cannot change the visibility
+    #[structopt(author)]
+    Unpublish(CargoBikecaseGistPublish),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseGistClone {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Path to create the package, defaults to `<workspace-root>/<clone-path-template>` or
+    /// `<workspace-root>/<package-name>`
+    #[structopt(long, parse(try_from_os_str = crate::parse_path))]
+    pub path: Option<PathBuf>,
+
+    /// Maximum size of each file downloaded separately for being too large for the API response
+    #[structopt(long, value_name("SIZE"), default_value("10M"), parse(try_from_str = parse_size))]
+    pub max_size: u64,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+
+    /// Gist ID, or an `alias` in `gist-ids`
+    pub gist_id: String,
+}
+
+/// A file whose name ends with `.toml` is read as a config file or a profile exported by `config
+/// export`. Any other file is read as a list of gist IDs, one per line, to clone into the default
+/// workspace.
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseGistCloneAll {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Fail instead of warning when the workspace manifest has invalid entries
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Replace the local values on conflicts
+    #[structopt(long)]
+    pub overwrite: bool,
+
+    /// Fail instead of creating the config or missing workspaces
+    #[structopt(long)]
+    pub no_auto_create: bool,
+
+    /// File in the gist to read, defaults to `bikecase.toml` or the only file
+    #[structopt(long, value_name("NAME"))]
+    pub file: Option<String>,
+
+    /// Maximum size of each file downloaded separately for being too large for the API response
+    #[structopt(long, value_name("SIZE"), default_value("10M"), parse(try_from_str = parse_size))]
+    pub max_size: u64,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+
+    /// ID (or an `alias` in `gist-ids`) of the gist containing the config
+    pub gist_id: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseGistPull {
+    /// [cargo] Package with the target to export
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
 
-    gist::push(PushOptions {
-        github_token: &github_token,
-        gist_id,
-        code: &code,
-        workspace_root: &metadata.workspace_root,
-        package: &package.name,
-        set_upstream,
-        private,
-        description: description.as_deref(),
-        dry_run,
-        str_width,
-    })?;
-    config.save(dry_run)
-}
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
 
-#[derive(StructOpt, Debug)]
-#[structopt(
-    author,
-    about,
-    settings(&[AppSettings::DeriveDisplayOrder, AppSettings::UnifiedHelpMessage])
-)]
-pub struct Bikecase {
-    /// [cargo] Number of parallel jobs, defaults to # of CPUs
-    #[structopt(long, value_name("N"))]
-    pub jobs: Option<u32>,
+    /// How to print diffs. `auto` prints only the numbers of changed lines for the diffs longer
+    /// than `--diff-threshold`
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::DiffMode::VARIANTS),
+        default_value("auto")
+    )]
+    pub diff: crate::DiffMode,
 
-    /// [cargo] Build artifacts in release mode, with optimizations
+    /// Number of changed lines above which `--diff auto` prints the numbers only
+    #[structopt(long, value_name("LINES"), default_value("200"))]
+    pub diff_threshold: usize,
+
+    /// Delete the files in `tests`, `examples`, and `benches` that are not in the gist, after a
+    /// confirmation
     #[structopt(long)]
-    pub release: bool,
+    pub delete: bool,
 
-    /// [cargo] Build artifacts with the specified profile
-    #[structopt(long, value_name("PROFILE-NAME"))]
-    pub profile: Option<String>,
+    /// Delete the files without the confirmation of `--delete`
+    #[structopt(short, long, requires("delete"))]
+    pub yes: bool,
 
-    /// [cargo] Space-separated list of features to activate
-    #[structopt(long, value_name("FEATURES"), min_values(1))]
-    pub features: Vec<String>,
+    /// Fail instead of prompting for the confirmation of `--delete`
+    #[structopt(long)]
+    pub no_input: bool,
 
-    /// [cargo] Activate all available features
+    /// Overwrite local files that differ from the gist, leaving `*.orig` backups
+    #[structopt(long, conflicts_with_all(&["ours", "backup-local"]))]
+    pub theirs: bool,
+
+    /// Keep local files that differ from the gist
+    #[structopt(long, conflicts_with("backup-local"))]
+    pub ours: bool,
+
+    /// Overwrite local files that differ from the gist, stashing them under
+    /// `.bikecase/backups/<timestamp>/` in the package. The default of these three is `pull-policy`
+    /// in the config
     #[structopt(long)]
-    pub all_features: bool,
+    pub backup_local: bool,
 
-    /// [cargo] Do not activate the `default` feature
+    /// Maximum size of each file downloaded separately for being too large for the API response
+    #[structopt(long, value_name("SIZE"), default_value("10M"), parse(try_from_str = parse_size))]
+    pub max_size: u64,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseGistPush {
+    /// [cargo] Package with the target to export
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
     #[structopt(long)]
-    pub no_default_features: bool,
+    pub ascii: bool,
 
-    /// [cargo] Build for the target triple
-    #[structopt(long, value_name("TRIPLE"))]
-    pub target: Option<PathBuf>,
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
 
-    /// [cargo] Error format
+    /// How to print diffs. `auto` prints only the numbers of changed lines for the diffs longer
+    /// than `--diff-threshold`
     #[structopt(
         long,
-        value_name("FMT"),
-        case_insensitive(true),
-        possible_values(&["human", "json", "short"]),
-        default_value("human")
+        value_name("WHEN"),
+        possible_values(crate::DiffMode::VARIANTS),
+        default_value("auto")
     )]
-    pub message_format: Vec<String>,
+    pub diff: crate::DiffMode,
 
-    /// [cargo] Use verbose output (-vv very verbose/build.rs output)
-    #[structopt(short, long, parse(from_occurrences))]
-    pub verbose: u32,
+    /// Number of changed lines above which `--diff auto` prints the numbers only
+    #[structopt(long, value_name("LINES"), default_value("200"))]
+    pub diff_threshold: usize,
 
-    /// [cargo] Require Cargo.lock and cache are up to date
+    /// Create a new gist when `gist_ids.<package>` is not set
+    #[structopt(short("u"), long)]
+    pub set_upstream: bool,
+
+    /// Make the gist private when `--set-upstream` is enabled
     #[structopt(long)]
-    pub frozen: bool,
+    pub private: bool,
 
-    /// [cargo] Require Cargo.lock is up to date
+    /// Set the description of the gist
     #[structopt(long)]
-    pub locked: bool,
+    pub description: Option<String>,
 
-    /// [cargo] Run without accessing the network
+    /// Do not use the first paragraph of the doc comment as the description of a gist without one
+    #[structopt(long, conflicts_with("description"))]
+    pub no_auto_description: bool,
+
+    /// Also push the entries of `Cargo.lock` for the package as `<package>.Cargo.lock`
     #[structopt(long)]
-    pub offline: bool,
+    pub with_lockfile: bool,
 
-    /// Save the script as src/bin/<NAME>.rs instead of src/main.rs
-    #[structopt(long, value_name("NAME"))]
-    pub bin: Option<String>,
+    /// Run `cargo test -p <package>` first, and do not push if it fails. Defaults to
+    /// `workspaces.<workspace>.push-require-tests`
+    #[structopt(long)]
+    pub require_tests: bool,
 
-    /// Path to the virtual manifest
-    #[structopt(long, value_name("PATH"))]
-    pub manifest_path: Option<PathBuf>,
+    /// Do not push if the package has uncommitted changes in git. Defaults to
+    /// `workspaces.<workspace>.push-if-clean`
+    #[structopt(long)]
+    pub if_clean: bool,
+
+    /// Open the gist in a browser after pushing
+    #[structopt(long)]
+    pub open: bool,
+
+    /// Bundle the libraries of `path` dependencies without `path-dependencies.<name>` in the
+    /// config into the script as modules
+    #[structopt(long)]
+    pub inline_path_deps: bool,
+
+    /// Append `- <date>: <MSG>` to `CHANGELOG.md` of the gist, creating it if missing
+    #[structopt(long, value_name("MSG"))]
+    pub changelog: Option<String>,
+
+    /// Print the URLs and the revision as JSON
+    #[structopt(long)]
+    pub json: bool,
 
     /// Path to the config file
-    #[structopt(long, value_name("PATH"), default_value(&config::PATH))]
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
     pub config: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseGistStatus {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
 
-    /// Coloring
+    /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
@@ -779,85 +6188,136 @@ pub struct Bikecase {
     )]
     pub color: crate::ColorChoice,
 
-    /// Path to the script
-    pub file: Option<PathBuf>,
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
 
-    /// Arguments for the compiled program
-    #[structopt(parse(from_os_str), raw(true))]
-    pub args: Vec<OsString>,
+    /// Do not print the header
+    #[structopt(long)]
+    pub no_header: bool,
+
+    /// Order of the rows. `modified`, `size`, and `last-run` are the newest/largest first
+    #[structopt(
+        long,
+        value_name("KEY"),
+        possible_values(crate::SortKey::VARIANTS),
+        default_value("name")
+    )]
+    pub sort: crate::SortKey,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
 }
 
 #[derive(StructOpt, Debug)]
-#[structopt(
-    author,
-    about,
-    global_settings(&[AppSettings::DeriveDisplayOrder, AppSettings::UnifiedHelpMessage])
-)]
-pub enum Cargo {
-    #[structopt(author, about)]
-    Bikecase(CargoBikecase),
+pub struct CargoBikecaseGistList {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Print at most N gists
+    #[structopt(long, value_name("N"))]
+    pub limit: Option<usize>,
+
+    /// Only gists updated at or after TIME (RFC 3339, or a duration ago such as `2weeks`)
+    #[structopt(long, value_name("TIME"), parse(try_from_str = crate::parse_since))]
+    pub since: Option<SystemTime>,
+
+    /// Do not print the header
+    #[structopt(long)]
+    pub no_header: bool,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
 }
 
 #[derive(StructOpt, Debug)]
-pub enum CargoBikecase {
-    /// Create a new workspace in an existing directory
-    #[structopt(author)]
-    InitWorkspace(CargoBikecaseInitWorkspace),
+pub struct CargoBikecaseGistComments {
+    /// [cargo] Package with the target to export
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
 
-    /// Create a new workspace member from a template
-    #[structopt(author)]
-    New(CargoBikecaseNew),
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
 
-    /// Remove a workspace member
-    #[structopt(author)]
-    Rm(CargoBikecaseRm),
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
 
-    /// Include a package in the workspace
-    #[structopt(author)]
-    Include(CargoBikecaseInclude),
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
 
-    /// Exclude a package from the workspace
-    #[structopt(author)]
-    Exclude(CargoBikecaseExclude),
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+}
 
-    /// Import a script as a package (in the same format as `cargo-script`)
-    #[structopt(author)]
-    Import(CargoBikecaseImport),
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseGistComment {
+    /// [cargo] Package with the target to export
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
 
-    /// Export a package as a script (in the same format as `cargo-script`)
-    #[structopt(author)]
-    Export(CargoBikecaseExport),
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
 
-    /// Gist
-    #[structopt(author)]
-    Gist(CargoBikecaseGist),
-}
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
 
-impl CargoBikecase {
-    pub fn color(&self) -> crate::ColorChoice {
-        match *self {
-            CargoBikecase::InitWorkspace(CargoBikecaseInitWorkspace { color, .. })
-            | CargoBikecase::New(CargoBikecaseNew { color, .. })
-            | CargoBikecase::Rm(CargoBikecaseRm { color, .. })
-            | CargoBikecase::Include(CargoBikecaseInclude { color, .. })
-            | CargoBikecase::Exclude(CargoBikecaseExclude { color, .. })
-            | CargoBikecase::Import(CargoBikecaseImport { color, .. })
-            | CargoBikecase::Export(CargoBikecaseExport { color, .. })
-            | CargoBikecase::Gist(CargoBikecaseGist::Clone(CargoBikecaseGistClone {
-                color, ..
-            }))
-            | CargoBikecase::Gist(CargoBikecaseGist::Pull(CargoBikecaseGistPull {
-                color, ..
-            }))
-            | CargoBikecase::Gist(CargoBikecaseGist::Push(CargoBikecaseGistPush {
-                color, ..
-            })) => color,
-        }
-    }
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Body of the comment
+    #[structopt(short, long, value_name("MESSAGE"))]
+    pub message: String,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
 }
 
-#[derive(StructOpt, Debug)]
-pub struct CargoBikecaseInitWorkspace {
+/// GitHub does not let gists change between public and secret, so a new gist is created with the
+/// same files and description, and the config is updated to point to it. The URL changes.
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseGistPublish {
+    /// [cargo] Package with the target to export
+    #[structopt(short, long, value_name("SPEC"))]
+    pub package: Option<String>,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
@@ -867,25 +6327,36 @@ pub struct CargoBikecaseInitWorkspace {
     )]
     pub color: crate::ColorChoice,
 
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
     /// Dry run
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// [cargo] Directory
-    #[structopt(default_value("."))]
-    pub path: PathBuf,
+    /// Delete the old gist after creating the new one
+    #[structopt(long)]
+    pub delete_old: bool,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
 }
 
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseNew {
-    /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
-    pub manifest_path: Option<PathBuf>,
+pub enum CargoBikecaseConfig {
+    /// Print the config as a portable profile
+    #[structopt(author)]
+    Export(CargoBikecaseConfigExport),
 
-    /// [cargo] Set the resulting package name, defaults to the directory name
-    #[structopt(long, value_name("NAME"))]
-    pub name: Option<String>,
+    /// Merge a profile into the config
+    #[structopt(author)]
+    Import(CargoBikecaseConfigImport),
+}
 
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseConfigExport {
     /// [cargo] Coloring
     #[structopt(
         long,
@@ -895,24 +6366,17 @@ pub struct CargoBikecaseNew {
     )]
     pub color: crate::ColorChoice,
 
-    /// Dry run
+    /// Draw with ASCII characters only, for terminals without Unicode
     #[structopt(long)]
-    pub dry_run: bool,
+    pub ascii: bool,
 
     /// Path to the config file
-    #[structopt(long, value_name("PATH"), default_value(&config::PATH))]
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
     pub config: PathBuf,
-
-    /// [cargo] Directory
-    pub path: PathBuf,
 }
 
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseRm {
-    /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
-    pub manifest_path: Option<PathBuf>,
-
+pub struct CargoBikecaseConfigImport {
     /// [cargo] Coloring
     #[structopt(
         long,
@@ -922,18 +6386,46 @@ pub struct CargoBikecaseRm {
     )]
     pub color: crate::ColorChoice,
 
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
     /// Dry run
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Package to remove
-    pub spec: String,
+    /// Prefer the values in the profile on conflicts
+    #[structopt(long)]
+    pub overwrite: bool,
+
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+
+    /// Path to the profile
+    #[structopt(parse(try_from_os_str = crate::parse_path))]
+    pub profile: PathBuf,
 }
 
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseInclude {
+pub enum CargoBikecaseDefaultMembers {
+    /// Add a member to `workspace.default-members`
+    #[structopt(author)]
+    Add(CargoBikecaseDefaultMembersAdd),
+
+    /// Remove a member from `workspace.default-members`
+    #[structopt(author)]
+    Rm(CargoBikecaseDefaultMembersRm),
+
+    /// List `workspace.default-members`
+    #[structopt(author)]
+    List(CargoBikecaseDefaultMembersList),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseDefaultMembersAdd {
     /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
     pub manifest_path: Option<PathBuf>,
 
     /// [cargo] Coloring
@@ -945,18 +6437,22 @@ pub struct CargoBikecaseInclude {
     )]
     pub color: crate::ColorChoice,
 
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
     /// Dry run
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Path to the Cargo package to include
-    pub path: String,
+    /// Package
+    pub spec: String,
 }
 
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseExclude {
+pub struct CargoBikecaseDefaultMembersRm {
     /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
     pub manifest_path: Option<PathBuf>,
 
     /// [cargo] Coloring
@@ -968,18 +6464,22 @@ pub struct CargoBikecaseExclude {
     )]
     pub color: crate::ColorChoice,
 
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
     /// Dry run
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Path to the Cargo package to exclude
-    pub path: String,
+    /// Package
+    pub spec: String,
 }
 
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseImport {
+pub struct CargoBikecaseDefaultMembersList {
     /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
     pub manifest_path: Option<PathBuf>,
 
     /// [cargo] Coloring
@@ -991,28 +6491,44 @@ pub struct CargoBikecaseImport {
     )]
     pub color: crate::ColorChoice,
 
-    /// Dry run
+    /// Draw with ASCII characters only, for terminals without Unicode
     #[structopt(long)]
-    pub dry_run: bool,
+    pub ascii: bool,
+}
 
-    /// Path to create the package, defaults to `<workspace-root>/<package-name>`
-    #[structopt(long)]
-    pub path: Option<PathBuf>,
+#[derive(StructOpt, Debug)]
+pub enum CargoBikecaseTrust {
+    /// List the trusted scripts
+    #[structopt(author)]
+    List(CargoBikecaseTrustList),
 
-    /// Path to the script
-    pub file: Option<PathBuf>,
+    /// Forget the trusted contents of a URL or a gist
+    #[structopt(author)]
+    Revoke(CargoBikecaseTrustRevoke),
 }
 
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseExport {
-    /// [cargo] Package with the target to export
-    #[structopt(short, long, value_name("SPEC"))]
-    pub package: Option<String>,
+pub struct CargoBikecaseTrustList {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
 
-    /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
-    pub manifest_path: Option<PathBuf>,
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Do not print the header
+    #[structopt(long)]
+    pub no_header: bool,
+}
 
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseTrustRevoke {
     /// [cargo] Coloring
     #[structopt(
         long,
@@ -1021,27 +6537,38 @@ pub struct CargoBikecaseExport {
         default_value("auto")
     )]
     pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// An `http(s)://` URL or `gist:<ID>`
+    pub source: String,
 }
 
 #[derive(StructOpt, Debug)]
-pub enum CargoBikecaseGist {
-    /// Clone a script from Gist
+pub enum CargoBikecaseSnapshot {
+    /// Archive the workspace manifest, the member sources, and the config section
     #[structopt(author)]
-    Clone(CargoBikecaseGistClone),
+    Create(CargoBikecaseSnapshotCreate),
 
-    /// Pull a script from Gist
+    /// Restore the workspace from a snapshot
     #[structopt(author)]
-    Pull(CargoBikecaseGistPull),
+    Restore(CargoBikecaseSnapshotRestore),
 
-    /// Pull a script to Gist
+    /// Show the changes since a snapshot
     #[structopt(author)]
-    Push(CargoBikecaseGistPush),
+    Diff(CargoBikecaseSnapshotDiff),
 }
 
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseGistClone {
+pub struct CargoBikecaseSnapshotCreate {
     /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
     pub manifest_path: Option<PathBuf>,
 
     /// [cargo] Coloring
@@ -1053,30 +6580,56 @@ pub struct CargoBikecaseGistClone {
     )]
     pub color: crate::ColorChoice,
 
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
     /// Dry run
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Path to create the package, defaults to `<workspace-root>/<package-name>`
+    /// Path to the config file
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
+    pub config: PathBuf,
+}
+
+/// Files that are not in the snapshot are left as they are.
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseSnapshotRestore {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
     #[structopt(long)]
-    pub path: Option<PathBuf>,
+    pub ascii: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
 
     /// Path to the config file
-    #[structopt(long, value_name("PATH"), default_value(&config::PATH))]
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
     pub config: PathBuf,
 
-    /// Gist ID
-    pub gist_id: String,
+    /// Snapshot name or path to restore from, defaults to the latest one
+    #[structopt(value_name("SNAPSHOT"))]
+    pub snapshot: Option<String>,
 }
 
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseGistPull {
-    /// [cargo] Package with the target to export
-    #[structopt(short, long, value_name("SPEC"))]
-    pub package: Option<String>,
-
+pub struct CargoBikecaseSnapshotDiff {
     /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
     pub manifest_path: Option<PathBuf>,
 
     /// [cargo] Coloring
@@ -1088,23 +6641,53 @@ pub struct CargoBikecaseGistPull {
     )]
     pub color: crate::ColorChoice,
 
-    /// Dry run
+    /// Draw with ASCII characters only, for terminals without Unicode
     #[structopt(long)]
-    pub dry_run: bool,
+    pub ascii: bool,
 
     /// Path to the config file
-    #[structopt(long, value_name("PATH"), default_value(&config::PATH))]
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
     pub config: PathBuf,
+
+    /// Snapshot name or path to compare with, defaults to the latest one
+    #[structopt(value_name("SNAPSHOT"))]
+    pub snapshot: Option<String>,
 }
 
+/// Read a script from stdin and write the filtered one to stdout. Set e.g.
+/// `filter.bikecase.clean` to `cargo bikecase filter clean --manifest-path <PATH>` in the git
+/// config, and `*.rs filter=bikecase` in `.gitattributes`.
 #[derive(StructOpt, Debug)]
-pub struct CargoBikecaseGistPush {
-    /// [cargo] Package with the target to export
-    #[structopt(short, long, value_name("SPEC"))]
-    pub package: Option<String>,
+pub enum CargoBikecaseFilter {
+    /// Inherit the dependencies from `workspace.dependencies` and normalize the manifest
+    #[structopt(author)]
+    Clean(CargoBikecaseFilterClean),
+
+    /// Expand the dependencies inherited from `workspace.dependencies`
+    #[structopt(author)]
+    Smudge(CargoBikecaseFilterSmudge),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseFilterClean {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+}
 
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseFilterSmudge {
     /// [cargo] Path to Cargo.toml
-    #[structopt(long, value_name("PATH"))]
+    #[structopt(long, value_name("PATH"), parse(try_from_os_str = crate::parse_path))]
     pub manifest_path: Option<PathBuf>,
 
     /// [cargo] Coloring
@@ -1115,28 +6698,91 @@ pub struct CargoBikecaseGistPush {
         default_value("auto")
     )]
     pub color: crate::ColorChoice,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseReplay {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
 
     /// Dry run
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Create a new gist when `gist_ids.<package>` is not set
-    #[structopt(short("u"), long)]
-    pub set_upstream: bool,
+    /// Path to the bundle
+    #[structopt(value_name("BUNDLE"), parse(try_from_os_str = crate::parse_path))]
+    pub bundle: PathBuf,
 
-    /// Make the gist private when `--set-upstream` is enabled
+    /// Arguments for the program, replacing the recorded ones
+    #[structopt(parse(from_os_str), raw(true))]
+    pub args: Vec<OsString>,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum CargoBikecaseSelf {
+    /// Replace `bikecase` and `cargo-bikecase` with the latest release
+    #[structopt(author)]
+    Update(CargoBikecaseSelfUpdate),
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseSelfUpdate {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
     #[structopt(long)]
-    pub private: bool,
+    pub ascii: bool,
 
-    /// Set the description of the gist
+    /// [cargo] Run without accessing the network
     #[structopt(long)]
-    pub description: Option<String>,
+    pub offline: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
 
     /// Path to the config file
-    #[structopt(long, value_name("PATH"), default_value(&config::PATH))]
+    #[structopt(long, value_name("PATH"), default_value(&config::PATH), parse(try_from_os_str = crate::parse_path))]
     pub config: PathBuf,
 }
 
+#[derive(StructOpt, Debug)]
+pub struct CargoBikecaseVersion {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(crate::ColorChoice::VARIANTS),
+        default_value("auto")
+    )]
+    pub color: crate::ColorChoice,
+
+    /// Draw with ASCII characters only, for terminals without Unicode
+    #[structopt(long)]
+    pub ascii: bool,
+
+    /// Print as JSON
+    #[structopt(long)]
+    pub json: bool,
+}
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct Context<W, I, P> {
@@ -1145,22 +6791,26 @@ pub struct Context<W, I, P> {
     pub data_local_dir: Option<PathBuf>,
     pub stdout: W,
     pub read_input: I,
+    /// Reads a line from the TTY without echoing it. Also used for yes/no prompts.
     pub read_password: P,
     pub init_logger: fn(crate::ColorChoice),
+    /// Used instead of `init_logger` for `--json-logs`.
+    pub init_json_logger: fn(),
     #[derivative(Debug = "ignore")]
     pub str_width: fn(&str) -> usize,
+    /// The config loaded by the first code path that needs it.
+    pub config_cache: ConfigCache,
 }
 
 impl Context<Stdout, fn() -> io::Result<String>, fn(&str) -> io::Result<String>> {
     pub fn new() -> anyhow::Result<Self> {
-        use crate::logger::init as init_logger;
+        use crate::logger::{init as init_logger, init_json as init_json_logger};
 
         let cwd = env::current_dir()
             .with_context(|| "couldn't get the current directory of the process")?;
         let home_dir = dirs::home_dir();
         let data_local_dir = dirs::data_local_dir();
         let stdout = io::stdout();
-        let str_width = UnicodeWidthStr::width;
 
         return Ok(Self {
             cwd,
@@ -1170,7 +6820,9 @@ impl Context<Stdout, fn() -> io::Result<String>, fn(&str) -> io::Result<String>>
             read_input,
             read_password,
             init_logger,
+            init_json_logger,
             str_width,
+            config_cache: ConfigCache::default(),
         });
 
         fn read_input() -> io::Result<String> {
@@ -1185,8 +6837,134 @@ impl Context<Stdout, fn() -> io::Result<String>, fn(&str) -> io::Result<String>>
     }
 }
 
-#[derive(EnumString, EnumVariantNames, IntoStaticStr, Debug, Clone, Copy)]
+/// Expands `~` and environment variables (`$VAR`/`${VAR}`) in a path given on the command line.
+fn parse_path(path: &OsStr) -> Result<PathBuf, OsString> {
+    match path.to_str() {
+        Some(path) => shellexpand::full(path)
+            .map(|path| path.into_owned().into())
+            .map_err(|err| err.to_string().into()),
+        None => Ok(path.into()),
+    }
+}
+
+/// Parses an RFC 3339 timestamp, or a duration to go back from now.
+fn parse_since(s: &str) -> Result<SystemTime, String> {
+    humantime::parse_rfc3339_weak(s)
+        .or_else(|_| {
+            let ago = humantime::parse_duration(s)?;
+            SystemTime::now()
+                .checked_sub(ago)
+                .ok_or(humantime::DurationError::NumberOverflow)
+        })
+        .map_err(|_| format!("expected an RFC 3339 timestamp or a duration: {:?}", s))
+}
+
+/// Formats a number of bytes with a binary suffix, in the form that [`parse_size`] accepts
+/// except for the fraction.
+fn format_size(size: u64) -> String {
+    let mut value = size as f64;
+    for suffix in ["B", "KiB", "MiB", "GiB"] {
+        if value < 1024.0 {
+            return if suffix == "B" {
+                format!("{}B", size)
+            } else {
+                format!("{:.1}{}", value, suffix)
+            };
+        }
+        value /= 1024.0;
+    }
+    format!("{:.1}TiB", value)
+}
+
+/// Parses a number of bytes with an optional binary suffix (`K`, `M`, `G`, or `T`).
+fn parse_size(s: &str) -> Result<u64, String> {
+    let err = || format!("expected a size like `512M` or `2G`: {:?}", s);
+    let digits = s.trim_end_matches(|c: char| !c.is_ascii_digit());
+    let shift = match &s[digits.len()..] {
+        "" | "B" => 0,
+        "K" | "KiB" => 10,
+        "M" | "MiB" => 20,
+        "G" | "GiB" => 30,
+        "T" | "TiB" => 40,
+        _ => return Err(err()),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(err)
+}
+
+#[derive(
+    EnumString,
+    EnumVariantNames,
+    IntoStaticStr,
+    Deserialize,
+    Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+)]
+#[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    Run,
+    Build,
+    Check,
+    Test,
+}
+
+#[derive(
+    EnumString, EnumVariantNames, IntoStaticStr, Deserialize, Serialize, Debug, Clone, Copy,
+)]
+#[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum Deny {
+    GitDeps,
+}
+
+#[derive(
+    EnumString, EnumVariantNames, IntoStaticStr, Deserialize, Serialize, Debug, Clone, Copy,
+)]
+#[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum SortKey {
+    Name,
+    Modified,
+    Size,
+    LastRun,
+}
+
+#[derive(
+    EnumString, EnumVariantNames, IntoStaticStr, Deserialize, Serialize, Debug, Clone, Copy,
+)]
+#[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum ProfilePreset {
+    FastCompile,
+    FastRun,
+    Balanced,
+}
+
+#[derive(
+    EnumString, EnumVariantNames, IntoStaticStr, Deserialize, Serialize, Debug, Clone, Copy,
+)]
+#[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum DiffMode {
+    Auto,
+    Always,
+    Never,
+    Stat,
+}
+
+#[derive(
+    EnumString, EnumVariantNames, IntoStaticStr, Deserialize, Serialize, Debug, Clone, Copy,
+)]
 #[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
 pub enum ColorChoice {
     Auto,
     Always,
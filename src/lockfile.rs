@@ -0,0 +1,122 @@
+use anyhow::{bail, Context as _};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Extracts the entries for `package_name` and its dependencies from the workspace's `Cargo.lock`.
+///
+/// The dependency graph is followed within the lockfile itself, so this does not run `cargo`.
+pub(crate) fn extract(workspace_root: &Path, package_name: &str) -> anyhow::Result<String> {
+    let mut doc = crate::fs::read_toml_edit(workspace_root.join("Cargo.lock"))?;
+    let packages = packages_mut(&mut doc)?;
+
+    let root = packages
+        .iter()
+        .position(|p| {
+            key(p).is_some_and(|(name, _, source)| name == package_name && source.is_none())
+        })
+        .with_context(|| format!("`{}` is not in Cargo.lock", package_name))?;
+
+    let mut reachable = BTreeSet::new();
+    let mut stack = vec![root];
+    while let Some(i) = stack.pop() {
+        if !reachable.insert(i) {
+            continue;
+        }
+        let deps = packages
+            .get(i)
+            .and_then(|p| p.get("dependencies"))
+            .and_then(Item::as_array)
+            .into_iter()
+            .flatten()
+            .flat_map(|d| d.as_str());
+        for dep in deps {
+            let mut dep = dep.split(' ');
+            let name = dep.next().unwrap_or_default();
+            let version = dep.next();
+            let found = packages.iter().position(|p| {
+                key(p).is_some_and(|(n, v, _)| {
+                    n == name && version.is_none_or(|version| v == version)
+                })
+            });
+            stack.extend(found);
+        }
+    }
+
+    let mut i = 0;
+    packages.retain(|_| {
+        i += 1;
+        reachable.contains(&(i - 1))
+    });
+    doc.remove("metadata");
+    Ok(doc.to_string())
+}
+
+/// Adds the entries in `lockfile` that the workspace's `Cargo.lock` lacks, so that `cargo` prefers
+/// the versions locked in it. Writes `lockfile` as it is if the workspace does not have
+/// `Cargo.lock`.
+pub(crate) fn restore(workspace_root: &Path, lockfile: &str, dry_run: bool) -> anyhow::Result<()> {
+    let path = workspace_root.join("Cargo.lock");
+    let mut incoming = lockfile
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the lockfile in the gist")?;
+
+    if !path.exists() {
+        packages_mut(&mut incoming)?;
+        return crate::fs::write(path, lockfile, dry_run);
+    }
+
+    let mut doc = crate::fs::read_toml_edit(&path)?;
+    let packages = packages_mut(&mut doc)?;
+    let existing = packages
+        .iter()
+        .filter_map(key_owned)
+        .collect::<BTreeSet<_>>();
+
+    let mut num_added = 0;
+    for package in packages_mut(&mut incoming)?.iter() {
+        if key_owned(package).is_some_and(|k| !existing.contains(&k)) {
+            packages.push(package.clone());
+            num_added += 1;
+        }
+    }
+
+    if num_added == 0 {
+        info_event!("{} already has the locked versions", path.display());
+        return Ok(());
+    }
+    info_event!(
+        "Adding {} locked package(s) to {}",
+        num_added,
+        path.display()
+    );
+    crate::fs::write(path, doc.to_string(), dry_run)
+}
+
+fn packages_mut(doc: &mut DocumentMut) -> anyhow::Result<&mut ArrayOfTables> {
+    match doc
+        .entry("package")
+        .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+    {
+        Item::ArrayOfTables(packages) => Ok(packages),
+        _ => bail!("`package` in Cargo.lock must be an array of tables"),
+    }
+}
+
+/// Returns the name, the version, and the source.
+fn key(package: &Table) -> Option<(&str, &str, Option<&str>)> {
+    let name = package.get("name")?.as_str()?;
+    let version = package.get("version")?.as_str()?;
+    let source = package.get("source").and_then(Item::as_str);
+    Some((name, version, source))
+}
+
+fn key_owned(package: &Table) -> Option<(String, String, Option<String>)> {
+    let (name, version, source) = key(package)?;
+    Some((
+        name.to_owned(),
+        version.to_owned(),
+        source.map(ToOwned::to_owned),
+    ))
+}
@@ -1,10 +1,21 @@
-use log::{info, Level, LevelFilter};
+use log::kv::{self, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::json;
 
-use std::fmt::Display;
-use std::io::Write as _;
-use std::iter;
+use std::env;
+use std::io::{self, Write as _};
+use std::time::SystemTime;
 
+/// Initializes the logger.
+///
+/// If `BIKECASE_LOG_FORMAT` is `json` and stderr is not a TTY, this is same as [`init_json`].
 pub(crate) fn init(color: crate::ColorChoice) {
+    if env::var_os("BIKECASE_LOG_FORMAT").is_some_and(|f| f == "json")
+        && !atty::is(atty::Stream::Stderr)
+    {
+        return init_json();
+    }
+
     env_logger::Builder::new()
         .format(|buf, record| {
             macro_rules! style(($fg:expr, $intense:expr) => ({
@@ -13,12 +24,23 @@ pub(crate) fn init(color: crate::ColorChoice) {
                 style
             }));
 
+            let theme = crate::ui::theme();
             let color = match record.level() {
-                Level::Error => env_logger::fmt::Color::Red,
-                Level::Warn => env_logger::fmt::Color::Yellow,
-                Level::Info => env_logger::fmt::Color::Cyan,
-                Level::Debug => env_logger::fmt::Color::Green,
-                Level::Trace => env_logger::fmt::Color::White,
+                Level::Error => theme.error,
+                Level::Warn => theme.warn,
+                Level::Info => theme.info,
+                Level::Debug => theme.debug,
+                Level::Trace => theme.trace,
+            };
+            // Set by `ui::info_diff`.
+            let diff_color = match record
+                .key_values()
+                .get(kv::Key::from_str("diff"))
+                .and_then(|v| v.to_borrowed_str())
+            {
+                Some("+") => Some(theme.added),
+                Some("-") => Some(theme.removed),
+                _ => None,
             };
 
             let path = record
@@ -28,50 +50,100 @@ pub(crate) fn init(color: crate::ColorChoice) {
                 .map(|p| format!(" {}", p))
                 .unwrap_or_default();
 
-            writeln!(
+            write!(
                 buf,
-                "{}{}{}{} {}",
+                "{}{}{}{} ",
                 style!(env_logger::fmt::Color::Black, true).value('['),
-                style!(color, false).value(record.level()),
+                style!(to_env_logger_color(color), false).value(record.level()),
                 path,
                 style!(env_logger::fmt::Color::Black, true).value(']'),
-                record.args(),
-            )
+            )?;
+            match diff_color {
+                Some(color) => writeln!(
+                    buf,
+                    "{}",
+                    style!(to_env_logger_color(color), false).value(record.args()),
+                ),
+                None => writeln!(buf, "{}", record.args()),
+            }
         })
         .filter_level(LEVEL_FILTER)
         .write_style(color.into())
         .init();
+}
 
-    #[cfg(debug_assertions)]
-    const LEVEL_FILTER: LevelFilter = LevelFilter::Debug;
-    #[cfg(not(debug_assertions))]
-    const LEVEL_FILTER: LevelFilter = LevelFilter::Info;
+fn to_env_logger_color(color: termcolor::Color) -> env_logger::fmt::Color {
+    match color {
+        termcolor::Color::Black => env_logger::fmt::Color::Black,
+        termcolor::Color::Blue => env_logger::fmt::Color::Blue,
+        termcolor::Color::Green => env_logger::fmt::Color::Green,
+        termcolor::Color::Red => env_logger::fmt::Color::Red,
+        termcolor::Color::Cyan => env_logger::fmt::Color::Cyan,
+        termcolor::Color::Magenta => env_logger::fmt::Color::Magenta,
+        termcolor::Color::Yellow => env_logger::fmt::Color::Yellow,
+        termcolor::Color::Ansi256(n) => env_logger::fmt::Color::Ansi256(n),
+        termcolor::Color::Rgb(r, g, b) => env_logger::fmt::Color::Rgb(r, g, b),
+        _ => env_logger::fmt::Color::White,
+    }
 }
 
-pub(crate) fn info_diff(orig: &str, edit: &str, name: impl Display, str_width: fn(&str) -> usize) {
-    let name = name.to_string();
-
-    let max_width = iter::once(&*name)
-        .chain(orig.lines())
-        .chain(edit.lines())
-        .map(str_width)
-        .max()
-        .unwrap_or(0);
-
-    let horz_bar = (0..max_width / str_width("─") - str_width("┌"))
-        .map(|_| '─')
-        .collect::<String>();
-
-    info!("┌{}", horz_bar);
-    info!("│{}", name);
-    info!("├{}", horz_bar);
-    for diff in diff::lines(orig, edit) {
-        let (pref, line) = match diff {
-            diff::Result::Left(l) => ("-", l),
-            diff::Result::Both(l, _) => (" ", l),
-            diff::Result::Right(l) => ("+", l),
-        };
-        info!("│{}{}", pref, line);
+/// Initializes the logger to write each record to stderr as a JSON line, for running under
+/// systemd, CI, or other orchestration.
+///
+/// ```json
+/// {"timestamp":"2020-04-01T00:00:00.000Z","level":"INFO","target":"bikecase::fs","message":"Wrote /path/to/file","fields":{"path":"/path/to/file"}}
+/// ```
+pub(crate) fn init_json() {
+    if log::set_boxed_logger(Box::new(JsonLogger)).is_ok() {
+        log::set_max_level(LEVEL_FILTER);
+    }
+}
+
+#[cfg(debug_assertions)]
+const LEVEL_FILTER: LevelFilter = LevelFilter::Debug;
+#[cfg(not(debug_assertions))]
+const LEVEL_FILTER: LevelFilter = LevelFilter::Info;
+
+struct JsonLogger;
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= LEVEL_FILTER
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        struct Fields(serde_json::Map<String, serde_json::Value>);
+
+        impl<'kvs> VisitSource<'kvs> for Fields {
+            fn visit_pair(
+                &mut self,
+                key: kv::Key<'kvs>,
+                value: kv::Value<'kvs>,
+            ) -> Result<(), kv::Error> {
+                self.0.insert(key.to_string(), value.to_string().into());
+                Ok(())
+            }
+        }
+
+        let mut fields = Fields(serde_json::Map::new());
+        let _ = record.key_values().visit(&mut fields);
+
+        let line = json!({
+            "timestamp": humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+            "fields": fields.0,
+        });
+        let mut stderr = io::stderr().lock();
+        let _ = writeln!(stderr, "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
     }
-    info!("└{}", horz_bar);
 }
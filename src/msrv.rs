@@ -0,0 +1,157 @@
+use itertools::Itertools as _;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::{env, str};
+
+/// Builds a hint for `cargo build` that failed because some packages require a newer rustc.
+///
+/// `stderr` is what `cargo build` printed. For each of the packages, the registry index cached
+/// under `$CARGO_HOME` is searched for the latest semver-compatible older version that supports the
+/// active rustc.
+///
+/// Returns `None` if the failure is not about `package.rust-version`.
+pub(crate) fn hint(stderr: &str, manifest_path: &Path, home_dir: Option<&Path>) -> Option<String> {
+    let stderr = strip_ansi(stderr);
+    let mut lines = stderr.lines();
+
+    let current = lines.find_map(|line| {
+        let rest = line.strip_prefix("error: rustc ")?;
+        rest.strip_suffix(" is not supported by the following package:")
+            .or_else(|| rest.strip_suffix(" is not supported by the following packages:"))
+    })?;
+
+    let incompatibles = lines
+        .map_while(|line| {
+            let (pkg, rust_version) = line.trim_start().split_once(" requires rustc ")?;
+            let (name, version) = pkg.split_once('@')?;
+            Some((name, version.parse::<Version>().ok()?, rust_version))
+        })
+        .collect::<Vec<_>>();
+    if incompatibles.is_empty() {
+        return None;
+    }
+
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir.map(|h| h.join(".cargo")));
+
+    let mut hint = format!(
+        "hint: some dependencies require a newer rustc than {}",
+        current,
+    );
+    for (name, version, rust_version) in incompatibles {
+        write!(
+            hint,
+            "\n  - `{}@{}` requires rustc {}",
+            name, version, rust_version,
+        )
+        .unwrap();
+        let compatible = cargo_home
+            .as_deref()
+            .and_then(|cargo_home| latest_compatible(cargo_home, name, &version, current));
+        if let Some(compatible) = compatible {
+            write!(
+                hint,
+                ". {} supports it: `cargo update -p {}@{} --precise {} --manifest-path {}`",
+                compatible,
+                name,
+                version,
+                compatible,
+                manifest_path.display(),
+            )
+            .unwrap();
+        }
+    }
+    hint += "\nPass `--ignore-rust-version` to build anyway";
+    Some(hint)
+}
+
+/// Finds the latest version of `name` that is older than `version`, semver-compatible with it, and
+/// declares a `rust-version` that `current` satisfies.
+fn latest_compatible(
+    cargo_home: &Path,
+    name: &str,
+    version: &Version,
+    current: &str,
+) -> Option<Version> {
+    let current = rust_version(current)?;
+    let name = name.to_lowercase();
+    let rel_path = match name.len() {
+        1 => Path::new("1").join(&name),
+        2 => Path::new("2").join(&name),
+        3 => Path::new("3").join(&name[..1]).join(&name),
+        _ => Path::new(&name[..2]).join(&name[2..4]).join(&name),
+    };
+
+    std::fs::read_dir(cargo_home.join("registry").join("index"))
+        .ok()?
+        .flatten()
+        .filter_map(|entry| std::fs::read(entry.path().join(".cache").join(&rel_path)).ok())
+        .flat_map(|cache| index_entries(&cache))
+        .filter(|entry| !entry.yanked)
+        .filter(|entry| {
+            entry
+                .rust_version
+                .as_deref()
+                .and_then(rust_version)
+                .is_some_and(|r| r <= current)
+        })
+        .filter_map(|entry| entry.vers.parse::<Version>().ok())
+        .filter(|vers| {
+            vers.pre.is_empty()
+                && vers < version
+                && VersionReq::parse(&format!("^{}", vers)).is_ok_and(|r| r.matches(version))
+        })
+        .max()
+}
+
+/// Parses a cache file of the registry index.
+///
+/// The file consists of a one-byte cache version, a 4-byte index version, and NUL-terminated
+/// strings: the index revision followed by pairs of a version and its JSON entry.
+fn index_entries(cache: &[u8]) -> Vec<IndexEntry> {
+    cache
+        .get(5..)
+        .unwrap_or_default()
+        .split(|&b| b == 0)
+        .skip(1)
+        .tuples()
+        .flat_map(|(_, json)| serde_json::from_slice(json))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct IndexEntry {
+    vers: String,
+    #[serde(default)]
+    yanked: bool,
+    #[serde(default)]
+    rust_version: Option<String>,
+}
+
+fn rust_version(s: &str) -> Option<(u64, u64, u64)> {
+    let mut nums = s.split('.').map(str::parse::<u64>);
+    let major = nums.next()?.ok()?;
+    let minor = nums.next().transpose().ok()?.unwrap_or(0);
+    let patch = nums.next().transpose().ok()?.unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+fn strip_ansi(s: &str) -> String {
+    let mut ret = "".to_owned();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            chars
+                .by_ref()
+                .take_while(|c| !c.is_ascii_alphabetic())
+                .for_each(drop);
+        } else {
+            ret.push(c);
+        }
+    }
+    ret
+}
@@ -0,0 +1,49 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Converts `stem` into a valid package name.
+///
+/// The name is lowercased, and runs of characters other than ASCII alphanumerics and `_` are
+/// replaced with single `-`s. Names that would be empty or start with a digit are prefixed with
+/// `script`.
+///
+/// - `2024-05-03 report` → `script-2024-05-03-report`
+/// - `Foo Bar` → `foo-bar`
+pub(crate) fn sanitize(stem: &str) -> String {
+    let mut name = "".to_owned();
+    for c in stem.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            name.push(c);
+        } else if !(name.is_empty() || name.ends_with('-')) {
+            name.push('-');
+        }
+    }
+    let name = name.trim_end_matches('-');
+
+    match name.chars().next() {
+        None => "script".to_owned(),
+        Some(c) if c.is_ascii_digit() => format!("script-{}", name),
+        Some(_) => name.to_owned(),
+    }
+}
+
+/// [`sanitize`]s the stem of `file_name`, suffixing it with `-2`, `-3`, ... while `taken` returns
+/// `true`.
+pub(crate) fn derive(file_name: &str, taken: impl FnMut(&str) -> bool) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or(file_name);
+    unique(sanitize(stem), taken)
+}
+
+/// Suffixes `name` with `-2`, `-3`, ... while `taken` returns `true`.
+pub(crate) fn unique(name: String, mut taken: impl FnMut(&str) -> bool) -> String {
+    if !taken(&name) {
+        return name;
+    }
+    (2..)
+        .map(|n| format!("{}-{}", name, n))
+        .find(|name| !taken(name))
+        .expect("should be found")
+}
@@ -1,8 +1,18 @@
-use duct::{Expression, IntoExecutablePath};
-use itertools::Itertools as _;
-use log::info;
+use crate::event::Event;
+
+use anyhow::bail;
+use duct::{Expression, Handle, IntoExecutablePath};
+use os_pipe::PipeReader;
 
 use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead as _, BufReader, Read as _, Write};
+use std::path::Path;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 pub(crate) fn cmd<T, U>(program: T, args: U) -> Expression
 where
@@ -24,19 +34,552 @@ where
 {
     let program = program.to_executable();
     let args = args.into_iter().map(Into::into).collect::<Vec<_>>();
-    info(&program, &args, false);
+    info(&program, &args, dry_run);
     if !dry_run {
         duct::cmd(program, args).run()?;
     }
     Ok(())
 }
 
+/// Opens `url` with `open` on macOS, `start` on Windows, and `xdg-open` otherwise.
+pub(crate) fn open_in_browser(url: &str) -> anyhow::Result<()> {
+    if cfg!(target_os = "macos") {
+        run("open", &[url], false)
+    } else if cfg!(windows) {
+        run("cmd", &["/C", "start", "", url], false)
+    } else {
+        run("xdg-open", &[url], false)
+    }
+}
+
+/// Runs `editor`, a command line split by whitespace.
+///
+/// `{path}` and `{file}` in it are replaced with `package_dir` and `file`. If it has neither,
+/// `target` is appended.
+pub(crate) fn open_in_editor(
+    editor: &str,
+    package_dir: &Path,
+    file: Option<&Path>,
+    target: &Path,
+) -> anyhow::Result<()> {
+    let mut words = editor.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => bail!("the editor command is empty"),
+    };
+
+    let mut has_placeholders = false;
+    let mut args = vec![];
+    for word in words {
+        if !word.contains("{path}") && !word.contains("{file}") {
+            args.push(OsString::from(word));
+            continue;
+        }
+        has_placeholders = true;
+        let mut arg = word.replace("{path}", &package_dir.to_string_lossy());
+        if arg.contains("{file}") {
+            let file = match file {
+                Some(file) => file,
+                None => bail!("`{{file}}` is used but the package has no default `bin` target"),
+            };
+            arg = arg.replace("{file}", &file.to_string_lossy());
+        }
+        args.push(arg.into());
+    }
+    if !has_placeholders {
+        args.push(target.into());
+    }
+
+    run(program, args, false)
+}
+
+/// Runs `expr`.
+///
+/// If `timeout` is set, the process is killed once it elapses. The process stays in the foreground
+/// process group, so that it can read from the terminal and receives Ctrl-C as `cargo run` does.
+/// Only the process itself is killed, not the ones it spawned.
+///
+/// If `prefix_output` is set, stdout and stderr of the process are piped through this process and
+/// each line is prefixed with the name and the stream.
+///
+/// If `capture` is set, stdout (and stderr) of the process are piped through this process and
+/// written to the file as well.
+///
+/// If `interrupt` is set, the process is stopped as soon as it tells to, and [`Interrupted`] is
+/// returned.
+pub(crate) fn run_child(
+    expr: Expression,
+    timeout: Option<Duration>,
+    limits: Limits,
+    prefix_output: Option<PrefixOutput<'_>>,
+    capture: Option<Capture<'_>>,
+    interrupt: Option<Interrupt<'_>>,
+) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    let expr = if limits.is_set() {
+        expr.before_spawn(move |cmd| {
+            use std::os::unix::process::CommandExt as _;
+
+            unsafe {
+                cmd.pre_exec(move || limits.set_rlimits());
+            }
+            Ok(())
+        })
+    } else {
+        expr
+    };
+
+    // Inspect the exit status ourselves so that hitting a limit is told apart from other failures.
+    let expr = if limits.is_set() {
+        expr.unchecked()
+    } else {
+        expr
+    };
+
+    let (stdout_capture, stderr_capture) = match capture {
+        Some(Capture { path, stderr }) => {
+            let file = Arc::new(Mutex::new(crate::fs::create(path)?));
+            (Some(file.clone()), Some(file).filter(|_| stderr))
+        }
+        None => (None, None),
+    };
+
+    let mut prefixers = vec![];
+    let stdout_writer = if prefix_output.is_some() || stdout_capture.is_some() {
+        let (reader, writer) = os_pipe::pipe()?;
+        prefixers.push(forward(
+            reader,
+            prefix_output,
+            "stdout",
+            io::stdout,
+            stdout_capture,
+        ));
+        Some(writer)
+    } else {
+        None
+    };
+    let stderr_writer = if prefix_output.is_some() || stderr_capture.is_some() {
+        let (reader, writer) = os_pipe::pipe()?;
+        prefixers.push(forward(
+            reader,
+            prefix_output,
+            "stderr",
+            io::stderr,
+            stderr_capture,
+        ));
+        Some(writer)
+    } else {
+        None
+    };
+    // Keep no intermediate expressions, which would hold the write ends.
+    let expr = match (stdout_writer, stderr_writer) {
+        (Some(stdout), Some(stderr)) => expr.stdout_file(stdout).stderr_file(stderr),
+        (Some(stdout), None) => expr.stdout_file(stdout),
+        (None, Some(stderr)) => expr.stderr_file(stderr),
+        (None, None) => expr,
+    };
+
+    let handle = expr.start()?;
+    // Close our ends of the pipes so that the readers reach EOF when the process exits.
+    drop(expr);
+
+    #[cfg(windows)]
+    let _job = if limits.is_set() {
+        Some(windows::JobObject::assign(&handle.pids(), limits)?)
+    } else {
+        None
+    };
+
+    let result = if timeout.is_some() || interrupt.is_some() {
+        supervise(&handle, timeout, interrupt)
+    } else {
+        handle.wait().map(drop).map_err(Into::into)
+    };
+    let result = result.and_then(|()| {
+        if limits.is_set() {
+            let status = handle.wait()?.status;
+            if !status.success() {
+                if let Some(resource) = limits.exceeded(status) {
+                    return Err(ResourceLimitExceeded { resource, status }.into());
+                }
+                bail!("the program exited with {}", status);
+            }
+        }
+        Ok(())
+    });
+
+    for prefixer in prefixers {
+        prefixer
+            .join()
+            .unwrap_or_else(|p| std::panic::resume_unwind(p))?;
+    }
+    result
+}
+
+/// Resource limits for the program.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Limits {
+    /// In bytes.
+    pub(crate) memory: Option<u64>,
+    pub(crate) cpu_seconds: Option<u64>,
+}
+
+impl Limits {
+    fn is_set(self) -> bool {
+        self.memory.is_some() || self.cpu_seconds.is_some()
+    }
+
+    /// Sets the limits to the current process. Called in the child between `fork` and `exec`.
+    #[cfg(unix)]
+    fn set_rlimits(self) -> io::Result<()> {
+        fn setrlimit(resource: RlimitResource, soft: u64, hard: u64) -> io::Result<()> {
+            unsafe {
+                let mut current = std::mem::zeroed::<libc::rlimit>();
+                if libc::getrlimit(resource, &mut current) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                // Raising the hard limit requires a privilege.
+                let hard = (hard as libc::rlim_t).min(current.rlim_max);
+                let soft = (soft as libc::rlim_t).min(hard);
+                let new = libc::rlimit {
+                    rlim_cur: soft,
+                    rlim_max: hard,
+                };
+                if libc::setrlimit(resource, &new) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        }
+
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        type RlimitResource = libc::__rlimit_resource_t;
+        #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+        type RlimitResource = libc::c_int;
+
+        if let Some(memory) = self.memory {
+            setrlimit(libc::RLIMIT_AS, memory, memory)?;
+        }
+        if let Some(cpu_seconds) = self.cpu_seconds {
+            // `SIGXCPU` at the soft limit, then `SIGKILL` at the hard limit if it is ignored.
+            setrlimit(libc::RLIMIT_CPU, cpu_seconds, cpu_seconds + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the name of the limit that `status` is likely to be caused by.
+    fn exceeded(self, status: ExitStatus) -> Option<&'static str> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt as _;
+
+            match status.signal()? {
+                libc::SIGXCPU | libc::SIGKILL if self.cpu_seconds.is_some() => Some("CPU time"),
+                // `handle_alloc_error` aborts.
+                libc::SIGABRT | libc::SIGSEGV if self.memory.is_some() => Some("memory"),
+                _ => None,
+            }
+        }
+        #[cfg(windows)]
+        {
+            // `STATUS_STACK_BUFFER_OVERRUN`, which `abort` results in.
+            const STATUS_FAIL_FAST: i32 = 0xC000_0409_u32 as i32;
+
+            match status.code()? {
+                code if code == windows::ERROR_NOT_ENOUGH_QUOTA && self.cpu_seconds.is_some() => {
+                    Some("CPU time")
+                }
+                STATUS_FAIL_FAST if self.memory.is_some() => Some("memory"),
+                _ => None,
+            }
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = status;
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ResourceLimitExceeded {
+    resource: &'static str,
+    status: ExitStatus,
+}
+
+impl fmt::Display for ResourceLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "resource limit exceeded: the program hit the {} limit ({})",
+            self.resource, self.status,
+        )
+    }
+}
+
+impl std::error::Error for ResourceLimitExceeded {}
+
+#[cfg(windows)]
+mod windows {
+    use super::Limits;
+
+    use winapi::shared::minwindef::FALSE;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::jobapi2::{
+        AssignProcessToJobObject, CreateJobObjectW, SetInformationJobObject,
+    };
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winnt::{
+        JobObjectExtendedLimitInformation, HANDLE, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_PROCESS_MEMORY, JOB_OBJECT_LIMIT_PROCESS_TIME, PROCESS_SET_QUOTA,
+        PROCESS_TERMINATE,
+    };
+
+    use std::{io, mem, ptr};
+
+    /// The exit code of a process terminated for exceeding `PerProcessUserTimeLimit`.
+    pub(super) const ERROR_NOT_ENOUGH_QUOTA: i32 =
+        winapi::shared::winerror::ERROR_NOT_ENOUGH_QUOTA as _;
+
+    /// A Job Object, closed on drop.
+    ///
+    /// The processes are assigned right after they start, so they run without the limits for a
+    /// moment.
+    pub(super) struct JobObject(HANDLE);
+
+    impl JobObject {
+        pub(super) fn assign(pids: &[u32], limits: Limits) -> io::Result<Self> {
+            unsafe {
+                let job = CreateJobObjectW(ptr::null_mut(), ptr::null());
+                if job.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let job = Self(job);
+
+                let mut info = mem::zeroed::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>();
+                if let Some(memory) = limits.memory {
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+                    info.ProcessMemoryLimit = memory as _;
+                }
+                if let Some(cpu_seconds) = limits.cpu_seconds {
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_TIME;
+                    // In 100-nanosecond ticks.
+                    *info
+                        .BasicLimitInformation
+                        .PerProcessUserTimeLimit
+                        .QuadPart_mut() = (cpu_seconds * 10_000_000) as _;
+                }
+                if SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &mut info as *mut _ as _,
+                    mem::size_of_val(&info) as _,
+                ) == 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+
+                for &pid in pids {
+                    let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, FALSE, pid);
+                    if process.is_null() {
+                        return Err(io::Error::last_os_error());
+                    }
+                    let assigned = AssignProcessToJobObject(job.0, process);
+                    let err = io::Error::last_os_error();
+                    CloseHandle(process);
+                    if assigned == 0 {
+                        return Err(err);
+                    }
+                }
+                Ok(job)
+            }
+        }
+    }
+
+    impl Drop for JobObject {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
+
+fn supervise(
+    handle: &Handle,
+    timeout: Option<Duration>,
+    interrupt: Option<Interrupt<'_>>,
+) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let mut polled_at = start;
+
+    loop {
+        if handle.try_wait()?.is_some() {
+            return Ok(());
+        }
+        if let Some(timeout) = timeout.filter(|&t| start.elapsed() >= t) {
+            handle.kill()?;
+            return Err(TimedOut(timeout).into());
+        }
+        if let Some(interrupt) = interrupt.filter(|i| polled_at.elapsed() >= i.interval) {
+            if (interrupt.poll)() {
+                stop(handle, interrupt.grace_period)?;
+                return Err(Interrupted.into());
+            }
+            polled_at = Instant::now();
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Sends `SIGTERM` to the process and waits for `grace_period` before killing it. Kills it right
+/// away without `grace_period` or on non-Unix platforms.
+fn stop(handle: &Handle, grace_period: Option<Duration>) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    if let Some(grace_period) = grace_period {
+        for pid in handle.pids() {
+            unsafe {
+                libc::kill(pid as _, libc::SIGTERM);
+            }
+        }
+        let deadline = Instant::now() + grace_period;
+        while Instant::now() < deadline {
+            // A non-zero exit status is expected here.
+            if !matches!(handle.try_wait(), Ok(None)) {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = grace_period;
+    handle.kill().map_err(Into::into)
+}
+
+fn forward<W: Write + 'static>(
+    reader: PipeReader,
+    prefix_output: Option<PrefixOutput<'_>>,
+    stream: &str,
+    writer: fn() -> W,
+    capture: Option<Arc<Mutex<File>>>,
+) -> thread::JoinHandle<io::Result<()>> {
+    match prefix_output {
+        Some(PrefixOutput { name, timestamps }) => prefix_lines(
+            reader,
+            format!("[{} {}]", name, stream),
+            timestamps,
+            writer,
+            capture,
+        ),
+        None => tee(reader, writer, capture),
+    }
+}
+
+fn prefix_lines<W: Write + 'static>(
+    reader: PipeReader,
+    prefix: String,
+    timestamps: bool,
+    writer: fn() -> W,
+    capture: Option<Arc<Mutex<File>>>,
+) -> thread::JoinHandle<io::Result<()>> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = vec![];
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            if let Some(capture) = &capture {
+                capture.lock().unwrap().write_all(&line)?;
+            }
+            let content = String::from_utf8_lossy(&line);
+            let content = content.trim_end_matches(&['\n', '\r'][..]);
+            let mut writer = writer();
+            if timestamps {
+                let now = humantime::format_rfc3339_millis(SystemTime::now());
+                writeln!(writer, "{} {} {}", now, prefix, content)?;
+            } else {
+                writeln!(writer, "{} {}", prefix, content)?;
+            }
+            writer.flush()?;
+            line.clear();
+        }
+        Ok(())
+    })
+}
+
+/// Copies the output to `writer` and `capture` as it comes, without waiting for newlines.
+fn tee<W: Write + 'static>(
+    mut reader: PipeReader,
+    writer: fn() -> W,
+    capture: Option<Arc<Mutex<File>>>,
+) -> thread::JoinHandle<io::Result<()>> {
+    thread::spawn(move || {
+        let mut buf = [0; 8192];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if let Some(capture) = &capture {
+                capture.lock().unwrap().write_all(&buf[..n])?;
+            }
+            let mut writer = writer();
+            writer.write_all(&buf[..n])?;
+            writer.flush()?;
+        }
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PrefixOutput<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) timestamps: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Capture<'a> {
+    pub(crate) path: &'a Path,
+    /// Whether to write stderr to the file as well.
+    pub(crate) stderr: bool,
+}
+
+/// Stops the process from outside, for `--watch`.
+#[derive(Clone, Copy)]
+pub(crate) struct Interrupt<'a> {
+    /// Called every `interval` while the process runs. The process is stopped once it returns
+    /// `true`.
+    pub(crate) poll: &'a dyn Fn() -> bool,
+    pub(crate) interval: Duration,
+    /// With `Some`, the process is asked to exit with `SIGTERM` before it is killed.
+    pub(crate) grace_period: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the program was stopped to be restarted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+#[derive(Debug)]
+pub(crate) struct TimedOut(Duration);
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "timed out after {}", humantime::format_duration(self.0))
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
 fn info(program: &OsStr, args: &[OsString], dry_run: bool) {
-    info!(
-        "{}Running `{}{}`",
-        if dry_run { "[dry-run] " } else { "" },
-        shell_escape::escape(program.to_string_lossy()),
-        args.iter()
-            .format_with("", |arg, f| f(&format_args!(" {}", arg.to_string_lossy()))),
-    );
+    crate::event::emit(Event::ProcessSpawned {
+        program: program.to_owned(),
+        args: args.to_owned(),
+        dry_run,
+    });
 }
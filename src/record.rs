@@ -0,0 +1,246 @@
+use crate::config::BikecaseConfigContent;
+
+use anyhow::{bail, Context as _};
+use serde::{Deserialize, Serialize};
+
+use std::io::{BufWriter, Read as _, Write as _};
+use std::path::{Path, PathBuf};
+
+static INFO_ENTRY: &str = "bundle.json";
+static SCRIPT_ENTRY: &str = "script.rs";
+static ARG_FILE_ENTRY: &str = "args.txt";
+static CONFIG_ENTRY: &str = "config.toml";
+static MANIFEST_ENTRY: &str = "workspace/Cargo.toml";
+
+static REDACTED: &str = "<redacted>";
+
+/// What `bikecase --record` captures to reproduce a run.
+#[derive(Debug)]
+pub(crate) struct Bundle {
+    pub(crate) info: BundleInfo,
+    pub(crate) script: String,
+    /// Content of `--arg-file`.
+    pub(crate) arg_file: Option<String>,
+    /// The config with the values of `env` redacted.
+    pub(crate) config: String,
+    /// The workspace manifest, if it existed.
+    pub(crate) manifest: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct BundleInfo {
+    pub(crate) bikecase_version: String,
+    pub(crate) cargo_version: Option<String>,
+    pub(crate) os: String,
+    pub(crate) arch: String,
+    pub(crate) workspace_root: PathBuf,
+    /// The parsed command line arguments (`crate::Bikecase`).
+    pub(crate) args: serde_json::Value,
+    /// Whether the program arguments and `--arg-file` were left out of `args`, without
+    /// `--record-args`.
+    #[serde(default)]
+    pub(crate) args_omitted: bool,
+}
+
+impl BundleInfo {
+    pub(crate) fn new(
+        mut args: serde_json::Value,
+        workspace_root: &Path,
+        record_args: bool,
+    ) -> Self {
+        args["record"] = serde_json::Value::Null;
+        args["record-args"] = false.into();
+        if !record_args {
+            args["args"] = serde_json::json!([]);
+            args["arg-file"] = serde_json::Value::Null;
+        }
+
+        Self {
+            bikecase_version: env!("CARGO_PKG_VERSION").to_owned(),
+            cargo_version: crate::workspace::cargo_version(),
+            os: std::env::consts::OS.to_owned(),
+            arch: std::env::consts::ARCH.to_owned(),
+            workspace_root: workspace_root.to_owned(),
+            args,
+            args_omitted: !record_args,
+        }
+    }
+}
+
+impl Bundle {
+    pub(crate) fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let file = BufWriter::new(crate::fs::create(path)?);
+        let mut builder = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+        let mut append = |path: &str, content: &[u8]| -> anyhow::Result<()> {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as _);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, content)?;
+            Ok(())
+        };
+        append(
+            INFO_ENTRY,
+            serde_json::to_string_pretty(&self.info)?.as_ref(),
+        )?;
+        append(SCRIPT_ENTRY, self.script.as_ref())?;
+        if let Some(arg_file) = &self.arg_file {
+            append(ARG_FILE_ENTRY, arg_file.as_ref())?;
+        }
+        append(CONFIG_ENTRY, self.config.as_ref())?;
+        if let Some(manifest) = &self.manifest {
+            append(MANIFEST_ENTRY, manifest.as_ref())?;
+        }
+        builder
+            .into_inner()?
+            .finish()?
+            .flush()
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        crate::event::emit(crate::event::Event::FileWritten {
+            path: path.to_owned(),
+            dry_run: false,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn read(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+
+        let (mut info, mut script, mut arg_file, mut config, mut manifest) =
+            (None, None, None, None, None);
+        for entry in archive
+            .entries()
+            .with_context(|| format!("failed to read {}", path.display()))?
+        {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.to_string_lossy().into_owned();
+            let mut content = "".to_owned();
+            entry.read_to_string(&mut content)?;
+
+            let slot = match &*entry_path {
+                p if p == INFO_ENTRY => &mut info,
+                p if p == SCRIPT_ENTRY => &mut script,
+                p if p == ARG_FILE_ENTRY => &mut arg_file,
+                p if p == CONFIG_ENTRY => &mut config,
+                p if p == MANIFEST_ENTRY => &mut manifest,
+                p => bail!("{}: unexpected entry: {}", path.display(), p),
+            };
+            *slot = Some(content);
+        }
+
+        let missing = |name: &str| format!("{}: missing `{}`", path.display(), name);
+        let info = info.with_context(|| missing(INFO_ENTRY))?;
+        Ok(Self {
+            info: serde_json::from_str(&info)
+                .with_context(|| format!("{}: invalid `{}`", path.display(), INFO_ENTRY))?,
+            script: script.with_context(|| missing(SCRIPT_ENTRY))?,
+            arg_file,
+            config: config.with_context(|| missing(CONFIG_ENTRY))?,
+            manifest,
+        })
+    }
+}
+
+/// Serializes `config`, replacing the values of `env` that are not references to secrets.
+///
+/// The key of the workspace at `workspace_root` is replaced with the expanded path so that
+/// [`relocate_config`] can find it.
+pub(crate) fn redact_config(
+    config: &BikecaseConfigContent,
+    workspace_root: &Path,
+    home_dir: Option<&Path>,
+) -> anyhow::Result<String> {
+    let key = config
+        .workspaces
+        .keys()
+        .find(|p| Path::new(&*p.expand(home_dir)) == workspace_root)
+        .map(ToString::to_string);
+
+    let mut config = toml::Value::try_from(config)?;
+
+    if let (Some(key), Some(workspaces)) = (
+        key,
+        config
+            .get_mut("workspaces")
+            .and_then(toml::Value::as_table_mut),
+    ) {
+        if let Some(workspace) = workspaces.remove(&key) {
+            workspaces.insert(workspace_root.to_string_lossy().into_owned(), workspace);
+        }
+    }
+
+    redact_env(&mut config);
+    Ok(toml::to_string_pretty(&config)?)
+}
+
+/// Replaces the values of `workspaces.*.env` and `workspaces.*.packages.*.env` in the serialized
+/// config that are not references to secrets.
+pub(crate) fn redact_env(config: &mut toml::Value) {
+    let workspaces = config
+        .get_mut("workspaces")
+        .and_then(toml::Value::as_table_mut)
+        .into_iter()
+        .flat_map(|t| t.iter_mut().map(|(_, v)| v));
+    for workspace in workspaces {
+        redact(workspace);
+        let packages = workspace
+            .get_mut("packages")
+            .and_then(toml::Value::as_table_mut)
+            .into_iter()
+            .flat_map(|t| t.iter_mut().map(|(_, v)| v));
+        for package in packages {
+            redact(package);
+        }
+    }
+
+    fn redact(table: &mut toml::Value) {
+        let values = table
+            .get_mut("env")
+            .and_then(toml::Value::as_table_mut)
+            .into_iter()
+            .flat_map(|t| t.iter_mut().map(|(_, v)| v));
+        for value in values {
+            if value.is_str() {
+                *value = REDACTED.into();
+            }
+        }
+    }
+}
+
+/// Moves the config of the workspace at `from` to `to`.
+pub(crate) fn relocate_config(config: &str, from: &Path, to: &Path) -> anyhow::Result<String> {
+    let mut config = config
+        .parse::<toml::Value>()
+        .with_context(|| "failed to parse the recorded config")?;
+    if let Some(workspaces) = config
+        .get_mut("workspaces")
+        .and_then(toml::Value::as_table_mut)
+    {
+        if let Some(workspace) = workspaces.remove(&*from.to_string_lossy()) {
+            workspaces.insert(to.to_string_lossy().into_owned(), workspace);
+        }
+    }
+    Ok(toml::to_string_pretty(&config)?)
+}
+
+/// Empties `workspace.members` and `workspace.default-members` of a recorded manifest, since the
+/// other members are not in the bundle.
+pub(crate) fn isolate_manifest(manifest: &str) -> anyhow::Result<String> {
+    let mut manifest = manifest
+        .parse::<toml_edit::DocumentMut>()
+        .with_context(|| "failed to parse the recorded manifest")?;
+    if let Some(workspace) = manifest
+        .get_mut("workspace")
+        .and_then(toml_edit::Item::as_table_like_mut)
+    {
+        for key in &["members", "default-members"] {
+            if workspace.contains_key(key) {
+                workspace.insert(key, toml_edit::value(toml_edit::Array::new()));
+            }
+        }
+    }
+    Ok(manifest.to_string())
+}
@@ -5,31 +5,171 @@ use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
 use syn::{Lit, Meta, MetaNameValue};
 
 use std::borrow::Cow;
-use std::fmt::Display;
-use std::iter;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
 use std::ops::Range;
 
 pub(crate) fn extract_cargo_lang_code<C: Display + Send + Sync + 'static, F: FnOnce() -> C>(
     code: &str,
     on_not_found: F,
 ) -> anyhow::Result<String> {
-    let (_, cargo_lang_code) = replace_cargo_lang_code(code, "", on_not_found)?;
-    Ok(cargo_lang_code)
+    replace_cargo_lang_code(code, "", None, on_not_found, io::sink())
 }
 
-pub(crate) fn replace_cargo_lang_code_with_default(code: &str) -> anyhow::Result<(String, String)> {
-    return replace_cargo_lang_code(code, MANIFEST, || {
-        anyhow!("could not find the `cargo` code block")
-    });
+const BOM: char = '\u{feff}';
 
-    static MANIFEST: &str = "# Leave blank.";
+/// The BOM and the line ending of a file.
+///
+/// Every function in this module strips them before parsing and puts them back on what it returns,
+/// so that scripts written on Windows are not converted to LF behind the user's back.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub(crate) struct TextStyle {
+    pub(crate) bom: bool,
+    pub(crate) crlf: bool,
 }
 
+impl TextStyle {
+    /// Detects the style of `text`. The line ending is decided by the first line.
+    pub(crate) fn detect(text: &str) -> Self {
+        Self {
+            bom: text.starts_with(BOM),
+            crlf: text.find('\n').is_some_and(|i| text[..i].ends_with('\r')),
+        }
+    }
+
+    /// Strips the BOM and converts the line endings to LF.
+    pub(crate) fn normalize(text: &str) -> (Self, Cow<'_, str>) {
+        let style = Self::detect(text);
+        let text = text.strip_prefix(BOM).unwrap_or(text);
+        let text = if text.contains("\r\n") {
+            text.replace("\r\n", "\n").into()
+        } else {
+            text.into()
+        };
+        (style, text)
+    }
+
+    fn line_ending(self) -> &'static str {
+        if self.crlf {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    fn restore(self, text: String) -> String {
+        let text = if self.crlf {
+            text.replace('\n', "\r\n")
+        } else {
+            text
+        };
+        if self.bom {
+            format!("{}{}", BOM, text)
+        } else {
+            text
+        }
+    }
+}
+
+impl Display for TextStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.crlf { "CRLF" } else { "LF" })?;
+        if self.bom {
+            f.write_str(" with BOM")?;
+        }
+        Ok(())
+    }
+}
+
+/// What `src/main.rs` of packages has in place of the manifest.
+pub(crate) static DEFAULT_MANIFEST: &str = "# Leave blank.";
+
+pub(crate) fn replace_cargo_lang_code_with_default(code: &str) -> anyhow::Result<(String, String)> {
+    let mut main_rs = vec![];
+    let cargo_toml = replace_cargo_lang_code(
+        code,
+        DEFAULT_MANIFEST,
+        None,
+        || anyhow!("could not find the `cargo` code block"),
+        &mut main_rs,
+    )?;
+    Ok((String::from_utf8(main_rs)?, cargo_toml))
+}
+
+/// Replaces the content of the manifest block with `with`, and the info string of its opening fence
+/// with `fence` if any, writing the converted code to `wtr` line by line.
+///
+/// Returns the original manifest. The lines are written as they are converted, so `wtr` should be
+/// buffered.
 pub(crate) fn replace_cargo_lang_code<C: Display + Send + Sync + 'static, F: FnOnce() -> C>(
     code: &str,
     with: &str,
+    fence: Option<&str>,
     on_not_found: F,
-) -> anyhow::Result<(String, String)> {
+    mut wtr: impl Write,
+) -> anyhow::Result<String> {
+    let (style, code) = TextStyle::normalize(code);
+    let (lines, manifest) = replace_manifest_lines(&code, with, fence, on_not_found)?;
+    if style.bom {
+        write!(wtr, "{}", BOM)?;
+    }
+    for line in lines {
+        wtr.write_all(line.as_bytes())?;
+        wtr.write_all(style.line_ending().as_bytes())?;
+    }
+    Ok(manifest)
+}
+
+/// Inserts `manifest` as a `cargo` code block at the beginning of the module doc of `code`, which
+/// does not have one yet.
+pub(crate) fn insert_cargo_lang_code(code: &str, manifest: &str) -> anyhow::Result<String> {
+    let (style, code) = TextStyle::normalize(code);
+    let syn::File { shebang, .. } = syn::parse_file(&code)?;
+    let mut lines = code.lines().peekable();
+
+    let mut converted = "".to_owned();
+    if shebang.is_some() {
+        converted += lines.next().unwrap_or_default();
+        converted += "\n";
+    }
+    converted += "//! ```cargo\n";
+    for line in manifest.trim_end().lines() {
+        if line.is_empty() {
+            converted += "//!\n";
+        } else {
+            converted += &format!("//! {}\n", line);
+        }
+    }
+    converted += "//! ```\n";
+    match lines.peek() {
+        Some(line) if line.starts_with("//!") => converted += "//!\n",
+        Some(line) if !line.is_empty() => converted += "\n",
+        _ => {}
+    }
+    for line in lines {
+        converted += line;
+        converted += "\n";
+    }
+    Ok(style.restore(converted))
+}
+
+/// Whether a fenced code block with the info string is the embedded manifest.
+///
+/// Any comma-separated tag can be `cargo`, so that ```` ```cargo,ignore ```` and
+/// ```` ```toml,cargo ```` (a `toml` block marked for Cargo) are accepted as well as
+/// ```` ```cargo ````. Plain ```` ```toml ```` blocks are not, since they are often just examples.
+pub(crate) fn is_manifest_fence(info: &str) -> bool {
+    info.split(',').any(|tag| tag.trim() == "cargo")
+}
+
+/// Returns the lines of the converted code, borrowing the ones outside of the module doc from
+/// `code`, and the original manifest.
+fn replace_manifest_lines<'a, C: Display + Send + Sync + 'static, F: FnOnce() -> C>(
+    code: &'a str,
+    with: &str,
+    fence: Option<&str>,
+    on_not_found: F,
+) -> anyhow::Result<(impl Iterator<Item = Cow<'a, str>>, String)> {
     let mut code_lines = code.lines().map(Cow::from).map(Some).collect::<Vec<_>>();
 
     let syn::File { shebang, attrs, .. } = syn::parse_file(code)?;
@@ -58,7 +198,7 @@ pub(crate) fn replace_cargo_lang_code<C: Display + Send + Sync + 'static, F: FnO
         if_chain! {
             if let Ok(meta) = attr.parse_meta();
             if let Meta::NameValue(MetaNameValue { path, lit, .. }) = meta;
-            if path.get_ident().map_or(false, |i| i == "doc");
+            if path.get_ident().is_some_and(|i| i == "doc");
             if let Lit::Str(lit_str) = lit;
             then {
                 doc += lit_str.value().trim_start_matches(' ');
@@ -86,29 +226,30 @@ pub(crate) fn replace_cargo_lang_code<C: Display + Send + Sync + 'static, F: FnO
             match &state {
                 State::None => {
                     if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(kind))) = event {
-                        if &*kind == "cargo" {
-                            state = State::Start;
+                        if is_manifest_fence(&kind) {
+                            state = State::Start(span.start);
                         }
                     }
                 }
-                State::Start => {
+                State::Start(block_start) => {
                     if let Event::Text(_) = event {
-                        state = State::Text(span);
+                        state = State::Text(*block_start, span);
                     }
                 }
-                State::Text(span) => {
+                State::Text(block_start, span) => {
                     if let Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(kind))) = event {
-                        if &*kind == "cargo" {
-                            state = State::End(span.clone());
+                        if is_manifest_fence(&kind) {
+                            state = State::End(*block_start, span.clone());
                         }
                     }
                 }
-                State::End(_) => {}
+                State::End(..) => {}
             }
             state
         })
         .end()
         .with_context(on_not_found)?;
+    let (block_start, doc_span) = doc_span;
 
     let with = if with.is_empty() || with.ends_with('\n') {
         with.to_owned()
@@ -116,38 +257,279 @@ pub(crate) fn replace_cargo_lang_code<C: Display + Send + Sync + 'static, F: FnO
         format!("{}\n", with)
     };
 
-    let converted_doc = format!("{}{}{}", &doc[..doc_span.start], with, &doc[doc_span.end..]);
+    let opening = match fence {
+        Some(fence) => {
+            let line_start = doc[..block_start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = line_start + doc[line_start..].find('\n').unwrap_or(0);
+            let line = &doc[line_start..line_end];
+            let indent = line.len() - line.trim_start().len();
+            let marker = line[indent..]
+                .find(|c| c != '`' && c != '~')
+                .map_or(line.len(), |i| indent + i);
 
-    let converted_code = shebang
-        .map(Into::into)
-        .into_iter()
-        .chain(converted_doc.lines().map(|line| {
+            let mut opening = doc[..line_start].to_owned();
+            opening += &line[..marker];
+            opening += fence;
+            opening += &doc[line_end..doc_span.start];
+            opening
+        }
+        None => doc[..doc_span.start].to_owned(),
+    };
+
+    let converted_doc = format!("{}{}{}", opening, with, &doc[doc_span.end..]);
+
+    let converted_doc_lines = converted_doc
+        .lines()
+        .map(|line| {
             if line.is_empty() {
                 "//!".into()
             } else {
                 format!("//! {}", line).into()
             }
-        }))
-        .chain(code_lines.into_iter().flatten())
-        .interleave_shortest(iter::repeat("\n".into()))
-        .join("");
+        })
+        .collect::<Vec<_>>();
+
+    let converted_lines = shebang
+        .map(Into::into)
+        .into_iter()
+        .chain(converted_doc_lines)
+        .chain(code_lines.into_iter().flatten());
 
-    return Ok((converted_code, doc[doc_span].to_owned()));
+    return Ok((converted_lines, doc[doc_span].to_owned()));
 
     #[derive(Debug)]
     enum State {
         None,
-        Start,
-        Text(Range<usize>),
-        End(Range<usize>),
+        Start(usize),
+        Text(usize, Range<usize>),
+        End(usize, Range<usize>),
     }
 
     impl State {
-        fn end(self) -> Option<Range<usize>> {
+        /// Returns the start of the code block and the span of its content.
+        fn end(self) -> Option<(usize, Range<usize>)> {
             match self {
-                Self::End(span) => Some(span),
+                Self::End(block_start, span) => Some((block_start, span)),
                 _ => None,
             }
         }
     }
 }
+
+/// Returns the first paragraph of the module doc, truncated to about `max_chars` characters at a
+/// word boundary.
+pub(crate) fn doc_summary(code: &str, max_chars: usize) -> anyhow::Result<Option<String>> {
+    let doc = raw_module_doc(code)?;
+
+    let mut summary = None::<String>;
+    for event in Parser::new_ext(&doc, Options::all()) {
+        match (&mut summary, event) {
+            (None, Event::Start(Tag::Paragraph)) => summary = Some("".to_owned()),
+            (Some(_), Event::End(Tag::Paragraph)) => break,
+            (Some(summary), Event::Text(text)) => *summary += &text,
+            (Some(summary), Event::Code(code)) => *summary += &format!("`{}`", code),
+            (Some(summary), Event::SoftBreak) | (Some(summary), Event::HardBreak) => {
+                *summary += " "
+            }
+            _ => {}
+        }
+    }
+
+    Ok(summary
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .map(|summary| {
+            if summary.chars().count() <= max_chars {
+                return summary;
+            }
+            let truncated = summary.chars().take(max_chars).collect::<String>();
+            let truncated = match truncated.rfind(char::is_whitespace) {
+                Some(i) if i > 0 => &truncated[..i],
+                _ => &truncated,
+            };
+            format!(
+                "{}…",
+                truncated.trim_end_matches(|c: char| c.is_ascii_punctuation())
+            )
+        }))
+}
+
+/// Returns the module doc without the manifest block, for `README.md`.
+pub(crate) fn module_doc(code: &str) -> anyhow::Result<String> {
+    let mut doc = raw_module_doc(code)?;
+
+    let manifest = Parser::new_ext(&doc, Options::all())
+        .into_offset_iter()
+        .find_map(|(event, span)| match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))
+                if is_manifest_fence(&info) =>
+            {
+                Some(span)
+            }
+            _ => None,
+        });
+    if let Some(manifest) = manifest {
+        doc.replace_range(manifest, "");
+    }
+
+    let mut ret = "".to_owned();
+    for line in doc.trim().lines() {
+        if !(line.is_empty() && (ret.is_empty() || ret.ends_with("\n\n"))) {
+            ret += line;
+            ret += "\n";
+        }
+    }
+    Ok(ret)
+}
+
+fn raw_module_doc(code: &str) -> anyhow::Result<String> {
+    let (_, code) = TextStyle::normalize(code);
+    let syn::File { attrs, .. } = syn::parse_file(&code)?;
+
+    let mut doc = "".to_owned();
+    for attr in attrs {
+        if_chain! {
+            if let Ok(meta) = attr.parse_meta();
+            if let Meta::NameValue(MetaNameValue { path, lit, .. }) = meta;
+            if path.get_ident().is_some_and(|i| i == "doc");
+            if let Lit::Str(lit_str) = lit;
+            then {
+                let value = lit_str.value();
+                doc += value.strip_prefix(' ').unwrap_or(&value);
+                doc += "\n";
+            }
+        }
+    }
+    Ok(doc)
+}
+
+/// Splits the top-level inline modules (`mod x { ... }`) out of `code`, replacing each of them
+/// with `mod x;`.
+///
+/// Returns the rewritten code and the names and contents of the modules. Modules with `#[path]`
+/// are left as they are. The modules get the line ending of `code`, but not the BOM.
+pub(crate) fn split_modules(code: &str) -> anyhow::Result<(String, Vec<(String, String)>)> {
+    let (style, code) = TextStyle::normalize(code);
+    let code = &*code;
+    let syn::File { items, .. } = syn::parse_file(code)?;
+
+    let mut replacements = vec![];
+    let mut modules = vec![];
+    for item in items {
+        if_chain! {
+            if let syn::Item::Mod(item_mod) = item;
+            if !item_mod.attrs.iter().any(|a| a.path.is_ident("path"));
+            if let Some((brace, _)) = &item_mod.content;
+            then {
+                let ident_end = byte_offset(code, item_mod.ident.span().end());
+                let (open, close) = (
+                    byte_offset(code, brace.span.start()),
+                    byte_offset(code, brace.span.end()),
+                );
+                let content = dedent(&code[open + 1..close - 1]);
+                let content = TextStyle { bom: false, ..style }.restore(content);
+                replacements.push((ident_end..close, ";".to_owned()));
+                modules.push((item_mod.ident.to_string(), content));
+            }
+        }
+    }
+    Ok((style.restore(replace_ranges(code, replacements)), modules))
+}
+
+/// Replaces the top-level `mod x;` in `code` with `mod x { ... }`, taking the contents from
+/// `read`. Declarations for which `read` returns `None` are left as they are.
+pub(crate) fn inline_modules(
+    code: &str,
+    mut read: impl FnMut(&str) -> anyhow::Result<Option<String>>,
+) -> anyhow::Result<String> {
+    let (style, code) = TextStyle::normalize(code);
+    let code = &*code;
+    let syn::File { items, .. } = syn::parse_file(code)?;
+
+    let mut replacements = vec![];
+    for item in items {
+        if_chain! {
+            if let syn::Item::Mod(item_mod) = item;
+            if !item_mod.attrs.iter().any(|a| a.path.is_ident("path"));
+            if let Some(semi) = item_mod.semi;
+            if let Some(content) = read(&item_mod.ident.to_string())?;
+            let content = content.strip_prefix(BOM).unwrap_or(&content);
+            then {
+                let ident_end = byte_offset(code, item_mod.ident.span().end());
+                let semi_end = byte_offset(code, semi.spans[0].end());
+                let content = content
+                    .lines()
+                    .map(|l| if l.is_empty() { "\n".to_owned() } else { format!("    {}\n", l) })
+                    .join("");
+                replacements.push((ident_end..semi_end, format!(" {{\n{}}}", content)));
+            }
+        }
+    }
+    Ok(style.restore(replace_ranges(code, replacements)))
+}
+
+/// Writes `mod name { content }` to `wtr`, to follow code written in `style`.
+pub(crate) fn write_module(
+    mut wtr: impl Write,
+    style: TextStyle,
+    name: &str,
+    content: &str,
+) -> io::Result<()> {
+    let line_ending = style.line_ending();
+    write!(wtr, "{}mod {} {{{}", line_ending, name, line_ending)?;
+    for line in content.lines() {
+        if !line.is_empty() {
+            write!(wtr, "    {}", line)?;
+        }
+        wtr.write_all(line_ending.as_bytes())?;
+    }
+    write!(wtr, "}}{}", line_ending)
+}
+
+fn byte_offset(code: &str, loc: proc_macro2::LineColumn) -> usize {
+    let line_start = code
+        .split_inclusive('\n')
+        .take(loc.line - 1)
+        .map(str::len)
+        .sum::<usize>();
+    let line = &code[line_start..];
+    line_start
+        + line
+            .char_indices()
+            .nth(loc.column)
+            .map_or(line.len(), |(i, _)| i)
+}
+
+fn replace_ranges(code: &str, replacements: Vec<(Range<usize>, String)>) -> String {
+    let mut code = code.to_owned();
+    for (range, with) in replacements.into_iter().rev() {
+        code.replace_range(range, &with);
+    }
+    code
+}
+
+/// Removes the leading and trailing blank lines and the common indentation.
+fn dedent(content: &str) -> String {
+    let lines = content
+        .lines()
+        .skip_while(|l| l.trim().is_empty())
+        .collect::<Vec<_>>();
+    let lines = &lines[..lines
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(0, |i| i + 1)];
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|l| match l.get(indent..) {
+            _ if l.trim().is_empty() => "\n".to_owned(),
+            Some(l) => format!("{}\n", l),
+            None => format!("{}\n", l),
+        })
+        .join("")
+}
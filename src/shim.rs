@@ -0,0 +1,52 @@
+use anyhow::{anyhow, bail};
+
+use std::ffi::OsString;
+
+/// Translates arguments for `cargo-script`/`cargo-eval` into ones for `bikecase`.
+///
+/// `args` should not contain the program name. Like `cargo-script`, scripts are built in release
+/// mode unless `--debug` is given.
+pub fn translate_cargo_script_args(
+    args: impl IntoIterator<Item = OsString>,
+) -> anyhow::Result<Vec<OsString>> {
+    let mut args = args.into_iter();
+    let mut ret = vec!["bikecase".into()];
+    let mut debug = false;
+
+    let script = loop {
+        let arg = args
+            .next()
+            .ok_or_else(|| anyhow!("missing the path to the script"))?;
+        let arg_str = arg.to_string_lossy();
+
+        match &*arg_str {
+            "--debug" => debug = true,
+            "--features" => {
+                let features = args
+                    .next()
+                    .ok_or_else(|| anyhow!("`--features` requires a value"))?;
+                ret.extend(vec!["--features".into(), features]);
+            }
+            // `cargo` decides whether to rebuild.
+            "--force" | "-f" | "--clear-cache" => {}
+            "--" => {
+                break args
+                    .next()
+                    .ok_or_else(|| anyhow!("missing the path to the script"))?;
+            }
+            _ if arg_str.starts_with("--features=") => ret.push(arg),
+            _ if arg_str.starts_with('-') => {
+                bail!("`{}` is not supported by bikecase", arg_str);
+            }
+            _ => break arg,
+        }
+    };
+
+    if !debug {
+        ret.push("--release".into());
+    }
+    ret.push(script);
+    ret.push("--".into());
+    ret.extend(args);
+    Ok(ret)
+}
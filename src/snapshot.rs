@@ -0,0 +1,308 @@
+use crate::config::BikecaseConfigWorkspace;
+
+use anyhow::{bail, Context as _};
+use cargo_metadata::Metadata;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use log::{info, warn};
+
+use std::collections::BTreeMap;
+use std::io::Read as _;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+static CONFIG_ENTRY: &str = "config.toml";
+static WORKSPACE_ENTRY: &str = "workspace";
+
+/// The workspace manifest, the member sources, and the config section of a workspace.
+#[derive(Default, Debug)]
+pub(crate) struct Snapshot {
+    /// Paths relative to the workspace root.
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    config: Option<String>,
+}
+
+impl Snapshot {
+    pub(crate) fn collect(
+        metadata: &Metadata,
+        config: Option<&BikecaseConfigWorkspace>,
+    ) -> anyhow::Result<Self> {
+        let root = &metadata.workspace_root;
+        let mut files = BTreeMap::new();
+
+        files.insert(
+            "Cargo.toml".into(),
+            std::fs::read(root.join("Cargo.toml"))
+                .with_context(|| format!("failed to read {}", root.join("Cargo.toml").display()))?,
+        );
+
+        for member in &metadata.workspace_members {
+            let package = &metadata[member];
+            let dir = package
+                .manifest_path
+                .parent()
+                .expect("`manifest_path` should end with \"Cargo.toml\"");
+
+            if dir == root {
+                bail!(
+                    "`{}` is not a virtual manifest",
+                    root.join("Cargo.toml").display()
+                );
+            }
+            if !dir.starts_with(root) {
+                warn!("Skipping `{}` (outside of the workspace)", package.name);
+                continue;
+            }
+
+            for entry in crate::fs::walk(dir) {
+                let entry = entry?;
+                let path = entry.path();
+                let excluded = path
+                    .strip_prefix(dir)?
+                    .components()
+                    .any(|c| ["target", ".git"].iter().any(|&n| c.as_os_str() == n));
+                if !excluded && path.is_file() {
+                    let content = std::fs::read(path)
+                        .with_context(|| format!("failed to read {}", path.display()))?;
+                    files.insert(path.strip_prefix(root)?.to_owned(), content);
+                }
+            }
+        }
+
+        let config = config
+            .map(|c| toml::to_string_pretty(c).expect("should not fail"))
+            .filter(|c| !c.is_empty());
+
+        Ok(Self { files, config })
+    }
+
+    pub(crate) fn read(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+
+        let mut this = Self::default();
+        for entry in archive
+            .entries()
+            .with_context(|| format!("failed to read {}", path.display()))?
+        {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.into_owned();
+            let mut content = vec![];
+            entry.read_to_end(&mut content)?;
+
+            if entry_path == Path::new(CONFIG_ENTRY) {
+                this.config = Some(String::from_utf8(content)?);
+            } else if let Ok(rel) = entry_path.strip_prefix(WORKSPACE_ENTRY) {
+                this.files.insert(rel.to_owned(), content);
+            } else {
+                bail!(
+                    "{}: unexpected entry: {}",
+                    path.display(),
+                    entry_path.display()
+                );
+            }
+        }
+        Ok(this)
+    }
+
+    pub(crate) fn write(&self, dir: &Path, dry_run: bool) -> anyhow::Result<PathBuf> {
+        let name = humantime::format_rfc3339_seconds(SystemTime::now())
+            .to_string()
+            .replace(':', "");
+        let path = dir.join(name).with_extension("tar.gz");
+        if path.exists() {
+            bail!("{} already exists", path.display());
+        }
+
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Default::default()));
+        let mut append = |path: &Path, content: &[u8]| -> anyhow::Result<()> {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as _);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, content)?;
+            Ok(())
+        };
+        if let Some(config) = &self.config {
+            append(CONFIG_ENTRY.as_ref(), config.as_ref())?;
+        }
+        for (rel, content) in &self.files {
+            append(&Path::new(WORKSPACE_ENTRY).join(rel), content)?;
+        }
+        let tarball = builder.into_inner()?.finish()?;
+
+        crate::fs::create_dir_all(dir, dry_run)?;
+        crate::fs::write(&path, tarball, dry_run)?;
+        Ok(path)
+    }
+
+    pub(crate) fn config(&self) -> anyhow::Result<Option<BikecaseConfigWorkspace>> {
+        self.config
+            .as_deref()
+            .map(|c| toml::from_str(c).with_context(|| "failed to parse the config section"))
+            .transpose()
+    }
+
+    /// Writes the files back. Files that are not in the snapshot are left as they are.
+    pub(crate) fn restore_files(&self, workspace_root: &Path, dry_run: bool) -> anyhow::Result<()> {
+        // The paths come from the tarball, which may have been crafted.
+        for rel in self.files.keys() {
+            if !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+                bail!("invalid path in the snapshot: {}", rel.display());
+            }
+        }
+        for (rel, content) in &self.files {
+            let path = workspace_root.join(rel);
+            if std::fs::read(&path).ok().as_ref() == Some(content) {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                if !parent.exists() {
+                    crate::fs::create_dir_all(parent, dry_run)?;
+                }
+            }
+            crate::fs::write(path, content, dry_run)?;
+        }
+        Ok(())
+    }
+
+    /// Compares `self` (older) with `other` (newer).
+    pub(crate) fn diff<'a>(&'a self, other: &'a Self) -> Vec<SnapshotDiff<'a>> {
+        let mut diffs = vec![];
+
+        let (config_from, config_to) = (self.config.as_deref(), other.config.as_deref());
+        if config_from != config_to {
+            diffs.push(SnapshotDiff {
+                path: CONFIG_ENTRY.as_ref(),
+                kind: SnapshotDiffKind::of(config_from.is_some(), config_to.is_some()),
+                from: config_from.map(str::as_bytes),
+                to: config_to.map(str::as_bytes),
+            });
+        }
+
+        let paths = self
+            .files
+            .keys()
+            .chain(other.files.keys())
+            .collect::<std::collections::BTreeSet<_>>();
+        for path in paths {
+            let (from, to) = (self.files.get(path), other.files.get(path));
+            if from != to {
+                diffs.push(SnapshotDiff {
+                    path,
+                    kind: SnapshotDiffKind::of(from.is_some(), to.is_some()),
+                    from: from.map(Vec::as_slice),
+                    to: to.map(Vec::as_slice),
+                });
+            }
+        }
+        diffs
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SnapshotDiff<'a> {
+    pub(crate) path: &'a Path,
+    pub(crate) kind: SnapshotDiffKind,
+    pub(crate) from: Option<&'a [u8]>,
+    pub(crate) to: Option<&'a [u8]>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SnapshotDiffKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl SnapshotDiffKind {
+    fn of(from: bool, to: bool) -> Self {
+        match (from, to) {
+            (false, _) => Self::Added,
+            (_, false) => Self::Removed,
+            _ => Self::Modified,
+        }
+    }
+}
+
+/// `<data_local_dir>/bikecase/snapshots/<hash of the workspace root>`
+pub(crate) fn dir(data_local_dir: Option<&Path>, workspace_root: &Path) -> anyhow::Result<PathBuf> {
+    let data_local_dir =
+        data_local_dir.with_context(|| "could not find the local data directory")?;
+    let hash = crate::cache::sha256(&workspace_root.to_string_lossy());
+    Ok(data_local_dir
+        .join("bikecase")
+        .join("snapshots")
+        .join(&hash[..16]))
+}
+
+/// Finds a snapshot by its name or path, or the latest one if `name` is `None`.
+pub(crate) fn find(dir: &Path, name: Option<&str>, cwd: &Path) -> anyhow::Result<PathBuf> {
+    if let Some(name) = name {
+        let as_path = cwd.join(name);
+        if as_path.is_file() {
+            return Ok(as_path);
+        }
+        let path = dir
+            .join(name.trim_end_matches(".tar.gz"))
+            .with_extension("tar.gz");
+        if !path.exists() {
+            bail!("no such snapshot: {:?}", name);
+        }
+        return Ok(path);
+    }
+
+    let latest = if dir.exists() {
+        std::fs::read_dir(dir)
+            .with_context(|| format!("failed to read {}", dir.display()))?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|p| p.to_string_lossy().ends_with(".tar.gz"))
+            .max()
+    } else {
+        None
+    };
+    let latest = latest.with_context(|| "no snapshots found for this workspace")?;
+    info!("Using the latest snapshot: {}", latest.display());
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snapshot;
+
+    use flate2::write::GzEncoder;
+
+    #[test]
+    fn restore_rejects_paths_out_of_workspace() -> anyhow::Result<()> {
+        let tempdir = tempfile::Builder::new()
+            .prefix("bikecase-snapshot-tests-")
+            .tempdir()?;
+
+        // `tar::Builder` refuses `..`, so the name is written to the header directly.
+        let mut header = tar::Header::new_old();
+        let name = b"workspace/../../escaped.rs";
+        header.as_old_mut().name[..name.len()].copy_from_slice(name);
+        header.set_size(0);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Default::default()));
+        builder.append(&header, &[][..])?;
+        let tarball = tempdir.path().join("snapshot.tar.gz");
+        std::fs::write(&tarball, builder.into_inner()?.finish()?)?;
+
+        let workspace_root = tempdir.path().join("a").join("ws");
+        std::fs::create_dir_all(&workspace_root)?;
+        let err = Snapshot::read(&tarball)?
+            .restore_files(&workspace_root, false)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid path in the snapshot: ../../escaped.rs",
+        );
+        assert!(!tempdir.path().join("escaped.rs").exists());
+        Ok(())
+    }
+}
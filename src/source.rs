@@ -0,0 +1,152 @@
+//! Where scripts are read from.
+
+use crate::event::{self, Event};
+
+use anyhow::{bail, ensure, Context as _};
+use url::Url;
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where to read a script from.
+///
+/// Parsed from the `FILE` argument of `bikecase` and `cargo bikecase import` with [`parse`]:
+///
+/// | Argument                   | Source      |
+/// | -------------------------- | ----------- |
+/// | (none)                     | `Stdin`     |
+/// | `http://…` or `https://…`  | `Url`       |
+/// | `gist:<ID>[@<REVISION>]`   | `Gist`      |
+/// | anything else              | `File`      |
+///
+/// `<ID>` may be an `alias` in `gist-ids` of the config, which the caller resolves.
+///
+/// [`parse`]: ScriptSource::parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptSource {
+    /// An absolute path to a local file.
+    File(PathBuf),
+    Stdin,
+    Url(Url),
+    Gist {
+        id: String,
+        /// The latest one if `None`.
+        revision: Option<String>,
+    },
+}
+
+impl ScriptSource {
+    /// Parses the `FILE` argument. Relative paths are resolved against `cwd`.
+    pub fn parse(arg: Option<&Path>, cwd: &Path) -> anyhow::Result<Self> {
+        let arg = match arg {
+            Some(arg) => arg,
+            None => return Ok(Self::Stdin),
+        };
+        if let Some(s) = arg.to_str() {
+            if s.starts_with("http://") || s.starts_with("https://") {
+                let url = s.parse().with_context(|| format!("invalid URL: {:?}", s))?;
+                return Ok(Self::Url(url));
+            }
+            if let Some(gist) = s.strip_prefix("gist:") {
+                let (id, revision) = match gist.split_once('@') {
+                    Some((id, revision)) => (id, Some(revision.to_owned())),
+                    None => (gist, None),
+                };
+                if id.is_empty()
+                    || !id
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    bail!("invalid gist ID: {:?}", id);
+                }
+                return Ok(Self::Gist {
+                    id: id.to_owned(),
+                    revision,
+                });
+            }
+        }
+        Ok(Self::File(cwd.join(arg.strip_prefix(".").unwrap_or(arg))))
+    }
+
+    /// The key of the trust store for remote sources: the URL, or `gist:<ID>`.
+    pub(crate) fn remote_key(&self) -> Option<String> {
+        match self {
+            Self::File(_) | Self::Stdin => None,
+            Self::Url(url) => Some(url.to_string()),
+            Self::Gist { id, .. } => Some(format!("gist:{}", id)),
+        }
+    }
+
+    /// Reads the script.
+    ///
+    /// `read_input` is taken for `Stdin`, and it is an error if it has already been taken.
+    pub fn resolve(
+        &self,
+        read_input: &mut Option<impl FnOnce() -> io::Result<String>>,
+    ) -> anyhow::Result<Script> {
+        match self {
+            Self::File(path) => Ok(Script {
+                code: crate::fs::read(path)?,
+                path: Some(path.clone()),
+                file_name: path
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .map(ToOwned::to_owned),
+            }),
+            Self::Stdin => {
+                let read_input = read_input.take().with_context(|| "stdin is already read")?;
+                Ok(Script {
+                    code: read_input()?,
+                    path: None,
+                    file_name: None,
+                })
+            }
+            Self::Url(url) => {
+                event::emit(Event::HttpRequest {
+                    method: "GET",
+                    url: url.to_string(),
+                    dry_run: false,
+                });
+                let res = crate::http::request("GET", url.as_ref()).call();
+                crate::http::raise_synthetic_error(&res)?;
+                event::emit(Event::HttpResponse {
+                    status: res.status(),
+                    status_text: res.status_text().to_owned(),
+                });
+                ensure!(res.status() == 200, "expected 200");
+                let file_name = url
+                    .path_segments()
+                    .and_then(|mut s| s.next_back())
+                    .filter(|s| !s.is_empty())
+                    .map(ToOwned::to_owned);
+                Ok(Script {
+                    code: res.into_string()?,
+                    path: None,
+                    file_name,
+                })
+            }
+            Self::Gist { id, revision } => {
+                let gist = crate::gist::retrieve_revision(
+                    id,
+                    revision.as_deref(),
+                    crate::gist::DEFAULT_MAX_SIZE,
+                )?;
+                Ok(Script {
+                    code: gist.script,
+                    path: None,
+                    file_name: Some(gist.script_filename),
+                })
+            }
+        }
+    }
+}
+
+/// A script read from a [`ScriptSource`].
+#[derive(Debug, Clone)]
+pub struct Script {
+    pub code: String,
+    /// The path for [`ScriptSource::File`].
+    pub path: Option<PathBuf>,
+    /// The file name to name the package after, e.g. `hello.rs`.
+    pub file_name: Option<String>,
+}
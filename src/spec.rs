@@ -0,0 +1,328 @@
+use anyhow::{anyhow, bail, Context as _};
+use cargo_metadata::Package;
+use url::Url;
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A package ID specification, in the syntax of `cargo help pkgid`.
+///
+/// - `foo`
+/// - `foo@0.1.0`, `foo:0.1.0`, `foo@0.1`
+/// - `path+file:///path/to/foo`, `path+file:///path/to/foo#0.1.0`, `file:///path/to/bar#foo@0.1.0`
+/// - `https://github.com/rust-lang/crates.io-index#foo@0.1.0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PackageIdSpec {
+    name: String,
+    version: Option<PartialVersion>,
+    url: Option<Url>,
+}
+
+impl PackageIdSpec {
+    /// Whether `package` matches `self`. Packages without `source` are compared with `file:` URLs
+    /// by their directories.
+    pub(crate) fn matches(&self, package: &Package) -> bool {
+        if package.name != self.name {
+            return false;
+        }
+
+        if let Some(version) = &self.version {
+            if !version.matches(&package.version.to_string()) {
+                return false;
+            }
+        }
+
+        if let Some(url) = &self.url {
+            let dir = package
+                .manifest_path
+                .parent()
+                .expect("`manifest_path` should end with \"Cargo.toml\"");
+            return match &package.source {
+                None => url.to_file_path().is_ok_and(|path| same_dirs(&path, dir)),
+                Some(source) => {
+                    let source = source.to_string();
+                    let source = source.split('#').next().unwrap_or_default();
+                    let source = source.splitn(2, '+').last().unwrap_or_default();
+                    let source = source.split('?').next().unwrap_or_default();
+                    source.trim_end_matches('/') == url.as_str().trim_end_matches('/')
+                }
+            };
+        }
+        true
+    }
+}
+
+impl FromStr for PackageIdSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> anyhow::Result<Self> {
+        let err = || format!("invalid package ID specification: `{}`", spec);
+
+        if !spec.contains("://") {
+            let (name, version) = split_name_and_version(spec).with_context(err)?;
+            validate_name(name).with_context(err)?;
+            return Ok(Self {
+                name: name.to_owned(),
+                version,
+                url: None,
+            });
+        }
+
+        let mut url = spec.parse::<Url>().with_context(err)?;
+        // `path+file://..`, `git+https://..`, `registry+https://..`, `sparse+https://..`
+        if let Some((_, scheme)) = url.scheme().split_once('+') {
+            let scheme = scheme.to_owned();
+            url = format!("{}{}", scheme, &url[url::Position::AfterScheme..])
+                .parse()
+                .with_context(err)?;
+        }
+
+        let fragment = url.fragment().map(ToOwned::to_owned);
+        url.set_fragment(None);
+        url.set_query(None);
+
+        let last_segment = || {
+            url.path_segments()
+                .and_then(|mut s| s.rfind(|s| !s.is_empty()))
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| anyhow!("the URL does not contain a package name"))
+        };
+
+        let (name, version) = match fragment.as_deref() {
+            None | Some("") => (last_segment().with_context(err)?, None),
+            Some(fragment) => {
+                let (name, version) = split_name_and_version(fragment).with_context(err)?;
+                if name.starts_with(|c: char| c.is_ascii_digit()) && version.is_none() {
+                    // `#0.1.0`
+                    let version = name.parse().with_context(err)?;
+                    (last_segment().with_context(err)?, Some(version))
+                } else {
+                    validate_name(name).with_context(err)?;
+                    (name.to_owned(), version)
+                }
+            }
+        };
+
+        Ok(Self {
+            name,
+            version,
+            url: Some(url),
+        })
+    }
+}
+
+impl fmt::Display for PackageIdSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(url) = &self.url {
+            write!(f, "{}#", url)?;
+        }
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, "@{}", version)?;
+        }
+        Ok(())
+    }
+}
+
+/// A version that may lack the minor and patch parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PartialVersion {
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    /// Pre-release and build metadata including the leading `-` or `+`, compared as is.
+    rest: Option<String>,
+}
+
+impl PartialVersion {
+    fn matches(&self, version: &str) -> bool {
+        let version = match version.parse::<Self>() {
+            Ok(version) => version,
+            Err(_) => return false,
+        };
+        self.major == version.major
+            && (self.minor.is_none() || self.minor == version.minor)
+            && (self.patch.is_none() || self.patch == version.patch)
+            && (self.rest.is_none() || self.rest == version.rest)
+    }
+}
+
+impl FromStr for PartialVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (nums, rest) = match s.find(['-', '+']) {
+            Some(i) => (&s[..i], Some(s[i..].to_owned())),
+            None => (s, None),
+        };
+        let mut nums = nums.split('.').map(|n| {
+            n.parse::<u64>()
+                .map_err(|_| anyhow!("invalid version: `{}`", s))
+        });
+        let major = nums.next().unwrap_or_else(|| unreachable!())?;
+        let minor = nums.next().transpose()?;
+        let patch = nums.next().transpose()?;
+        if nums.next().is_some() || (rest.is_some() && patch.is_none()) {
+            bail!("invalid version: `{}`", s);
+        }
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            rest,
+        })
+    }
+}
+
+impl fmt::Display for PartialVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.major)?;
+        if let Some(minor) = self.minor {
+            write!(f, ".{}", minor)?;
+        }
+        if let Some(patch) = self.patch {
+            write!(f, ".{}", patch)?;
+        }
+        if let Some(rest) = &self.rest {
+            write!(f, "{}", rest)?;
+        }
+        Ok(())
+    }
+}
+
+fn split_name_and_version(s: &str) -> anyhow::Result<(&str, Option<PartialVersion>)> {
+    match s.find(['@', ':']) {
+        Some(i) => Ok((&s[..i], Some(s[i + 1..].parse()?))),
+        None => Ok((s, None)),
+    }
+}
+
+fn validate_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("invalid package name: `{}`", name);
+    }
+    Ok(())
+}
+
+fn same_dirs(path: &Path, dir: &Path) -> bool {
+    let canonicalize = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_owned());
+    canonicalize(path) == canonicalize(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PackageIdSpec, PartialVersion};
+
+    use cargo_metadata::Package;
+    use url::Url;
+
+    use std::path::Path;
+
+    fn spec(s: &str) -> PackageIdSpec {
+        s.parse().unwrap()
+    }
+
+    fn version(s: &str) -> Option<PartialVersion> {
+        Some(s.parse().unwrap())
+    }
+
+    fn member(manifest_dir: &Path) -> Package {
+        serde_json::from_value(serde_json::json!({
+            "name": "foo",
+            "version": "0.1.0",
+            "id": "foo 0.1.0 (path+file:///foo)",
+            "source": null,
+            "description": null,
+            "dependencies": [],
+            "license": null,
+            "license_file": null,
+            "targets": [],
+            "features": {},
+            "manifest_path": manifest_dir.join("Cargo.toml"),
+            "readme": null,
+            "repository": null,
+            "links": null,
+            "publish": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn from_str_name_and_version() {
+        for s in &["foo@0.1.0", "foo:0.1.0"] {
+            let spec = spec(s);
+            assert_eq!(spec.name, "foo");
+            assert_eq!(spec.version, version("0.1.0"));
+            assert_eq!(spec.url, None);
+        }
+
+        assert_eq!(spec("foo").version, None);
+        assert!("foo@".parse::<PackageIdSpec>().is_err());
+        assert!("foo bar".parse::<PackageIdSpec>().is_err());
+    }
+
+    #[test]
+    fn from_str_url() {
+        let spec1 = spec("path+file:///path/to/foo#0.1.0");
+        assert_eq!(spec1.name, "foo");
+        assert_eq!(spec1.version, version("0.1.0"));
+        assert_eq!(spec1.url, Some("file:///path/to/foo".parse().unwrap()));
+
+        let spec2 = spec("path+file:///path/to/bar#foo@0.1");
+        assert_eq!(spec2.name, "foo");
+        assert_eq!(spec2.version, version("0.1"));
+        assert_eq!(spec2.url, Some("file:///path/to/bar".parse().unwrap()));
+
+        let spec3 = spec("https://github.com/rust-lang/crates.io-index#foo");
+        assert_eq!(spec3.name, "foo");
+        assert_eq!(spec3.version, None);
+    }
+
+    #[test]
+    fn partial_version_from_str() {
+        let v = "1.2.3-beta.1+build".parse::<PartialVersion>().unwrap();
+        assert_eq!((v.major, v.minor, v.patch), (1, Some(2), Some(3)));
+        assert_eq!(v.rest.as_deref(), Some("-beta.1+build"));
+        assert_eq!(v.to_string(), "1.2.3-beta.1+build");
+
+        let v = "1".parse::<PartialVersion>().unwrap();
+        assert_eq!((v.major, v.minor, v.patch, v.rest), (1, None, None, None));
+
+        for s in &["", "1.2-beta", "1+build", "1.2.3.4", "1.x"] {
+            assert!(s.parse::<PartialVersion>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn partial_version_matches() {
+        assert!(version("1").unwrap().matches("1.2.3"));
+        assert!(version("1.2").unwrap().matches("1.2.3"));
+        assert!(!version("1.3").unwrap().matches("1.2.3"));
+        assert!(version("1.2.3-beta").unwrap().matches("1.2.3-beta"));
+        assert!(!version("1.2.3-beta").unwrap().matches("1.2.3"));
+    }
+
+    #[test]
+    fn matches_member_without_source() {
+        let dir = tempfile::Builder::new()
+            .prefix("bikecase-spec-")
+            .tempdir()
+            .unwrap();
+        let package = member(dir.path());
+        let url = Url::from_directory_path(dir.path()).unwrap();
+        let other = Url::from_directory_path(dir.path().join("bar")).unwrap();
+
+        assert!(spec("foo").matches(&package));
+        assert!(spec("foo@0.1").matches(&package));
+        assert!(!spec("foo@0.2").matches(&package));
+        assert!(!spec("bar").matches(&package));
+        assert!(spec(&format!("path+{}#foo@0.1.0", url)).matches(&package));
+        assert!(!spec(&format!("path+{}#foo", other)).matches(&package));
+        assert!(!spec("https://github.com/rust-lang/crates.io-index#foo").matches(&package));
+    }
+}
@@ -0,0 +1,490 @@
+//! A subset of [Handlebars] for the files with the `.hbs` suffix in the template package.
+//!
+//! Supported are `{{var}}`, `{{#if cond}}`/`{{#unless cond}}` with `{{else}}`, `(eq a "b")` and
+//! `(ne a "b")` as conditions, comments, `~` for trimming whitespace, and `\{{` for a literal
+//! `{{`. Values are not HTML-escaped. Block tags and comments standing alone on their lines remove
+//! the lines, as Handlebars does.
+//!
+//! [Handlebars]: https://handlebarsjs.com/
+
+use anyhow::{bail, Context as _};
+
+use std::collections::BTreeMap;
+
+/// The suffix stripped from the output file names.
+pub(crate) const EXTENSION: &str = "hbs";
+
+/// Renders `template` with `vars`.
+///
+/// Undefined variables are errors in `{{var}}`, and are falsy in conditions. So are empty values
+/// and `false`.
+pub(crate) fn render(template: &str, vars: &BTreeMap<String, String>) -> anyhow::Result<String> {
+    let (texts, tags) = tokenize(template)?;
+    let mut tokens = texts
+        .into_iter()
+        .zip(tags.into_iter().map(Some).chain(Some(None)));
+    let (nodes, _) = parse(&mut tokens, None)?;
+    let mut output = String::new();
+    eval(&nodes, vars, &mut output)?;
+    Ok(output)
+}
+
+#[derive(Debug)]
+struct Tag {
+    kind: TagKind,
+    line: usize,
+    trim_left: bool,
+    trim_right: bool,
+}
+
+#[derive(Debug)]
+enum TagKind {
+    Var(String),
+    Open(Block, Cond),
+    Else,
+    Close(Block),
+    Comment,
+}
+
+impl TagKind {
+    fn is_standalone_candidate(&self) -> bool {
+        !matches!(self, Self::Var(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Block {
+    If,
+    Unless,
+}
+
+impl Block {
+    fn name(self) -> &'static str {
+        match self {
+            Self::If => "if",
+            Self::Unless => "unless",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Cond {
+    Var(String),
+    Eq(Operand, Operand),
+    Ne(Operand, Operand),
+}
+
+#[derive(Debug)]
+enum Operand {
+    Var(String),
+    Literal(String),
+}
+
+impl Operand {
+    fn value<'a>(&'a self, vars: &'a BTreeMap<String, String>) -> Option<&'a str> {
+        match self {
+            Self::Var(name) => vars.get(name).map(|s| &**s),
+            Self::Literal(value) => Some(value),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var(String, usize),
+    Block {
+        negate: bool,
+        cond: Cond,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// Splits `template` into texts and tags. `texts.len() == tags.len() + 1`, and each tag sits
+/// between `texts[i]` and `texts[i + 1]`.
+fn tokenize(template: &str) -> anyhow::Result<(Vec<String>, Vec<Tag>)> {
+    let mut texts = vec![String::new()];
+    let mut tags = vec![];
+    let mut rest = template;
+    let mut line = 1;
+
+    while let Some(start) = rest.find("{{") {
+        let (text, after) = rest.split_at(start);
+        line += text.matches('\n').count();
+        let current = texts.last_mut().expect("should not be empty");
+        if let Some(text) = text.strip_suffix('\\') {
+            current.push_str(text);
+            current.push_str("{{");
+            rest = &after[2..];
+            continue;
+        }
+        current.push_str(text);
+
+        let (after, close) = match after.strip_prefix("{{{") {
+            Some(after) => (after, "}}}"),
+            None => (&after[2..], "}}"),
+        };
+        let end = if after.trim_start_matches('~').starts_with("!--") {
+            // `}}` may appear in long comments.
+            [("--}}", 2), ("--~}}", 3)]
+                .iter()
+                .flat_map(|&(end, offset)| after.find(end).map(|i| i + offset))
+                .min()
+        } else {
+            after.find(close)
+        }
+        .with_context(|| format!("line {}: unclosed `{{{{`", line))?;
+        let inner = &after[..end];
+        rest = &after[end + close.len()..];
+
+        let (inner, trim_left) = match inner.strip_prefix('~') {
+            Some(inner) => (inner, true),
+            None => (inner, false),
+        };
+        let (inner, trim_right) = match inner.strip_suffix('~') {
+            Some(inner) => (inner, true),
+            None => (inner, false),
+        };
+        tags.push(Tag {
+            kind: parse_tag(inner.trim(), line)?,
+            line,
+            trim_left,
+            trim_right,
+        });
+        texts.push(String::new());
+        line += inner.matches('\n').count();
+    }
+    texts
+        .last_mut()
+        .expect("should not be empty")
+        .push_str(rest);
+
+    // Decide on the original texts, since a line may contain nothing but consecutive tags.
+    let standalone = tags
+        .iter()
+        .enumerate()
+        .map(|(i, tag)| {
+            let (before, after) = (&texts[i], &texts[i + 1]);
+            let starts_line = match before.rfind('\n') {
+                Some(j) => is_blank(&before[j + 1..]),
+                None => i == 0 && is_blank(before),
+            };
+            let ends_line = match after.find('\n') {
+                Some(j) => is_blank(&after[..j]),
+                None => i + 1 == tags.len() && is_blank(after),
+            };
+            tag.kind.is_standalone_candidate() && starts_line && ends_line
+        })
+        .collect::<Vec<_>>();
+
+    for (i, tag) in tags.iter().enumerate() {
+        if tag.trim_left {
+            let len = texts[i].trim_end().len();
+            texts[i].truncate(len);
+        } else if standalone[i] {
+            let len = texts[i].trim_end_matches(&[' ', '\t'][..]).len();
+            texts[i].truncate(len);
+        }
+        if tag.trim_right {
+            texts[i + 1] = texts[i + 1].trim_start().to_owned();
+        } else if standalone[i] {
+            let after = &texts[i + 1];
+            texts[i + 1] = match after.find('\n') {
+                Some(j) => after[j + 1..].to_owned(),
+                None => String::new(),
+            };
+        }
+    }
+    return Ok((texts, tags));
+
+    fn is_blank(s: &str) -> bool {
+        s.chars().all(|c| c == ' ' || c == '\t' || c == '\r')
+    }
+}
+
+fn parse_tag(inner: &str, line: usize) -> anyhow::Result<TagKind> {
+    if inner.starts_with('!') {
+        return Ok(TagKind::Comment);
+    }
+    if inner == "else" {
+        return Ok(TagKind::Else);
+    }
+    if let Some(rest) = inner.strip_prefix('#') {
+        let (name, cond) = rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len()));
+        let block = parse_block(name, line)?;
+        return Ok(TagKind::Open(block, parse_cond(cond.trim(), line)?));
+    }
+    if let Some(name) = inner.strip_prefix('/') {
+        return Ok(TagKind::Close(parse_block(name.trim(), line)?));
+    }
+    if is_identifier(inner) {
+        return Ok(TagKind::Var(inner.to_owned()));
+    }
+    bail!("line {}: unsupported expression: `{{{{{}}}}}`", line, inner);
+
+    fn parse_block(name: &str, line: usize) -> anyhow::Result<Block> {
+        match name {
+            "if" => Ok(Block::If),
+            "unless" => Ok(Block::Unless),
+            _ => bail!(
+                "line {}: unsupported block `{}`. Only `if` and `unless` are supported",
+                line,
+                name,
+            ),
+        }
+    }
+}
+
+fn parse_cond(cond: &str, line: usize) -> anyhow::Result<Cond> {
+    if is_identifier(cond) {
+        return Ok(Cond::Var(cond.to_owned()));
+    }
+    let inner = cond
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .with_context(|| format!("line {}: invalid condition: `{}`", line, cond))?;
+
+    let mut words = vec![];
+    let mut rest = inner.trim_start();
+    while !rest.is_empty() {
+        let (word, after) = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted
+                .find('"')
+                .with_context(|| format!("line {}: unclosed string: `{}`", line, cond))?;
+            (
+                Operand::Literal(quoted[..end].to_owned()),
+                &quoted[end + 1..],
+            )
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (Operand::Var(rest[..end].to_owned()), &rest[end..])
+        };
+        words.push(word);
+        rest = after.trim_start();
+    }
+
+    let mut words = words.into_iter();
+    match (words.next(), words.next(), words.next(), words.next()) {
+        (Some(Operand::Var(helper)), Some(lhs), Some(rhs), None) if helper == "eq" => {
+            Ok(Cond::Eq(lhs, rhs))
+        }
+        (Some(Operand::Var(helper)), Some(lhs), Some(rhs), None) if helper == "ne" => {
+            Ok(Cond::Ne(lhs, rhs))
+        }
+        _ => bail!(
+            "line {}: unsupported condition: `{}`. Only `(eq a b)` and `(ne a b)` are supported",
+            line,
+            cond,
+        ),
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Parses until the end of `open`, returning the nodes before and after `{{else}}`.
+fn parse(
+    tokens: &mut impl Iterator<Item = (String, Option<Tag>)>,
+    open: Option<(Block, usize)>,
+) -> anyhow::Result<(Vec<Node>, Vec<Node>)> {
+    let mut nodes = vec![];
+    let mut otherwise = None::<Vec<Node>>;
+
+    while let Some((text, tag)) = tokens.next() {
+        let current = otherwise.as_mut().unwrap_or(&mut nodes);
+        if !text.is_empty() {
+            current.push(Node::Text(text));
+        }
+        let tag = match tag {
+            Some(tag) => tag,
+            None => break,
+        };
+        match tag.kind {
+            TagKind::Var(name) => current.push(Node::Var(name, tag.line)),
+            TagKind::Comment => {}
+            TagKind::Open(block, cond) => {
+                let (then, otherwise) = parse(tokens, Some((block, tag.line)))?;
+                current.push(Node::Block {
+                    negate: block == Block::Unless,
+                    cond,
+                    then,
+                    otherwise,
+                });
+            }
+            TagKind::Else => match open {
+                Some(_) if otherwise.is_none() => otherwise = Some(vec![]),
+                _ => bail!("line {}: unexpected `{{{{else}}}}`", tag.line),
+            },
+            TagKind::Close(block) => match open {
+                Some((open, _)) if open == block => {
+                    return Ok((nodes, otherwise.unwrap_or_default()));
+                }
+                _ => bail!("line {}: unexpected `{{{{/{}}}}}`", tag.line, block.name()),
+            },
+        }
+    }
+
+    if let Some((block, line)) = open {
+        bail!("line {}: unclosed `{{{{#{}}}}}`", line, block.name());
+    }
+    Ok((nodes, vec![]))
+}
+
+fn eval(
+    nodes: &[Node],
+    vars: &BTreeMap<String, String>,
+    output: &mut String,
+) -> anyhow::Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Var(name, line) => {
+                let value = vars.get(name).with_context(|| {
+                    format!(
+                        "line {}: `{}` is not defined. Pass it with `--var {}=<VALUE>`",
+                        line, name, name,
+                    )
+                })?;
+                output.push_str(value);
+            }
+            Node::Block {
+                negate,
+                cond,
+                then,
+                otherwise,
+            } => {
+                let truthy = match cond {
+                    Cond::Var(name) => vars
+                        .get(name)
+                        .is_some_and(|v| !v.is_empty() && v != "false"),
+                    Cond::Eq(lhs, rhs) => lhs.value(vars) == rhs.value(vars),
+                    Cond::Ne(lhs, rhs) => lhs.value(vars) != rhs.value(vars),
+                };
+                eval(
+                    if truthy != *negate { then } else { otherwise },
+                    vars,
+                    output,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses `KEY=VALUE` for `--var`. `KEY` alone means `KEY=true`.
+pub(crate) fn parse_var(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s.split_once('=').unwrap_or((s, "true"));
+    if !is_identifier(key) {
+        return Err(format!("invalid variable name: {:?}", key));
+    }
+    Ok((key.to_owned(), value.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    fn render(template: &str, vars: &[(&str, &str)]) -> anyhow::Result<String> {
+        let vars = vars
+            .iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect::<BTreeMap<_, _>>();
+        super::render(template, &vars)
+    }
+
+    fn render_err(template: &str) -> String {
+        render(template, &[]).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn vars() -> anyhow::Result<()> {
+        assert_eq!(render("{{name}}\n", &[("name", "a<b")])?, "a<b\n");
+        assert_eq!(render("{{{ name }}}", &[("name", "a")])?, "a");
+        Ok(())
+    }
+
+    #[test]
+    fn standalone() -> anyhow::Result<()> {
+        let template = "a\n  {{#if x}}  \nb\n{{else}}\nc\n{{/if}}\n{{! comment }}\nd\n";
+        assert_eq!(render(template, &[("x", "true")])?, "a\nb\nd\n");
+        assert_eq!(render(template, &[])?, "a\nc\nd\n");
+        assert_eq!(render("{{#if x}}{{/if}}\na", &[])?, "\na");
+        assert_eq!(render("a {{#if x}}b{{/if}}\n", &[])?, "a \n");
+        assert_eq!(render("{{x}}\n", &[("x", "")])?, "\n");
+        Ok(())
+    }
+
+    #[test]
+    fn trim() -> anyhow::Result<()> {
+        assert_eq!(render("a \n {{~x~}} \n b", &[("x", "-")])?, "a-b");
+        assert_eq!(render("a {{~x}} b", &[("x", "-")])?, "a- b");
+        assert_eq!(
+            render("[\n  {{~#if x~}}\n  y\n  {{~/if~}}\n]", &[("x", "1")])?,
+            "[y]",
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn escape() -> anyhow::Result<()> {
+        assert_eq!(render("\\{{x}} {{x}}", &[("x", "a")])?, "{{x}} a");
+        Ok(())
+    }
+
+    #[test]
+    fn nested_blocks() -> anyhow::Result<()> {
+        let template = "{{#if a}}{{#unless b}}1{{else}}2{{/unless}}{{else}}3{{/if}}";
+        assert_eq!(render(template, &[("a", "1")])?, "1");
+        assert_eq!(render(template, &[("a", "1"), ("b", "1")])?, "2");
+        assert_eq!(render(template, &[("a", "false"), ("b", "1")])?, "3");
+        Ok(())
+    }
+
+    #[test]
+    fn conditions() -> anyhow::Result<()> {
+        let template = r#"{{#if (eq kind "bin")}}b{{/if}}{{#if (ne kind other)}}n{{/if}}"#;
+        assert_eq!(render(template, &[("kind", "bin"), ("other", "bin")])?, "b");
+        assert_eq!(render(template, &[("kind", "lib")])?, "n");
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(render_err("a\n{{#if x}}\nb"), "line 2: unclosed `{{#if}}`");
+        assert_eq!(
+            render_err("{{#if x}}\n{{/unless}}"),
+            "line 2: unexpected `{{/unless}}`",
+        );
+        assert_eq!(
+            render_err("{{#if x}}{{else}}{{else}}{{/if}}"),
+            "line 1: unexpected `{{else}}`",
+        );
+        assert_eq!(render_err("\n\n{{else}}"), "line 3: unexpected `{{else}}`");
+        assert_eq!(render_err("a\n\n{{x"), "line 3: unclosed `{{`");
+        assert_eq!(
+            render_err("{{#each xs}}{{/each}}"),
+            "line 1: unsupported block `each`. Only `if` and `unless` are supported",
+        );
+        assert_eq!(
+            render_err("{{!-- {{x}}\n--}}\n{{x}}"),
+            "line 3: `x` is not defined. Pass it with `--var x=<VALUE>`",
+        );
+    }
+
+    #[test]
+    fn parse_var() {
+        assert_eq!(
+            super::parse_var("a=b=c"),
+            Ok(("a".to_owned(), "b=c".to_owned())),
+        );
+        assert_eq!(
+            super::parse_var("a"),
+            Ok(("a".to_owned(), "true".to_owned())),
+        );
+        assert!(super::parse_var("a b=c").is_err());
+    }
+}
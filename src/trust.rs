@@ -0,0 +1,159 @@
+//! Approvals of scripts fetched from URLs and gists.
+
+use crate::ScriptSource;
+
+use anyhow::{bail, Context as _};
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item};
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// `<data local dir>/bikecase/trust.json`, which records the remote scripts approved to run by
+/// their sources and the SHA-256 of their contents.
+#[derive(Debug)]
+pub(crate) struct TrustStore {
+    path: PathBuf,
+    entries: Vec<TrustEntry>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct TrustEntry {
+    /// A URL or `gist:<ID>`.
+    pub(crate) source: String,
+    pub(crate) sha256: String,
+    /// RFC 3339.
+    pub(crate) trusted_at: String,
+}
+
+impl TrustStore {
+    pub(crate) fn load(data_local_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let path = data_local_dir
+            .with_context(|| "could not find the local data directory")?
+            .join("bikecase")
+            .join("trust.json");
+        let entries = if path.exists() {
+            let json = crate::fs::read(&path)?;
+            serde_json::from_str(&json)
+                .with_context(|| format!("failed to parse {}", path.display()))?
+        } else {
+            vec![]
+        };
+        Ok(Self { path, entries })
+    }
+
+    pub(crate) fn entries(&self) -> &[TrustEntry] {
+        &self.entries
+    }
+
+    fn is_trusted(&self, source: &str, sha256: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.source == source && e.sha256 == sha256)
+    }
+
+    fn trust(&mut self, source: &str, sha256: &str) {
+        self.entries.push(TrustEntry {
+            source: source.to_owned(),
+            sha256: sha256.to_owned(),
+            trusted_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        });
+    }
+
+    /// Removes the entries for `source`. Returns the number of them.
+    pub(crate) fn revoke(&mut self, source: &str) -> usize {
+        let len = self.entries.len();
+        self.entries.retain(|e| e.source != source);
+        len - self.entries.len()
+    }
+
+    pub(crate) fn save(&self, dry_run: bool) -> anyhow::Result<()> {
+        let dir = self.path.parent().expect("should not be empty");
+        crate::fs::create_dir_all(dir, dry_run)?;
+        let json = serde_json::to_string_pretty(&self.entries).expect("should not fail") + "\n";
+        crate::fs::write(&self.path, json, dry_run)
+    }
+}
+
+/// Makes sure that `code` from `source` is approved to run, if `source` is remote.
+///
+/// Unrecognized content is approved with `trust`, or by answering the prompt, which shows the
+/// dependencies of the script. `no_input` makes it an error instead.
+pub(crate) fn check(
+    source: &ScriptSource,
+    code: &str,
+    data_local_dir: Option<&Path>,
+    trust: bool,
+    no_input: bool,
+    read_tty: &mut impl FnMut(&str) -> io::Result<String>,
+) -> anyhow::Result<()> {
+    let source = match source.remote_key() {
+        Some(source) => source,
+        None => return Ok(()),
+    };
+    let sha256 = crate::cache::sha256(code);
+
+    let mut store = TrustStore::load(data_local_dir)?;
+    if store.is_trusted(&source, &sha256) {
+        return Ok(());
+    }
+
+    if !trust {
+        let not_trusted = || {
+            format!(
+                "{} has not been trusted. Review it and run with `--trust`",
+                source,
+            )
+        };
+        if no_input {
+            bail!("{}", not_trusted());
+        }
+        info_event!("{} has not been run before (SHA-256: {})", source, sha256);
+        match dependencies(code) {
+            Ok(deps) if deps.is_empty() => info_event!("It has no dependencies"),
+            Ok(deps) => {
+                info_event!("It depends on:");
+                for dep in deps {
+                    info_event!("  {}", dep);
+                }
+            }
+            Err(err) => info_event!("Could not read the dependencies: {}", err),
+        }
+        let answer = read_tty("Run it? [y/N] ").with_context(not_trusted)?;
+        if !["y", "yes"].contains(&&*answer.trim().to_lowercase()) {
+            bail!("{}", not_trusted());
+        }
+    }
+
+    store.trust(&source, &sha256);
+    store.save(false)?;
+    info_event!("Trusted {}", source);
+    Ok(())
+}
+
+/// `<name> <requirement>` of each dependency in the manifest of `code`.
+fn dependencies(code: &str) -> anyhow::Result<Vec<String>> {
+    let manifest =
+        crate::rust::extract_cargo_lang_code(code, || "could not find the `cargo` code block")?;
+    let doc = manifest
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the manifest")?;
+    let deps = match doc.get("dependencies").and_then(Item::as_table_like) {
+        Some(deps) => deps,
+        None => return Ok(vec![]),
+    };
+    Ok(deps
+        .iter()
+        .map(|(name, dep)| {
+            let req = dep
+                .as_str()
+                .or_else(|| dep.get("version").and_then(Item::as_str))
+                .or_else(|| dep.get("git").and_then(Item::as_str))
+                .or_else(|| dep.get("path").and_then(Item::as_str))
+                .unwrap_or("*");
+            format!("{} {}", name, req)
+        })
+        .collect())
+}
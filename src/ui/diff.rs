@@ -0,0 +1,125 @@
+use crate::rust::TextStyle;
+
+use log::info;
+use terminal_size::Width;
+use unicode_segmentation::UnicodeSegmentation as _;
+
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::io;
+use std::iter;
+use std::sync::RwLock;
+
+static DIFF_MODE: RwLock<(crate::DiffMode, usize)> = RwLock::new((crate::DiffMode::Auto, 200));
+
+/// Sets how [`info_diff`] prints diffs. `threshold` is the number of changed lines above which
+/// [`crate::DiffMode::Auto`] prints a diffstat.
+pub(crate) fn set_diff_mode(mode: crate::DiffMode, threshold: usize) {
+    *DIFF_MODE.write().unwrap_or_else(|e| e.into_inner()) = (mode, threshold);
+}
+
+/// Prints the diff between `orig` and `edit`.
+///
+/// The lines are compared ignoring their line endings and the BOM. If either differs, a separate
+/// line says so instead of marking every line as changed.
+pub(crate) fn info_diff(orig: &str, edit: &str, name: impl Display, str_width: fn(&str) -> usize) {
+    let (orig_style, orig) = TextStyle::normalize(orig);
+    let (edit_style, edit) = TextStyle::normalize(edit);
+    let diff = diff::lines(&orig, &edit);
+    let (mode, threshold) = *DIFF_MODE.read().unwrap_or_else(|e| e.into_inner());
+
+    if !matches!(mode, crate::DiffMode::Never)
+        && orig_style != edit_style
+        && !orig.is_empty()
+        && !edit.is_empty()
+    {
+        info!("{}: {} → {}", name, orig_style, edit_style);
+    }
+
+    let num_changed =
+        |pred: fn(&diff::Result<&str>) -> bool| diff.iter().filter(|d| pred(d)).count();
+    let added = num_changed(|d| matches!(d, diff::Result::Right(_)));
+    let removed = num_changed(|d| matches!(d, diff::Result::Left(_)));
+    let stat = match mode {
+        crate::DiffMode::Auto => added + removed > threshold,
+        crate::DiffMode::Always => false,
+        crate::DiffMode::Never => return,
+        crate::DiffMode::Stat => true,
+    };
+    if stat {
+        info!("{} | +{} -{}", name, added, removed);
+        return;
+    }
+
+    let g = super::theme().glyphs;
+
+    // `[INFO] │` + the prefix
+    let max_content_width = terminal_width().map(|w| w.saturating_sub(9).max(1));
+
+    let name = name.to_string();
+    let name = truncate(&name, max_content_width, g.ellipsis, str_width);
+
+    let lines = diff
+        .into_iter()
+        .map(|diff| {
+            let (pref, line) = match diff {
+                diff::Result::Left(l) => ("-", l),
+                diff::Result::Both(l, _) => (" ", l),
+                diff::Result::Right(l) => ("+", l),
+            };
+            (
+                pref,
+                truncate(line, max_content_width, g.ellipsis, str_width),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let max_width = iter::once(&*name)
+        .chain(lines.iter().map(|(_, l)| &**l))
+        .map(str_width)
+        .max()
+        .unwrap_or(0);
+
+    let horz_bar = g
+        .horizontal
+        .repeat((max_width / str_width(g.horizontal).max(1)).saturating_sub(str_width(g.top_left)));
+
+    info!("{}{}", g.top_left, horz_bar);
+    info!("{}{}", g.vertical, name);
+    info!("{}{}", g.left_tee, horz_bar);
+    for (pref, line) in lines {
+        // `diff` is for coloring the line.
+        info!(diff = pref; "{}{}{}", g.vertical, pref, line);
+    }
+    info!("{}{}", g.bottom_left, horz_bar);
+}
+
+fn terminal_width() -> Option<usize> {
+    if atty::is(atty::Stream::Stderr) {
+        terminal_size::terminal_size_of(io::stderr()).map(|(Width(w), _)| w.into())
+    } else {
+        None
+    }
+}
+
+fn truncate<'a>(
+    s: &'a str,
+    max_width: Option<usize>,
+    ellipsis: &str,
+    str_width: fn(&str) -> usize,
+) -> Cow<'a, str> {
+    match max_width {
+        Some(max_width) if str_width(s) > max_width => {
+            let (mut acc, mut acc_width) = ("".to_owned(), 0);
+            for g in s.graphemes(true) {
+                acc_width += str_width(g);
+                if acc_width + str_width(ellipsis) > max_width {
+                    break;
+                }
+                acc += g;
+            }
+            (acc + ellipsis).into()
+        }
+        _ => s.into(),
+    }
+}
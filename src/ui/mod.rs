@@ -0,0 +1,37 @@
+//! What is drawn on the terminal: diffs, tables, and spinners, in the colors and glyphs of the
+//! [`Theme`](theme::Theme).
+
+mod diff;
+mod spinner;
+mod table;
+mod theme;
+
+pub(crate) use self::diff::{info_diff, set_diff_mode};
+pub(crate) use self::spinner::Spinner;
+pub(crate) use self::table::{sort_listing, Cell, PrintOptions, Table};
+pub(crate) use self::theme::{configure, set_ascii, theme, UiConfig};
+
+use unicode_segmentation::UnicodeSegmentation as _;
+use unicode_width::UnicodeWidthChar as _;
+
+/// Returns the display width of `s`, measuring each extended grapheme cluster as a whole.
+///
+/// Emoji sequences joined with ZWJs, ones with VS16, and flags occupy 2 columns, unlike what
+/// summing up the widths of the `char`s gives.
+pub fn str_width(s: &str) -> usize {
+    s.graphemes(true)
+        .map(|g| {
+            let mut chars = g.chars();
+            let first = chars.next().expect("graphemes should not be empty");
+            let is_emoji_sequence = chars.next().is_some()
+                && g.chars().any(|c| {
+                    c == '\u{200d}' || c == '\u{fe0f}' || ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+                });
+            if is_emoji_sequence {
+                2
+            } else {
+                first.width().unwrap_or(0)
+            }
+        })
+        .sum()
+}
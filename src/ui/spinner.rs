@@ -0,0 +1,53 @@
+use std::io::{self, Write as _};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shows `message` with a spinner on stderr until dropped.
+///
+/// Nothing is shown if stderr is not a TTY. Log records written meanwhile would be mixed into the
+/// line, so this is for quiet operations such as waiting for a captured process.
+pub(crate) struct Spinner {
+    running: Option<(mpsc::Sender<()>, JoinHandle<()>)>,
+}
+
+impl Spinner {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        if !atty::is(atty::Stream::Stderr) {
+            return Self { running: None };
+        }
+
+        let message = message.into();
+        let frames = super::theme().glyphs.spinner;
+        let (tx, rx) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            let mut width = 0;
+            for frame in frames.iter().cycle() {
+                let line = format!("{} {}", frame, message);
+                width = width.max(super::str_width(&line));
+                let mut stderr = io::stderr().lock();
+                let _ = write!(stderr, "\r{}", line);
+                let _ = stderr.flush();
+                drop(stderr);
+                if rx.recv_timeout(INTERVAL) != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+            }
+            let _ = write!(io::stderr(), "\r{}\r", " ".repeat(width));
+        });
+        Self {
+            running: Some((tx, handle)),
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        if let Some((tx, handle)) = self.running.take() {
+            drop(tx);
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use termcolor::{Ansi, Color, ColorSpec, NoColor, WriteColor};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization as _;
+
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) struct Table {
+    header: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub(crate) fn new(header: &[&'static str]) -> Self {
+        Self {
+            header: header.to_owned(),
+            rows: vec![],
+        }
+    }
+
+    pub(crate) fn push_row(&mut self, row: Vec<Cell>) {
+        debug_assert_eq!(row.len(), self.header.len());
+        self.rows.push(row);
+    }
+
+    pub(crate) fn print(&self, wtr: impl Write, opts: PrintOptions) -> io::Result<()> {
+        if opts.color {
+            self.print_with_color(Ansi::new(wtr), opts)
+        } else {
+            self.print_with_color(NoColor::new(wtr), opts)
+        }
+    }
+
+    fn print_with_color(&self, mut wtr: impl WriteColor, opts: PrintOptions) -> io::Result<()> {
+        let PrintOptions {
+            header,
+            tsv,
+            str_width,
+            ..
+        } = opts;
+
+        let header = if header {
+            Some(
+                self.header
+                    .iter()
+                    .map(|&h| Cell::new(h).bold())
+                    .collect::<Vec<_>>(),
+            )
+        } else {
+            None
+        };
+
+        let widths = (0..self.header.len())
+            .map(|i| {
+                header
+                    .iter()
+                    .chain(&self.rows)
+                    .map(|row| str_width(&row[i].text))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        for row in header.iter().chain(&self.rows) {
+            for (i, cell) in row.iter().enumerate() {
+                let is_last = i + 1 == row.len();
+
+                if tsv {
+                    wtr.set_color(&cell.spec)?;
+                    wtr.write_all(cell.text.replace(&['\t', '\n'][..], " ").as_ref())?;
+                    wtr.reset()?;
+                    if !is_last {
+                        wtr.write_all(b"\t")?;
+                    }
+                } else {
+                    wtr.set_color(&cell.spec)?;
+                    wtr.write_all(cell.text.as_ref())?;
+                    wtr.reset()?;
+                    if !is_last {
+                        let padding = widths[i] - str_width(&cell.text) + 2;
+                        write!(wtr, "{}", " ".repeat(padding))?;
+                    }
+                }
+            }
+            writeln!(wtr)?;
+        }
+        wtr.flush()
+    }
+}
+
+pub(crate) struct Cell {
+    text: String,
+    spec: ColorSpec,
+}
+
+impl Cell {
+    pub(crate) fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            spec: ColorSpec::new(),
+        }
+    }
+
+    pub(crate) fn fg(mut self, color: Color) -> Self {
+        self.spec.set_fg(Some(color));
+        self
+    }
+
+    pub(crate) fn bold(mut self) -> Self {
+        self.spec.set_bold(true);
+        self
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct PrintOptions {
+    pub(crate) color: bool,
+    pub(crate) header: bool,
+    pub(crate) tsv: bool,
+    pub(crate) str_width: fn(&str) -> usize,
+}
+
+impl PrintOptions {
+    /// Colors and aligns the table only when stdout is a TTY.
+    ///
+    /// On a console without VT processing, the table is not colored even with `--color always`,
+    /// since the colors are written as ANSI escape sequences.
+    pub(crate) fn for_stdout(
+        color: crate::ColorChoice,
+        no_header: bool,
+        str_width: fn(&str) -> usize,
+    ) -> Self {
+        let is_tty = atty::is(atty::Stream::Stdout);
+        Self {
+            color: match color {
+                crate::ColorChoice::Auto => is_tty && console_supports_ansi(),
+                crate::ColorChoice::Always => !is_tty || console_supports_ansi(),
+                crate::ColorChoice::Never => false,
+            },
+            header: !no_header,
+            tsv: !is_tty,
+            str_width,
+        }
+    }
+}
+
+/// Tries to enable the VT processing of the console for stdout. Legacy consoles lack it.
+#[cfg(windows)]
+fn console_supports_ansi() -> bool {
+    winapi_util::console::Console::stdout()
+        .and_then(|mut console| console.set_virtual_terminal_processing(true))
+        .is_ok()
+}
+
+#[cfg(not(windows))]
+fn console_supports_ansi() -> bool {
+    true
+}
+
+/// Compares `a` and `b` by their base letters (compatibility-decomposed, without diacritics, and
+/// case-folded), then with diacritics, then by the code points so that the order is total and does
+/// not depend on the platform or the locale.
+///
+/// `é` sorts with `e` and `ﬁ` with `fi`, but this is not the full Unicode Collation Algorithm.
+fn collate(a: &str, b: &str) -> Ordering {
+    let fold = |s: &str, strip_marks: bool| {
+        s.nfkd()
+            .filter(|&c| !(strip_marks && is_combining_mark(c)))
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    fold(a, true)
+        .cmp(&fold(b, true))
+        .then_with(|| fold(a, false).cmp(&fold(b, false)))
+        .then_with(|| a.cmp(b))
+}
+
+/// Sorts the rows of a listing of packages by `key`.
+///
+/// `modified` (the newest file), `size` (the sum of the files), and `last-run` are in descending
+/// order. The rows without the value (e.g. never run) come last, and ties are ordered by name.
+pub(crate) fn sort_listing<T>(
+    items: impl IntoIterator<Item = T>,
+    key: crate::SortKey,
+    name: impl Fn(&T) -> &str,
+    dir: impl Fn(&T) -> &Path,
+    last_run: impl Fn(&T) -> Option<SystemTime>,
+) -> Vec<T> {
+    let since_epoch = |t: SystemTime| t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+
+    let mut items = items
+        .into_iter()
+        .map(|item| {
+            let value = match key {
+                crate::SortKey::Name => None,
+                crate::SortKey::Modified => dir_stats(dir(&item)).0.map(since_epoch),
+                crate::SortKey::Size => Some(dir_stats(dir(&item)).1.into()),
+                crate::SortKey::LastRun => last_run(&item).map(since_epoch),
+            };
+            (value, item)
+        })
+        .collect::<Vec<_>>();
+    items.sort_by(|(v1, item1), (v2, item2)| {
+        v2.cmp(v1).then_with(|| collate(name(item1), name(item2)))
+    });
+    return items.into_iter().map(|(_, item)| item).collect();
+
+    /// The newest modification time and the total size of the files in `dir`.
+    fn dir_stats(dir: &Path) -> (Option<SystemTime>, u64) {
+        crate::fs::walk(dir)
+            .flatten()
+            .flat_map(|entry| entry.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .fold((None, 0), |(newest, size), metadata| {
+                (newest.max(metadata.modified().ok()), size + metadata.len())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn collate() {
+        let mut names = vec![
+            "b",
+            "Émile",
+            "a",
+            "émile",
+            "emma",
+            "Zeta",
+            "ﬁle",
+            "fig",
+            "Ångström",
+        ];
+        names.sort_by(|a, b| super::collate(a, b));
+        assert_eq!(
+            names,
+            [
+                "a",
+                "Ångström",
+                "b",
+                "Émile",
+                "émile",
+                "emma",
+                "fig",
+                "ﬁle",
+                "Zeta"
+            ],
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::Context as _;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use termcolor::Color;
+
+use std::env;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::RwLock;
+
+static THEME: Lazy<RwLock<Theme>> = Lazy::new(|| RwLock::new(Theme::new(!supports_unicode())));
+static ASCII_FLAG: AtomicBool = AtomicBool::new(false);
+
+/// `[ui]` in the config.
+#[derive(Deserialize, Serialize, Default, Clone, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct UiConfig {
+    /// Draw with ASCII characters only. Defaults to whether the locale is not UTF-8. `--ascii`
+    /// enables this regardless.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) ascii: Option<bool>,
+    #[serde(default)]
+    pub(crate) colors: UiConfigColors,
+}
+
+impl UiConfig {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `[ui.colors]`. Each value is a color name (`red`, `cyan`, ...), an ANSI 256-color number, or
+/// `r,g,b`.
+#[derive(Deserialize, Serialize, Default, Clone, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) struct UiConfigColors {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) warn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) info: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) debug: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) trace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) added: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) removed: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) ok: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) accent: Option<String>,
+}
+
+/// The colors and glyphs for the terminal.
+///
+/// The colors are for the levels of the log records, the lines of diffs, and table cells that
+/// report success (`ok`) or stand out (`accent`).
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Theme {
+    pub(crate) glyphs: &'static Glyphs,
+    pub(crate) error: Color,
+    pub(crate) warn: Color,
+    pub(crate) info: Color,
+    pub(crate) debug: Color,
+    pub(crate) trace: Color,
+    pub(crate) added: Color,
+    pub(crate) removed: Color,
+    pub(crate) ok: Color,
+    pub(crate) accent: Color,
+}
+
+impl Theme {
+    fn new(ascii: bool) -> Self {
+        Self {
+            glyphs: if ascii { &ASCII } else { &UNICODE },
+            error: Color::Red,
+            warn: Color::Yellow,
+            info: Color::Cyan,
+            debug: Color::Green,
+            trace: Color::White,
+            added: Color::Green,
+            removed: Color::Red,
+            ok: Color::Green,
+            accent: Color::Yellow,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Glyphs {
+    pub(crate) horizontal: &'static str,
+    pub(crate) vertical: &'static str,
+    pub(crate) top_left: &'static str,
+    pub(crate) left_tee: &'static str,
+    pub(crate) bottom_left: &'static str,
+    pub(crate) ellipsis: &'static str,
+    pub(crate) spinner: &'static [&'static str],
+}
+
+static UNICODE: Glyphs = Glyphs {
+    horizontal: "─",
+    vertical: "│",
+    top_left: "┌",
+    left_tee: "├",
+    bottom_left: "└",
+    ellipsis: "…",
+    spinner: &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+};
+
+static ASCII: Glyphs = Glyphs {
+    horizontal: "-",
+    vertical: "|",
+    top_left: "+",
+    left_tee: "+",
+    bottom_left: "+",
+    ellipsis: "...",
+    spinner: &["|", "/", "-", "\\"],
+};
+
+pub(crate) fn theme() -> Theme {
+    *THEME.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Applies `--ascii`.
+pub(crate) fn set_ascii(ascii: bool) {
+    if ascii {
+        ASCII_FLAG.store(true, atomic::Ordering::Relaxed);
+        THEME.write().unwrap_or_else(|e| e.into_inner()).glyphs = &ASCII;
+    }
+}
+
+/// Applies `[ui]` in the config.
+pub(crate) fn configure(config: &UiConfig) -> anyhow::Result<()> {
+    let UiConfig { ascii, colors } = config;
+
+    let ascii = ASCII_FLAG.load(atomic::Ordering::Relaxed) || ascii.unwrap_or(!supports_unicode());
+    let mut theme = Theme::new(ascii);
+    for (role, value, color) in [
+        ("error", &colors.error, &mut theme.error),
+        ("warn", &colors.warn, &mut theme.warn),
+        ("info", &colors.info, &mut theme.info),
+        ("debug", &colors.debug, &mut theme.debug),
+        ("trace", &colors.trace, &mut theme.trace),
+        ("added", &colors.added, &mut theme.added),
+        ("removed", &colors.removed, &mut theme.removed),
+        ("ok", &colors.ok, &mut theme.ok),
+        ("accent", &colors.accent, &mut theme.accent),
+    ] {
+        if let Some(value) = value {
+            *color = value
+                .parse()
+                .with_context(|| format!("`ui.colors.{}`: invalid color {:?}", role, value))?;
+        }
+    }
+    *THEME.write().unwrap_or_else(|e| e.into_inner()) = theme;
+    Ok(())
+}
+
+/// Whether the terminal is expected to render box-drawing characters.
+///
+/// On Unix, the first non-empty one of `$LC_ALL`, `$LC_CTYPE`, and `$LANG` has to be UTF-8, if
+/// any.
+fn supports_unicode() -> bool {
+    if cfg!(windows) {
+        return true;
+    }
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .flat_map(env::var)
+        .find(|v| !v.is_empty())
+        .is_none_or(|v| {
+            let v = v.to_lowercase();
+            v.contains("utf-8") || v.contains("utf8")
+        })
+}
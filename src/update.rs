@@ -0,0 +1,166 @@
+use anyhow::{bail, Context as _};
+use log::{info, warn};
+use self_update::backends::github::ReleaseList;
+use self_update::update::Release;
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::{env, fs};
+
+static REPO_OWNER: &str = "qryxip";
+static REPO_NAME: &str = "bikecase";
+static BINS: &[&str] = &["bikecase", "cargo-bikecase"];
+static CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Prints a notice if a newer release is available.
+///
+/// GitHub is queried at most once per `CHECK_INTERVAL`, and the result is cached in the data
+/// directory. Failures are only logged, since this should not prevent running the script.
+pub(crate) fn check(data_local_dir: Option<&Path>) {
+    let cache_path = match data_local_dir {
+        Some(data_local_dir) => data_local_dir.join("bikecase").join("update-check.json"),
+        None => return,
+    };
+
+    let cached = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<UpdateCheck>(&s).ok())
+        .filter(|c| {
+            humantime::parse_rfc3339(&c.checked_at)
+                .ok()
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|d| d < CHECK_INTERVAL)
+        });
+
+    let latest = match cached {
+        Some(UpdateCheck { latest, .. }) => latest,
+        None => {
+            // Failures are cached as well, so that we do not retry on every run while offline.
+            let latest = match latest_release() {
+                Ok(release) => Some(release.version),
+                Err(err) => {
+                    warn!("Failed to check for updates: {}", err);
+                    None
+                }
+            };
+            let check = UpdateCheck {
+                checked_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                latest: latest.clone(),
+            };
+            let write = || -> anyhow::Result<()> {
+                if let Some(parent) = cache_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&cache_path, serde_json::to_string(&check)?)?;
+                Ok(())
+            };
+            if let Err(err) = write() {
+                warn!("Failed to write {}: {}", cache_path.display(), err);
+            }
+            latest
+        }
+    };
+
+    let latest = match latest {
+        Some(latest) => latest,
+        None => return,
+    };
+    if self_update::version::bump_is_greater(CURRENT_VERSION, &latest).unwrap_or(false) {
+        info!(
+            "bikecase {} is available (current: {}). Run `cargo bikecase self update` to update",
+            latest, CURRENT_VERSION,
+        );
+    }
+}
+
+/// Downloads the latest release and replaces `bikecase` and `cargo-bikecase` with the binaries in
+/// it.
+pub(crate) fn self_update(dry_run: bool) -> anyhow::Result<()> {
+    let release = latest_release()?;
+    if !self_update::version::bump_is_greater(CURRENT_VERSION, &release.version)? {
+        info!("bikecase {} is up to date", CURRENT_VERSION);
+        return Ok(());
+    }
+    info!(
+        "Updating bikecase {} -> {}",
+        CURRENT_VERSION, release.version
+    );
+
+    let target = self_update::get_target();
+    let asset = release
+        .asset_for(target, None)
+        .with_context(|| format!("no asset found for `{}`", target))?;
+
+    let current_exe = env::current_exe()?;
+    let bin_dir = current_exe
+        .parent()
+        .with_context(|| format!("{} has no parent", current_exe.display()))?;
+    let destinations = BINS
+        .iter()
+        .map(|&bin| {
+            (
+                bin,
+                bin_dir.join(format!("{}{}", bin, env::consts::EXE_SUFFIX)),
+            )
+        })
+        .filter(|(_, path)| path.exists())
+        .collect::<Vec<_>>();
+
+    if dry_run {
+        info!("[dry-run] Downloading {}", asset.name);
+        for (_, path) in &destinations {
+            info!("[dry-run] Replacing {}", path.display());
+        }
+        return Ok(());
+    }
+
+    let tempdir = self_update::TempDir::new()?;
+    let archive_path = tempdir.path().join(&asset.name);
+    info!("Downloading {}", asset.name);
+    self_update::Download::from_url(&asset.download_url)
+        .set_header("accept".parse()?, "application/octet-stream".parse()?)
+        .show_progress(true)
+        .download_to(fs::File::create(&archive_path)?)?;
+
+    // The archives are created by `.github/workflows/ci.yml` as
+    // `bikecase-v<version>-<target>/{bikecase,cargo-bikecase}`.
+    let archive_dir = PathBuf::from(format!("bikecase-v{}-{}", release.version, target));
+    for (bin, path) in destinations {
+        let in_archive = archive_dir.join(format!("{}{}", bin, env::consts::EXE_SUFFIX));
+        self_update::Extract::from_source(&archive_path)
+            .extract_file(tempdir.path(), &in_archive)
+            .with_context(|| format!("failed to extract `{}`", in_archive.display()))?;
+        let new_exe = tempdir.path().join(&in_archive);
+        if path == current_exe {
+            self_update::self_replace::self_replace(&new_exe)?;
+        } else {
+            self_update::Move::from_source(&new_exe)
+                .replace_using_temp(&tempdir.path().join(format!("{}.old", bin)))
+                .to_dest(&path)?;
+        }
+        info!("Replaced {}", path.display());
+    }
+    Ok(())
+}
+
+fn latest_release() -> anyhow::Result<Release> {
+    let releases = ReleaseList::configure()
+        .repo_owner(REPO_OWNER)
+        .repo_name(REPO_NAME)
+        .build()?
+        .fetch()?;
+    match releases.into_iter().next() {
+        Some(release) => Ok(release),
+        None => bail!("no releases found in {}/{}", REPO_OWNER, REPO_NAME),
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct UpdateCheck {
+    checked_at: String,
+    latest: Option<String>,
+}
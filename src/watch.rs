@@ -0,0 +1,76 @@
+//! Polling for `bikecase --watch`.
+//!
+//! The files are polled instead of subscribing to the notification APIs of the OSes, so that
+//! this works the same on every platform, including network drives.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+pub(crate) const INTERVAL: Duration = Duration::from_millis(200);
+
+/// The script and its package, except the outputs of the program (`captures`) and of Cargo
+/// (`target`).
+pub(crate) struct Watched {
+    script: PathBuf,
+    package_dir: Option<PathBuf>,
+}
+
+impl Watched {
+    pub(crate) fn new(script: PathBuf, package_dir: Option<PathBuf>) -> Self {
+        Self {
+            script,
+            package_dir,
+        }
+    }
+
+    /// Takes a snapshot of the files, honoring `.gitignore`s.
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        let excluded = self
+            .package_dir
+            .iter()
+            .flat_map(|dir| vec![dir.join("captures"), dir.join("target")])
+            .collect::<Vec<_>>();
+        let files = Some(&self.script)
+            .into_iter()
+            .chain(&self.package_dir)
+            .flat_map(crate::fs::walk)
+            .flatten()
+            .filter(|entry| !excluded.iter().any(|e| entry.path().starts_with(e)))
+            .flat_map(|entry| {
+                let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+                Some((
+                    entry.into_path(),
+                    (metadata.modified().ok(), metadata.len()),
+                ))
+            })
+            .collect();
+        Snapshot(files)
+    }
+
+    /// Whether any file is modified, added, or removed since `snapshot`.
+    pub(crate) fn has_changed(&self, snapshot: &Snapshot) -> bool {
+        self.snapshot() != *snapshot
+    }
+
+    /// Blocks until any file is modified, added, or removed since `snapshot`.
+    pub(crate) fn wait_for_change(&self, snapshot: &Snapshot) {
+        loop {
+            thread::sleep(INTERVAL);
+            if self.has_changed(snapshot) {
+                settle();
+                return;
+            }
+        }
+    }
+}
+
+/// The modification times and the sizes of the watched files.
+#[derive(PartialEq, Debug)]
+pub(crate) struct Snapshot(BTreeMap<PathBuf, (Option<SystemTime>, u64)>);
+
+/// Lets editors finish writing.
+pub(crate) fn settle() {
+    thread::sleep(INTERVAL);
+}
@@ -1,33 +1,235 @@
-use crate::{logger, rust};
+use crate::config::BikecaseConfigPathDependency;
+use crate::event::{self, Event};
+use crate::rust;
+use crate::spec::PackageIdSpec;
 
 use anyhow::{anyhow, bail, ensure, Context as _};
 use cargo_metadata::{Metadata, Package, Target};
+use indexmap::{indexmap, IndexMap, IndexSet};
 use itertools::Itertools as _;
-use log::info;
 use serde::Deserialize;
-use toml_edit::Document;
-use url::Url;
+use toml_edit::{DocumentMut, ImDocument, InlineTable, Item, RawString, Table, TableLike, Value};
 
+use std::collections::BTreeMap;
+use std::convert::TryInto as _;
 use std::ffi::{OsStr, OsString};
-use std::path::{Path, PathBuf};
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
 use std::{env, str};
 
-pub(crate) fn create_workspace(dir: impl AsRef<Path>, dry_run: bool) -> anyhow::Result<()> {
+/// Creates a virtual manifest in `dir`.
+///
+/// `resolver` and `edition` (written as `workspace.package.edition`) default to the latest ones
+/// that the installed `cargo` supports.
+pub(crate) fn create_workspace(
+    dir: impl AsRef<Path>,
+    default_members_limit: Option<usize>,
+    resolver: Option<&str>,
+    edition: Option<&str>,
+    profile_preset: Option<crate::ProfilePreset>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
     let dir = dir.as_ref();
+    // The manifest is not written in dry runs, e.g. for `--print-config` before the config exists,
+    // so `cargo` is not spawned for it.
+    let cargo_version = if !dry_run && (resolver.is_none() || edition.is_none()) {
+        cargo_version().and_then(|v| parse_cargo_version(&v))
+    } else {
+        None
+    };
+    let resolver = resolver.or_else(|| default_resolver(cargo_version.as_ref()?));
+    let edition = edition.or_else(|| default_edition(cargo_version.as_ref()?));
+
+    let mut cargo_toml = "[workspace]\nmembers = []\nexclude = []\n".to_owned();
+    if let Some(resolver) = resolver {
+        cargo_toml += &format!("resolver = {:?}\n", resolver);
+    }
+    if default_members_limit.is_some() {
+        cargo_toml += "default-members = []\n";
+    }
+    if let Some(edition) = edition {
+        cargo_toml += &format!("\n[workspace.package]\nedition = {:?}\n", edition);
+    }
+    if let Some(limit) = default_members_limit {
+        cargo_toml += &format!(
+            "\n[workspace.metadata.bikecase]\ndefault-members-limit = {}\n",
+            limit,
+        );
+    }
+    if let Some(profile_preset) = profile_preset {
+        let mut doc = cargo_toml
+            .parse::<DocumentMut>()
+            .expect("should be a valid TOML document");
+        apply_profile_preset(&mut doc, profile_preset)?;
+        cargo_toml = doc.to_string();
+    }
     crate::fs::create_dir_all(dir, dry_run)?;
-    crate::fs::write(dir.join("Cargo.toml"), CARGO_TOML, dry_run)?;
-    info!("Created a new workspace: {}", dir.display());
+    crate::fs::write(dir.join("Cargo.toml"), cargo_toml, dry_run)?;
+    event::emit(Event::WorkspaceCreated {
+        workspace_root: dir.to_owned(),
+        dry_run,
+    });
     return Ok(());
 
-    static CARGO_TOML: &str = r#"[workspace]
-members = []
-exclude = []
-"#;
+    fn default_resolver(cargo_version: &semver::Version) -> Option<&'static str> {
+        match (cargo_version.major, cargo_version.minor) {
+            (1, 84..) => Some("3"),
+            (1, 51..) => Some("2"),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `preset` into `[profile.dev]` of the virtual manifest in `workspace_root`.
+pub(crate) fn write_profile_preset(
+    workspace_root: &Path,
+    preset: crate::ProfilePreset,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    raise_unless_virtual(workspace_root)?;
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+    apply_profile_preset(&mut cargo_toml, preset)?;
+    info_event!("`profile.dev`: {}", <&str>::from(preset));
+    crate::fs::write(&manifest_path, cargo_toml.to_string(), dry_run)
+}
+
+/// Replaces the keys in `[profile.dev]` and `[profile.dev.package."*"]` that the presets set,
+/// leaving the others as they are.
+fn apply_profile_preset(
+    cargo_toml: &mut DocumentMut,
+    preset: crate::ProfilePreset,
+) -> anyhow::Result<()> {
+    const KEYS: &[&str] = &["opt-level", "debug", "split-debuginfo", "incremental"];
+
+    let (dev, dependencies): (Vec<(&str, Value)>, _) = match preset {
+        crate::ProfilePreset::FastCompile => (
+            vec![("debug", 0.into()), ("incremental", true.into())],
+            None,
+        ),
+        crate::ProfilePreset::FastRun => {
+            (vec![("opt-level", 1.into()), ("debug", 0.into())], Some(3))
+        }
+        crate::ProfilePreset::Balanced => {
+            let mut dev = vec![("debug", 1.into())];
+            // `-C split-debuginfo=unpacked` is unstable for MSVC.
+            if !cfg!(windows) {
+                dev.push(("split-debuginfo", "unpacked".into()));
+            }
+            (dev, Some(2))
+        }
+    };
+
+    let profile = cargo_toml
+        .entry("profile")
+        .or_insert_with(implicit_table)
+        .as_table_mut()
+        .with_context(|| "`profile` must be a table")?;
+    let dev_table = profile
+        .entry("dev")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .with_context(|| "`profile.dev` must be a table")?;
+    for key in KEYS {
+        dev_table.remove(key);
+    }
+    if let Some(packages) = dev_table.get_mut("package").and_then(Item::as_table_mut) {
+        if let Some(all) = packages.get_mut("*").and_then(Item::as_table_mut) {
+            all.remove("opt-level");
+            if all.is_empty() {
+                packages.remove("*");
+            }
+        }
+        if packages.is_empty() {
+            dev_table.remove("package");
+        }
+    }
+    for (key, value) in dev {
+        dev_table[key] = Item::Value(value);
+    }
+    if let Some(opt_level) = dependencies {
+        let packages = dev_table
+            .entry("package")
+            .or_insert_with(implicit_table)
+            .as_table_mut()
+            .with_context(|| "`profile.dev.package` must be a table")?;
+        packages
+            .entry("*")
+            .or_insert_with(|| Item::Table(Table::new()))["opt-level"] =
+            toml_edit::value(opt_level);
+    }
+    return Ok(());
+
+    fn implicit_table() -> Item {
+        let mut table = Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    }
+}
+
+/// The latest edition that `cargo_version` supports.
+fn default_edition(cargo_version: &semver::Version) -> Option<&'static str> {
+    match (cargo_version.major, cargo_version.minor) {
+        (1, 85..) => Some("2024"),
+        (1, 56..) => Some("2021"),
+        (1, 31..) => Some("2018"),
+        _ => None,
+    }
+}
+
+/// Returns the output of `cargo --version`, e.g. `cargo 1.85.0 (d73d2caf9 2024-12-31)`.
+pub(crate) fn cargo_version() -> Option<String> {
+    let cargo_exe = cargo_exe().ok()?;
+    let output = duct::cmd(cargo_exe, &["--version"])
+        .stdout_capture()
+        .stderr_null()
+        .unchecked()
+        .run()
+        .ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim_end().to_owned())
+}
+
+/// Returns the output of `rustc --version`, with `$RUSTC` if set.
+///
+/// With `dir`, `rustc` is run there so that `rust-toolchain.toml` in it is honored.
+pub(crate) fn rustc_version(dir: Option<&Path>) -> Option<String> {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let mut cmd = duct::cmd(rustc, &["--version"]);
+    if let Some(dir) = dir {
+        cmd = cmd.dir(dir);
+    }
+    let output = cmd.stdout_capture().stderr_null().unchecked().run().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim_end().to_owned()).filter(|s| !s.is_empty())
+}
+
+/// Returns the output of `rustc --print sysroot` run in `dir`, with `$RUSTC` if set.
+pub(crate) fn rustc_sysroot(dir: &Path) -> Option<PathBuf> {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = duct::cmd(rustc, &["--print", "sysroot"])
+        .dir(dir)
+        .stdout_capture()
+        .stderr_null()
+        .unchecked()
+        .run()
+        .ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim_end().into()).filter(|s: &PathBuf| !s.as_os_str().is_empty())
 }
 
+/// `cargo 1.85.0-nightly (d73d2caf9 2024-12-31)` → `1.85.0-nightly`
+fn parse_cargo_version(output: &str) -> Option<semver::Version> {
+    output.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Returns `--manifest-path` relative to `cwd`, or finds `Cargo.toml` upward from `cwd`.
+///
+/// The manifest may be of a member. Its workspace is found with `cargo metadata`, and `cargo pkgid`
+/// picks the member when `-p` is omitted.
 pub(crate) fn manifest_path(manifest_path: Option<&Path>, cwd: &Path) -> anyhow::Result<PathBuf> {
     manifest_path
-        .map(ToOwned::to_owned)
+        .map(|p| cwd.join(p))
         .or_else(|| {
             cwd.ancestors()
                 .map(|p| p.join("Cargo.toml"))
@@ -71,116 +273,1616 @@ pub(crate) fn cargo_metadata_no_deps(
     Ok(metadata)
 }
 
-pub(crate) fn raise_unless_virtual(workspace_root: &Path) -> anyhow::Result<()> {
-    let manifest_path = workspace_root.join("Cargo.toml");
-    let CargoToml { package } = crate::fs::read_toml(&manifest_path)?;
-    if package.is_some() {
-        bail!(
-            "the target manifest must be a virtual one: {}",
-            manifest_path.display(),
-        );
+/// Runs `cargo metadata` resolving the dependencies. `flags` are passed as they are (e.g. `--locked`).
+pub(crate) fn cargo_metadata(
+    manifest_path: &Path,
+    color: crate::ColorChoice,
+    flags: &[&str],
+    cwd: &Path,
+) -> anyhow::Result<Metadata> {
+    let program = cargo_exe()?;
+    let mut args = vec![
+        "metadata".as_ref(),
+        "--manifest-path".as_ref(),
+        manifest_path.as_os_str(),
+        "--format-version".as_ref(),
+        "1".as_ref(),
+        "--color".as_ref(),
+        <&str>::from(color).as_ref(),
+    ];
+    args.extend(flags.iter().map(AsRef::<OsStr>::as_ref));
+    let metadata = crate::process::cmd(program, &args).dir(cwd).read()?;
+    let metadata = serde_json::from_str::<Metadata>(&metadata)?;
+    Ok(metadata)
+}
+
+/// Fails if `package_dir` has uncommitted changes, including untracked files.
+///
+/// Does nothing with a warning if `package_dir` is not in a git work tree.
+pub(crate) fn ensure_clean(package_dir: &Path) -> anyhow::Result<()> {
+    let output = crate::process::cmd("git", &["status", "--porcelain", "--", "."])
+        .dir(package_dir)
+        .stdout_capture()
+        .stderr_null()
+        .unchecked()
+        .run();
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            warn_event!(
+                "{} is not in a git work tree. Skipping the check for uncommitted changes",
+                package_dir.display(),
+            );
+            return Ok(());
+        }
+    };
+    let changes = str::from_utf8(&output.stdout)?;
+    if !changes.is_empty() {
+        bail!(
+            "{} has uncommitted changes:\n{}",
+            package_dir.display(),
+            changes.trim_end(),
+        );
+    }
+    Ok(())
+}
+
+/// Finds the workspace root from the manifests, running `cargo metadata` only if they are not
+/// enough.
+pub(crate) fn workspace_root(
+    manifest_path: &Path,
+    color: crate::ColorChoice,
+    cwd: &Path,
+) -> anyhow::Result<PathBuf> {
+    match workspace_root_from_manifests(&cwd.join(manifest_path))? {
+        Some(workspace_root) => Ok(workspace_root),
+        None => cargo_metadata_no_deps(manifest_path, color, cwd).map(|m| m.workspace_root),
+    }
+}
+
+/// A script exported from a member.
+pub(crate) struct ExportedScript {
+    workspace_root: PathBuf,
+    package_dir: PathBuf,
+    src_path: PathBuf,
+    /// The manifest with the workspace dependencies expanded.
+    cargo_toml: String,
+    /// The inlined `path` dependencies, as pairs of the module name and the content.
+    modules: Vec<(String, String)>,
+}
+
+impl ExportedScript {
+    fn new(
+        workspace_root: &Path,
+        package_dir: &Path,
+        src_path: &Path,
+        cargo_toml: &str,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            workspace_root: workspace_root.to_owned(),
+            package_dir: package_dir.to_owned(),
+            src_path: src_path.to_owned(),
+            cargo_toml: expand_workspace_dependencies(workspace_root, cargo_toml)?,
+            modules: vec![],
+        })
+    }
+
+    /// Resolves the `path` dependencies in the manifest, which would be broken outside of the
+    /// workspace.
+    ///
+    /// Each one is replaced with `path-dependencies.<name>` in the config, which `mapping` loads
+    /// lazily. Otherwise it is inlined into the script as `mod <name>` if `inline`, or this fails.
+    pub(crate) fn resolve_path_dependencies(
+        &mut self,
+        inline: bool,
+        mapping: impl FnOnce() -> anyhow::Result<BTreeMap<String, BikecaseConfigPathDependency>>,
+    ) -> anyhow::Result<()> {
+        let mut cargo_toml = self
+            .cargo_toml
+            .parse::<DocumentMut>()
+            .with_context(|| "failed to parse the manifest")?;
+
+        let has_path_dependencies = dependency_tables_mut(&mut cargo_toml)
+            .iter()
+            .any(|(_, deps)| deps.iter().any(|(_, dep)| dep.get("path").is_some()));
+        if !has_path_dependencies {
+            return Ok(());
+        }
+        let mapping = mapping()?;
+
+        let mut inlined = vec![];
+        for (table_path, deps) in dependency_tables_mut(&mut cargo_toml) {
+            let names = deps
+                .iter()
+                .filter(|(_, dep)| dep.get("path").is_some())
+                .map(|(name, _)| name.to_owned())
+                .collect::<Vec<_>>();
+
+            for name in names {
+                let dep = deps.get_mut(&name).expect("should exist");
+                let path = dep["path"]
+                    .as_str()
+                    .with_context(|| format!("`{}.{}.path` must be a string", table_path, name))?
+                    .to_owned();
+
+                if let Some(replacement) = mapping.get(&name) {
+                    let fields = replacement.fields();
+                    ensure!(
+                        fields.iter().any(|&(k, _)| k == "version" || k == "git"),
+                        "`path-dependencies.{}` must have `version` or `git`",
+                        name,
+                    );
+                    let dep = dep.as_table_like_mut().expect("should have `path`");
+                    dep.remove("path");
+                    for (key, value) in fields {
+                        dep.insert(key, toml_edit::value(value));
+                    }
+                    info_event!(
+                        "`{}.{}`: replaced `path` with `path-dependencies.{}`",
+                        table_path,
+                        name,
+                        name,
+                    );
+                } else if inline && table_path == "dependencies" {
+                    deps.remove(&name);
+                    inlined.push((name, path));
+                } else {
+                    bail!(
+                        "`{}.{}` is a `path` dependency on {:?}, which will be broken outside of \
+                         the workspace. Set `path-dependencies.{}` in the config to a published \
+                         version or a Git repository{}",
+                        table_path,
+                        name,
+                        path,
+                        name,
+                        if table_path == "dependencies" {
+                            ", or enable `--inline-path-deps`"
+                        } else {
+                            ""
+                        },
+                    );
+                }
+            }
+        }
+
+        for (name, path) in inlined {
+            let dir = self.package_dir.join(&path);
+            let (content, lib_deps) =
+                read_path_dependency_for_inlining(&self.workspace_root, &name, &dir)?;
+            let deps = cargo_toml["dependencies"]
+                .as_table_like_mut()
+                .expect("should have had the dependency");
+            for (lib_dep_name, lib_dep) in lib_deps {
+                if deps.get(&lib_dep_name).is_none() {
+                    deps.insert(&lib_dep_name, lib_dep);
+                }
+            }
+            info_event!("`dependencies.{}`: inlined {:?}", name, path);
+            self.modules.push((name.replace('-', "_"), content));
+        }
+
+        self.cargo_toml = cargo_toml.to_string();
+        Ok(())
+    }
+
+    /// Writes the script to `wtr` line by line, with the info string of the opening fence of the
+    /// manifest replaced with `fence` if any.
+    ///
+    /// Exported scripts may be generated ones of megabytes, so the converted one is not built in
+    /// memory.
+    pub(crate) fn write(&self, fence: Option<&str>, wtr: impl Write) -> anyhow::Result<()> {
+        let code = crate::fs::read(&self.src_path)?;
+        let code = match self.src_path.parent() {
+            Some(src_dir) => inline_modules(&code, src_dir)?,
+            None => code,
+        };
+        let mut wtr = BufWriter::new(wtr);
+        rust::replace_cargo_lang_code(
+            &code,
+            &self.cargo_toml,
+            fence,
+            || {
+                anyhow!(
+                    "could not find the `cargo` code block: {}",
+                    self.src_path.display(),
+                )
+            },
+            &mut wtr,
+        )?;
+        let style = rust::TextStyle::detect(&code);
+        for (name, content) in &self.modules {
+            rust::write_module(&mut wtr, style, name, content)?;
+        }
+        wtr.flush().map_err(Into::into)
+    }
+
+    /// Same as [`write`](Self::write), but returns the script.
+    pub(crate) fn to_code(&self, fence: Option<&str>) -> anyhow::Result<String> {
+        let mut code = vec![];
+        self.write(fence, &mut code)?;
+        Ok(String::from_utf8(code)?)
+    }
+}
+
+/// Exports the script of a member from the manifests, without `cargo metadata`.
+///
+/// Returns `None` when the manifests are not enough: the member is not listed verbatim in
+/// `workspace.members`, `spec` is not a plain name, or the `bin` target may be other than
+/// `src/main.rs`.
+pub(crate) fn export_script_from_manifests(
+    manifest_path: &Path,
+    spec: Option<&str>,
+) -> anyhow::Result<Option<ExportedScript>> {
+    let workspace_root = match workspace_root_from_manifests(manifest_path)? {
+        Some(workspace_root) => workspace_root,
+        None => return Ok(None),
+    };
+
+    let member_dir = match spec {
+        None => match manifest_path.parent() {
+            Some(dir) if dir != workspace_root => dir.to_owned(),
+            _ => return Ok(None),
+        },
+        Some(spec) => {
+            if !spec
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Ok(None);
+            }
+            let workspace_manifest = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+            let members = match verbatim_members(&workspace_manifest) {
+                Some(members) => members,
+                None => return Ok(None),
+            };
+            let mut found = None;
+            for member in members {
+                let dir = workspace_root.join(member);
+                let cargo_toml = crate::fs::read_toml_edit(dir.join("Cargo.toml"))?;
+                let name = cargo_toml
+                    .get("package")
+                    .and_then(|p| p.get("name"))
+                    .and_then(Item::as_str);
+                if name == Some(spec) {
+                    found = Some(dir);
+                    break;
+                }
+            }
+            match found {
+                Some(dir) => dir,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let cargo_toml_path = member_dir.join("Cargo.toml");
+    let cargo_toml = crate::fs::read(&cargo_toml_path)?;
+    let doc = cargo_toml.parse::<DocumentMut>().with_context(|| {
+        format!(
+            "failed to parse the TOML file at {}",
+            cargo_toml_path.display()
+        )
+    })?;
+    let package = match doc.get("package") {
+        Some(package) => package,
+        None => return Ok(None),
+    };
+    let main_rs = member_dir.join("src").join("main.rs");
+    if doc.contains_key("bin")
+        || package.get("default-run").is_some()
+        || package.get("autobins").and_then(Item::as_bool) == Some(false)
+        || member_dir.join("src").join("bin").exists()
+        || !main_rs.exists()
+    {
+        return Ok(None);
+    }
+    ExportedScript::new(&workspace_root, &member_dir, &main_rs, &cargo_toml).map(Some)
+}
+
+/// Embeds `Cargo.toml` of the package in `package_dir` into `src/main.rs` as a `cargo` code block,
+/// so that it can be exported as a script.
+///
+/// Returns the path to `src/main.rs` and the new content without writing.
+pub(crate) fn scriptify(package_dir: &Path) -> anyhow::Result<(PathBuf, String)> {
+    let cargo_toml_path = package_dir.join("Cargo.toml");
+    let cargo_toml = crate::fs::read(&cargo_toml_path)?;
+    let doc = cargo_toml.parse::<DocumentMut>().with_context(|| {
+        format!(
+            "failed to parse the TOML file at {}",
+            cargo_toml_path.display()
+        )
+    })?;
+    let src_dir = package_dir.join("src");
+    let main_rs = src_dir.join("main.rs");
+    if doc.contains_key("bin")
+        || doc.contains_key("lib")
+        || src_dir.join("lib.rs").exists()
+        || src_dir.join("bin").exists()
+        || !main_rs.exists()
+    {
+        bail!(
+            "could not scriptify {}: only packages with just `src/main.rs` are supported",
+            package_dir.display(),
+        );
+    }
+
+    let code = crate::fs::read(&main_rs)?;
+    if rust::extract_cargo_lang_code(&code, || "").is_ok() {
+        bail!("{} already has a `cargo` code block", main_rs.display());
+    }
+    let code = rust::insert_cargo_lang_code(&code, &cargo_toml)?;
+    Ok((main_rs, code))
+}
+
+/// Finds the root of the workspace that `manifest_path` belongs to, following what Cargo does for
+/// the simple cases.
+///
+/// Returns `None` if `package.workspace` is set, or if the workspace does not list the member
+/// verbatim in `workspace.members`.
+fn workspace_root_from_manifests(manifest_path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let member_dir = manifest_path
+        .parent()
+        .with_context(|| "empty manifest path")?;
+    let cargo_toml = crate::fs::read_toml_edit(manifest_path)?;
+    if cargo_toml.contains_key("workspace") {
+        return Ok(Some(member_dir.to_owned()));
+    }
+    if cargo_toml
+        .get("package")
+        .and_then(|p| p.get("workspace"))
+        .is_some()
+    {
+        return Ok(None);
+    }
+
+    for workspace_root in member_dir.ancestors().skip(1) {
+        let workspace_manifest = workspace_root.join("Cargo.toml");
+        if !workspace_manifest.exists() {
+            continue;
+        }
+        let workspace_manifest = crate::fs::read_toml_edit(workspace_manifest)?;
+        if !workspace_manifest.contains_key("workspace") {
+            continue;
+        }
+        let is_member = verbatim_members(&workspace_manifest).is_some_and(|members| {
+            members.iter().any(|m| {
+                workspace_root
+                    .join(m)
+                    .components()
+                    .eq(member_dir.components())
+            })
+        });
+        return Ok(Some(workspace_root.to_owned()).filter(|_| is_member));
+    }
+    Ok(None)
+}
+
+/// `workspace.members`, or `None` if it contains globs.
+fn verbatim_members(workspace_manifest: &DocumentMut) -> Option<Vec<&str>> {
+    let members = match workspace_manifest["workspace"].get("members") {
+        Some(members) => members.as_array()?,
+        None => return Some(vec![]),
+    };
+    members
+        .iter()
+        .map(|m| m.as_str().filter(|m| !m.contains(&['*', '?', '['][..])))
+        .collect()
+}
+
+pub(crate) fn raise_unless_virtual(workspace_root: &Path) -> anyhow::Result<()> {
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let CargoToml { package, .. } = crate::fs::read_toml(&manifest_path)?;
+    if package.is_some() {
+        bail!(
+            "the target manifest must be a virtual one: {}",
+            manifest_path.display(),
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn package_name(cargo_toml: &str) -> anyhow::Result<String> {
+    toml::from_str::<CargoToml>(cargo_toml)
+        .with_context(|| "failed to parse the manifest")?
+        .package
+        .map(|CargoTomlPackage { name, .. }| name)
+        .with_context(|| "missing `package.name`")
+}
+
+/// Sets `package.name` derived from `file_name` if the manifest does not have one, recording
+/// `file_name` as `package.metadata.bikecase.original-filename`.
+///
+/// A name is considered to be taken if `<workspace_root>/<name>` is a package that was created from
+/// another file, so that importing the same file again updates the same package.
+pub(crate) fn name_after_file(
+    workspace_root: &Path,
+    cargo_toml: &str,
+    file_name: Option<&str>,
+) -> anyhow::Result<String> {
+    let mut doc = cargo_toml
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the manifest")?;
+    let file_name = match file_name {
+        Some(file_name) if doc.get("package").and_then(|p| p.get("name")).is_none() => file_name,
+        _ => return Ok(cargo_toml.to_owned()),
+    };
+
+    let name = derive_name(workspace_root, file_name);
+    doc["package"]["name"] = toml_edit::value(&name);
+    set_original_filename(&mut doc, file_name)?;
+    info_event!("`package.name`: {:?} (derived from {:?})", name, file_name);
+    Ok(doc.to_string())
+}
+
+/// Returns the `package.name` that [`import_script`] gives to `script` named `file_name`.
+pub(crate) fn script_package_name(
+    workspace_root: &Path,
+    script: &str,
+    file_name: Option<&str>,
+) -> anyhow::Result<String> {
+    let (_, cargo_toml) = rust::replace_cargo_lang_code_with_default(script)?;
+    let doc = cargo_toml
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the manifest")?;
+    match (doc.get("package").and_then(|p| p.get("name")), file_name) {
+        (Some(name), _) => name
+            .as_str()
+            .map(ToOwned::to_owned)
+            .with_context(|| "`package.name` must be a string"),
+        (None, Some(file_name)) => Ok(derive_name(workspace_root, file_name)),
+        (None, None) => bail!("missing `package.name`"),
+    }
+}
+
+/// Derives a package name from `file_name`, taking the one of the package created from the same
+/// file if any.
+fn derive_name(workspace_root: &Path, file_name: &str) -> String {
+    return crate::naming::derive(file_name, |name| {
+        let manifest_path = workspace_root.join(name).join("Cargo.toml");
+        manifest_path.exists()
+            && crate::fs::read_toml_edit(&manifest_path)
+                .ok()
+                .as_ref()
+                .and_then(original_filename)
+                != Some(file_name)
+    });
+
+    fn original_filename(doc: &DocumentMut) -> Option<&str> {
+        doc.get("package")?
+            .get("metadata")?
+            .get("bikecase")?
+            .get("original-filename")?
+            .as_str()
+    }
+}
+
+/// Embeds a manifest into `code` from `file_name` of a gist, which does not have the `cargo` code
+/// block.
+///
+/// The package is named after the file, or after `description` if the file is just `main.rs`.
+/// `package.metadata.bikecase.synthetic-manifest` records that the manifest did not come from the
+/// gist. It is embedded into the gist on the next `gist push`.
+pub(crate) fn synthesize_manifest(
+    workspace_root: &Path,
+    code: &str,
+    file_name: &str,
+    description: &str,
+) -> anyhow::Result<String> {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or(file_name);
+    let name = match description.trim() {
+        description if stem == "main" && !description.is_empty() => description,
+        _ => stem,
+    };
+    let name = crate::naming::unique(crate::naming::sanitize(name), |name| {
+        workspace_root.join(name).exists()
+    });
+    let edition = cargo_version()
+        .and_then(|v| parse_cargo_version(&v))
+        .as_ref()
+        .and_then(default_edition);
+
+    let mut cargo_toml = format!("[package]\nname = {:?}\nversion = \"0.1.0\"\n", name);
+    if let Some(edition) = edition {
+        cargo_toml += &format!("edition = {:?}\n", edition);
+    }
+    cargo_toml += &format!(
+        "\n[package.metadata.bikecase]\noriginal-filename = {}\nsynthetic-manifest = true\n",
+        Value::from(file_name),
+    );
+    info_event!(
+        "{:?} does not have the `cargo` code block. Synthesized a manifest for {:?}",
+        file_name,
+        name,
+    );
+    rust::insert_cargo_lang_code(code, &cargo_toml)
+}
+
+/// Whether `package.metadata.bikecase.synthetic-manifest` is `true`.
+pub(crate) fn is_synthetic_manifest(cargo_toml: &str) -> bool {
+    cargo_toml.parse::<DocumentMut>().is_ok_and(|doc| {
+        doc.get("package")
+            .and_then(|p| p.get("metadata"))
+            .and_then(|m| m.get("bikecase"))
+            .and_then(|b| b.get("synthetic-manifest"))
+            .and_then(Item::as_bool)
+            == Some(true)
+    })
+}
+
+/// Sets `package.metadata.bikecase.original-filename`.
+pub(crate) fn set_original_filename(
+    cargo_toml: &mut DocumentMut,
+    file_name: &str,
+) -> anyhow::Result<()> {
+    let implicit_table = || {
+        let mut table = toml_edit::Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    };
+    let metadata = cargo_toml["package"]["metadata"]
+        .or_insert(implicit_table())
+        .as_table_like_mut()
+        .with_context(|| "`package.metadata` must be a table")?;
+    metadata
+        .entry("bikecase")
+        .or_insert(implicit_table())
+        .as_table_like_mut()
+        .with_context(|| "`package.metadata.bikecase` must be a table")?
+        .insert("original-filename", toml_edit::value(file_name));
+    Ok(())
+}
+
+/// Reads `package.metadata.bikecase.gist.extra-files`, the paths of the non-Rust files pushed to
+/// the gist along with the script, relative to the package.
+pub(crate) fn gist_extra_files(cargo_toml: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let doc = cargo_toml
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the manifest")?;
+    let extra_files = match doc
+        .get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("bikecase"))
+        .and_then(|b| b.get("gist"))
+        .and_then(|g| g.get("extra-files"))
+    {
+        Some(extra_files) => extra_files,
+        None => return Ok(vec![]),
+    };
+    let err = || anyhow!("`package.metadata.bikecase.gist.extra-files` must be an array of paths");
+    extra_files
+        .as_array()
+        .ok_or_else(err)?
+        .iter()
+        .map(|path| {
+            let path = PathBuf::from(path.as_str().ok_or_else(err)?);
+            if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+                bail!(
+                    "`package.metadata.bikecase.gist.extra-files`: {:?} is not a relative path \
+                     in the package",
+                    path,
+                );
+            }
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Prunes `cargo_toml` to what the `bin` target needs, for exporting it alone.
+///
+/// The `required-features` of `bin` are added to `features.default`. The features and the
+/// optional dependencies that are not enabled by the default features are removed, as well as
+/// `[[bin]]` and `package.default-run`.
+fn prune_manifest(cargo_toml: &str, bin: &str) -> anyhow::Result<String> {
+    let mut doc = cargo_toml
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the manifest")?;
+
+    let required_features = doc
+        .get("bin")
+        .and_then(Item::as_array_of_tables)
+        .and_then(|bins| {
+            bins.iter()
+                .find(|b| b.get("name").and_then(Item::as_str) == Some(bin))
+        })
+        .and_then(|b| b.get("required-features"))
+        .and_then(Item::as_array)
+        .map(|fs| fs.iter().flat_map(Value::as_str).map(ToOwned::to_owned))
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    let features = doc
+        .get("features")
+        .and_then(Item::as_table_like)
+        .map(|features| {
+            features
+                .iter()
+                .map(|(name, values)| {
+                    let values = values
+                        .as_array()
+                        .map(|vs| vs.iter().flat_map(Value::as_str).map(ToOwned::to_owned))
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>();
+                    (name.to_owned(), values)
+                })
+                .collect::<BTreeMap<_, _>>()
+        })
+        .unwrap_or_default();
+
+    let mut default = features.get("default").cloned().unwrap_or_default();
+    for feature in &required_features {
+        if !default.contains(feature) {
+            default.push(feature.clone());
+        }
+    }
+
+    // `feature`, `dep:name`, `name/feature`, or `name?/feature`.
+    let mut enabled_features = IndexSet::new();
+    let mut enabled_deps = IndexSet::new();
+    let mut stack = default.clone();
+    while let Some(value) = stack.pop() {
+        if let Some(dep) = value.strip_prefix("dep:") {
+            enabled_deps.insert(dep.to_owned());
+        } else if let Some((dep, _)) = value.split_once('/') {
+            let dep = dep.trim_end_matches('?');
+            if features.contains_key(dep) {
+                stack.push(dep.to_owned());
+            } else {
+                enabled_deps.insert(dep.to_owned());
+            }
+        } else if let Some(values) = features.get(&value) {
+            if enabled_features.insert(value) {
+                stack.extend(values.iter().cloned());
+            }
+        } else {
+            enabled_deps.insert(value);
+        }
+    }
+
+    if let Some(table) = doc.get_mut("features").and_then(Item::as_table_like_mut) {
+        for name in features.keys() {
+            if name != "default" && !enabled_features.contains(name) {
+                table.remove(name);
+            }
+        }
+        if !default.is_empty() {
+            table.insert(
+                "default",
+                toml_edit::value(default.iter().collect::<toml_edit::Array>()),
+            );
+        }
+    } else if !default.is_empty() {
+        doc["features"]["default"] = toml_edit::value(default.iter().collect::<toml_edit::Array>());
+    }
+
+    remove_disabled_deps(doc.as_table_mut(), &enabled_deps);
+    if let Some(targets) = doc.get_mut("target").and_then(Item::as_table_like_mut) {
+        for (_, target) in targets.iter_mut() {
+            if let Some(target) = target.as_table_like_mut() {
+                remove_disabled_deps(target, &enabled_deps);
+            }
+        }
+    }
+
+    doc.remove("bin");
+    if let Some(package) = doc.get_mut("package").and_then(Item::as_table_like_mut) {
+        package.remove("default-run");
+    }
+    return Ok(doc.to_string());
+
+    fn remove_disabled_deps(table: &mut dyn TableLike, enabled: &IndexSet<String>) {
+        for key in &["dependencies", "build-dependencies"] {
+            if let Some(deps) = table.get_mut(key).and_then(Item::as_table_like_mut) {
+                let disabled = deps
+                    .iter()
+                    .filter(|(name, dep)| {
+                        dep.get("optional").and_then(Item::as_bool) == Some(true)
+                            && !enabled.contains(*name)
+                    })
+                    .map(|(name, _)| name.to_owned())
+                    .collect::<Vec<_>>();
+                for name in disabled {
+                    deps.remove(&name);
+                }
+            }
+        }
+    }
+}
+
+/// Adds a package with `cargo_toml` and `bin` to the workspace, or updates it.
+///
+/// Returns the package name, the manifest path, the path `bin` is written to, and the name of the
+/// `bin` target that should be passed to `cargo build --bin`, if any.
+///
+/// `bin` is saved as the `[[bin]]` named `bin_name`, `package.default-run`, or the only `[[bin]]`,
+/// in this order. Other `[[bin]]`s whose files do not exist get stub files so that the package
+/// builds.
+pub(crate) fn add_member(
+    metadata: &Metadata,
+    cargo_toml: &str,
+    bin: &str,
+    bin_name: Option<&str>,
+    strict: bool,
+    dry_run: bool,
+) -> anyhow::Result<(String, PathBuf, PathBuf, Option<String>)> {
+    let CargoToml { package, bin: bins } =
+        toml::from_str::<CargoToml>(cargo_toml).with_context(|| "failed to parse the manifest")?;
+    let CargoTomlPackage {
+        name,
+        default_run,
+        autobins,
+    } = package.with_context(|| "`package.name` is missing")?;
+
+    let manifest_path = if let Some(package) = metadata
+        .packages
+        .iter()
+        .find(|p| metadata.workspace_members.contains(&p.id) && p.name == name)
+    {
+        info_event!(
+            "`{}` already exists: {}",
+            name,
+            metadata.workspace_root.display(),
+        );
+        package.manifest_path.clone()
+    } else {
+        let package_dir = metadata.workspace_root.join(&name);
+        ensure!(!package_dir.exists(), "{} exists", package_dir.display());
+        modify_members(
+            &metadata.workspace_root,
+            Some(&package_dir),
+            None,
+            None,
+            Some(&package_dir),
+            strict,
+            dry_run,
+        )?;
+        package_dir.join("Cargo.toml")
+    };
+
+    let package_dir = manifest_path
+        .parent()
+        .expect("should end with \"Cargo.toml\"");
+    let src_path = |target: &CargoTomlTarget| match &target.path {
+        Some(path) => package_dir.join(path),
+        None if target.name == name => package_dir.join("src").join("main.rs"),
+        None => package_dir
+            .join("src")
+            .join("bin")
+            .join(&target.name)
+            .with_extension("rs"),
+    };
+
+    for target in &bins {
+        if let Some(path) = &target.path {
+            ensure!(
+                path.is_relative()
+                    && !path
+                        .components()
+                        .any(|c| c == std::path::Component::ParentDir),
+                "`[[bin]]` `{}`: `path` must be a relative path inside the package: {}",
+                target.name,
+                path.display(),
+            );
+        }
+    }
+    if let Some((target1, target2)) = bins
+        .iter()
+        .tuple_combinations()
+        .find(|(t1, t2)| t1.name == t2.name || src_path(t1) == src_path(t2))
+    {
+        bail!(
+            "`[[bin]]` `{}` and `{}` conflict: {}",
+            target1.name,
+            target2.name,
+            src_path(target1).display(),
+        );
+    }
+
+    let (bin_target, bin_path) = match bin_name.or(default_run.as_deref()) {
+        Some(target_name) => {
+            let bin_path = if let Some(target) = bins.iter().find(|t| t.name == target_name) {
+                src_path(target)
+            } else if autobins == Some(false) {
+                bail!(
+                    "`{}` is not declared in `[[bin]]` and `package.autobins` is `false`",
+                    target_name,
+                );
+            } else if bin_name.is_some() {
+                package_dir
+                    .join("src")
+                    .join("bin")
+                    .join(target_name)
+                    .with_extension("rs")
+            } else {
+                src_path(&CargoTomlTarget {
+                    name: target_name.to_owned(),
+                    path: None,
+                })
+            };
+            (Some(target_name.to_owned()), bin_path)
+        }
+        None => match &*bins {
+            [] => (None, package_dir.join("src").join("main.rs")),
+            [target] => (Some(target.name.clone()), src_path(target)),
+            _ => bail!(
+                "could not determine which `[[bin]]` to save the script as: [{}]. Set \
+                 `package.default-run` or use `--bin`",
+                bins.iter().map(|t| &t.name).format(", "),
+            ),
+        },
+    };
+
+    crate::fs::create_dir_all(bin_path.parent().expect("should not empty"), dry_run)?;
+    write_unless_up_to_date(&manifest_path, cargo_toml, dry_run)?;
+    write_unless_up_to_date(&bin_path, bin, dry_run)?;
+
+    for target in &bins {
+        let path = src_path(target);
+        if path != bin_path && !path.exists() {
+            info_event!(
+                "Creating a stub for `[[bin]]` `{}`: {}",
+                target.name,
+                path.display(),
+            );
+            crate::fs::create_dir_all(path.parent().expect("should not empty"), dry_run)?;
+            crate::fs::write(&path, "fn main() {}\n", dry_run)?;
+        }
+    }
+
+    return Ok((name, manifest_path, bin_path, bin_target));
+
+    fn write_unless_up_to_date(path: &Path, content: &str, dry_run: bool) -> anyhow::Result<()> {
+        if path.exists() && crate::fs::read(path)? == content {
+            info_event!("{} is up to date", path.display());
+            Ok(())
+        } else {
+            crate::fs::write(path, content, dry_run)
+        }
+    }
+}
+
+pub(crate) fn modify_package_name(cargo_toml: &mut DocumentMut, name: &str) -> anyhow::Result<()> {
+    let old_name = cargo_toml
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(Item::as_str)
+        .with_context(|| "`package.name` must be a string")?
+        .to_owned();
+
+    cargo_toml["package"]["name"] = toml_edit::value(name);
+    info_event!("`package.name`: {:?} → {:?}", old_name, name);
+    Ok(())
+}
+
+pub(crate) fn modify_package_version(cargo_toml: &mut DocumentMut, version: &str) {
+    info_event!(
+        "`package.version`: {:?} → {:?}",
+        cargo_toml
+            .get("package")
+            .and_then(|p| p.get("version"))
+            .and_then(Item::as_str),
+        version,
+    );
+    cargo_toml["package"]["version"] = toml_edit::value(version)
+}
+
+pub(crate) fn modify_package_publish(cargo_toml: &mut DocumentMut, publish: bool) {
+    info_event!(
+        "`package.publish`: {:?} → {}",
+        cargo_toml
+            .get("package")
+            .and_then(|p| p.get("publish"))
+            .and_then(Item::as_bool),
+        publish,
+    );
+    cargo_toml["package"]["publish"] = toml_edit::value(publish)
+}
+
+/// Replaces dependencies that are identical to ones in `workspace.dependencies` with `{ workspace = true }`.
+pub(crate) fn inherit_workspace_dependencies(
+    workspace_root: &Path,
+    cargo_toml: &str,
+) -> anyhow::Result<String> {
+    let workspace_manifest = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    let workspace_deps = match workspace_dependencies(&workspace_manifest) {
+        Some(workspace_deps) => workspace_deps,
+        None => return Ok(cargo_toml.to_owned()),
+    };
+
+    let mut cargo_toml = cargo_toml
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the manifest")?;
+
+    for (table_path, deps) in dependency_tables_mut(&mut cargo_toml) {
+        for (name, dep) in deps.iter_mut() {
+            let workspace_dep = match workspace_deps.get(name.get()) {
+                Some(workspace_dep) => workspace_dep,
+                None => continue,
+            };
+            let (dep_fields, workspace_dep_fields) =
+                match (dependency_fields(dep), dependency_fields(workspace_dep)) {
+                    (Some(d), Some(w)) if !d.contains_key("workspace") => (d, w),
+                    _ => continue,
+                };
+
+            let same_source = !dep_fields.contains_key("path")
+                && SOURCE_KEYS.iter().all(|k| {
+                    dep_fields.get(k).map(Value::as_str)
+                        == workspace_dep_fields.get(k).map(Value::as_str)
+                });
+            let same_default_features = ["default-features", "default_features"]
+                .iter()
+                .filter_map(|k| dep_fields.get(k))
+                .all(|v| {
+                    let workspace_default_features = ["default-features", "default_features"]
+                        .iter()
+                        .find_map(|k| workspace_dep_fields.get(k))
+                        .and_then(Value::as_bool)
+                        .unwrap_or(true);
+                    v.as_bool() == Some(workspace_default_features)
+                });
+            if !(same_source && same_default_features) {
+                continue;
+            }
+
+            let mut inherited = InlineTable::new();
+            inherited.insert("workspace", true.into());
+            for (key, value) in dep_fields {
+                if !(SOURCE_KEYS.contains(&key) || key.starts_with("default")) {
+                    inherited.insert(key, value);
+                }
+            }
+            inherited.fmt();
+            info_event!(
+                "`{}.{}`: inherited from `workspace.dependencies`",
+                table_path,
+                name.get(),
+            );
+            *dep = toml_edit::value(inherited);
+        }
+    }
+
+    Ok(cargo_toml.to_string())
+}
+
+/// Expands `{ workspace = true }` dependencies with `workspace.dependencies` so that the manifest stands alone.
+pub(crate) fn expand_workspace_dependencies(
+    workspace_root: &Path,
+    cargo_toml: &str,
+) -> anyhow::Result<String> {
+    let mut cargo_toml = cargo_toml
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the manifest")?;
+
+    let workspace_manifest = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    let workspace_deps = workspace_dependencies(&workspace_manifest);
+
+    for (table_path, deps) in dependency_tables_mut(&mut cargo_toml) {
+        for (name, dep) in deps.iter_mut() {
+            let dep_fields = match dependency_fields(dep) {
+                Some(dep_fields) if dep_fields.contains_key("workspace") => dep_fields,
+                _ => continue,
+            };
+
+            let workspace_dep_fields = workspace_deps
+                .and_then(|deps| deps.get(name.get()))
+                .and_then(dependency_fields)
+                .with_context(|| {
+                    format!(
+                        "`{}.{}` is inherited but `workspace.dependencies.{}` is not found",
+                        table_path,
+                        name.get(),
+                        name.get(),
+                    )
+                })?;
+
+            let mut expanded = InlineTable::new();
+            for (key, value) in workspace_dep_fields {
+                let value = match (key, value.as_str()) {
+                    ("path", Some(path)) => workspace_root
+                        .join(path)
+                        .to_str()
+                        .with_context(|| format!("{:?} is not valid UTF-8 path", path))?
+                        .into(),
+                    _ => value,
+                };
+                expanded.insert(key, value);
+            }
+            for (key, value) in dep_fields {
+                match (key, expanded.get_mut("features")) {
+                    ("workspace", _) => {}
+                    ("features", Some(Value::Array(features))) => {
+                        for feature in value.as_array().into_iter().flatten() {
+                            if !features.iter().any(|f| f.as_str() == feature.as_str()) {
+                                features.push(feature.clone());
+                            }
+                        }
+                    }
+                    _ => {
+                        expanded.insert(key, value);
+                    }
+                }
+            }
+
+            expanded.fmt();
+            info_event!(
+                "`{}.{}`: expanded with `workspace.dependencies`",
+                table_path,
+                name.get(),
+            );
+            *dep = match expanded.get("version") {
+                Some(Value::String(version)) if expanded.len() == 1 => {
+                    toml_edit::value(version.value().clone())
+                }
+                _ => toml_edit::value(expanded),
+            };
+        }
+    }
+
+    Ok(cargo_toml.to_string())
+}
+
+/// Reads the library of the path dependency `name` in `dir` as a module, with its `crate::` paths
+/// rewritten to `crate::<name>::`, and its dependencies.
+fn read_path_dependency_for_inlining(
+    workspace_root: &Path,
+    name: &str,
+    dir: &Path,
+) -> anyhow::Result<(String, Vec<(String, Item)>)> {
+    let cargo_toml_path = dir.join("Cargo.toml");
+    let cargo_toml =
+        expand_workspace_dependencies(workspace_root, &crate::fs::read(&cargo_toml_path)?)
+            .with_context(|| format!("failed to read {}", cargo_toml_path.display()))?;
+    let mut cargo_toml = cargo_toml
+        .parse::<DocumentMut>()
+        .with_context(|| format!("failed to parse {}", cargo_toml_path.display()))?;
+
+    let lib = cargo_toml.get("lib");
+    ensure!(
+        lib.and_then(|l| l.get("proc-macro"))
+            .and_then(Item::as_bool)
+            != Some(true),
+        "could not inline `{}`: procedural macro crates are not supported",
+        name,
+    );
+    ensure!(
+        cargo_toml
+            .get("package")
+            .and_then(|p| p.get("build"))
+            .is_none()
+            && !dir.join("build.rs").exists(),
+        "could not inline `{}`: build scripts are not supported",
+        name,
+    );
+    let lib_path = dir.join(
+        lib.and_then(|l| l.get("path"))
+            .and_then(Item::as_str)
+            .unwrap_or("src/lib.rs"),
+    );
+
+    let mut deps = vec![];
+    if let Some(table) = cargo_toml
+        .get_mut("dependencies")
+        .and_then(Item::as_table_like_mut)
+    {
+        for (dep_name, dep) in table.iter_mut() {
+            ensure!(
+                dep.get("path").is_none(),
+                "could not inline `{}`: it has a `path` dependency `{}` too",
+                name,
+                dep_name.get(),
+            );
+            let mut dep = dep.clone();
+            if let Some(dep) = dep.as_inline_table_mut() {
+                dep.fmt();
+            }
+            deps.push((dep_name.get().to_owned(), dep));
+        }
+    }
+
+    let code = crate::fs::read(&lib_path)?;
+    let code = match lib_path.parent() {
+        Some(src_dir) => inline_modules(&code, src_dir)?,
+        None => code,
+    };
+    let code = code.replace("crate::", &format!("crate::{}::", name.replace('-', "_")));
+    Ok((code, deps))
+}
+
+/// Cleans `script` for `git` (`filter clean`): replaces the dependencies resolved from the local
+/// `workspace.dependencies` with `workspace = true`, and normalizes the formatting of the manifest.
+///
+/// Scripts without the manifest are returned as they are.
+pub(crate) fn clean_script(workspace_root: &Path, script: &str) -> anyhow::Result<String> {
+    map_manifest(script, |cargo_toml| {
+        normalize_manifest(&inherit_workspace_dependencies(workspace_root, cargo_toml)?)
+    })
+}
+
+/// Smudges `script` for `git` (`filter smudge`): expands `{ workspace = true }` dependencies, as
+/// `export` does.
+///
+/// Scripts without the manifest are returned as they are.
+pub(crate) fn smudge_script(workspace_root: &Path, script: &str) -> anyhow::Result<String> {
+    map_manifest(script, |cargo_toml| {
+        expand_workspace_dependencies(workspace_root, cargo_toml)
+    })
+}
+
+fn map_manifest(
+    script: &str,
+    f: impl FnOnce(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    let cargo_toml = match rust::extract_cargo_lang_code(script, || "") {
+        Ok(cargo_toml) => cargo_toml,
+        Err(_) => return Ok(script.to_owned()),
+    };
+    let mut converted = vec![];
+    rust::replace_cargo_lang_code(
+        script,
+        &f(&cargo_toml)?,
+        None,
+        || "could not find the `cargo` code block",
+        &mut converted,
+    )?;
+    Ok(String::from_utf8(converted)?)
+}
+
+/// Normalizes the whitespace in `cargo_toml`, keeping the comments.
+fn normalize_manifest(cargo_toml: &str) -> anyhow::Result<String> {
+    let mut cargo_toml = cargo_toml
+        .parse::<DocumentMut>()
+        .with_context(|| "failed to parse the manifest")?;
+    normalize_table(cargo_toml.as_table_mut());
+    return Ok(format!("{}\n", cargo_toml.to_string().trim()));
+
+    fn normalize_table(table: &mut Table) {
+        let decor = table.decor_mut();
+        decor.set_prefix(format!("\n{}", comments(decor.prefix())));
+        decor.set_suffix(trailing_comment(decor.suffix()));
+
+        for (mut key, item) in table.iter_mut() {
+            key.dotted_decor_mut().clear();
+            match item {
+                Item::Value(value) => {
+                    let decor = key.leaf_decor_mut();
+                    decor.set_prefix(comments(decor.prefix()));
+                    decor.set_suffix(" ");
+                    normalize_value(value);
+                    let decor = value.decor_mut();
+                    decor.set_prefix(" ");
+                    decor.set_suffix(trailing_comment(decor.suffix()));
+                }
+                Item::Table(table) => {
+                    key.leaf_decor_mut().clear();
+                    normalize_table(table);
+                }
+                Item::ArrayOfTables(tables) => {
+                    key.leaf_decor_mut().clear();
+                    tables.iter_mut().for_each(normalize_table);
+                }
+                Item::None => {}
+            }
+        }
+    }
+
+    fn normalize_value(value: &mut Value) {
+        match value {
+            Value::Array(array) => {
+                array.iter_mut().for_each(normalize_value);
+                array.fmt();
+            }
+            Value::InlineTable(table) => {
+                table.iter_mut().for_each(|(_, v)| normalize_value(v));
+                table.fmt();
+            }
+            _ => {}
+        }
+    }
+
+    /// The comment lines in `decor`, without the indentation and the blank lines.
+    fn comments(decor: Option<&RawString>) -> String {
+        decor
+            .and_then(RawString::as_str)
+            .unwrap_or("")
+            .lines()
+            .map(str::trim)
+            .filter(|l| l.starts_with('#'))
+            .map(|l| format!("{}\n", l))
+            .join("")
+    }
+
+    fn trailing_comment(decor: Option<&RawString>) -> String {
+        match decor.and_then(RawString::as_str).map(str::trim) {
+            Some(comment) if comment.starts_with('#') => format!(" {}", comment),
+            _ => "".to_owned(),
+        }
+    }
+}
+
+static SOURCE_KEYS: &[&str] = &[
+    "version", "git", "branch", "tag", "rev", "path", "registry", "package",
+];
+
+/// Returns whether each dependency of `cargo_toml` is inherited from `workspace.dependencies`, keyed
+/// by the kind (`dependencies`, `dev-dependencies`, or `build-dependencies`) and the package name.
+pub(crate) fn inherited_dependencies(
+    cargo_toml: &DocumentMut,
+    workspace_manifest: &DocumentMut,
+) -> BTreeMap<(String, String), bool> {
+    let workspace_deps = workspace_dependencies(workspace_manifest);
+    let mut cargo_toml = cargo_toml.clone();
+
+    let mut inherited = BTreeMap::new();
+    for (table_key, table) in dependency_tables_mut(&mut cargo_toml) {
+        let kind = table_key.rsplit('.').next().unwrap_or_default().to_owned();
+        for (name, dep) in table.iter() {
+            let is_inherited = dep.get("workspace").and_then(Item::as_bool) == Some(true);
+            let source = if is_inherited {
+                workspace_deps.and_then(|d| d.get(name))
+            } else {
+                Some(dep)
+            };
+            let package = source
+                .and_then(|d| d.get("package"))
+                .and_then(Item::as_str)
+                .unwrap_or(name);
+            inherited.insert((kind.clone(), package.to_owned()), is_inherited);
+        }
+    }
+    inherited
+}
+
+fn workspace_dependencies(workspace_manifest: &DocumentMut) -> Option<&dyn TableLike> {
+    workspace_manifest
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(Item::as_table_like)
+}
+
+/// Merges the dependency tables of `from` into `cargo_toml`. Dependencies in both are overwritten.
+pub(crate) fn merge_dependencies(
+    cargo_toml: &mut DocumentMut,
+    from: &DocumentMut,
+) -> anyhow::Result<()> {
+    static KINDS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+    for kind in KINDS {
+        if let Some(deps) = from.get(kind).and_then(Item::as_table_like) {
+            merge(&mut cargo_toml[kind], kind, deps)?;
+        }
+    }
+
+    let targets = from.get("target").and_then(Item::as_table_like);
+    for (cfg, target) in targets.into_iter().flat_map(|t| t.iter()) {
+        // Keep the quotes of `'cfg(..)'`.
+        let cfg_key = targets
+            .and_then(|t| t.get_key_value(cfg))
+            .map(|(k, _)| k.clone())
+            .expect("should exist");
+        for kind in KINDS {
+            if let Some(deps) = target.get(kind).and_then(Item::as_table_like) {
+                let table_path = format!("target.{:?}.{}", cfg, kind);
+                let target = cargo_toml["target"]
+                    .or_insert(implicit_table())
+                    .as_table_like_mut()
+                    .with_context(|| "`target` must be a table")?
+                    .entry_format(&cfg_key)
+                    .or_insert(implicit_table());
+                merge(&mut target[kind], &table_path, deps)?;
+            }
+        }
+    }
+    return Ok(());
+
+    fn implicit_table() -> Item {
+        let mut table = toml_edit::Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    }
+
+    fn merge(dst: &mut Item, table_path: &str, src: &dyn TableLike) -> anyhow::Result<()> {
+        let dst = dst
+            .or_insert(toml_edit::table())
+            .as_table_like_mut()
+            .with_context(|| format!("`{}` must be a table", table_path))?;
+        for (name, dep) in src.iter() {
+            let verb = match dst.get(name) {
+                Some(prev) if prev.to_string().trim() == dep.to_string().trim() => continue,
+                Some(_) => "overwritten",
+                None => "added",
+            };
+            dst.insert(name, dep.clone());
+            info_event!("`{}.{}`: {} from the script", table_path, name, verb);
+        }
+        Ok(())
     }
-    Ok(())
 }
 
-pub(crate) fn add_member(
-    metadata: &Metadata,
-    cargo_toml: &str,
-    bin: &str,
-    bin_name: Option<&str>,
-    dry_run: bool,
-) -> anyhow::Result<String> {
-    let CargoTomlPackage { name, .. } = toml::from_str::<CargoToml>(cargo_toml)
-        .with_context(|| "failed to parse the manifest")?
-        .package
-        .with_context(|| "`package.name` is missing")?;
+/// Returns the 1-based line numbers of the dependency entries in `cargo_toml`, keyed by the
+/// package names. A package may be declared in multiple tables (e.g. `dev-dependencies` and
+/// `target.'cfg(..)'.dependencies`).
+pub(crate) fn dependency_lines(cargo_toml: &str) -> anyhow::Result<BTreeMap<String, Vec<usize>>> {
+    static KINDS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
 
-    let manifest_path = if let Some(package) = metadata
-        .packages
+    let doc = ImDocument::parse(cargo_toml).with_context(|| "failed to parse the manifest")?;
+    let root = doc.as_table();
+
+    let parents = Some(root as &dyn TableLike)
+        .into_iter()
+        .chain(
+            root.get("target")
+                .and_then(Item::as_table_like)
+                .into_iter()
+                .flat_map(|t| t.iter())
+                .flat_map(|(_, cfg)| cfg.as_table_like()),
+        )
+        .collect::<Vec<_>>();
+    let tables = parents
         .iter()
-        .find(|p| metadata.workspace_members.contains(&p.id) && p.name == name)
+        .flat_map(|parent| KINDS.iter().flat_map(move |&kind| parent.get(kind)))
+        .flat_map(Item::as_table_like);
+
+    let mut lines = BTreeMap::<_, Vec<_>>::new();
+    for table in tables {
+        for (name, _) in table.iter() {
+            let (key, dep) = table.get_key_value(name).expect("should exist");
+            let package = dep
+                .get("package")
+                .and_then(Item::as_str)
+                .unwrap_or(name)
+                .to_owned();
+            if let Some(span) = key.span().or_else(|| dep.span()) {
+                let line = cargo_toml[..span.start].matches('\n').count() + 1;
+                lines.entry(package).or_default().push(line);
+            }
+        }
+    }
+    for lines in lines.values_mut() {
+        lines.sort_unstable();
+    }
+    Ok(lines)
+}
+
+/// Returns the directories of the path dependencies of the member at `manifest_path`, and of the
+/// ones in `[workspace.dependencies]` and `[patch]` of the workspace. Manifests that cannot be read
+/// are skipped.
+pub(crate) fn path_dependency_dirs(manifest_path: &Path, workspace_root: &Path) -> Vec<PathBuf> {
+    let read = |path: &Path| crate::fs::read_toml_edit(path).ok();
+    let mut dirs = vec![];
+
+    if let (Some(mut cargo_toml), Some(member_dir)) = (read(manifest_path), manifest_path.parent())
     {
-        info!(
-            "`{}` already exists: {}",
-            name,
-            metadata.workspace_root.display(),
-        );
-        package.manifest_path.clone()
-    } else {
-        let package_dir = metadata.workspace_root.join(&name);
-        ensure!(!package_dir.exists(), "{} exists", package_dir.display());
-        modify_members(
-            &metadata.workspace_root,
-            Some(&package_dir),
-            None,
-            None,
-            Some(&package_dir),
-            dry_run,
-        )?;
-        package_dir.join("Cargo.toml")
-    };
+        for (_, deps) in dependency_tables_mut(&mut cargo_toml) {
+            for (_, dep) in deps.iter() {
+                if let Some(path) = dep.get("path").and_then(Item::as_str) {
+                    dirs.push(member_dir.join(path));
+                }
+            }
+        }
+    }
 
-    let bin_path = if let Some(bin_name) = bin_name {
-        manifest_path
-            .with_file_name("src")
-            .join("bin")
-            .join(bin_name)
-            .with_extension("rs")
-    } else {
-        manifest_path.with_file_name("src").join("main.rs")
+    if let Some(workspace_manifest) = read(&workspace_root.join("Cargo.toml")) {
+        let patches = workspace_manifest
+            .get("patch")
+            .and_then(Item::as_table_like)
+            .into_iter()
+            .flat_map(|p| p.iter())
+            .flat_map(|(_, deps)| deps.as_table_like());
+        for deps in workspace_dependencies(&workspace_manifest)
+            .into_iter()
+            .chain(patches)
+        {
+            for (_, dep) in deps.iter() {
+                if let Some(path) = dep.get("path").and_then(Item::as_str) {
+                    dirs.push(workspace_root.join(path));
+                }
+            }
+        }
+    }
+    dirs
+}
+
+/// Returns the `CARGO_MANIFEST_*` and `CARGO_PKG_*` variables that `cargo run` sets, from the member
+/// at `manifest_path`. Fields with `workspace = true` are taken from `[workspace.package]`.
+pub(crate) fn package_env(
+    manifest_path: &Path,
+    workspace_root: &Path,
+) -> anyhow::Result<Vec<(&'static str, OsString)>> {
+    let cargo_toml = crate::fs::read_toml_edit(manifest_path)?;
+    let workspace_manifest = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml")).ok();
+    let workspace_package = workspace_manifest
+        .as_ref()
+        .and_then(|m| m.get("workspace")?.get("package"));
+
+    let field = |key: &str| {
+        let value = cargo_toml.get("package")?.get(key)?;
+        if value.get("workspace").and_then(Item::as_bool) == Some(true) {
+            workspace_package?.get(key)
+        } else {
+            Some(value)
+        }
+    };
+    let string = |key: &str| {
+        field(key)
+            .and_then(Item::as_str)
+            .unwrap_or_default()
+            .to_owned()
     };
 
-    crate::fs::create_dir_all(bin_path.parent().expect("should not empty"), dry_run)?;
-    write_unless_up_to_date(&manifest_path, cargo_toml, dry_run)?;
-    write_unless_up_to_date(&bin_path, bin, dry_run)?;
+    let version = field("version")
+        .and_then(Item::as_str)
+        .unwrap_or("0.0.0")
+        .parse::<semver::Version>()
+        .with_context(|| format!("invalid `package.version` in {}", manifest_path.display()))?;
+    let authors = field("authors")
+        .and_then(Item::as_array)
+        .into_iter()
+        .flatten()
+        .flat_map(Value::as_str)
+        .join(":");
+    let manifest_dir = manifest_path
+        .parent()
+        .expect("should end with \"Cargo.toml\"");
 
-    return Ok(name);
+    Ok(vec![
+        ("CARGO_MANIFEST_DIR", manifest_dir.into()),
+        ("CARGO_MANIFEST_PATH", manifest_path.into()),
+        ("CARGO_PKG_NAME", string("name").into()),
+        ("CARGO_PKG_VERSION", version.to_string().into()),
+        ("CARGO_PKG_VERSION_MAJOR", version.major.to_string().into()),
+        ("CARGO_PKG_VERSION_MINOR", version.minor.to_string().into()),
+        ("CARGO_PKG_VERSION_PATCH", version.patch.to_string().into()),
+        ("CARGO_PKG_VERSION_PRE", version.pre.to_string().into()),
+        ("CARGO_PKG_AUTHORS", authors.into()),
+        ("CARGO_PKG_DESCRIPTION", string("description").into()),
+        ("CARGO_PKG_HOMEPAGE", string("homepage").into()),
+        ("CARGO_PKG_REPOSITORY", string("repository").into()),
+        ("CARGO_PKG_LICENSE", string("license").into()),
+        ("CARGO_PKG_LICENSE_FILE", string("license-file").into()),
+        ("CARGO_PKG_RUST_VERSION", string("rust-version").into()),
+        ("CARGO_PKG_README", string("readme").into()),
+    ])
+}
 
-    fn write_unless_up_to_date(path: &Path, content: &str, dry_run: bool) -> anyhow::Result<()> {
-        if path.exists() && crate::fs::read(path)? == content {
-            info!("{} is up to date", path.display());
-            Ok(())
-        } else {
-            crate::fs::write(path, content, dry_run)
+fn dependency_tables_mut(cargo_toml: &mut DocumentMut) -> Vec<(String, &mut dyn TableLike)> {
+    static KINDS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+    let mut tables = vec![];
+    for (key, item) in cargo_toml.iter_mut() {
+        if KINDS.contains(&key.get()) {
+            if let Some(table) = item.as_table_like_mut() {
+                tables.push((key.get().to_owned(), table));
+            }
+        } else if key.get() == "target" {
+            for (cfg, item) in item
+                .as_table_like_mut()
+                .into_iter()
+                .flat_map(|t| t.iter_mut())
+            {
+                let cfg = cfg.get().to_owned();
+                for (key, item) in item
+                    .as_table_like_mut()
+                    .into_iter()
+                    .flat_map(|t| t.iter_mut())
+                {
+                    if KINDS.contains(&key.get()) {
+                        if let Some(table) = item.as_table_like_mut() {
+                            tables.push((format!("target.{:?}.{}", cfg, key.get()), table));
+                        }
+                    }
+                }
+            }
         }
     }
+    tables
 }
 
-pub(crate) fn modify_package_name(cargo_toml: &mut Document, name: &str) -> anyhow::Result<()> {
-    let old_name = cargo_toml["package"]["name"]
-        .as_str()
-        .with_context(|| "`package.name` must be a string")?
-        .to_owned();
-
-    cargo_toml["package"]["name"] = toml_edit::value(name);
-    info!("`package.name`: {:?} → {:?}", old_name, name);
-    Ok(())
+fn dependency_fields(dep: &Item) -> Option<IndexMap<&str, Value>> {
+    if let Some(version) = dep.as_str() {
+        Some(indexmap!("version" => version.into()))
+    } else {
+        let fields = dep
+            .as_table_like()?
+            .iter()
+            .filter_map(|(k, v)| Some((k, v.as_value()?.clone())))
+            .collect();
+        Some(fields)
+    }
 }
 
-pub(crate) fn modify_package_version(cargo_toml: &mut Document, version: &str) {
-    info!(
-        "`package.version`: {:?} → {:?}",
-        cargo_toml["version"].as_str(),
-        version,
-    );
-    cargo_toml["package"]["version"] = toml_edit::value(version)
+/// The directory that `cargo bikecase archive` moves members into, relative to the workspace root.
+pub(crate) const ARCHIVE_DIR: &str = "archive";
+
+/// Finds the package named `name` in [`ARCHIVE_DIR`].
+pub(crate) fn archived_package_dir(workspace_root: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let archive_dir = workspace_root.join(ARCHIVE_DIR);
+    if archive_dir.is_dir() {
+        let entries = std::fs::read_dir(&archive_dir)
+            .with_context(|| format!("failed to read {}", archive_dir.display()))?;
+        for entry in entries {
+            let dir = entry?.path();
+            let manifest_path = dir.join("Cargo.toml");
+            if manifest_path.is_file()
+                && package_name(&crate::fs::read(&manifest_path)?)
+                    .ok()
+                    .as_deref()
+                    == Some(name)
+            {
+                return Ok(dir);
+            }
+        }
+    }
+    bail!("`{}` is not archived in {}", name, archive_dir.display(),);
 }
 
-pub(crate) fn modify_package_publish(cargo_toml: &mut Document, publish: bool) {
-    info!(
-        "`package.publish`: {:?} → {}",
-        cargo_toml["publish"].as_bool(),
-        publish,
-    );
-    cargo_toml["package"]["publish"] = toml_edit::value(publish)
+/// Plans removing `packages` from the workspace.
+///
+/// Returns `packages` sorted so that each package comes before the members it depends on, and the
+/// pairs of a remaining member and a package in `packages` that it depends on. Packages in a
+/// dependency cycle keep their order.
+///
+/// Members are found by name among the dependencies without `source`, which are path dependencies.
+pub(crate) fn plan_removal<'a>(
+    metadata: &'a Metadata,
+    packages: &[&'a Package],
+) -> (Vec<&'a Package>, Vec<(&'a Package, &'a Package)>) {
+    let depends_on = |dependent: &Package, dependency: &Package| {
+        dependent
+            .dependencies
+            .iter()
+            .any(|d| d.source.is_none() && d.name == dependency.name)
+    };
+
+    let broken = metadata
+        .packages
+        .iter()
+        .filter(|p| {
+            metadata.workspace_members.contains(&p.id) && !packages.iter().any(|r| r.id == p.id)
+        })
+        .flat_map(|remaining| {
+            packages
+                .iter()
+                .filter(move |&&removed| depends_on(remaining, removed))
+                .map(move |&removed| (remaining, removed))
+        })
+        .collect();
+
+    let mut rest = packages.to_owned();
+    let mut order = vec![];
+    while !rest.is_empty() {
+        let i = (0..rest.len())
+            .find(|&i| {
+                !rest
+                    .iter()
+                    .any(|p| p.id != rest[i].id && depends_on(p, rest[i]))
+            })
+            .unwrap_or(0);
+        order.push(rest.remove(i));
+    }
+    (order, broken)
 }
 
+/// Modifies `workspace.members` and `workspace.exclude`, then checks the entries.
+///
+/// Entries are compared as paths, so `foo`, `./foo`, and `foo/` are the same. Removing removes all
+/// of them, and adding does nothing if any of them is present. The final state of the arrays is
+/// reported.
+///
+/// The problems found by the check are warned, or raised as an error without writing the manifest
+/// if `strict` is `true`.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn modify_members<'a>(
     workspace_root: &Path,
     add_to_workspace_members: Option<&'a Path>,
     add_to_workspace_exclude: Option<&'a Path>,
     rm_from_workspace_members: Option<&'a Path>,
     rm_from_workspace_exclude: Option<&'a Path>,
+    strict: bool,
     dry_run: bool,
 ) -> anyhow::Result<()> {
     let manifest_path = workspace_root.join("Cargo.toml");
@@ -198,86 +1900,409 @@ pub(crate) fn modify_members<'a>(
             rm_from_workspace_exclude,
         ),
     ] {
-        let relative_to_root = |path: &'a Path| -> _ {
-            let path = path.strip_prefix(workspace_root).unwrap_or(path);
-            path.to_str()
-                .with_context(|| format!("{:?} is not valid UTF-8 path", path))
-        };
-
-        let same_paths = |value: &toml_edit::Value, target: &str| -> _ {
-            value.as_str().map_or(false, |s| {
-                workspace_root.join(s) == workspace_root.join(target)
-            })
-        };
-
         let array = cargo_toml["workspace"][param]
             .or_insert(toml_edit::value(toml_edit::Array::default()))
             .as_array_mut()
             .with_context(|| format!("`workspace.{}` must be an array", param))?;
         if let Some(add) = *add {
-            let add = relative_to_root(add)?;
-            if !dry_run && array.iter().all(|m| !same_paths(m, add)) {
-                array.push(add);
+            let add = relative_to_root(workspace_root, add)?;
+            // Keep the first of the aliases.
+            let mut found = false;
+            array.retain(|m| {
+                let same = same_paths(workspace_root, m, &add);
+                let keep = !(same && found);
+                found |= same;
+                keep
+            });
+            if !found {
+                array.push(&add);
             }
-            info!("Added to {:?} to `workspace.{}`", add, param);
+            event::emit(Event::MemberAdded {
+                member: add,
+                field: param,
+                dry_run,
+            });
         }
         if let Some(rm) = rm {
-            let rm = relative_to_root(rm)?;
-            if !dry_run {
-                let i = array.iter().position(|m| same_paths(m, rm));
-                if let Some(i) = i {
-                    array.remove(i);
+            let rm = relative_to_root(workspace_root, rm)?;
+            array.retain(|m| !same_paths(workspace_root, m, &rm));
+            event::emit(Event::MemberRemoved {
+                member: rm,
+                field: param,
+                dry_run,
+            });
+        }
+    }
+
+    if let Some(add) = add_to_workspace_members {
+        if let Some(limit) = default_members_limit(&cargo_toml) {
+            let add = relative_to_root(workspace_root, add)?;
+            push_default_member(&mut cargo_toml, workspace_root, &add, Some(limit), dry_run)?;
+        }
+    }
+    if let Some(rm) = rm_from_workspace_members {
+        if cargo_toml["workspace"].get("default-members").is_some() {
+            let rm = relative_to_root(workspace_root, rm)?;
+            remove_default_member(&mut cargo_toml, workspace_root, &rm, dry_run)?;
+        }
+    }
+
+    for param in &["members", "exclude"] {
+        if let Some(array) = cargo_toml["workspace"][param].as_array() {
+            info_event!(
+                "`workspace.{}`: [{}]",
+                param,
+                array.iter().format_with(", ", |v, f| f(&format_args!(
+                    "{}",
+                    v.clone().decorated("", "")
+                ))),
+            );
+        }
+    }
+
+    // The directory of a new member may be created after this function.
+    let just_added = add_to_workspace_members
+        .map(|p| relative_to_root(workspace_root, p))
+        .transpose()?;
+    let problems = check_members(&cargo_toml, workspace_root, just_added.as_deref());
+    if strict && !problems.is_empty() {
+        bail!(
+            "{}: [{}]",
+            manifest_path.display(),
+            problems.iter().format("; "),
+        );
+    }
+    for problem in problems {
+        warn_event!("{}: {}", manifest_path.display(), problem);
+    }
+
+    crate::fs::write(&manifest_path, cargo_toml.to_string(), dry_run)?;
+    Ok(())
+}
+
+/// Finds duplicate, nonexistent and conflicting entries in `workspace.members`,
+/// `workspace.exclude`, and `workspace.default-members`.
+fn check_members(
+    cargo_toml: &DocumentMut,
+    workspace_root: &Path,
+    just_added: Option<&str>,
+) -> Vec<String> {
+    let mut problems = vec![];
+    let mut entries = IndexMap::<&str, Vec<PathBuf>>::new();
+
+    for &key in &["members", "exclude", "default-members"] {
+        let array = match cargo_toml["workspace"].get(key) {
+            None => continue,
+            Some(item) => match item.as_array() {
+                Some(array) => array,
+                None => {
+                    problems.push(format!("`workspace.{}` is not an array", key));
+                    continue;
+                }
+            },
+        };
+        let paths = entries.entry(key).or_default();
+
+        for value in array {
+            let entry = match value.as_str() {
+                Some(entry) => entry,
+                None => {
+                    let value = value.clone().decorated("", "");
+                    problems.push(format!("`workspace.{}`: {} is not a string", key, value));
+                    continue;
                 }
+            };
+            let path = workspace_root.join(entry);
+
+            if paths.contains(&path) {
+                problems.push(format!("`workspace.{}`: {:?} is listed twice", key, entry));
+                continue;
+            }
+            if key != "exclude"
+                && !is_glob(entry)
+                && just_added.is_none_or(|a| workspace_root.join(a) != path)
+                && !path.join("Cargo.toml").exists()
+            {
+                problems.push(format!(
+                    "`workspace.{}`: {:?} does not contain `Cargo.toml`",
+                    key, entry,
+                ));
             }
-            info!("Removed {:?} from `workspace.{}`", rm, param);
+            paths.push(path);
         }
     }
 
-    crate::fs::write(&manifest_path, cargo_toml.to_string(), dry_run)?;
+    let members = entries.get("members").cloned().unwrap_or_default();
+    for path in entries.get("exclude").into_iter().flatten() {
+        if members.contains(path) {
+            problems.push(format!(
+                "{:?} is in both `workspace.members` and `workspace.exclude`",
+                relative_to_root(workspace_root, path).unwrap_or_default(),
+            ));
+        }
+    }
+    if !cargo_toml["workspace"]
+        .get("members")
+        .and_then(Item::as_array)
+        .is_some_and(|a| a.iter().any(|v| v.as_str().is_some_and(is_glob)))
+    {
+        for path in entries.get("default-members").into_iter().flatten() {
+            if !members.contains(path) {
+                problems.push(format!(
+                    "`workspace.default-members`: {:?} is not in `workspace.members`",
+                    relative_to_root(workspace_root, path).unwrap_or_default(),
+                ));
+            }
+        }
+    }
+    return problems;
+
+    fn is_glob(entry: &str) -> bool {
+        entry.contains(&['*', '?', '['][..])
+    }
+}
+
+/// Modifies `workspace.default-members`, writing the manifest only if it changes.
+///
+/// `add` is moved to the front, and the array is truncated to
+/// `workspace.metadata.bikecase.default-members-limit` if it is set.
+pub(crate) fn modify_default_members(
+    workspace_root: &Path,
+    add: Option<&Path>,
+    rm: Option<&Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let orig = crate::fs::read(&manifest_path)?;
+    let mut cargo_toml = orig.parse::<DocumentMut>().with_context(|| {
+        format!(
+            "failed to parse the TOML file at {}",
+            manifest_path.display()
+        )
+    })?;
+
+    if let Some(add) = add {
+        let add = relative_to_root(workspace_root, add)?;
+        let limit = default_members_limit(&cargo_toml);
+        push_default_member(&mut cargo_toml, workspace_root, &add, limit, dry_run)?;
+    }
+    if let Some(rm) = rm {
+        let rm = relative_to_root(workspace_root, rm)?;
+        remove_default_member(&mut cargo_toml, workspace_root, &rm, dry_run)?;
+    }
+
+    let cargo_toml = cargo_toml.to_string();
+    if cargo_toml != orig {
+        crate::fs::write(&manifest_path, cargo_toml, dry_run)?;
+    }
+    Ok(())
+}
+
+/// Moves the member to the front of `workspace.default-members` if the workspace has
+/// `workspace.metadata.bikecase.default-members-limit`.
+pub(crate) fn touch_default_member(
+    workspace_root: &Path,
+    member_dir: &Path,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    if default_members_limit(&cargo_toml).is_some() {
+        modify_default_members(workspace_root, Some(member_dir), None, dry_run)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn default_members(workspace_root: &Path) -> anyhow::Result<Vec<String>> {
+    let cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    let default_members = match cargo_toml["workspace"].get("default-members") {
+        Some(default_members) => default_members,
+        None => return Ok(vec![]),
+    };
+    default_members
+        .as_array()
+        .with_context(|| "`workspace.default-members` must be an array")?
+        .iter()
+        .map(|m| {
+            m.as_str()
+                .map(ToOwned::to_owned)
+                .with_context(|| "`workspace.default-members` must be an array of strings")
+        })
+        .collect()
+}
+
+fn default_members_limit(cargo_toml: &DocumentMut) -> Option<usize> {
+    cargo_toml
+        .get("workspace")?
+        .get("metadata")?
+        .get("bikecase")?
+        .get("default-members-limit")?
+        .as_integer()?
+        .try_into()
+        .ok()
+}
+
+fn push_default_member(
+    cargo_toml: &mut DocumentMut,
+    workspace_root: &Path,
+    add: &str,
+    limit: Option<usize>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let array = default_members_mut(cargo_toml)?;
+    let i = array
+        .iter()
+        .position(|m| same_paths(workspace_root, m, add));
+    if let Some(i) = i {
+        if i == 0 {
+            return Ok(());
+        }
+        array.remove(i);
+    }
+    array.insert(0, add);
+    event::emit(Event::MemberAdded {
+        member: add.to_owned(),
+        field: "default-members",
+        dry_run,
+    });
+
+    if let Some(limit) = limit {
+        while array.len() > limit {
+            let removed = array.remove(array.len() - 1);
+            event::emit(Event::MemberRemoved {
+                member: removed.as_str().unwrap_or_default().to_owned(),
+                field: "default-members",
+                dry_run,
+            });
+        }
+    }
+    array.fmt();
+    Ok(())
+}
+
+fn remove_default_member(
+    cargo_toml: &mut DocumentMut,
+    workspace_root: &Path,
+    rm: &str,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let array = default_members_mut(cargo_toml)?;
+    let i = array.iter().position(|m| same_paths(workspace_root, m, rm));
+    if let Some(i) = i {
+        array.remove(i);
+        array.fmt();
+        event::emit(Event::MemberRemoved {
+            member: rm.to_owned(),
+            field: "default-members",
+            dry_run,
+        });
+    }
     Ok(())
 }
 
+fn default_members_mut(cargo_toml: &mut DocumentMut) -> anyhow::Result<&mut toml_edit::Array> {
+    cargo_toml["workspace"]["default-members"]
+        .or_insert(toml_edit::value(toml_edit::Array::default()))
+        .as_array_mut()
+        .with_context(|| "`workspace.default-members` must be an array")
+}
+
+/// Returns `path` relative to `workspace_root` as an entry of `workspace.*`, without `.` and
+/// trailing slashes.
+fn relative_to_root(workspace_root: &Path, path: &Path) -> anyhow::Result<String> {
+    let path = path.strip_prefix(workspace_root).unwrap_or(path);
+    let path = path
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect::<PathBuf>();
+    path.into_os_string()
+        .into_string()
+        .map_err(|path| anyhow!("{:?} is not valid UTF-8 path", path))
+}
+
+fn same_paths(workspace_root: &Path, value: &Value, target: &str) -> bool {
+    value
+        .as_str()
+        .is_some_and(|s| workspace_root.join(s) == workspace_root.join(target))
+}
+
 pub(crate) fn import_script(
     workspace_root: &Path,
     script: &str,
+    split_modules: bool,
+    strict: bool,
     dry_run: bool,
-    str_width: fn(&str) -> usize,
-    path: impl FnOnce(&str) -> PathBuf,
+    file_name: Option<&str>,
+    path: impl FnOnce(&str) -> anyhow::Result<PathBuf>,
 ) -> anyhow::Result<String> {
     let (main_rs, cargo_toml) = rust::replace_cargo_lang_code_with_default(script)?;
+    let (main_rs, modules) = if split_modules {
+        rust::split_modules(&main_rs)?
+    } else {
+        (main_rs, vec![])
+    };
+    let cargo_toml = inherit_workspace_dependencies(workspace_root, &cargo_toml)?;
+    let cargo_toml = name_after_file(workspace_root, &cargo_toml, file_name)?;
 
-    let package_name = toml::from_str::<CargoToml>(&cargo_toml)
+    let package_name = cargo_toml
+        .parse::<DocumentMut>()
         .with_context(|| "failed to parse the manifest")?
-        .package
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(Item::as_str)
         .with_context(|| "missing `package.name`")?
-        .name;
+        .to_owned();
 
-    let path = path(&package_name);
+    let path = path(&package_name)?;
 
     let prev_cargo_toml = prev_content(&path.join("Cargo.toml"))?;
     let prev_main_rs = prev_content(&path.join("src").join("main.rs"))?;
+    let modules = modules
+        .into_iter()
+        .map(|(name, content)| {
+            let path = path.join("src").join(name).with_extension("rs");
+            let prev = prev_content(&path)?;
+            Ok((path, prev, content))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
     crate::fs::create_dir_all(&path, dry_run)?;
     crate::fs::write(path.join("Cargo.toml"), &cargo_toml, dry_run)?;
 
     crate::fs::create_dir_all(path.join("src"), dry_run)?;
     crate::fs::write(path.join("src").join("main.rs"), &main_rs, dry_run)?;
+    for (path, _, content) in &modules {
+        crate::fs::write(path, content, dry_run)?;
+    }
 
-    modify_members(&workspace_root, Some(&*path), None, None, None, dry_run)?;
+    modify_members(
+        workspace_root,
+        Some(&*path),
+        None,
+        None,
+        None,
+        strict,
+        dry_run,
+    )?;
 
-    logger::info_diff(
-        &prev_cargo_toml,
-        &cargo_toml,
-        path.join("Cargo.toml").display(),
-        str_width,
-    );
+    event::emit(Event::Diff {
+        name: path.join("Cargo.toml").display().to_string(),
+        orig: prev_cargo_toml,
+        edit: cargo_toml,
+    });
 
-    logger::info_diff(
-        &prev_main_rs,
-        &main_rs,
-        path.join("src").join("main.rs").display(),
-        str_width,
-    );
+    event::emit(Event::Diff {
+        name: path.join("src").join("main.rs").display().to_string(),
+        orig: prev_main_rs,
+        edit: main_rs,
+    });
+
+    for (path, orig, edit) in modules {
+        event::emit(Event::Diff {
+            name: path.display().to_string(),
+            orig,
+            edit,
+        });
+    }
 
     return Ok(package_name);
 
@@ -294,13 +2319,25 @@ pub(crate) fn import_script(
 pub(crate) struct CargoToml {
     #[serde(default)]
     package: Option<CargoTomlPackage>,
+    #[serde(default)]
+    bin: Vec<CargoTomlTarget>,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
 struct CargoTomlPackage {
     name: String,
     #[serde(default)]
     default_run: Option<String>,
+    #[serde(default)]
+    autobins: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct CargoTomlTarget {
+    name: String,
+    #[serde(default)]
+    path: Option<PathBuf>,
 }
 
 pub(crate) trait MetadataExt {
@@ -328,32 +2365,25 @@ impl MetadataExt for Metadata {
             crate::process::cmd(&cargo_exe, &args).run()?;
         }
 
-        let args = [
-            Some("pkgid".as_ref()),
-            Some("--manifest-path".as_ref()),
-            Some(manifest_path.as_ref()),
-            spec.map(OsStr::new),
-        ];
-        let args = args.iter().flatten();
-        let output = crate::process::cmd(cargo_exe, args)
-            .dir(&self.workspace_root)
-            .stdout_capture()
-            .stderr_capture()
-            .unchecked()
-            .run()?;
-        let stdout = str::from_utf8(&output.stdout)?.trim_end();
-        let stderr = str::from_utf8(&output.stderr)?.trim_end();
-        ensure!(output.status.success(), "{}", stderr);
-
-        let url = stdout.parse::<Url>()?;
-        let fragment = url.fragment().expect("the URL should contain fragment");
-        let spec_name = match *fragment.splitn(2, ':').collect::<Vec<_>>() {
-            [name, _] => name,
-            [_] => url
-                .path_segments()
-                .and_then(Iterator::last)
-                .expect("should contain name"),
-            _ => unreachable!(),
+        let spec = match spec {
+            Some(spec) => spec.parse::<PackageIdSpec>()?,
+            None => {
+                let args = [
+                    "pkgid".as_ref(),
+                    "--manifest-path".as_ref(),
+                    manifest_path.as_os_str(),
+                ];
+                let output = crate::process::cmd(cargo_exe, &args)
+                    .dir(&self.workspace_root)
+                    .stdout_capture()
+                    .stderr_capture()
+                    .unchecked()
+                    .run()?;
+                let stdout = str::from_utf8(&output.stdout)?.trim_end();
+                let stderr = str::from_utf8(&output.stderr)?.trim_end();
+                ensure!(output.status.success(), "{}", stderr);
+                stdout.parse()?
+            }
         };
 
         self.packages
@@ -364,16 +2394,22 @@ impl MetadataExt for Metadata {
                     "the metadata should be in `--no-deps` mode",
                 );
             })
-            .find(|Package { name, .. }| name == spec_name)
-            .with_context(|| {
-                let spec = spec.expect("should be present here");
-                format!("package `{}` is not a member of the workspace", spec)
-            })
+            .find(|package| spec.matches(package))
+            .with_context(|| format!("package `{}` is not a member of the workspace", spec))
     }
 }
 
 pub(crate) trait PackageExt {
     fn find_default_bin(&self) -> anyhow::Result<(&Path, String)>;
+    fn find_bin(&self, name: &str) -> anyhow::Result<(&Path, String)>;
+    fn export_script(&self, workspace_root: &Path, wtr: impl Write) -> anyhow::Result<()>;
+    fn export_bin_script(
+        &self,
+        workspace_root: &Path,
+        bin: Option<&str>,
+        prune_manifest: bool,
+    ) -> anyhow::Result<ExportedScript>;
+    fn read_gist_extra_files(&self) -> anyhow::Result<BTreeMap<PathBuf, String>>;
 }
 
 impl PackageExt for Package {
@@ -389,7 +2425,7 @@ impl PackageExt for Package {
             .targets
             .iter()
             .filter(|Target { kind, name, .. }| {
-                kind.contains(&"bin".to_owned()) && default_run.map_or(true, |d| d == name)
+                kind.contains(&"bin".to_owned()) && default_run.is_none_or(|d| d == name)
             })
             .exactly_one()
             .map_err(|err| match err.count() {
@@ -399,4 +2435,80 @@ impl PackageExt for Package {
 
         Ok((src_path, cargo_toml_str))
     }
+
+    fn find_bin(&self, name: &str) -> anyhow::Result<(&Path, String)> {
+        let Target { src_path, .. } = self
+            .targets
+            .iter()
+            .find(|t| t.kind.contains(&"bin".to_owned()) && t.name == name)
+            .with_context(|| format!("no `bin` target named `{}` in `{}`", name, self.name))?;
+        Ok((src_path, crate::fs::read(&self.manifest_path)?))
+    }
+
+    fn export_script(&self, workspace_root: &Path, wtr: impl Write) -> anyhow::Result<()> {
+        self.export_bin_script(workspace_root, None, false)?
+            .write(None, wtr)
+    }
+
+    fn export_bin_script(
+        &self,
+        workspace_root: &Path,
+        bin: Option<&str>,
+        prune_manifest: bool,
+    ) -> anyhow::Result<ExportedScript> {
+        let (src_path, cargo_toml) = match bin {
+            Some(bin) => self.find_bin(bin)?,
+            None => self.find_default_bin()?,
+        };
+        let cargo_toml = if prune_manifest {
+            let bin = match bin {
+                Some(bin) => bin,
+                None => self
+                    .targets
+                    .iter()
+                    .find(|t| t.src_path == src_path)
+                    .map(|t| &*t.name)
+                    .expect("should be found"),
+            };
+            self::prune_manifest(&cargo_toml, bin)?
+        } else {
+            cargo_toml
+        };
+        let package_dir = self
+            .manifest_path
+            .parent()
+            .expect("`manifest_path` should end with \"Cargo.toml\"");
+        ExportedScript::new(workspace_root, package_dir, src_path, &cargo_toml)
+    }
+
+    fn read_gist_extra_files(&self) -> anyhow::Result<BTreeMap<PathBuf, String>> {
+        let package_dir = self
+            .manifest_path
+            .parent()
+            .expect("`manifest_path` should end with \"Cargo.toml\"");
+        gist_extra_files(&crate::fs::read(&self.manifest_path)?)?
+            .into_iter()
+            .map(|path| {
+                let content = crate::fs::read(package_dir.join(&path))?;
+                Ok((path, content))
+            })
+            .collect()
+    }
+}
+
+/// Re-inlines `mod x;` in `code` with `dir/x.rs` or `dir/x/mod.rs`, so that packages imported
+/// with `--split-modules` are exported as single files.
+fn inline_modules(code: &str, dir: &Path) -> anyhow::Result<String> {
+    rust::inline_modules(code, |name| {
+        for path in &[
+            dir.join(name).with_extension("rs"),
+            dir.join(name).join("mod.rs"),
+        ] {
+            if path.exists() {
+                let content = crate::fs::read(path)?;
+                return inline_modules(&content, &dir.join(name)).map(Some);
+            }
+        }
+        Ok(None)
+    })
 }
@@ -0,0 +1,84 @@
+mod common;
+
+use crate::common::Env;
+
+#[test]
+fn archive_and_unarchive() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/Cargo.toml",
+        "[workspace]\nmembers = [\"app\", \"lib\", \"old\"]\nexclude = []\n",
+    )?;
+    for (name, deps) in &[
+        ("app", "lib = { path = \"../lib\" }\n"),
+        ("lib", ""),
+        ("old", ""),
+    ] {
+        env.write(
+            &format!("ws/{}/Cargo.toml", name),
+            &format!(
+                "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2018\"\n\n\
+                 [dependencies]\n{}",
+                name, deps,
+            ),
+        )?;
+        env.write(&format!("ws/{}/src/lib.rs", name), "")?;
+    }
+    env.write(
+        "ws/bikecase-workspace.toml",
+        "[gist-ids]\nold = \"0bd00001\"\n",
+    )?;
+
+    let error = env
+        .cargo_bikecase_with_config(&["archive", "lib"])
+        .unwrap_err();
+    insta::assert_snapshot!(
+        error,
+        @"`app` depends on `lib`. Archive them first, or use `--force` to leave the workspace unbuildable"
+    );
+
+    let outcome = env.cargo_bikecase_with_config(&["archive", "old"]).unwrap();
+    insta::assert_snapshot!(outcome.events(), @r###"
+    spawn cargo
+    remove "old" from members
+    add "archive/old" to exclude
+    INFO `workspace.members`: ["app", "lib"]
+    INFO `workspace.exclude`: ["archive/old"]
+    write [ROOT]/ws/Cargo.toml
+    move [ROOT]/ws/old [ROOT]/ws/archive/old
+    write [ROOT]/ws/bikecase-workspace.toml
+    write [ROOT]/bikecase.toml
+    "###);
+    assert!(!env.path("ws/old").exists());
+    assert!(env.path("ws/archive/old/Cargo.toml").exists());
+    insta::assert_snapshot!(env.read("ws/Cargo.toml")?, @r###"
+    [workspace]
+    members = ["app", "lib"]
+    exclude = ["archive/old"]
+    "###);
+    insta::assert_snapshot!(env.read("ws/bikecase-workspace.toml")?, @r###"
+    [gist-ids]
+
+    [archived-gist-ids]
+    old = '0bd00001'
+    "###);
+
+    let error = env
+        .cargo_bikecase_with_config(&["unarchive", "app"])
+        .unwrap_err();
+    insta::assert_snapshot!(error, @"`app` is not archived in [ROOT]/ws/archive");
+
+    env.cargo_bikecase_with_config(&["unarchive", "old"])
+        .unwrap();
+    assert!(env.path("ws/old/Cargo.toml").exists());
+    insta::assert_snapshot!(env.read("ws/Cargo.toml")?, @r###"
+    [workspace]
+    members = ["app", "lib", "old"]
+    exclude = []
+    "###);
+    insta::assert_snapshot!(env.read("ws/bikecase-workspace.toml")?, @r###"
+    [gist-ids]
+    old = '0bd00001'
+    "###);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+#![cfg(unix)]
+
+mod common;
+
+use crate::common::{Env, HELLO_RS};
+
+/// A report of `cargo audit --json` with a vulnerability in `hello` and one in a crate that
+/// `hello` does not depend on.
+static REPORT: &str = r#"{
+  "vulnerabilities": {
+    "found": true,
+    "count": 2,
+    "list": [
+      {
+        "advisory": { "id": "RUSTSEC-0000-0001", "title": "Hello is vulnerable" },
+        "package": { "name": "hello", "version": "0.1.0" }
+      },
+      {
+        "advisory": { "id": "RUSTSEC-0000-0002", "title": "Other is vulnerable" },
+        "package": { "name": "other", "version": "1.0.0" }
+      }
+    ]
+  },
+  "warnings": {}
+}"#;
+
+#[test]
+fn reports_only_the_dependencies_of_the_package() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.fake_tool(
+        "cargo-audit",
+        &format!("#!/bin/sh\ncat <<'EOF'\n{}\nEOF\nexit 1\n", REPORT),
+    )?;
+    env.write("hello.rs", HELLO_RS)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+
+    let outcome = env.cargo_bikecase(&[
+        "audit",
+        "-p",
+        "hello",
+        "--offline",
+        "--color",
+        "never",
+        "--then-run",
+    ]);
+    insta::assert_snapshot!(outcome.stdout, @r"
+    ID	KIND	CRATE	TITLE
+    RUSTSEC-0000-0001	vulnerability	hello 0.1.0	Hello is vulnerable
+    ");
+    insta::assert_snapshot!(
+        outcome.unwrap_err(),
+        @"`hello`: 1 vulnerable dependencies found"
+    );
+    Ok(())
+}
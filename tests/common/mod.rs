@@ -0,0 +1,557 @@
+//! A harness that drives `bikecase` and `cargo bikecase` in-process.
+//!
+//! Each [`Env`] is a temporary directory with a fake home, a workspace (`ws`), and a config file
+//! (`bikecase.toml`). Events are captured through `bikecase::set_event_sink`, and GitHub is
+//! replaced with [`MockGithub`]. Fake external tools are put in `bin` with [`Env::fake_tool`].
+
+#![allow(dead_code)]
+
+use bikecase::{Bikecase, Cargo, Context, Event};
+use once_cell::sync::Lazy;
+use structopt::StructOpt as _;
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{self, BufRead as _, BufReader, Read as _, Write as _};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::{env, fs, iter, thread};
+
+/// The event sink is process-global, so the commands are run one by one.
+static LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// A minimal script with the manifest.
+pub static HELLO_RS: &str = r#"//! ```cargo
+//! [package]
+//! name = "hello"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {
+    println!("Hello!");
+}
+"#;
+
+pub struct Env {
+    tempdir: tempfile::TempDir,
+}
+
+impl Env {
+    pub fn new() -> anyhow::Result<Self> {
+        let tempdir = tempfile::Builder::new()
+            .prefix("bikecase-tests-")
+            .tempdir()?;
+        let env = Self { tempdir };
+
+        fs::create_dir_all(env.path("home"))?;
+        fs::create_dir_all(env.path("data"))?;
+        env.write("ws/Cargo.toml", "[workspace]\nmembers = []\nexclude = []\n")?;
+        env.write(
+            "template/Cargo.toml",
+            "[package]\nname = \"template\"\nversion = \"0.0.0\"\nedition = \"2018\"\n",
+        )?;
+        env.write("template/src/main.rs", "fn main() {}\n")?;
+        env.write(
+            "bikecase.toml",
+            &format!(
+                "default-workspace = {:?}\ntemplate-package = {:?}\n\n[workspaces]\n",
+                env.path("ws"),
+                env.path("template"),
+            ),
+        )?;
+        Ok(env)
+    }
+
+    pub fn root(&self) -> &Path {
+        self.tempdir.path()
+    }
+
+    pub fn path(&self, rel: &str) -> PathBuf {
+        self.root().join(rel)
+    }
+
+    pub fn write(&self, rel: &str, content: &str) -> io::Result<()> {
+        let path = self.path(rel);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)
+    }
+
+    pub fn read(&self, rel: &str) -> io::Result<String> {
+        fs::read_to_string(self.path(rel)).map(|s| self.redact(&s))
+    }
+
+    /// Writes an executable `bin/<name>` running the shell script `script`. `bin` is prepended to
+    /// `$PATH` only while the commands of this `Env` run.
+    #[cfg(unix)]
+    pub fn fake_tool(&self, name: &str, script: &str) -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let rel = format!("bin/{}", name);
+        self.write(&rel, script)?;
+        fs::set_permissions(self.path(&rel), fs::Permissions::from_mode(0o755))
+    }
+
+    /// Replaces the path of the temporary directory with `[ROOT]`.
+    pub fn redact(&self, s: &str) -> String {
+        let root = self.root().to_string_lossy();
+        s.replace(&*root, "[ROOT]")
+    }
+
+    /// Runs `cargo bikecase <args>... --config <PATH>` in `ws`.
+    ///
+    /// Use this for the commands that read the config, or the config in the real home is used.
+    pub fn cargo_bikecase_with_config(&self, args: &[&str]) -> Outcome {
+        let config = self.path("bikecase.toml");
+        let mut args = args.to_owned();
+        args.push("--config");
+        args.push(config.to_str().unwrap());
+        self.cargo_bikecase(&args)
+    }
+
+    /// Runs `cargo bikecase <args>...` in `ws`.
+    pub fn cargo_bikecase(&self, args: &[&str]) -> Outcome {
+        self.cargo_bikecase_with_stdin(args, "")
+    }
+
+    /// [`cargo_bikecase`](Self::cargo_bikecase) with `stdin` as the standard input.
+    pub fn cargo_bikecase_with_stdin(&self, args: &[&str], stdin: &str) -> Outcome {
+        let mut all_args = vec![OsString::from("cargo"), "bikecase".into()];
+        all_args.extend(args.iter().map(Into::into));
+
+        self.capture(self.path("ws"), stdin, |ctx| {
+            let Cargo::Bikecase(opt) = Cargo::from_iter_safe(all_args)?;
+            bikecase::cargo_bikecase(opt, ctx)
+        })
+    }
+
+    /// Runs `bikecase --config <config> <args>...` in the root with `stdin` as the standard input.
+    pub fn bikecase(&self, args: &[&str], stdin: &str) -> Outcome {
+        let mut all_args = vec![
+            OsString::from("bikecase"),
+            "--config".into(),
+            self.path("bikecase.toml").into(),
+        ];
+        all_args.extend(args.iter().map(Into::into));
+
+        self.capture(self.root().to_owned(), stdin, |ctx| {
+            let opt = Bikecase::from_iter_safe(all_args)?;
+            bikecase::bikecase(opt, ctx)
+        })
+    }
+
+    fn capture(
+        &self,
+        cwd: PathBuf,
+        stdin: &str,
+        f: impl FnOnce(Context<&mut Vec<u8>, StdinFn, PasswordFn>) -> anyhow::Result<()>,
+    ) -> Outcome {
+        let _guard = lock();
+        let _path = RestorePath::prepend(self.path("bin"));
+
+        let events = Arc::new(Mutex::new(vec![]));
+        bikecase::set_event_sink({
+            let events = events.clone();
+            move |event: &Event| events.lock().unwrap().push(event.clone())
+        });
+
+        let stdin = stdin.to_owned();
+        let mut stdout = vec![];
+        let result = f(Context {
+            cwd,
+            home_dir: Some(self.path("home")),
+            data_local_dir: Some(self.path("data")),
+            stdout: &mut stdout,
+            read_input: Box::new(move || Ok(stdin)),
+            read_password: |_: &str| Err(io::Error::other("no TTY in tests")),
+            init_logger: |_| (),
+            init_json_logger: || (),
+            str_width: bikecase::str_width,
+            config_cache: Default::default(),
+        });
+
+        bikecase::set_event_sink(bikecase::LogSink::default());
+        let events = events.lock().unwrap().clone();
+        Outcome {
+            error: result
+                .err()
+                .map(|e| self.redact(&format!("{:#}", e)).trim_end().to_owned()),
+            stdout: self.redact(&String::from_utf8_lossy(&stdout)),
+            events: events.iter().filter_map(|e| self.render(e)).collect(),
+            spawned: events
+                .iter()
+                .filter_map(|e| match e {
+                    Event::ProcessSpawned { program, args, .. } => Some(
+                        iter::once(Path::new(program).file_stem()?.to_os_string())
+                            .chain(args.iter().cloned())
+                            .map(|a| self.redact(&a.to_string_lossy()))
+                            .collect(),
+                    ),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Renders an event for snapshots. `Finished` and the arguments of processes are omitted,
+    /// since they depend on the environment.
+    fn render(&self, event: &Event) -> Option<String> {
+        let path = |p: &Path| self.redact(&p.to_string_lossy());
+        let dry_run = |dry_run: bool| if dry_run { "[dry-run] " } else { "" };
+        Some(match event {
+            Event::FileWritten {
+                path: p,
+                dry_run: d,
+            } => format!("{}write {}", dry_run(*d), path(p)),
+            Event::FileCopied {
+                src,
+                dst,
+                dry_run: d,
+            } => {
+                format!("{}copy {} {}", dry_run(*d), path(src), path(dst))
+            }
+            Event::FileMoved {
+                src,
+                dst,
+                dry_run: d,
+            } => {
+                format!("{}move {} {}", dry_run(*d), path(src), path(dst))
+            }
+            Event::FileRemoved {
+                path: p,
+                dry_run: d,
+            } => format!("{}remove {}", dry_run(*d), path(p)),
+            Event::SymlinkCreated {
+                src,
+                dst,
+                dry_run: d,
+            } => {
+                format!("{}symlink {} {}", dry_run(*d), path(dst), path(src))
+            }
+            Event::WorkspaceCreated {
+                workspace_root,
+                dry_run: d,
+            } => format!("{}create workspace {}", dry_run(*d), path(workspace_root)),
+            Event::MemberAdded {
+                member,
+                field,
+                dry_run: d,
+            } => format!("{}add {:?} to {}", dry_run(*d), member, field),
+            Event::MemberRemoved {
+                member,
+                field,
+                dry_run: d,
+            } => format!("{}remove {:?} from {}", dry_run(*d), member, field),
+            Event::HttpRequest {
+                method,
+                url,
+                dry_run: d,
+            } => {
+                let url = url.replacen(&*MockGithub::get().url, "https://api.github.com/", 1);
+                format!("{}{} {}", dry_run(*d), method, url)
+            }
+            Event::HttpResponse { status, .. } => format!("{}", status),
+            Event::ProcessSpawned {
+                program,
+                dry_run: d,
+                ..
+            } => format!(
+                "{}spawn {}",
+                dry_run(*d),
+                Path::new(program).file_stem()?.to_string_lossy(),
+            ),
+            Event::Diff { name, orig, edit } => format!(
+                "diff {}\n{}",
+                self.redact(name),
+                similar::TextDiff::from_lines(orig, edit)
+                    .unified_diff()
+                    .context_radius(1),
+            ),
+            Event::Message { level, message } => format!("{} {}", level, self.redact(message)),
+            Event::Finished { .. } => return None,
+            event => format!("{:?}", event),
+        })
+    }
+}
+
+type StdinFn = Box<dyn FnOnce() -> io::Result<String>>;
+type PasswordFn = fn(&str) -> io::Result<String>;
+
+fn lock() -> MutexGuard<'static, ()> {
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Restores `$PATH` on drop. `$PATH` is process-global, so this is used only under [`lock`].
+struct RestorePath(Option<OsString>);
+
+impl RestorePath {
+    fn prepend(dir: PathBuf) -> Self {
+        let orig = env::var_os("PATH");
+        if dir.exists() {
+            let paths =
+                iter::once(dir).chain(env::split_paths(orig.as_deref().unwrap_or_default()));
+            env::set_var("PATH", env::join_paths(paths).unwrap());
+        }
+        Self(orig)
+    }
+}
+
+impl Drop for RestorePath {
+    fn drop(&mut self) {
+        match self.0.take() {
+            Some(path) => env::set_var("PATH", path),
+            None => env::remove_var("PATH"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Outcome {
+    /// The error with its causes, separated by `: `.
+    pub error: Option<String>,
+    pub stdout: String,
+    pub events: Vec<String>,
+    /// The spawned processes as `[<program>, <args>...]`, for checking the arguments where the
+    /// environment does not matter.
+    pub spawned: Vec<Vec<String>>,
+}
+
+impl Outcome {
+    /// Panics with the error and the events if the command failed.
+    pub fn unwrap(self) -> Self {
+        if let Some(error) = &self.error {
+            panic!("{}\n\n{}", error, self.events.join("\n"));
+        }
+        self
+    }
+
+    pub fn unwrap_err(self) -> String {
+        let events = self.events();
+        self.error
+            .unwrap_or_else(|| panic!("expected an error:\n\n{}", events))
+    }
+
+    pub fn events(&self) -> String {
+        self.events.join("\n")
+    }
+}
+
+/// A fake of the gists API, shared in the process.
+///
+/// The responses are registered by paths such as `/gists/0123abcd`, so tests should use distinct
+/// gist IDs. A path with a query is matched before the one without.
+pub struct MockGithub {
+    url: String,
+    responses: Mutex<HashMap<(String, String), Response>>,
+    raws: Mutex<HashMap<String, (String, Option<usize>)>>,
+    user_agents: Mutex<HashMap<String, String>>,
+}
+
+type Response = (u16, Option<String>, String);
+
+impl MockGithub {
+    pub fn get() -> &'static Self {
+        static MOCK: Lazy<MockGithub> = Lazy::new(MockGithub::start);
+        &MOCK
+    }
+
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        env::set_var("BIKECASE_GITHUB_API_URL", &url);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let _ = MockGithub::get().respond(stream);
+            }
+        });
+
+        Self {
+            url,
+            responses: Mutex::new(HashMap::new()),
+            raws: Mutex::new(HashMap::new()),
+            user_agents: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The URL of `path` on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.url, path.trim_start_matches('/'))
+    }
+
+    /// Registers `GET <path>` returning `content` as a raw file, honoring `Range: bytes=<N>-`.
+    ///
+    /// If `cut_at` is set, the first response is closed after that many bytes of the content.
+    pub fn raw(&self, path: &str, content: &str, cut_at: Option<usize>) {
+        self.raws
+            .lock()
+            .unwrap()
+            .insert(path.to_owned(), (content.to_owned(), cut_at));
+    }
+
+    /// Registers a response for `method` and `path`.
+    pub fn on(&self, method: &str, path: &str, status: u16, body: serde_json::Value) {
+        self.responses.lock().unwrap().insert(
+            (method.to_owned(), path.to_owned()),
+            (status, None, body.to_string()),
+        );
+    }
+
+    /// Returns `User-Agent` of the last request to `path`.
+    pub fn user_agent(&self, path: &str) -> Option<String> {
+        self.user_agents.lock().unwrap().get(path).cloned()
+    }
+
+    /// Registers `GET <path>` returning `items`, with a `Link` header pointing to `next`.
+    pub fn page(&self, path: &str, items: serde_json::Value, next: Option<&str>) {
+        let link = next.map(|next| {
+            let next = format!("{}{}", self.url, next.trim_start_matches('/'));
+            format!("<{}>; rel=\"next\", <{}>; rel=\"last\"", next, next)
+        });
+        self.responses.lock().unwrap().insert(
+            ("GET".to_owned(), path.to_owned()),
+            (200, link, items.to_string()),
+        );
+    }
+
+    /// Registers `GET /gists/<id>` returning a gist with `files`.
+    pub fn gist(&self, id: &str, description: &str, files: &[(&str, &str)]) {
+        let files = files
+            .iter()
+            .map(|(name, content)| {
+                (
+                    (*name).to_owned(),
+                    serde_json::json!({ "filename": name, "content": content, "truncated": false }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+        self.on(
+            "GET",
+            &format!("/gists/{}", id),
+            200,
+            serde_json::json!({
+                "description": description,
+                "public": false,
+                "html_url": format!("https://gist.github.com/{}", id),
+                "owner": { "login": "octocat" },
+                "created_at": "2020-04-01T12:34:56Z",
+                "files": files,
+                "truncated": false,
+            }),
+        );
+    }
+
+    fn respond(&self, stream: std::net::TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = "".to_owned();
+        reader.read_line(&mut request_line)?;
+        let mut words = request_line.split_whitespace();
+        let method = words.next().unwrap_or_default().to_owned();
+        let path_and_query = words.next().unwrap_or_default().to_owned();
+        let path = path_and_query.split('?').next().unwrap_or_default();
+
+        let mut content_length = 0;
+        let mut range_start = None;
+        loop {
+            let mut header = "".to_owned();
+            reader.read_line(&mut header)?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+                if name.eq_ignore_ascii_case("range") {
+                    range_start = value
+                        .trim()
+                        .strip_prefix("bytes=")
+                        .and_then(|r| r.strip_suffix('-'))
+                        .and_then(|r| r.parse::<usize>().ok());
+                }
+                if name.eq_ignore_ascii_case("user-agent") {
+                    self.user_agents
+                        .lock()
+                        .unwrap()
+                        .insert(path.to_owned(), value.trim().to_owned());
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+
+        let raw = self
+            .raws
+            .lock()
+            .unwrap()
+            .get_mut(path)
+            .map(|(content, cut_at)| {
+                let cut_at = cut_at.take();
+                (content.clone(), cut_at)
+            });
+        if let Some((content, cut_at)) = raw {
+            let start = range_start.unwrap_or(0);
+            let (status, content_range) = match range_start {
+                Some(start) => (
+                    "206 Partial Content",
+                    format!(
+                        "Content-Range: bytes {}-{}/{}\r\n",
+                        start,
+                        content.len() - 1,
+                        content.len(),
+                    ),
+                ),
+                None => ("200 OK", String::new()),
+            };
+            let body = &content.as_bytes()[start..];
+            let mut stream = stream;
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: text/plain\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                content_range,
+                body.len(),
+            )?;
+            stream.write_all(&body[..cut_at.unwrap_or(body.len()).min(body.len())])?;
+            return stream.flush();
+        }
+
+        let (status, link, body) = {
+            let responses = self.responses.lock().unwrap();
+            responses
+                .get(&(method.clone(), path_and_query.clone()))
+                .or_else(|| responses.get(&(method, path.to_owned())))
+                .cloned()
+                .unwrap_or_else(|| (404, None, r#"{"message":"Not Found"}"#.to_owned()))
+        };
+        let body = if status == 204 { String::new() } else { body };
+        let link = link.map_or_else(String::new, |link| format!("Link: {}\r\n", link));
+        let reason = match status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            404 => "Not Found",
+            _ => "Unknown",
+        };
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason,
+            link,
+            body.len(),
+            body,
+        )?;
+        stream.flush()
+    }
+}
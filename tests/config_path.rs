@@ -0,0 +1,39 @@
+//! The default config path is computed once per process, so this is the only test in the binary.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use crate::common::Env;
+
+use std::env;
+
+#[test]
+fn migrates_legacy_config() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env::remove_var("BIKECASE_CONFIG_DIR");
+    env::set_var("XDG_CONFIG_HOME", env.path("xdg"));
+    env.write(
+        "xdg/bikecase.toml",
+        &format!(
+            "default-workspace = {:?}\ntemplate-package = {:?}\n\n[workspaces]\n",
+            env.path("ws"),
+            env.path("template"),
+        ),
+    )?;
+
+    let outcome = env.cargo_bikecase(&["config", "export"]).unwrap();
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    default-workspace = '[ROOT]/ws'
+    template-package = '[ROOT]/template'
+    derive-package-names = false
+
+    [workspaces]
+    "###);
+    assert!(!env.path("xdg/bikecase.toml").exists());
+    assert!(env.path("xdg/bikecase/bikecase.toml").exists());
+
+    env.cargo_bikecase(&["config", "export"]).unwrap();
+    assert!(env.path("xdg/bikecase/bikecase.toml").exists());
+    Ok(())
+}
@@ -0,0 +1,344 @@
+mod common;
+
+use crate::common::{Env, HELLO_RS};
+
+#[test]
+fn reads_manifests_without_cargo_metadata() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+
+    let outcome = env.cargo_bikecase(&["export", "-p", "hello"]).unwrap();
+    assert_eq!(outcome.events(), "");
+    assert_eq!(outcome.stdout, HELLO_RS);
+    Ok(())
+}
+
+#[test]
+fn falls_back_to_cargo_metadata_for_bin_tables() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+    let cargo_toml = env.read("ws/hello/Cargo.toml")?;
+    env.write(
+        "ws/hello/Cargo.toml",
+        &format!(
+            "{}\n[[bin]]\nname = \"hello\"\npath = \"src/main.rs\"\n",
+            cargo_toml
+        ),
+    )?;
+
+    let outcome = env.cargo_bikecase(&["export", "-p", "hello"]).unwrap();
+    assert!(outcome.events().starts_with("spawn cargo"));
+    Ok(())
+}
+
+#[test]
+fn emit_fence() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", &HELLO_RS.replace("```cargo", "```cargo,ignore"))?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+
+    let outcome = env.cargo_bikecase(&["export", "-p", "hello"]).unwrap();
+    assert!(outcome.stdout.starts_with("//! ```cargo,ignore\n"));
+
+    let outcome = env
+        .cargo_bikecase(&["export", "-p", "hello", "--emit-fence", "toml,cargo"])
+        .unwrap();
+    assert_eq!(
+        outcome.stdout,
+        HELLO_RS.replace("```cargo", "```toml,cargo"),
+    );
+
+    let outcome = env
+        .cargo_bikecase(&["export", "-p", "hello", "--emit-fence", "cargo"])
+        .unwrap();
+    assert_eq!(outcome.stdout, HELLO_RS);
+    Ok(())
+}
+
+#[test]
+fn preserves_crlf_and_bom() -> anyhow::Result<()> {
+    let script = format!("\u{feff}{}", HELLO_RS.replace('\n', "\r\n"));
+
+    let env = Env::new()?;
+    env.write("hello.rs", &script)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+    assert_eq!(
+        env.read("ws/hello/src/main.rs")?,
+        "\u{feff}//! ```cargo\r\n//! # Leave blank.\r\n//! ```\r\n\r\nfn main() {\r\n    println!(\"Hello!\");\r\n}\r\n",
+    );
+    assert!(env
+        .read("ws/hello/Cargo.toml")?
+        .contains("name = \"hello\"\nversion = \"0.1.0\"\n"));
+
+    let outcome = env.cargo_bikecase(&["export", "-p", "hello"]).unwrap();
+    assert_eq!(outcome.stdout, script);
+
+    let outcome = env
+        .cargo_bikecase(&["export", "-p", "hello", "--emit-fence", "toml,cargo"])
+        .unwrap();
+    assert_eq!(outcome.stdout, script.replace("```cargo", "```toml,cargo"),);
+    Ok(())
+}
+
+#[test]
+fn reinlines_split_modules() -> anyhow::Result<()> {
+    static SCRIPT: &str = r#"//! ```cargo
+//! [package]
+//! name = "hello"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+use crate::greeting::greet;
+
+fn main() {
+    greet();
+}
+
+mod greeting {
+    pub(crate) fn greet() {
+        println!("Hello!");
+    }
+
+    #[cfg(test)]
+    mod tests {
+        #[test]
+        fn it_works() {}
+    }
+}
+"#;
+
+    let env = Env::new()?;
+    env.write("hello.rs", SCRIPT)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", "--split-modules", hello.to_str().unwrap()])
+        .unwrap();
+    insta::assert_snapshot!(env.read("ws/hello/src/main.rs")?, @r###"
+    //! ```cargo
+    //! # Leave blank.
+    //! ```
+
+    use crate::greeting::greet;
+
+    fn main() {
+        greet();
+    }
+
+    mod greeting;
+    "###);
+    insta::assert_snapshot!(env.read("ws/hello/src/greeting.rs")?, @r###"
+    pub(crate) fn greet() {
+        println!("Hello!");
+    }
+
+    #[cfg(test)]
+    mod tests {
+        #[test]
+        fn it_works() {}
+    }
+    "###);
+
+    let outcome = env.cargo_bikecase(&["export", "-p", "hello"]).unwrap();
+    assert_eq!(outcome.stdout, SCRIPT);
+
+    let script = SCRIPT.replace('\n', "\r\n");
+    env.write("hello.rs", &script)?;
+    env.cargo_bikecase(&["rm", "hello"]).unwrap();
+    env.cargo_bikecase(&["import", "--split-modules", hello.to_str().unwrap()])
+        .unwrap();
+    assert!(env
+        .read("ws/hello/src/greeting.rs")?
+        .starts_with("pub(crate) fn greet() {\r\n"));
+    let outcome = env.cargo_bikecase(&["export", "-p", "hello"]).unwrap();
+    assert_eq!(outcome.stdout, script);
+    Ok(())
+}
+
+#[test]
+fn prune_manifest() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/tools/Cargo.toml",
+        r#"[package]
+name = "tools"
+version = "0.1.0"
+edition = "2018"
+default-run = "tools"
+
+[[bin]]
+name = "tools"
+path = "src/main.rs"
+
+[[bin]]
+name = "fetch"
+required-features = ["net"]
+
+[features]
+net = ["dep:ureq", "json"]
+json = ["serde_json"]
+tui = ["crossterm"]
+
+[dependencies]
+itertools = "0.9.0"
+ureq = { version = "0.12.0", optional = true }
+serde_json = { version = "1.0.50", optional = true }
+crossterm = { version = "0.17.0", optional = true }
+"#,
+    )?;
+    env.write("ws/tools/src/main.rs", "fn main() {}\n")?;
+    env.write(
+        "ws/tools/src/bin/fetch.rs",
+        "//! ```cargo\n//! # Leave blank.\n//! ```\n\nfn main() {}\n",
+    )?;
+    env.cargo_bikecase(&["include", "tools"]).unwrap();
+
+    let outcome = env
+        .cargo_bikecase(&[
+            "export",
+            "-p",
+            "tools",
+            "--bin",
+            "fetch",
+            "--prune-manifest",
+        ])
+        .unwrap();
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    //! ```cargo
+    //! [package]
+    //! name = "tools"
+    //! version = "0.1.0"
+    //! edition = "2018"
+    //!
+    //! [features]
+    //! net = ["dep:ureq", "json"]
+    //! json = ["serde_json"]
+    //! default = ["net"]
+    //!
+    //! [dependencies]
+    //! itertools = "0.9.0"
+    //! ureq = { version = "0.12.0", optional = true }
+    //! serde_json = { version = "1.0.50", optional = true }
+    //! ```
+
+    fn main() {}
+    "###);
+    Ok(())
+}
+
+fn write_path_dependent(env: &Env) -> anyhow::Result<()> {
+    env.write(
+        "ws/util/Cargo.toml",
+        "[package]\nname = \"util\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+         [dependencies]\nitertools = \"0.9.0\"\n",
+    )?;
+    env.write(
+        "ws/util/src/lib.rs",
+        "mod fmt;\n\npub use crate::fmt::shout;\n",
+    )?;
+    env.write(
+        "ws/util/src/fmt.rs",
+        "pub fn shout(s: &str) -> String {\n    s.to_uppercase()\n}\n",
+    )?;
+    env.write(
+        "ws/app/Cargo.toml",
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+         [dependencies]\nutil = { path = \"../util\" }\n",
+    )?;
+    env.write(
+        "ws/app/src/main.rs",
+        "//! ```cargo\n//! # Leave blank.\n//! ```\n\n\
+         fn main() {\n    println!(\"{}\", util::shout(\"hi\"));\n}\n",
+    )?;
+    env.cargo_bikecase(&["include", "app"]).unwrap();
+    Ok(())
+}
+
+#[test]
+fn fails_for_path_dependencies() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    write_path_dependent(&env)?;
+
+    let error = env
+        .cargo_bikecase_with_config(&["export", "-p", "app"])
+        .unwrap_err();
+    insta::assert_snapshot!(error, @"`dependencies.util` is a `path` dependency on \"../util\", which will be broken outside of the workspace. Set `path-dependencies.util` in the config to a published version or a Git repository, or enable `--inline-path-deps`");
+    Ok(())
+}
+
+#[test]
+fn replaces_path_dependencies_with_config() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    write_path_dependent(&env)?;
+    env.write(
+        "bikecase.toml",
+        "[path-dependencies.util]\ngit = \"https://github.com/example/util\"\ntag = \"v0.1.0\"\n",
+    )?;
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["export", "-p", "app"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events(), @"INFO `dependencies.util`: replaced `path` with `path-dependencies.util`");
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    //! ```cargo
+    //! [package]
+    //! name = "app"
+    //! version = "0.1.0"
+    //! edition = "2018"
+    //!
+    //! [dependencies]
+    //! util = { git = "https://github.com/example/util", tag = "v0.1.0" }
+    //! ```
+
+    fn main() {
+        println!("{}", util::shout("hi"));
+    }
+    "###);
+    Ok(())
+}
+
+#[test]
+fn inline_path_deps() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    write_path_dependent(&env)?;
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["export", "-p", "app", "--inline-path-deps"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events(), @r###"INFO `dependencies.util`: inlined "../util""###);
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    //! ```cargo
+    //! [package]
+    //! name = "app"
+    //! version = "0.1.0"
+    //! edition = "2018"
+    //!
+    //! [dependencies]
+    //! itertools = "0.9.0"
+    //! ```
+
+    fn main() {
+        println!("{}", util::shout("hi"));
+    }
+
+    mod util {
+        mod fmt {
+            pub fn shout(s: &str) -> String {
+                s.to_uppercase()
+            }
+        }
+
+        pub use crate::util::fmt::shout;
+    }
+    "###);
+    Ok(())
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use crate::common::Env;
+
+static HELLO_RS: &str = r#"//! ```cargo
+//! [package]
+//! name    =  "hello"   # the name
+//!   version = "0.1.0"
+//! edition = "2018"
+//!
+//!
+//! # Inherited on clean
+//! [dependencies]
+//! anyhow = {version="1.0.30",features=[ "backtrace" ]}
+//! ```
+
+fn main() {}
+"#;
+
+#[test]
+fn clean_and_smudge() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/Cargo.toml",
+        "[workspace]\nmembers = []\nexclude = []\n\n[workspace.dependencies]\nanyhow = \"1.0.30\"\n",
+    )?;
+
+    let clean = env.cargo_bikecase_with_stdin(&["filter", "clean"], HELLO_RS);
+    let clean = clean.unwrap().stdout;
+    insta::assert_snapshot!(clean, @r###"
+    //! ```cargo
+    //! [package]
+    //! name = "hello" # the name
+    //! version = "0.1.0"
+    //! edition = "2018"
+    //!
+    //! # Inherited on clean
+    //! [dependencies]
+    //! anyhow = { workspace = true, features = ["backtrace"] }
+    //! ```
+
+    fn main() {}
+    "###);
+
+    let outcome = env.cargo_bikecase_with_stdin(&["filter", "clean"], &clean);
+    assert_eq!(outcome.unwrap().stdout, clean);
+
+    let smudge = env.cargo_bikecase_with_stdin(&["filter", "smudge"], &clean);
+    insta::assert_snapshot!(smudge.unwrap().stdout, @r###"
+    //! ```cargo
+    //! [package]
+    //! name = "hello" # the name
+    //! version = "0.1.0"
+    //! edition = "2018"
+    //!
+    //! # Inherited on clean
+    //! [dependencies]
+    //! anyhow = { version = "1.0.30", features = ["backtrace"] }
+    //! ```
+
+    fn main() {}
+    "###);
+    Ok(())
+}
+
+#[test]
+fn passes_through_scripts_without_manifests() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    let outcome = env.cargo_bikecase_with_stdin(&["filter", "clean"], "fn main() {}\n");
+    assert_eq!(outcome.unwrap().stdout, "fn main() {}\n");
+    Ok(())
+}
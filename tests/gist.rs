@@ -0,0 +1,719 @@
+mod common;
+
+use crate::common::{Env, MockGithub, HELLO_RS};
+
+use std::fs;
+use std::process::Command;
+use std::time::SystemTime;
+
+static HELLO_RS_EDITED: &str = r#"//! ```cargo
+//! [package]
+//! name = "hello"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {
+    println!("Hello, world!");
+}
+"#;
+
+#[test]
+fn clone() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().gist("c10e0001", "Says hello", &[("hello.rs", HELLO_RS)]);
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "clone", "c10e0001"])
+        .unwrap();
+    insta::assert_snapshot!("clone_events", outcome.events());
+    insta::assert_snapshot!("clone_manifest", env.read("ws/hello/Cargo.toml")?);
+    insta::assert_snapshot!("clone_config", env.read("bikecase.toml")?);
+    Ok(())
+}
+
+#[test]
+fn clone_not_found() -> anyhow::Result<()> {
+    let env = Env::new()?;
+
+    let outcome = env.cargo_bikecase_with_config(&["gist", "clone", "404e0001"]);
+    insta::assert_snapshot!(outcome.unwrap_err(), @"expected 200");
+    Ok(())
+}
+
+#[test]
+fn clone_without_description() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().on(
+        "GET",
+        "/gists/d35c0001",
+        200,
+        serde_json::json!({
+            "description": null,
+            "html_url": "https://gist.github.com/d35c0001",
+            "owner": null,
+            "files": {
+                "hello.rs": { "filename": "hello.rs", "content": HELLO_RS },
+            },
+            "forks": [],
+        }),
+    );
+
+    env.cargo_bikecase_with_config(&["gist", "clone", "d35c0001"])
+        .unwrap();
+    assert!(env.read("ws/hello/src/main.rs")?.contains("Hello!"));
+    Ok(())
+}
+
+#[test]
+fn clone_invalid_response() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().on(
+        "GET",
+        "/gists/1e5a0001",
+        200,
+        serde_json::json!({ "message": "unexpected" }),
+    );
+
+    let outcome = env.cargo_bikecase_with_config(&["gist", "clone", "1e5a0001"]);
+    insta::assert_snapshot!(outcome.unwrap_err(), @r###"failed to parse the response: {"message":"unexpected"}: missing field `files` at line 1 column 24"###);
+    Ok(())
+}
+
+#[test]
+fn clone_truncated() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    let mock = MockGithub::get();
+    mock.raw("/raw/7a5c0001/hello.rs", HELLO_RS, Some(20));
+    mock.on(
+        "GET",
+        "/gists/7a5c0001",
+        200,
+        serde_json::json!({
+            "description": "",
+            "html_url": "https://gist.github.com/7a5c0001",
+            "owner": null,
+            "files": {
+                "hello.rs": {
+                    "filename": "hello.rs",
+                    "truncated": true,
+                    "raw_url": mock.url("/raw/7a5c0001/hello.rs"),
+                },
+            },
+        }),
+    );
+
+    let error = env
+        .cargo_bikecase_with_config(&["gist", "clone", "--max-size", "16", "7a5c0001"])
+        .unwrap_err();
+    insta::assert_snapshot!(error, @"hello.rs is larger than `--max-size` (136 > 16 bytes)");
+
+    mock.raw("/raw/7a5c0001/hello.rs", HELLO_RS, Some(20));
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "clone", "7a5c0001"])
+        .unwrap();
+    insta::assert_snapshot!(env.redact(&outcome.events()), @r###"
+    spawn cargo
+    GET https://api.github.com/gists/7a5c0001
+    200
+    GET https://api.github.com/raw/7a5c0001/hello.rs
+    200
+    GET https://api.github.com/raw/7a5c0001/hello.rs
+    206
+    write [ROOT]/ws/hello/Cargo.toml
+    write [ROOT]/ws/hello/src/main.rs
+    add "hello" to members
+    INFO `workspace.members`: ["hello"]
+    INFO `workspace.exclude`: []
+    write [ROOT]/ws/Cargo.toml
+    diff [ROOT]/ws/hello/Cargo.toml
+    @@ -0,0 +1,4 @@
+    +[package]
+    +name = "hello"
+    +version = "0.1.0"
+    +edition = "2018"
+
+    diff [ROOT]/ws/hello/src/main.rs
+    @@ -0,0 +1,7 @@
+    +//! ```cargo
+    +//! # Leave blank.
+    +//! ```
+    +
+    +fn main() {
+    +    println!("Hello!");
+    +}
+
+    write [ROOT]/bikecase.toml
+    "###);
+    assert!(env.read("ws/hello/src/main.rs")?.contains("Hello!"));
+    Ok(())
+}
+
+#[test]
+fn pull_ours() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().gist("0a5e0001", "", &[("hello.rs", HELLO_RS)]);
+    env.cargo_bikecase_with_config(&["gist", "clone", "0a5e0001"])
+        .unwrap();
+    env.write("ws/hello/src/main.rs", "fn main() {}\n")?;
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "pull", "-p", "hello", "--ours"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events());
+    assert_eq!(env.read("ws/hello/src/main.rs")?, "fn main() {}\n");
+    Ok(())
+}
+
+#[test]
+fn pull_theirs() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().gist("7e1e0001", "", &[("hello.rs", HELLO_RS)]);
+    env.cargo_bikecase_with_config(&["gist", "clone", "7e1e0001"])
+        .unwrap();
+    MockGithub::get().gist("7e1e0001", "", &[("hello.rs", HELLO_RS_EDITED)]);
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "pull", "-p", "hello", "--theirs"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events());
+    let main_rs = fs::read_to_string(env.path("ws/hello/src/main.rs"))?;
+    let orig = fs::read_to_string(env.path("ws/hello/src/main.rs.orig"))?;
+    assert!(main_rs.contains("Hello, world!"));
+    assert!(orig.contains("Hello!"));
+    Ok(())
+}
+
+#[test]
+fn pull_delete() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().gist("de1e0001", "", &[("hello.rs", HELLO_RS)]);
+    env.cargo_bikecase_with_config(&["gist", "clone", "de1e0001"])
+        .unwrap();
+    env.write("ws/hello/tests/local.rs", "")?;
+
+    let not_confirmed = "`--delete` would delete 1 file(s). Run with `--yes` to confirm";
+    let error = env
+        .cargo_bikecase_with_config(&["gist", "pull", "-p", "hello", "--delete", "--no-input"])
+        .unwrap_err();
+    assert_eq!(error, not_confirmed);
+    // The prompt fails without a TTY.
+    let error = env
+        .cargo_bikecase_with_config(&["gist", "pull", "-p", "hello", "--delete"])
+        .unwrap_err();
+    assert!(error.starts_with(not_confirmed), "{}", error);
+    assert!(env.path("ws/hello/tests/local.rs").exists());
+
+    env.cargo_bikecase_with_config(&["gist", "pull", "-p", "hello", "--delete", "--yes"])
+        .unwrap();
+    assert!(!env.path("ws/hello/tests/local.rs").exists());
+    Ok(())
+}
+
+#[test]
+fn list_follows_next_links() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("home/github-token", "token")?;
+    let config = fs::read_to_string(env.path("bikecase.toml"))?;
+    env.write(
+        "bikecase.toml",
+        &format!(
+            "github-token = {{ kind = \"File\", path = {:?} }}\n{}",
+            env.path("home/github-token"),
+            config,
+        ),
+    )?;
+
+    let summary = |id: &str, public: bool| {
+        serde_json::json!({
+            "id": id,
+            "description": format!("Gist {}", id),
+            "public": public,
+            "updated_at": "2020-04-01T12:34:56Z",
+            "files": { format!("{}.rs", id): {} },
+        })
+    };
+    let mock = MockGithub::get();
+    mock.page(
+        "/gists?per_page=100",
+        serde_json::json!([summary("a", true), summary("b", false)]),
+        Some("/gists?per_page=100&page=2"),
+    );
+    mock.page(
+        "/gists?per_page=100&page=2",
+        serde_json::json!([summary("c", true), summary("d", true)]),
+        Some("/gists?per_page=100&page=3"),
+    );
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "list", "--limit", "3", "--color", "never"])
+        .unwrap();
+    insta::assert_snapshot!("list_follows_next_links_events", outcome.events());
+    insta::assert_snapshot!("list_follows_next_links_stdout", outcome.stdout);
+    Ok(())
+}
+
+#[test]
+fn workspace_file() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().gist("f11e0001", "", &[("hello.rs", HELLO_RS)]);
+    env.write("ws/bikecase-workspace.toml", "")?;
+
+    env.cargo_bikecase_with_config(&["gist", "clone", "f11e0001"])
+        .unwrap();
+    insta::assert_snapshot!(
+        env.read("ws/bikecase-workspace.toml")?,
+        @r###"
+    [gist-ids]
+    hello = 'f11e0001'
+    "###
+    );
+    assert!(!env.read("bikecase.toml")?.contains("f11e0001"));
+
+    env.cargo_bikecase_with_config(&["gist", "pull", "-p", "hello", "--theirs"])
+        .unwrap();
+    Ok(())
+}
+
+#[test]
+fn clone_with_lockfile() -> anyhow::Result<()> {
+    static HELLO_CARGO_LOCK: &str = r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 3
+
+[[package]]
+name = "hello"
+version = "0.1.0"
+dependencies = [
+ "itoa",
+]
+
+[[package]]
+name = "itoa"
+version = "0.4.5"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "b8b7a7c0c47db5545ed3fef7468ee7bb5b74691498139e4b3f6a20685dc6dd8e"
+"#;
+
+    let env = Env::new()?;
+    MockGithub::get().gist(
+        "10ce0001",
+        "",
+        &[
+            ("hello.rs", HELLO_RS),
+            ("hello.Cargo.lock", HELLO_CARGO_LOCK),
+        ],
+    );
+
+    env.cargo_bikecase_with_config(&["gist", "clone", "10ce0001"])
+        .unwrap();
+    let cargo_lock = env.read("ws/Cargo.lock")?;
+    assert!(cargo_lock.contains("name = \"itoa\"\nversion = \"0.4.5\""));
+    assert!(!env.path("ws/hello/Cargo.lock").exists());
+    assert!(!env.path("ws/hello/hello.Cargo.lock").exists());
+    Ok(())
+}
+
+#[test]
+fn push_if_clean() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().gist("c1ea0001", "", &[("hello.rs", HELLO_RS)]);
+    env.cargo_bikecase_with_config(&["gist", "clone", "c1ea0001"])
+        .unwrap();
+    Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(env.path("ws"))
+        .status()?;
+
+    let outcome = env.cargo_bikecase_with_config(&["gist", "push", "-p", "hello", "--if-clean"]);
+    insta::assert_snapshot!(outcome.unwrap_err(), @r###"
+    [ROOT]/ws/hello has uncommitted changes:
+    ?? hello/
+    "###);
+    Ok(())
+}
+
+#[test]
+fn push_require_tests_by_default() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().gist("7e570001", "", &[("hello.rs", HELLO_RS)]);
+    env.cargo_bikecase_with_config(&["gist", "clone", "7e570001"])
+        .unwrap();
+    env.write(
+        "bikecase.toml",
+        &format!(
+            "default-workspace = {ws:?}\ntemplate-package = {template:?}\n\n[workspaces.{ws:?}]\npush-require-tests = true\n\n[workspaces.{ws:?}.gist-ids]\nhello = \"7e570001\"\n",
+            ws = env.path("ws"),
+            template = env.path("template"),
+        ),
+    )?;
+    env.write(
+        "ws/hello/src/main.rs",
+        "fn main() {}\n\n#[test]\nfn fails() {\n    panic!();\n}\n",
+    )?;
+
+    let outcome = env.cargo_bikecase_with_config(&["gist", "push", "-p", "hello"]);
+    assert!(outcome
+        .unwrap_err()
+        .starts_with("the tests of `hello` failed. Not pushing"));
+    Ok(())
+}
+
+#[test]
+fn publish() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    let github = MockGithub::get();
+    github.gist("9b1c0001", "Says hello", &[("hello.rs", HELLO_RS)]);
+    github.on(
+        "POST",
+        "/gists",
+        201,
+        serde_json::json!({
+            "id": "9b1c0002",
+            "html_url": "https://gist.github.com/9b1c0002",
+            "files": {
+                "hello.rs": {
+                    "raw_url": "https://gist.githubusercontent.com/octocat/9b1c0002/raw/hello.rs",
+                },
+            },
+            "history": [{ "version": "0123456789abcdef" }],
+        }),
+    );
+    github.on("DELETE", "/gists/9b1c0001", 204, serde_json::Value::Null);
+    env.cargo_bikecase_with_config(&["gist", "clone", "9b1c0001"])
+        .unwrap();
+    env.write("github-token", "token")?;
+    let config = env
+        .read("bikecase.toml")?
+        .replace("[ROOT]", &env.root().to_string_lossy());
+    env.write(
+        "bikecase.toml",
+        &format!(
+            "{}\n[github-token]\nkind = \"File\"\npath = {:?}\n",
+            config,
+            env.path("github-token"),
+        ),
+    )?;
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "publish", "-p", "hello", "--delete-old"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events(), @r###"
+    spawn cargo
+    spawn cargo
+    GET https://api.github.com/gists/9b1c0001
+    200
+    POST https://api.github.com/gists
+    201
+    INFO Created `9b1c0002` as a public gist
+    WARN The URL changes from https://gist.github.com/9b1c0001 to https://gist.github.com/9b1c0002. Comments, stars, forks, and revisions are not carried over
+    DELETE https://api.github.com/gists/9b1c0001
+    204
+    INFO Deleted `9b1c0001`
+    INFO `workspaces."[ROOT]/ws".gist_ids."hello"`: Some("9b1c0001") → Some("9b1c0002")
+    write [ROOT]/bikecase.toml
+    "###);
+    insta::assert_snapshot!(outcome.stdout, @"URL: https://gist.github.com/9b1c0002");
+    assert!(env.read("bikecase.toml")?.contains("9b1c0002"));
+
+    github.gist("9b1c0002", "Says hello", &[("hello.rs", HELLO_RS)]);
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "unpublish", "-p", "hello"])
+        .unwrap();
+    assert!(outcome.events().contains("`9b1c0002` is already secret"));
+    Ok(())
+}
+
+#[test]
+fn extra_files() -> anyhow::Result<()> {
+    static HELLO_RS: &str = r#"//! ```cargo
+//! [package]
+//! name = "hello"
+//! version = "0.1.0"
+//! edition = "2018"
+//!
+//! [package.metadata.bikecase.gist]
+//! extra-files = ["data.csv", "build.rs"]
+//! ```
+
+fn main() {
+    print!("{}", include_str!("../data.csv"));
+}
+"#;
+
+    let env = Env::new()?;
+    let github = MockGithub::get();
+    github.gist(
+        "e47a0001",
+        "",
+        &[
+            ("hello.rs", HELLO_RS),
+            ("data.csv", "a,b\n1,2\n"),
+            ("build.rs", "fn main() {}\n"),
+        ],
+    );
+    github.on(
+        "PATCH",
+        "/gists/e47a0001",
+        200,
+        serde_json::json!({
+            "id": "e47a0001",
+            "html_url": "https://gist.github.com/e47a0001",
+        }),
+    );
+
+    env.cargo_bikecase_with_config(&["gist", "clone", "e47a0001"])
+        .unwrap();
+    assert_eq!(env.read("ws/hello/data.csv")?, "a,b\n1,2\n");
+    assert_eq!(env.read("ws/hello/build.rs")?, "fn main() {}\n");
+
+    env.write("github-token", "token")?;
+    let config = env
+        .read("bikecase.toml")?
+        .replace("[ROOT]", &env.root().to_string_lossy());
+    env.write(
+        "bikecase.toml",
+        &format!(
+            "{}\n[github-token]\nkind = \"File\"\npath = {:?}\n",
+            config,
+            env.path("github-token"),
+        ),
+    )?;
+    env.write("ws/hello/data.csv", "a,b\n3,4\n")?;
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "push", "-p", "hello"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events(), @r###"
+    spawn cargo
+    spawn cargo
+    GET https://api.github.com/gists/e47a0001
+    200
+    PATCH https://api.github.com/gists/e47a0001
+    200
+    INFO Updated `e47a0001`
+    diff <description>
+
+    diff hello.rs
+
+    diff build.rs
+
+    diff data.csv
+    @@ -1,2 +1,2 @@
+     a,b
+    -1,2
+    +3,4
+
+    write [ROOT]/bikecase.toml
+    "###);
+    Ok(())
+}
+
+#[test]
+fn clone_without_manifest() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    let github = MockGithub::get();
+    github.gist(
+        "5e7a0001",
+        "Say hello",
+        &[("main.rs", "fn main() {\n    println!(\"Hello!\");\n}\n")],
+    );
+    github.on(
+        "PATCH",
+        "/gists/5e7a0001",
+        200,
+        serde_json::json!({
+            "id": "5e7a0001",
+            "html_url": "https://gist.github.com/5e7a0001",
+        }),
+    );
+
+    env.cargo_bikecase_with_config(&["gist", "clone", "5e7a0001"])
+        .unwrap();
+    let cargo_toml = env.read("ws/say-hello/Cargo.toml")?;
+    let cargo_toml = cargo_toml
+        .lines()
+        .filter(|l| !l.starts_with("edition = "))
+        .collect::<Vec<_>>()
+        .join("\n");
+    insta::assert_snapshot!(cargo_toml, @r###"
+    [package]
+    name = "say-hello"
+    version = "0.1.0"
+
+    [package.metadata.bikecase]
+    original-filename = "main.rs"
+    synthetic-manifest = true
+    "###);
+    insta::assert_snapshot!(env.read("ws/say-hello/src/main.rs")?, @r###"
+    //! ```cargo
+    //! # Leave blank.
+    //! ```
+
+    fn main() {
+        println!("Hello!");
+    }
+    "###);
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "pull", "-p", "say-hello"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events(), @r###"
+    spawn cargo
+    spawn cargo
+    GET https://api.github.com/gists/5e7a0001
+    200
+    INFO No changes: [ROOT]/ws/say-hello/src/main.rs
+    INFO No changes: [ROOT]/ws/say-hello/Cargo.toml
+    "###);
+
+    env.write("github-token", "token")?;
+    let config = env
+        .read("bikecase.toml")?
+        .replace("[ROOT]", &env.root().to_string_lossy());
+    env.write(
+        "bikecase.toml",
+        &format!(
+            "{}\n[github-token]\nkind = \"File\"\npath = {:?}\n",
+            config,
+            env.path("github-token"),
+        ),
+    )?;
+    let outcome = env
+        .cargo_bikecase_with_config(&["gist", "push", "-p", "say-hello"])
+        .unwrap();
+    let events = outcome
+        .events()
+        .lines()
+        .filter(|l| !l.contains("edition = "))
+        .collect::<Vec<_>>()
+        .join("\n");
+    insta::assert_snapshot!(events, @r###"
+    spawn cargo
+    GET https://api.github.com/gists/5e7a0001
+    200
+    PATCH https://api.github.com/gists/5e7a0001
+    200
+    INFO Updated `5e7a0001`
+    diff <description>
+
+    diff main.rs
+    @@ -1 +1,12 @@
+    +//! ```cargo
+    +//! [package]
+    +//! name = "say-hello"
+    +//! version = "0.1.0"
+    +//!
+    +//! [package.metadata.bikecase]
+    +//! original-filename = "main.rs"
+    +//! synthetic-manifest = true
+    +//! ```
+    +
+     fn main() {
+
+    write [ROOT]/bikecase.toml
+    "###);
+    Ok(())
+}
+
+#[test]
+fn alias() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    MockGithub::get().gist("a11a0001", "", &[("hello.rs", HELLO_RS)]);
+    env.write(
+        "ws/bikecase-workspace.toml",
+        "[gist-ids]\nfractal = { id = \"a11a0001\", alias = \"greeting\" }\n",
+    )?;
+
+    env.cargo_bikecase_with_config(&["gist", "clone", "greeting"])
+        .unwrap();
+    assert!(env.read("ws/hello/src/main.rs")?.contains("Hello!"));
+    insta::assert_snapshot!(env.read("ws/bikecase-workspace.toml")?, @r###"
+    [gist-ids]
+    hello = 'a11a0001'
+
+    [gist-ids.fractal]
+    id = 'a11a0001'
+    alias = 'greeting'
+    "###);
+    Ok(())
+}
+
+#[test]
+fn changelog() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    let github = MockGithub::get();
+    github.gist(
+        "c4a90001",
+        "",
+        &[
+            ("hello.rs", HELLO_RS),
+            ("CHANGELOG.md", "# Changelog\n\n- 2020-04-01: initial\n"),
+        ],
+    );
+    github.on(
+        "PATCH",
+        "/gists/c4a90001",
+        200,
+        serde_json::json!({
+            "id": "c4a90001",
+            "html_url": "https://gist.github.com/c4a90001",
+        }),
+    );
+
+    env.cargo_bikecase_with_config(&["gist", "clone", "c4a90001"])
+        .unwrap();
+    env.write("github-token", "token")?;
+    let config = env
+        .read("bikecase.toml")?
+        .replace("[ROOT]", &env.root().to_string_lossy());
+    env.write(
+        "bikecase.toml",
+        &format!(
+            "{}\n[github-token]\nkind = \"File\"\npath = {:?}\n",
+            config,
+            env.path("github-token"),
+        ),
+    )?;
+    let main_rs = env
+        .read("ws/hello/src/main.rs")?
+        .replace("Hello!", "Hello, world!");
+    env.write("ws/hello/src/main.rs", &main_rs)?;
+
+    let outcome = env
+        .cargo_bikecase_with_config(&[
+            "gist",
+            "push",
+            "-p",
+            "hello",
+            "--changelog",
+            "greet the world",
+        ])
+        .unwrap();
+    let today = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    insta::assert_snapshot!(outcome.events().replace(&today[..10], "[DATE]"), @r###"
+    spawn cargo
+    spawn cargo
+    GET https://api.github.com/gists/c4a90001
+    200
+    PATCH https://api.github.com/gists/c4a90001
+    200
+    INFO Updated `c4a90001`
+    diff <description>
+
+    diff hello.rs
+    @@ -8,3 +8,3 @@
+     fn main() {
+    -    println!("Hello!");
+    +    println!("Hello, world!");
+     }
+
+    diff CHANGELOG.md
+    @@ -3 +3,2 @@
+     - 2020-04-01: initial
+    +- [DATE]: greet the world
+
+    write [ROOT]/bikecase.toml
+    "###);
+    Ok(())
+}
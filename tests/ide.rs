@@ -0,0 +1,63 @@
+mod common;
+
+use crate::common::Env;
+
+#[test]
+fn rust_project() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "util/Cargo.toml",
+        "[package]\nname = \"util\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n[features]\nfast = []\n",
+    )?;
+    env.write("util/src/lib.rs", "")?;
+    env.write(
+        "ws/tool/Cargo.toml",
+        &format!(
+            "[package]\nname = \"tool\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n\
+             [dependencies]\nutil = {{ path = {:?}, features = [\"fast\"] }}\n",
+            env.path("util"),
+        ),
+    )?;
+    env.write("ws/tool/src/main.rs", "fn main() {}\n")?;
+    env.cargo_bikecase(&["include", "tool"]).unwrap();
+
+    let outcome = env
+        .cargo_bikecase(&["ide", "-p", "tool", "--rust-project", "--print"])
+        .unwrap();
+    let mut rust_project = serde_json::from_str::<serde_json::Value>(&outcome.stdout)?;
+    assert!(rust_project["sysroot"].is_string());
+    rust_project.as_object_mut().unwrap().remove("sysroot");
+    let rust_project = serde_json::to_string_pretty(&rust_project)?;
+    insta::assert_snapshot!(env.redact(&rust_project), @r###"
+    {
+      "crates": [
+        {
+          "display_name": "util",
+          "root_module": "[ROOT]/util/src/lib.rs",
+          "edition": "2018",
+          "deps": [],
+          "cfg": [
+            "feature=\"fast\""
+          ],
+          "is_workspace_member": false,
+          "is_proc_macro": false
+        },
+        {
+          "display_name": "tool",
+          "root_module": "[ROOT]/ws/tool/src/main.rs",
+          "edition": "2018",
+          "deps": [
+            {
+              "crate": 0,
+              "name": "util"
+            }
+          ],
+          "cfg": [],
+          "is_workspace_member": true,
+          "is_proc_macro": false
+        }
+      ]
+    }
+    "###);
+    Ok(())
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use crate::common::{Env, MockGithub};
+
+static REPORT_RS: &str = r#"//! ```cargo
+//! [package]
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {}
+"#;
+
+#[test]
+fn derives_package_names_from_file_names() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("scripts/2024-05-03 report.rs", REPORT_RS)?;
+    env.write("other/2024-05-03  REPORT.rs", REPORT_RS)?;
+
+    let script = env.path("scripts/2024-05-03 report.rs");
+    let outcome = env
+        .cargo_bikecase(&["import", script.to_str().unwrap()])
+        .unwrap();
+    insta::assert_snapshot!("derives_package_names_events", outcome.events());
+    insta::assert_snapshot!(
+        "derives_package_names_manifest",
+        env.read("ws/script-2024-05-03-report/Cargo.toml")?,
+    );
+
+    let other = env.path("other/2024-05-03  REPORT.rs");
+    env.cargo_bikecase(&["import", other.to_str().unwrap()])
+        .unwrap();
+    env.cargo_bikecase(&["import", script.to_str().unwrap()])
+        .unwrap();
+    insta::assert_snapshot!(
+        "derives_package_names_workspace",
+        env.read("ws/Cargo.toml")?,
+    );
+    Ok(())
+}
+
+#[test]
+fn imports_directory() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    let script = |name: &str| {
+        format!(
+            "//! ```cargo\n//! [package]\n//! name = {:?}\n//! version = \"0.1.0\"\n\
+             //! edition = \"2018\"\n//! ```\n\nfn main() {{}}\n",
+            name,
+        )
+    };
+    env.write("scripts/a.rs", &script("a"))?;
+    env.write("scripts/b.rs", &script("b"))?;
+    env.write("scripts/sub/b.rs", &script("b"))?;
+
+    let scripts = env.path("scripts");
+    let outcome = env
+        .cargo_bikecase(&["import", scripts.to_str().unwrap()])
+        .unwrap();
+    let events = outcome.events();
+    let skipped = events
+        .lines()
+        .filter(|l| l.contains("Skipping") && l.contains("`b` already exists"))
+        .count();
+    assert_eq!(skipped, 1, "{}", events);
+    let workspace_manifest = env.read("ws/Cargo.toml")?;
+    assert!(workspace_manifest.contains("\"a\"") && workspace_manifest.contains("\"b\""));
+
+    // The root contains the workspace, whose members are not imported as scripts.
+    let root = env.root().to_owned();
+    env.cargo_bikecase(&["import", root.to_str().unwrap()])
+        .unwrap();
+    assert_eq!(env.read("ws/Cargo.toml")?, workspace_manifest);
+    Ok(())
+}
+
+#[test]
+fn requires_package_names_from_stdin() -> anyhow::Result<()> {
+    let env = Env::new()?;
+
+    let error = env
+        .cargo_bikecase_with_stdin(&["import", "--dry-run"], REPORT_RS)
+        .unwrap_err();
+    insta::assert_snapshot!(error, @"missing `package.name`");
+    Ok(())
+}
+
+#[test]
+fn imports_from_urls_and_gist_revisions() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    let mock = MockGithub::get();
+    mock.raw("/raw/u41c0001/url-report.rs", REPORT_RS, None);
+    mock.on(
+        "GET",
+        "/gists/41c0002/0123abc",
+        200,
+        serde_json::json!({
+            "description": "",
+            "html_url": "https://gist.github.com/41c0002",
+            "owner": null,
+            "files": {
+                "gist-report.rs": { "filename": "gist-report.rs", "content": REPORT_RS },
+            },
+        }),
+    );
+
+    let url = mock.url("/raw/u41c0001/url-report.rs");
+    env.cargo_bikecase(&["import", &url]).unwrap();
+    env.cargo_bikecase(&["import", "gist:41c0002@0123abc"])
+        .unwrap();
+    insta::assert_snapshot!(env.read("ws/Cargo.toml")?, @r###"
+    [workspace]
+    members = ["url-report", "gist-report"]
+    exclude = []
+    "###);
+
+    let error = env
+        .cargo_bikecase(&["import", "gist:../41c0002"])
+        .unwrap_err();
+    insta::assert_snapshot!(error, @r###"invalid gist ID: "../41c0002""###);
+    Ok(())
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use crate::common::Env;
+
+#[test]
+fn scriptify() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/tool/Cargo.toml",
+        "[package]\nname = \"tool\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n[dependencies]\n",
+    )?;
+    env.write(
+        "ws/tool/src/main.rs",
+        "//! A tool.\n\nfn main() {\n    println!(\"Hello!\");\n}\n",
+    )?;
+
+    env.cargo_bikecase(&["include", "--scriptify", "tool"])
+        .unwrap();
+    insta::assert_snapshot!(env.read("ws/tool/src/main.rs")?, @r###"
+    //! ```cargo
+    //! [package]
+    //! name = "tool"
+    //! version = "0.1.0"
+    //! edition = "2018"
+    //!
+    //! [dependencies]
+    //! ```
+    //!
+    //! A tool.
+
+    fn main() {
+        println!("Hello!");
+    }
+    "###);
+    insta::assert_snapshot!(env.read("ws/Cargo.toml")?, @r###"
+    [workspace]
+    members = ["tool"]
+    exclude = []
+    "###);
+
+    let outcome = env.cargo_bikecase(&["export", "-p", "tool"]).unwrap();
+    assert_eq!(outcome.stdout, env.read("ws/tool/src/main.rs")?);
+
+    let error = env
+        .cargo_bikecase(&["include", "--scriptify", "tool"])
+        .unwrap_err();
+    insta::assert_snapshot!(
+        env.redact(&error),
+        @"[ROOT]/ws/tool/src/main.rs already has a `cargo` code block"
+    );
+    Ok(())
+}
+
+#[test]
+fn scriptify_rejects_libraries() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/both/Cargo.toml",
+        "[package]\nname = \"both\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+    )?;
+    env.write("ws/both/src/main.rs", "fn main() {}\n")?;
+    env.write("ws/both/src/lib.rs", "")?;
+
+    let error = env
+        .cargo_bikecase(&["include", "--scriptify", "both"])
+        .unwrap_err();
+    insta::assert_snapshot!(
+        env.redact(&error),
+        @"could not scriptify [ROOT]/ws/both: only packages with just `src/main.rs` are supported"
+    );
+    insta::assert_snapshot!(env.read("ws/Cargo.toml")?, @r###"
+    [workspace]
+    members = []
+    exclude = []
+    "###);
+    Ok(())
+}
+
+#[test]
+fn include_and_exclude_are_consistent() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/Cargo.toml",
+        "[workspace]\nmembers = [\"tool/\", \"./tool\"]\nexclude = [\"tool\"]\n",
+    )?;
+    env.write(
+        "ws/tool/Cargo.toml",
+        "[package]\nname = \"tool\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+    )?;
+    env.write("ws/tool/src/main.rs", "fn main() {}\n")?;
+
+    let outcome = env.cargo_bikecase(&["include", "./tool/"]).unwrap();
+    insta::assert_snapshot!(env.read("ws/Cargo.toml")?, @r###"
+    [workspace]
+    members = ["tool/"]
+    exclude = []
+    "###);
+    insta::assert_snapshot!(outcome.events(), @r###"
+    spawn cargo
+    add "tool" to members
+    remove "tool" from exclude
+    INFO `workspace.members`: ["tool/"]
+    INFO `workspace.exclude`: []
+    write [ROOT]/ws/Cargo.toml
+    "###);
+
+    let outcome = env.cargo_bikecase(&["exclude", "tool/"]).unwrap();
+    insta::assert_snapshot!(env.read("ws/Cargo.toml")?, @r###"
+    [workspace]
+    members = []
+    exclude = ["tool"]
+    "###);
+    insta::assert_snapshot!(outcome.events(), @r###"
+    spawn cargo
+    remove "tool" from members
+    add "tool" to exclude
+    INFO `workspace.members`: []
+    INFO `workspace.exclude`: ["tool"]
+    write [ROOT]/ws/Cargo.toml
+    "###);
+
+    env.cargo_bikecase(&["exclude", "tool"]).unwrap();
+    env.cargo_bikecase(&["include", "tool"]).unwrap();
+    env.cargo_bikecase(&["include", "tool"]).unwrap();
+    insta::assert_snapshot!(env.read("ws/Cargo.toml")?, @r###"
+    [workspace]
+    members = ["tool"]
+    exclude = []
+    "###);
+    Ok(())
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use crate::common::Env;
+
+#[test]
+fn resolver_and_edition() -> anyhow::Result<()> {
+    let env = Env::new()?;
+
+    env.cargo_bikecase(&[
+        "init-workspace",
+        "--resolver",
+        "2",
+        "--edition",
+        "2021",
+        "--default-members",
+        "3",
+        "../ws2",
+    ])
+    .unwrap();
+    insta::assert_snapshot!(env.read("ws2/Cargo.toml")?, @r###"
+    [workspace]
+    members = []
+    exclude = []
+    resolver = "2"
+    default-members = []
+
+    [workspace.package]
+    edition = "2021"
+
+    [workspace.metadata.bikecase]
+    default-members-limit = 3
+    "###);
+
+    let output = std::process::Command::new(env!("CARGO"))
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(env.path("ws2"))
+        .output()?;
+    assert!(output.status.success());
+
+    // Detected from the installed `cargo`.
+    env.cargo_bikecase(&["init-workspace", "../ws3"]).unwrap();
+    let manifest = env.read("ws3/Cargo.toml")?;
+    assert!(manifest.contains("\nresolver = "));
+    assert!(manifest.contains("\n[workspace.package]\nedition = "));
+    Ok(())
+}
+
+#[test]
+fn profile_preset() -> anyhow::Result<()> {
+    let env = Env::new()?;
+
+    env.cargo_bikecase(&[
+        "init-workspace",
+        "--resolver",
+        "2",
+        "--edition",
+        "2021",
+        "--profile-preset",
+        "fast-run",
+        "../ws2",
+    ])
+    .unwrap();
+    insta::assert_snapshot!(env.read("ws2/Cargo.toml")?, @r###"
+    [workspace]
+    members = []
+    exclude = []
+    resolver = "2"
+
+    [workspace.package]
+    edition = "2021"
+
+    [profile.dev]
+    opt-level = 1
+    debug = 0
+
+    [profile.dev.package."*"]
+    opt-level = 3
+    "###);
+
+    // The keys that the presets do not set are kept.
+    env.write(
+        "ws2/Cargo.toml",
+        &format!(
+            "{}overflow-checks = false\n",
+            env.read("ws2/Cargo.toml")?
+                .replace("[profile.dev]\n", "[profile.dev]\nlto = false\n")
+        ),
+    )?;
+    let outcome = env
+        .cargo_bikecase(&["optimize-workspace", "--preset", "fast-compile", "../ws2"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events(), @r###"
+    INFO `profile.dev`: fast-compile
+    write [ROOT]/ws/../ws2/Cargo.toml
+    "###);
+    insta::assert_snapshot!(env.read("ws2/Cargo.toml")?, @r###"
+    [workspace]
+    members = []
+    exclude = []
+    resolver = "2"
+
+    [workspace.package]
+    edition = "2021"
+
+    [profile.dev]
+    lto = false
+    debug = 0
+    incremental = true
+
+    [profile.dev.package."*"]
+    overflow-checks = false
+    "###);
+
+    let output = std::process::Command::new(env!("CARGO"))
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(env.path("ws2"))
+        .output()?;
+    assert!(output.status.success());
+    Ok(())
+}
@@ -0,0 +1,279 @@
+#![cfg(unix)]
+
+use bikecase::{Cargo, Context};
+use structopt::StructOpt as _;
+
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt as _;
+use std::os::unix::fs::PermissionsExt as _;
+use std::path::Path;
+use std::{fs, io};
+
+#[test]
+fn recreates_symlinks() -> anyhow::Result<()> {
+    let tempdir = setup()?;
+    let root = tempdir.path();
+
+    run(root, &[])?;
+
+    let new = root.join("ws").join("new");
+    assert_eq!(
+        fs::read_link(new.join("src").join("lib.rs"))?,
+        Path::new("main.rs")
+    );
+    assert_eq!(
+        fs::read_link(new.join("README.md"))?,
+        new.join("src").join("main.rs"),
+    );
+    assert_eq!(fs::read_link(new.join("outside"))?, root.join("outside"));
+    Ok(())
+}
+
+#[test]
+fn follows_symlinks() -> anyhow::Result<()> {
+    let tempdir = setup()?;
+    let root = tempdir.path();
+
+    run(root, &["--follow-symlinks"])?;
+
+    let new = root.join("ws").join("new");
+    for rel in &["src/lib.rs", "README.md", "outside"] {
+        let path = new.join(rel);
+        assert!(!fs::symlink_metadata(&path)?.file_type().is_symlink());
+    }
+    assert_eq!(fs::read_to_string(new.join("src").join("lib.rs"))?, MAIN_RS,);
+    assert_eq!(fs::read_to_string(new.join("outside"))?, "outside\n");
+    Ok(())
+}
+
+#[test]
+fn copies_permissions() -> anyhow::Result<()> {
+    let tempdir = setup()?;
+    let root = tempdir.path();
+
+    run(root, &[])?;
+
+    let mode = fs::metadata(root.join("ws").join("new").join("run.sh"))?
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o755);
+    Ok(())
+}
+
+#[test]
+fn copies_non_utf8_file_names() -> anyhow::Result<()> {
+    let tempdir = setup()?;
+    let root = tempdir.path();
+
+    run(root, &[])?;
+
+    let path = root
+        .join("ws")
+        .join("new")
+        .join(OsStr::from_bytes(b"data-\xff.txt"));
+    assert_eq!(fs::read_to_string(path)?, "data\n");
+    Ok(())
+}
+
+#[test]
+fn from_script() -> anyhow::Result<()> {
+    let tempdir = setup()?;
+    let root = tempdir.path();
+    fs::write(
+        root.join("script.rs"),
+        r#"//! ```cargo
+//! [package]
+//! name = "script"
+//! version = "0.1.0"
+//! edition = "2018"
+//!
+//! [dependencies]
+//! itertools = "0.9.0"
+//!
+//! [target.'cfg(unix)'.dependencies]
+//! libc = "0.2.0"
+//! ```
+
+fn main() {
+    println!("Hello!");
+}
+"#,
+    )?;
+
+    run(
+        root,
+        &["--from-script", &root.join("script.rs").to_string_lossy()],
+    )?;
+
+    let new = root.join("ws").join("new");
+    assert_eq!(
+        fs::read_to_string(new.join("Cargo.toml"))?,
+        r#"[package]
+name = "new"
+version = "0.0.0"
+edition = "2018"
+
+[dependencies]
+itertools = "0.9.0"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2.0"
+"#,
+    );
+    assert_eq!(
+        fs::read_to_string(new.join("src").join("main.rs"))?,
+        "//! ```cargo\n//! # Leave blank.\n//! ```\n\nfn main() {\n    println!(\"Hello!\");\n}\n",
+    );
+    assert!(!fs::symlink_metadata(new.join("src").join("main.rs"))?
+        .file_type()
+        .is_symlink());
+    assert_eq!(
+        fs::read_link(new.join("src").join("lib.rs"))?,
+        Path::new("main.rs")
+    );
+    Ok(())
+}
+
+#[test]
+fn renders_handlebars() -> anyhow::Result<()> {
+    let tempdir = setup()?;
+    let root = tempdir.path();
+    let template = root.join("template");
+    fs::create_dir_all(template.join(".github").join("workflows"))?;
+    fs::write(
+        template
+            .join(".github")
+            .join("workflows")
+            .join("ci.yml.hbs"),
+        r#"{{#if (eq ci "github")}}
+name: CI
+on: [push]
+jobs:
+  {{name}}:
+    runs-on: ubuntu-latest
+    env:
+      TOKEN: $\{{ secrets.TOKEN }}
+{{/if}}
+"#,
+    )?;
+    fs::write(
+        template.join("NOTES.md.hbs"),
+        r#"# {{name}}
+{{! `author` is in `template-vars`. }}
+{{#unless private}}
+By {{author}}.
+{{else}}
+Private.
+{{/unless}}
+"#,
+    )?;
+    let mut config = fs::read_to_string(root.join("bikecase.toml"))?;
+    config += "\n[template-vars]\nauthor = \"alice\"\n";
+    fs::write(root.join("bikecase.toml"), config)?;
+
+    run(root, &["--var", "ci=github", "--var", "author=bob"])?;
+
+    let new = root.join("ws").join("new");
+    assert_eq!(
+        fs::read_to_string(new.join(".github").join("workflows").join("ci.yml"))?,
+        r#"name: CI
+on: [push]
+jobs:
+  new:
+    runs-on: ubuntu-latest
+    env:
+      TOKEN: ${{ secrets.TOKEN }}
+"#,
+    );
+    assert_eq!(
+        fs::read_to_string(new.join("NOTES.md"))?,
+        "# new\nBy bob.\n",
+    );
+    assert!(!new.join("NOTES.md.hbs").exists());
+
+    fs::remove_dir_all(&new)?;
+    fs::write(
+        root.join("ws").join("Cargo.toml"),
+        "[workspace]\nmembers = []\n",
+    )?;
+    run(root, &["--var", "private"])?;
+
+    assert!(!new.join(".github").exists());
+    assert_eq!(
+        fs::read_to_string(new.join("NOTES.md"))?,
+        "# new\nPrivate.\n",
+    );
+    Ok(())
+}
+
+static MAIN_RS: &str = "fn main() {}\n";
+
+fn setup() -> anyhow::Result<tempfile::TempDir> {
+    let tempdir = tempfile::Builder::new()
+        .prefix("bikecase-tests-new-")
+        .tempdir()?;
+    let root = tempdir.path();
+
+    fs::create_dir(root.join("ws"))?;
+    fs::write(
+        root.join("ws").join("Cargo.toml"),
+        "[workspace]\nmembers = []\n",
+    )?;
+
+    let template = root.join("template");
+    fs::create_dir_all(template.join("src"))?;
+    fs::write(
+        template.join("Cargo.toml"),
+        "[package]\nname = \"template\"\nversion = \"0.0.0\"\nedition = \"2018\"\n",
+    )?;
+    fs::write(template.join("src").join("main.rs"), MAIN_RS)?;
+    std::os::unix::fs::symlink("main.rs", template.join("src").join("lib.rs"))?;
+    std::os::unix::fs::symlink(
+        template.join("src").join("main.rs"),
+        template.join("README.md"),
+    )?;
+    fs::write(root.join("outside"), "outside\n")?;
+    std::os::unix::fs::symlink(root.join("outside"), template.join("outside"))?;
+    fs::write(template.join("run.sh"), "#!/bin/sh\n")?;
+    fs::set_permissions(template.join("run.sh"), fs::Permissions::from_mode(0o755))?;
+    fs::write(template.join(OsStr::from_bytes(b"data-\xff.txt")), "data\n")?;
+
+    fs::write(
+        root.join("bikecase.toml"),
+        format!(
+            "default-workspace = {:?}\ntemplate-package = {:?}\n\n[workspaces]\n",
+            root.join("ws"),
+            template,
+        ),
+    )?;
+    Ok(tempdir)
+}
+
+fn run(root: &Path, args: &[&str]) -> anyhow::Result<()> {
+    let mut all_args = vec![OsString::from("cargo"), "bikecase".into(), "new".into()];
+    all_args.extend(args.iter().map(Into::into));
+    all_args.extend(vec![
+        "--manifest-path".into(),
+        root.join("ws").join("Cargo.toml").into(),
+        "--config".into(),
+        root.join("bikecase.toml").into(),
+        root.join("ws").join("new").into(),
+    ]);
+    let Cargo::Bikecase(opt) = Cargo::from_iter_safe(all_args)?;
+
+    bikecase::cargo_bikecase(
+        opt,
+        Context {
+            cwd: root.to_owned(),
+            home_dir: None,
+            data_local_dir: None,
+            stdout: io::sink(),
+            read_input: || unreachable!(),
+            read_password: |_: &str| unreachable!(),
+            init_logger: |_| (),
+            init_json_logger: || (),
+            str_width: bikecase::str_width,
+            config_cache: Default::default(),
+        },
+    )
+}
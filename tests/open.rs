@@ -0,0 +1,40 @@
+mod common;
+
+use crate::common::Env;
+
+use std::fs;
+
+#[test]
+fn open() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/tool/Cargo.toml",
+        "[package]\nname = \"tool\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+    )?;
+    env.write("ws/tool/src/main.rs", "fn main() {}\n")?;
+    env.cargo_bikecase(&["include", "tool"]).unwrap();
+
+    let outcome = env
+        .cargo_bikecase_with_config(&["open", "-p", "tool", "--print"])
+        .unwrap();
+    insta::assert_snapshot!(env.redact(&outcome.stdout), @"[ROOT]/ws/tool/src/main.rs");
+    let outcome = env
+        .cargo_bikecase_with_config(&["open", "-p", "tool", "--print", "--dir"])
+        .unwrap();
+    insta::assert_snapshot!(env.redact(&outcome.stdout), @"[ROOT]/ws/tool");
+
+    let config = fs::read_to_string(env.path("bikecase.toml"))?;
+    env.write(
+        "bikecase.toml",
+        &format!("editor = \"cp {{file}} {{path}}/opened.rs\"\n{}", config),
+    )?;
+    let outcome = env
+        .cargo_bikecase_with_config(&["open", "-p", "tool"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.events(), @r###"
+    spawn cargo
+    spawn cp
+    "###);
+    assert_eq!(env.read("ws/tool/opened.rs")?, "fn main() {}\n");
+    Ok(())
+}
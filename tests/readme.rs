@@ -0,0 +1,59 @@
+mod common;
+
+use crate::common::Env;
+
+static HELLO_RS: &str = r#"//! Says hello.
+//!
+//! ```cargo
+//! [package]
+//! name = "hello"
+//! version = "0.1.0"
+//! license = "MIT OR Apache-2.0"
+//! edition = "2018"
+//! ```
+//!
+//! # Usage
+//!
+//! ```console
+//! $ hello
+//! Hello!
+//! ```
+
+fn main() {
+    println!("Hello!");
+}
+"#;
+
+#[test]
+fn generates_readme_from_module_doc() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+
+    env.cargo_bikecase(&["readme", "-p", "hello"]).unwrap();
+    insta::assert_snapshot!(env.read("ws/hello/README.md")?, @r###"
+    # hello
+
+    Says hello.
+
+    # Usage
+
+    ```console
+    $ hello
+    Hello!
+    ```
+
+    License: MIT OR Apache-2.0
+    "###);
+    env.cargo_bikecase(&["readme", "-p", "hello", "--check"])
+        .unwrap();
+
+    env.write("ws/hello/README.tpl", "{{readme}}")?;
+    let error = env
+        .cargo_bikecase(&["readme", "-p", "hello", "--check"])
+        .unwrap_err();
+    assert!(error.ends_with("README.md is not up to date"), "{}", error);
+    Ok(())
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use crate::common::Env;
+
+fn setup() -> anyhow::Result<Env> {
+    let env = Env::new()?;
+    env.write(
+        "ws/Cargo.toml",
+        "[workspace]\nmembers = [\"app\", \"lib\", \"other\"]\nexclude = []\n",
+    )?;
+    for (name, deps) in &[
+        ("app", "lib = { path = \"../lib\" }\n"),
+        ("lib", ""),
+        ("other", ""),
+    ] {
+        env.write(
+            &format!("ws/{}/Cargo.toml", name),
+            &format!(
+                "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2018\"\n\n\
+                 [dependencies]\n{}",
+                name, deps,
+            ),
+        )?;
+        env.write(&format!("ws/{}/src/lib.rs", name), "")?;
+    }
+    Ok(env)
+}
+
+#[test]
+fn dependents_first() -> anyhow::Result<()> {
+    let env = setup()?;
+
+    let outcome = env.cargo_bikecase(&["rm", "lib", "app"]).unwrap();
+    insta::assert_snapshot!(outcome.events());
+    assert!(!env.path("ws/app").exists());
+    assert!(!env.path("ws/lib").exists());
+    assert!(env.path("ws/other").exists());
+    Ok(())
+}
+
+#[test]
+fn remaining_dependents() -> anyhow::Result<()> {
+    let env = setup()?;
+
+    let error = env.cargo_bikecase(&["rm", "lib", "other"]).unwrap_err();
+    insta::assert_snapshot!(
+        error,
+        @"`app` depends on `lib`. Remove them together, or use `--force` to leave the workspace unbuildable"
+    );
+    assert!(env.path("ws/lib").exists());
+    assert!(env.path("ws/other").exists());
+
+    env.cargo_bikecase(&["rm", "--force", "lib"]).unwrap();
+    assert!(!env.path("ws/lib").exists());
+    Ok(())
+}
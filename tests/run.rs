@@ -0,0 +1,1078 @@
+mod common;
+
+use crate::common::{Env, HELLO_RS};
+
+use std::fs;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn dry_run() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+
+    let outcome = env.bikecase(&["--dry-run", "hello.rs"], "").unwrap();
+    insta::assert_snapshot!(outcome.events());
+    Ok(())
+}
+
+#[test]
+fn stdin_data_conflicts_with_script_from_stdin() -> anyhow::Result<()> {
+    let env = Env::new()?;
+
+    let error = env.bikecase(&["--stdin-data", "-"], HELLO_RS).unwrap_err();
+    insta::assert_snapshot!(
+        error,
+        @"`--stdin-data -` cannot be used when the script is read from stdin"
+    );
+    Ok(())
+}
+
+#[test]
+fn no_auto_create() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    fs::remove_dir_all(env.path("ws"))?;
+
+    let error = env
+        .bikecase(&["--no-auto-create", "hello.rs"], "")
+        .unwrap_err();
+    insta::assert_snapshot!(
+        error,
+        @"[ROOT]/ws does not exist and auto-creation is disabled by `--no-auto-create`. Create it with `cargo bikecase init-workspace [ROOT]/ws`"
+    );
+    assert!(!env.path("ws").exists());
+    Ok(())
+}
+
+#[test]
+fn auto_create_disabled_in_config() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    fs::remove_dir_all(env.path("ws"))?;
+    let config = fs::read_to_string(env.path("bikecase.toml"))?;
+    env.write("bikecase.toml", &format!("auto-create = false\n{}", config))?;
+
+    let error = env.bikecase(&["hello.rs"], "").unwrap_err();
+    insta::assert_snapshot!(
+        error,
+        @"[ROOT]/ws does not exist and auto-creation is disabled by `auto-create = false`. Create it with `cargo bikecase init-workspace [ROOT]/ws`"
+    );
+    Ok(())
+}
+
+#[test]
+fn dry_run_does_not_create_anything() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    fs::remove_dir_all(env.path("ws"))?;
+    fs::remove_file(env.path("bikecase.toml"))?;
+    env.write(
+        "bikecase.toml",
+        &format!("default-workspace = {:?}\n", env.path("ws")),
+    )?;
+
+    let outcome = env.bikecase(&["--dry-run", "hello.rs"], "").unwrap();
+    insta::assert_snapshot!(outcome.events());
+    assert!(!env.path("ws").exists());
+    Ok(())
+}
+
+#[test]
+fn pipe() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "greet.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "greet"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {
+    println!("hello, {}", std::env::args().nth(1).unwrap());
+}
+"#,
+    )?;
+    env.write(
+        "shout.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "shout"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+use std::io::Read as _;
+
+fn main() {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).unwrap();
+    std::fs::write(std::env::args().nth(1).unwrap(), input.to_uppercase()).unwrap();
+}
+"#,
+    )?;
+
+    let out = env.path("out.txt");
+    env.bikecase(
+        &[
+            "--pipe",
+            "greet.rs:'big world'",
+            "--",
+            &format!("shout.rs:{}", out.display()),
+        ],
+        "",
+    )
+    .unwrap();
+    assert_eq!(fs::read_to_string(out)?, "HELLO, BIG WORLD\n");
+    Ok(())
+}
+
+#[test]
+fn default_run_among_multiple_bins() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "multi.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "multi"
+//! version = "0.1.0"
+//! edition = "2018"
+//! default-run = "main"
+//!
+//! [[bin]]
+//! name = "helper"
+//!
+//! [[bin]]
+//! name = "main"
+//! path = "src/main.rs"
+//! ```
+
+fn main() {
+    std::fs::write(std::env::args().nth(1).unwrap(), "main").unwrap();
+}
+"#,
+    )?;
+
+    env.bikecase(&["multi.rs", "--", "{script_dir}/out.txt"], "")
+        .unwrap();
+    assert_eq!(env.read("out.txt")?, "main");
+    assert_eq!(env.read("ws/multi/src/bin/helper.rs")?, "fn main() {}\n");
+    Ok(())
+}
+
+#[test]
+fn multiple_bins_without_default_run() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "multi.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "multi"
+//! version = "0.1.0"
+//! edition = "2018"
+//!
+//! [[bin]]
+//! name = "a"
+//!
+//! [[bin]]
+//! name = "b"
+//! ```
+
+fn main() {}
+"#,
+    )?;
+
+    let error = env.bikecase(&["multi.rs"], "").unwrap_err();
+    insta::assert_snapshot!(
+        error,
+        @"could not determine which `[[bin]]` to save the script as: [a, b]. Set `package.default-run` or use `--bin`"
+    );
+    Ok(())
+}
+
+#[test]
+fn rust_version_hint() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "newer/Cargo.toml",
+        r#"[package]
+name = "newer"
+version = "0.1.0"
+edition = "2018"
+rust-version = "1.999"
+"#,
+    )?;
+    env.write("newer/src/lib.rs", "")?;
+    env.write(
+        "msrv.rs",
+        &format!(
+            r#"//! ```cargo
+//! [package]
+//! name = "msrv"
+//! version = "0.1.0"
+//! edition = "2018"
+//!
+//! [dependencies]
+//! newer = {{ path = {:?} }}
+//! ```
+
+fn main() {{}}
+"#,
+            env.path("newer"),
+        ),
+    )?;
+
+    let error = env.bikecase(&["--offline", "msrv.rs"], "").unwrap_err();
+    assert!(error.contains("  - `newer@0.1.0` requires rustc 1.999\n"));
+    assert!(error.ends_with("\nPass `--ignore-rust-version` to build anyway"));
+
+    env.bikecase(&["--offline", "--ignore-rust-version", "msrv.rs"], "")
+        .unwrap();
+    Ok(())
+}
+
+#[test]
+fn json_logs() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bikecase"))
+        .args([
+            "--config",
+            "bikecase.toml",
+            "--json-logs",
+            "--dry-run",
+            "hello.rs",
+        ])
+        .current_dir(env.root())
+        .output()?;
+    assert!(output.status.success());
+
+    let lines = String::from_utf8(output.stderr)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    let written = lines
+        .iter()
+        .find(|l| {
+            l["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("[dry-run] Wrote ")
+        })
+        .unwrap();
+    assert_eq!(written["level"], "INFO");
+    assert_eq!(written["target"], "bikecase::event");
+    assert_eq!(
+        written["fields"]["path"],
+        env.path("ws/Cargo.toml").to_str().unwrap(),
+    );
+    assert_eq!(written["fields"]["dry_run"], "true");
+    Ok(())
+}
+
+#[test]
+fn print_config() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "bikecase.toml",
+        "default-workspace = \"~/ws\"\n\n[github-token]\nkind = \"File\"\npath = \"~/github-token\"\n\n[workspaces.\"~/ws\".gist-ids]\nhello = \"c10e0001\"\n\n[workspaces.\"~/ws\".env]\nDATABASE_URL = \"postgres://secret@localhost/db\"\nAPI_KEY = { kind = \"File\", path = \"~/api-key\" }\n\n[workspaces.\"~/ws\".packages.hello.env]\nTOKEN = \"secret\"\n",
+    )?;
+
+    let outcome = env
+        .bikecase(&["--print-config", "--no-auto-create"], "")
+        .unwrap();
+    insta::assert_snapshot!(env.redact(&outcome.stdout), @r###"
+    # [ROOT]/bikecase.toml
+    auto-create = false
+    check-for-updates = false
+    default-workspace = '[ROOT]/home/ws'
+    derive-package-names = false
+    disable-self-update = false
+
+    [github-token]
+    kind = 'File'
+    path = '[ROOT]/home/github-token'
+    [workspaces."[ROOT]/home/ws".env]
+    DATABASE_URL = '<redacted>'
+
+    [workspaces."[ROOT]/home/ws".env.API_KEY]
+    kind = 'File'
+    path = '~/api-key'
+
+    [workspaces."[ROOT]/home/ws".gist-ids]
+    hello = 'c10e0001'
+    [workspaces."[ROOT]/home/ws".packages.hello.env]
+    TOKEN = '<redacted>'
+    "###);
+    Ok(())
+}
+
+#[test]
+fn build_args() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+
+    let outcome = env
+        .bikecase(
+            &[
+                "--dry-run",
+                "--jobs",
+                "2",
+                "-vv",
+                "--features",
+                "a",
+                "--release",
+                "--features",
+                "b",
+                "--color",
+                "never",
+                "--offline",
+                "hello.rs",
+                "--",
+                "--release",
+            ],
+            "",
+        )
+        .unwrap();
+    let build = outcome
+        .spawned
+        .iter()
+        .find(|args| args[1] == "build")
+        .unwrap();
+    insta::assert_snapshot!(
+        build.join(" "),
+        @"cargo build -p hello --manifest-path [ROOT]/ws/Cargo.toml --color never --message-format json-render-diagnostics --jobs 2 --release --features a --features b -vv --offline"
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn max_memory() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "alloc.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "alloc"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {
+    let xs = vec![1u8; 4 << 30];
+    println!("{}", xs[xs.len() - 1]);
+}
+"#,
+    )?;
+
+    let error = env
+        .bikecase(&["--max-memory", "256M", "alloc.rs"], "")
+        .unwrap_err();
+    insta::assert_snapshot!(
+        error,
+        @"resource limit exceeded: the program hit the memory limit (signal: 6 (SIGABRT))"
+    );
+
+    let error = env
+        .bikecase(&["--max-memory", "256X", "alloc.rs"], "")
+        .unwrap_err();
+    assert!(error.to_string().contains("expected a size"));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn timeout_reads_stdin_in_foreground_group() -> anyhow::Result<()> {
+    /// The process group ID in `/proc/<pid>/stat`.
+    fn pgrp(stat: &str) -> &str {
+        stat.rsplit(')')
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .nth(2)
+            .unwrap()
+    }
+
+    let env = Env::new()?;
+    env.write(
+        "echo.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "echo"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+use std::io;
+
+fn main() {
+    let mut line = "".to_owned();
+    io::stdin().read_line(&mut line).unwrap();
+    let stat = std::fs::read_to_string("/proc/self/stat").unwrap();
+    print!("{}{}", line, stat);
+    if line == "sleep\n" {
+        std::thread::sleep(std::time::Duration::from_secs(60));
+    }
+}
+"#,
+    )?;
+    env.write("hello.txt", "hello\n")?;
+    env.write("sleep.txt", "sleep\n")?;
+
+    env.bikecase(
+        &[
+            "--timeout",
+            "60s",
+            "--stdin-data",
+            "hello.txt",
+            "--capture=out.log",
+            "echo.rs",
+        ],
+        "",
+    )
+    .unwrap();
+    let out = env.read("out.log")?;
+    let (line, stat) = out.split_at("hello\n".len());
+    assert_eq!(line, "hello\n");
+    // Not moved to a process group of its own, which would stop it on reading from a terminal.
+    assert_eq!(pgrp(stat), pgrp(&fs::read_to_string("/proc/self/stat")?));
+
+    let start = Instant::now();
+    let error = env
+        .bikecase(
+            &["--timeout", "1s", "--stdin-data", "sleep.txt", "echo.rs"],
+            "",
+        )
+        .unwrap_err();
+    assert_eq!(error, "timed out after 1s");
+    assert!(start.elapsed() < Duration::from_secs(30));
+    Ok(())
+}
+
+#[test]
+fn check_mode() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "fails.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "fails"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {
+    panic!("should not be run");
+}
+"#,
+    )?;
+
+    let outcome = env
+        .bikecase(&["--mode", "check", "--color", "never", "fails.rs"], "")
+        .unwrap();
+    assert_eq!(outcome.stdout, "");
+    let check = outcome
+        .spawned
+        .iter()
+        .find(|args| args[1] == "check")
+        .unwrap();
+    insta::assert_snapshot!(
+        check.join(" "),
+        @"cargo check -p fails --manifest-path [ROOT]/ws/Cargo.toml --color never --message-format human"
+    );
+
+    let outcome = env
+        .bikecase(&["--check", "--color", "never", "fails.rs"], "")
+        .unwrap();
+    assert_eq!(outcome.stdout, "");
+    assert!(outcome.spawned.iter().any(|args| args[1] == "check"));
+
+    env.write(
+        "fails.rs",
+        &env.read("fails.rs")?
+            .replace("panic!(\"should not be run\");", "let _: u32 = \"\";"),
+    )?;
+    let error = env
+        .bikecase(&["--check", "--color", "never", "fails.rs"], "")
+        .unwrap_err();
+    assert!(error.starts_with("`cargo check` failed"));
+    Ok(())
+}
+
+#[test]
+fn capture() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "noisy.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "noisy"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {
+    println!("to stdout");
+    eprintln!("to stderr");
+}
+"#,
+    )?;
+
+    env.bikecase(&["--capture=out.log", "noisy.rs"], "")
+        .unwrap();
+    assert_eq!(env.read("out.log")?, "to stdout\n");
+
+    env.bikecase(&["--capture=out.log", "--capture-stderr", "noisy.rs"], "")
+        .unwrap();
+    let captured = env.read("out.log")?;
+    assert!(captured.contains("to stdout\n") && captured.contains("to stderr\n"));
+
+    let outcome = env.bikecase(&["--capture", "noisy.rs"], "").unwrap();
+    let captures = fs::read_dir(env.path("ws/noisy/captures"))?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(captures.len(), 1);
+    assert_eq!(fs::read_to_string(captures[0].path())?, "to stdout\n");
+    assert!(outcome
+        .events()
+        .contains(&env.redact(&captures[0].path().display().to_string())));
+    Ok(())
+}
+
+#[test]
+fn examples() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    env.bikecase(&["hello.rs"], "").unwrap();
+    env.write(
+        "ws/hello/examples/demo.rs",
+        "fn main() {\n    println!(\"Demo!\");\n}\n",
+    )?;
+
+    let outcome = env.bikecase(&["--list-targets", "hello.rs"], "").unwrap();
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    bin hello
+    example demo
+    "###);
+
+    let outcome = env
+        .bikecase(&["--example", "demo", "--capture=out.log", "hello.rs"], "")
+        .unwrap();
+    assert!(outcome
+        .spawned
+        .iter()
+        .any(|args| args.contains(&"--example".to_owned())));
+    assert_eq!(env.read("out.log")?, "Demo!\n");
+    Ok(())
+}
+
+#[test]
+fn miri() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+
+    let outcome = env
+        .bikecase(
+            &[
+                "--dry-run",
+                "--miri",
+                "--miri-flags",
+                "-Zmiri-disable-isolation",
+                "hello.rs",
+                "--",
+                "a",
+            ],
+            "",
+        )
+        .unwrap();
+    assert!(outcome
+        .spawned
+        .iter()
+        .any(|args| args.join(" ") == "rustup component add miri --toolchain nightly"));
+    let miri = outcome
+        .spawned
+        .iter()
+        .find(|args| args[1] == "+nightly")
+        .unwrap();
+    insta::assert_snapshot!(
+        miri.join(" "),
+        @"cargo +nightly miri run -p hello --manifest-path [ROOT]/ws/Cargo.toml --color auto --message-format human -- a"
+    );
+    Ok(())
+}
+
+#[test]
+fn skip_build() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+
+    let error = env.bikecase(&["-S", "hello.rs"], "").unwrap_err();
+    insta::assert_snapshot!(error, @"`hello` has not been built with these options yet. Run without `--skip-build` first");
+
+    env.bikecase(&["hello.rs"], "").unwrap();
+    env.write("hello.rs", &HELLO_RS.replace("Hello!", "Changed!"))?;
+    let outcome = env
+        .bikecase(&["--skip-build", "--capture=out.log", "hello.rs"], "")
+        .unwrap();
+    assert!(!outcome
+        .spawned
+        .iter()
+        .any(|args| args.contains(&"build".to_owned())));
+    assert_eq!(env.read("out.log")?, "Hello!\n");
+
+    let error = env
+        .bikecase(&["-S", "--release", "hello.rs"], "")
+        .unwrap_err();
+    insta::assert_snapshot!(error, @"`hello` has not been built with these options yet. Run without `--skip-build` first");
+    Ok(())
+}
+
+#[test]
+fn runs_cached_executable() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    let builds = |outcome: &common::Outcome| {
+        outcome
+            .spawned
+            .iter()
+            .filter(|args| args.contains(&"build".to_owned()))
+            .count()
+    };
+
+    let outcome = env.bikecase(&["hello.rs"], "").unwrap();
+    assert_eq!(builds(&outcome), 1);
+
+    let outcome = env
+        .bikecase(&["--capture=out.log", "hello.rs"], "")
+        .unwrap();
+    assert_eq!(builds(&outcome), 0);
+    assert_eq!(env.read("out.log")?, "Hello!\n");
+
+    let outcome = env.bikecase(&["--release", "hello.rs"], "").unwrap();
+    assert_eq!(builds(&outcome), 1);
+
+    env.write("ws/hello/src/unused.rs", "")?;
+    let outcome = env.bikecase(&["hello.rs"], "").unwrap();
+    assert_eq!(builds(&outcome), 1);
+
+    // The workspace manifest (e.g. `[profile]`) and the path dependencies are inputs too.
+    let workspace_manifest = env.read("ws/Cargo.toml")?;
+    env.write(
+        "ws/Cargo.toml",
+        &format!("{}\n[profile.dev]\nopt-level = 1\n", workspace_manifest),
+    )?;
+    let outcome = env.bikecase(&["hello.rs"], "").unwrap();
+    assert_eq!(builds(&outcome), 1);
+    let outcome = env.bikecase(&["hello.rs"], "").unwrap();
+    assert_eq!(builds(&outcome), 0);
+
+    env.write(
+        "dep/Cargo.toml",
+        "[package]\nname = \"dep\"\nversion = \"0.0.0\"\nedition = \"2018\"\n",
+    )?;
+    env.write("dep/src/lib.rs", "")?;
+    env.write(
+        "hello.rs",
+        &HELLO_RS.replace(
+            "//! ```\n",
+            &format!(
+                "//!\n//! [dependencies]\n//! dep = {{ path = {:?} }}\n//! ```\n",
+                env.path("dep"),
+            ),
+        ),
+    )?;
+    let outcome = env.bikecase(&["hello.rs"], "").unwrap();
+    assert_eq!(builds(&outcome), 1);
+    env.write("dep/src/lib.rs", "pub fn f() {}\n")?;
+    let outcome = env.bikecase(&["hello.rs"], "").unwrap();
+    assert_eq!(builds(&outcome), 1);
+    Ok(())
+}
+
+#[test]
+fn sets_cargo_run_env() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "env.rs",
+        r#"//! ```cargo
+//! [package]
+//! name = "env"
+//! version = "1.2.3-alpha.1"
+//! authors = ["a", "b"]
+//! edition = "2018"
+//! description = "Prints the environment"
+//! ```
+
+fn main() {
+    for name in &[
+        "CARGO_BIN_NAME",
+        "CARGO_PKG_NAME",
+        "CARGO_PKG_VERSION",
+        "CARGO_PKG_VERSION_MAJOR",
+        "CARGO_PKG_VERSION_MINOR",
+        "CARGO_PKG_VERSION_PATCH",
+        "CARGO_PKG_VERSION_PRE",
+        "CARGO_PKG_AUTHORS",
+        "CARGO_PKG_DESCRIPTION",
+        "CARGO_PKG_HOMEPAGE",
+        "CARGO_MANIFEST_DIR",
+    ] {
+        println!("{}={}", name, std::env::var(name).unwrap());
+    }
+    let var = if cfg!(windows) {
+        "PATH"
+    } else if cfg!(target_os = "macos") {
+        "DYLD_FALLBACK_LIBRARY_PATH"
+    } else {
+        "LD_LIBRARY_PATH"
+    };
+    let deps = std::env::current_exe().unwrap().parent().unwrap().join("deps");
+    let paths = std::env::var_os(var).unwrap();
+    assert!(std::env::split_paths(&paths).any(|p| p == deps));
+    assert!(std::env::var_os("CARGO").is_some());
+}
+"#,
+    )?;
+
+    env.bikecase(&["--capture=out.log", "env.rs"], "").unwrap();
+    let out = env
+        .read("out.log")?
+        .replace(&*env.root().to_string_lossy(), "[ROOT]");
+    insta::assert_snapshot!(out, @r###"
+    CARGO_BIN_NAME=env
+    CARGO_PKG_NAME=env
+    CARGO_PKG_VERSION=1.2.3-alpha.1
+    CARGO_PKG_VERSION_MAJOR=1
+    CARGO_PKG_VERSION_MINOR=2
+    CARGO_PKG_VERSION_PATCH=3
+    CARGO_PKG_VERSION_PRE=alpha.1
+    CARGO_PKG_AUTHORS=a:b
+    CARGO_PKG_DESCRIPTION=Prints the environment
+    CARGO_PKG_HOMEPAGE=
+    CARGO_MANIFEST_DIR=[ROOT]/ws/env
+    "###);
+    Ok(())
+}
+
+#[test]
+fn arg_file_skips_empty_lines() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "args.rs",
+        &format!(
+            r#"//! ```cargo
+//! [package]
+//! name = "args"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {{
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    std::fs::write({:?}, format!("{{:?}}", args)).unwrap();
+}}
+"#,
+            env.path("args.txt"),
+        ),
+    )?;
+    env.write("arg-file.txt", "\n--name\n\na b\r\n\n")?;
+
+    env.bikecase(&["--arg-file", "arg-file.txt", "args.rs", "--", "last"], "")
+        .unwrap();
+    assert_eq!(env.read("args.txt")?, r#"["--name", "a b", "last"]"#);
+    Ok(())
+}
+
+#[test]
+fn record_omits_args() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "args.rs",
+        &format!(
+            r#"//! ```cargo
+//! [package]
+//! name = "args"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {{
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    std::fs::write({:?}, args.join(" ")).unwrap();
+}}
+"#,
+            env.path("args.txt"),
+        ),
+    )?;
+    let bundle = env.path("bundle.tar.zst");
+    let bundle = bundle.to_str().unwrap();
+
+    env.bikecase(&["--record", bundle, "args.rs", "--", "secret"], "")
+        .unwrap();
+    assert_eq!(env.read("args.txt")?, "secret");
+    let outcome = env.cargo_bikecase(&["replay", bundle]).unwrap();
+    assert_eq!(env.read("args.txt")?, "");
+    assert!(outcome.events().contains("were not recorded"));
+    env.cargo_bikecase(&["replay", bundle, "--", "given"])
+        .unwrap();
+    assert_eq!(env.read("args.txt")?, "given");
+
+    env.bikecase(
+        &[
+            "--record",
+            bundle,
+            "--record-args",
+            "args.rs",
+            "--",
+            "secret",
+        ],
+        "",
+    )
+    .unwrap();
+    env.cargo_bikecase(&["replay", bundle]).unwrap();
+    assert_eq!(env.read("args.txt")?, "secret");
+    Ok(())
+}
+
+#[test]
+fn install() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    let config = fs::read_to_string(env.path("bikecase.toml"))?;
+    env.write(
+        "bikecase.toml",
+        &format!("install-dir = '{}'\n{}", env.path("bin").display(), config),
+    )?;
+
+    let outcome = env.bikecase(&["--install", "hello.rs"], "").unwrap();
+    assert!(outcome
+        .spawned
+        .iter()
+        .any(|args| args.contains(&"build".to_owned()) && args.contains(&"--release".to_owned())));
+    // The earlier events include the run state, whose name depends on the temporary directory.
+    let events = outcome.events();
+    let events = events.lines().collect::<Vec<_>>();
+    insta::assert_snapshot!(events[events.len() - 2..].join("\n"), @r###"
+    copy [ROOT]/ws/target/release/hello [ROOT]/bin/hello
+    INFO Installed hello as [ROOT]/bin/hello
+    "###);
+    let installed = env
+        .path("bin")
+        .join(format!("hello{}", std::env::consts::EXE_SUFFIX));
+    let output = Command::new(installed).output()?;
+    assert_eq!(output.stdout, b"Hello!\n");
+    Ok(())
+}
+
+#[test]
+fn watch() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+
+    let (script, log) = (env.path("hello.rs"), env.path("out.log"));
+    let editor = thread::spawn(move || {
+        let wait_for = |expected: &str| {
+            let start = Instant::now();
+            while fs::read_to_string(&log).ok().as_deref() != Some(expected) {
+                if start.elapsed() > Duration::from_secs(60) {
+                    return false;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            true
+        };
+        let first = wait_for("Hello!\n");
+        fs::write(&script, HELLO_RS.replace("Hello!", "Changed!")).unwrap();
+        let second = first && wait_for("Changed!\n");
+        // Stops the watcher.
+        fs::remove_file(&script).unwrap();
+        (first, second)
+    });
+
+    let outcome = env
+        .bikecase(&["--watch", "--capture=out.log", "hello.rs"], "")
+        .unwrap();
+    assert_eq!(editor.join().unwrap(), (true, true));
+    assert_eq!(
+        outcome
+            .spawned
+            .iter()
+            .filter(|args| args.contains(&"build".to_owned()))
+            .count(),
+        2,
+    );
+    assert_eq!(
+        env.read("ws/hello/src/main.rs")?,
+        HELLO_RS.replace("Hello!", "Changed!")
+    );
+    Ok(())
+}
+
+#[test]
+fn watch_restarts_running_program() -> anyhow::Result<()> {
+    static SERVER_RS: &str = r#"//! ```cargo
+//! [package]
+//! name = "server"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+fn main() {
+    println!("Hello!");
+    std::thread::sleep(std::time::Duration::from_secs(600));
+}
+"#;
+
+    let env = Env::new()?;
+    env.write("server.rs", SERVER_RS)?;
+
+    let (script, log) = (env.path("server.rs"), env.path("out.log"));
+    let extra = env.path("ws/server/src/extra.rs");
+    let start = Instant::now();
+    let editor = thread::spawn(move || {
+        let wait_for = |expected: &str| {
+            let start = Instant::now();
+            while fs::read_to_string(&log).ok().as_deref() != Some(expected) {
+                if start.elapsed() > Duration::from_secs(60) {
+                    return false;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            true
+        };
+        let first = wait_for("Hello!\n");
+        fs::write(&script, SERVER_RS.replace("Hello!", "Changed!")).unwrap();
+        let second = first && wait_for("Changed!\n");
+        // Changes in the package restart the program as well.
+        fs::remove_file(&log).unwrap();
+        fs::write(&extra, "").unwrap();
+        let third = second && wait_for("Changed!\n");
+        fs::remove_file(&script).unwrap();
+        (first, second, third)
+    });
+
+    let outcome = env
+        .bikecase(&["--watch", "--capture=out.log", "server.rs"], "")
+        .unwrap();
+    assert_eq!(editor.join().unwrap(), (true, true, true));
+    assert!(start.elapsed() < Duration::from_secs(300));
+    assert_eq!(
+        outcome
+            .spawned
+            .iter()
+            .filter(|args| args.contains(&"build".to_owned()))
+            .count(),
+        3,
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn restart_on_change() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    // `sh` takes over the process so that `SIGTERM` can be trapped without dependencies.
+    let server_rs = format!(
+        r#"//! ```cargo
+//! [package]
+//! name = "server"
+//! version = "0.1.0"
+//! edition = "2018"
+//! ```
+
+use std::os::unix::process::CommandExt as _;
+use std::process::Command;
+
+fn main() {{
+    let script = "trap 'echo Terminated >> {}; exit 0' TERM; echo Hello!; while :; do sleep 0.1; done";
+    Command::new("sh").args(&["-c", script]).exec();
+}}
+"#,
+        env.path("terminated.log").display(),
+    );
+    env.write("server.rs", &server_rs)?;
+
+    let (script, log) = (env.path("server.rs"), env.path("out.log"));
+    let terminated = env.path("terminated.log");
+    let editor = thread::spawn(move || {
+        let wait_for = |path: &std::path::Path, expected: &str| {
+            let start = Instant::now();
+            while fs::read_to_string(path).ok().as_deref() != Some(expected) {
+                if start.elapsed() > Duration::from_secs(60) {
+                    return false;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            true
+        };
+        let first = wait_for(&log, "Hello!\n");
+        fs::write(&script, server_rs.replace("Hello!", "Changed!")).unwrap();
+        let second = first && wait_for(&log, "Changed!\n");
+        let third = second && wait_for(&terminated, "Terminated\n");
+        fs::remove_file(&script).unwrap();
+        (first, second, third)
+    });
+
+    env.bikecase(
+        &[
+            "--restart-on-change",
+            "--grace-period",
+            "30s",
+            "--capture=out.log",
+            "server.rs",
+        ],
+        "",
+    )
+    .unwrap();
+    assert_eq!(editor.join().unwrap(), (true, true, true));
+    assert_eq!(env.read("terminated.log")?, "Terminated\nTerminated\n");
+    Ok(())
+}
+
+#[test]
+fn test() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "hello.rs",
+        &format!(
+            "{}\n#[cfg(test)]\nmod tests {{\n    #[test]\n    fn passes() {{}}\n\n    \
+             #[test]\n    fn fails() {{\n        panic!();\n    }}\n}}\n",
+            HELLO_RS,
+        ),
+    )?;
+
+    let outcome = env
+        .bikecase(&["--dry-run", "--test", "hello.rs", "--", "passes"], "")
+        .unwrap();
+    insta::assert_snapshot!(
+        outcome.spawned.last().unwrap()[1..].join(" "),
+        @"test -p hello --manifest-path [ROOT]/ws/Cargo.toml --color auto --message-format human -- passes"
+    );
+
+    env.bikecase(
+        &["--test", "--capture=out.log", "hello.rs", "--", "passes"],
+        "",
+    )
+    .unwrap();
+    let log = env.read("out.log")?;
+    assert!(log.contains("test tests::passes ... ok"));
+    assert!(!log.contains("tests::fails"));
+
+    env.bikecase(
+        &[
+            "--test",
+            "--capture=out.log",
+            "--capture-stderr",
+            "hello.rs",
+        ],
+        "",
+    )
+    .unwrap_err();
+    assert!(env
+        .read("out.log")?
+        .contains("test tests::fails ... FAILED"));
+    Ok(())
+}
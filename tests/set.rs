@@ -0,0 +1,70 @@
+mod common;
+
+use crate::common::{Env, HELLO_RS};
+
+#[test]
+fn set_and_clear() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+
+    env.cargo_bikecase_with_config(&[
+        "set",
+        "-p",
+        "hello",
+        "--release",
+        "--features",
+        "a",
+        "b",
+        "--rustflags",
+        "-C target-cpu=native",
+    ])
+    .unwrap();
+    insta::assert_snapshot!("set_and_clear_config", env.read("bikecase.toml")?);
+
+    let outcome = env.bikecase(&["--dry-run", "hello.rs"], "").unwrap();
+    assert!(outcome
+        .events()
+        .contains("INFO Setting `RUSTFLAGS` for `cargo build`"));
+
+    env.cargo_bikecase_with_config(&["set", "-p", "hello", "--clear", "--features", "c"])
+        .unwrap();
+    insta::assert_snapshot!("set_and_clear_cleared", env.read("bikecase.toml")?);
+    Ok(())
+}
+
+#[test]
+fn member_manifest_path() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+
+    env.cargo_bikecase_with_config(&["set", "--manifest-path", "hello/Cargo.toml", "--release"])
+        .unwrap();
+
+    let outcome = env
+        .bikecase(
+            &[
+                "--manifest-path",
+                "ws/hello/Cargo.toml",
+                "--dry-run",
+                "hello.rs",
+            ],
+            "",
+        )
+        .unwrap();
+    let build = outcome
+        .spawned
+        .iter()
+        .find(|args| args[1] == "build")
+        .unwrap();
+    assert!(build.windows(2).any(|w| w == ["--profile", "release"]));
+    assert!(build
+        .windows(2)
+        .any(|w| w == ["--manifest-path", "[ROOT]/ws/Cargo.toml"]));
+    Ok(())
+}
@@ -0,0 +1,103 @@
+#![cfg(unix)]
+
+mod common;
+
+use crate::common::{Env, HELLO_RS};
+
+use std::fs;
+
+/// Reports of `cargo bloat --message-format json` without and with `--crates`.
+static FUNCTIONS: &str = r#"{"file-size":409600,"text-section-size":204800,"functions":[{"crate":"std","name":"std::rt::lang_start_internal","size":10240},{"name":"main","size":512}]}"#;
+static CRATES: &str = r#"{"file-size":409600,"text-section-size":204800,"crates":[{"name":"std","size":153600},{"name":"hello","size":1024}]}"#;
+
+#[test]
+fn compares_with_stripped() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write("hello.rs", HELLO_RS)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+
+    let outcome = env
+        .cargo_bikecase(&[
+            "size",
+            "-p",
+            "hello",
+            "--offline",
+            "--color",
+            "never",
+            "--strip",
+        ])
+        .unwrap();
+    let lines = outcome.stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "BINARY\tSIZE\tSTRIPPED");
+    let row = lines[1].split('\t').collect::<Vec<_>>();
+    assert_eq!(row[0], "target/release/hello");
+    assert!(row[2].contains("(-"), "{:?}", row);
+    assert_eq!(lines.len(), 2);
+
+    // The binary of `release` is not overwritten with the stripped one.
+    let size = |rel: &str| fs::metadata(env.path(rel)).map(|m| m.len());
+    assert!(size("ws/target/bikecase-stripped/hello")? < size("ws/target/release/hello")?);
+    Ok(())
+}
+
+#[test]
+fn bloat() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.fake_tool(
+        "cargo-bloat",
+        &format!(
+            "#!/bin/sh\ncase \" $* \" in\n  *' --crates '*) echo '{}' ;;\n  *) echo '{}' ;;\nesac\n",
+            CRATES, FUNCTIONS,
+        ),
+    )?;
+    env.write("hello.rs", HELLO_RS)?;
+    let hello = env.path("hello.rs");
+    env.cargo_bikecase(&["import", hello.to_str().unwrap()])
+        .unwrap();
+
+    let outcome = env
+        .cargo_bikecase(&[
+            "size",
+            "-p",
+            "hello",
+            "--offline",
+            "--color",
+            "never",
+            "--bloat",
+            "-n",
+            "2",
+        ])
+        .unwrap();
+    let (_, bloat) = outcome.stdout.split_once("\n\n").unwrap();
+    insta::assert_snapshot!(bloat, @r###"
+    FILE	.TEXT	SIZE	CRATE	NAME
+    2.5%	5.0%	10.0KiB	std	std::rt::lang_start_internal
+    0.1%	0.2%	512B	?	main
+    "###);
+    insta::assert_snapshot!(
+        outcome.spawned.last().unwrap().join(" "),
+        @"cargo bloat -p hello --manifest-path [ROOT]/ws/Cargo.toml --release -n 2 --message-format json"
+    );
+
+    let outcome = env
+        .cargo_bikecase(&[
+            "size",
+            "-p",
+            "hello",
+            "--offline",
+            "--color",
+            "never",
+            "--bloat",
+            "--crates",
+            "--no-header",
+        ])
+        .unwrap();
+    let (_, bloat) = outcome.stdout.split_once("\n\n").unwrap();
+    insta::assert_snapshot!(bloat, @r###"
+    37.5%	75.0%	150.0KiB	std
+    0.2%	0.5%	1.0KiB	hello
+    "###);
+    Ok(())
+}
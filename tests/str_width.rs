@@ -0,0 +1,42 @@
+use bikecase::str_width;
+
+#[test]
+fn ascii() {
+    assert_eq!(str_width(""), 0);
+    assert_eq!(str_width("Cargo.toml"), 10);
+}
+
+#[test]
+fn cjk() {
+    assert_eq!(str_width("日本語"), 6);
+    assert_eq!(str_width("한국어"), 6);
+    assert_eq!(str_width("a日b"), 4);
+}
+
+#[test]
+fn combining_characters() {
+    assert_eq!(str_width("e\u{301}"), 1);
+    assert_eq!(str_width("か\u{3099}"), 2);
+}
+
+#[test]
+fn emoji() {
+    assert_eq!(str_width("🦀"), 2);
+    assert_eq!(str_width("👩\u{200d}💻"), 2);
+    assert_eq!(str_width("👨\u{200d}👩\u{200d}👧\u{200d}👦"), 2);
+    assert_eq!(str_width("❤\u{fe0f}"), 2);
+    assert_eq!(str_width("🇯🇵"), 2);
+    assert_eq!(str_width("// 🦀 は蟹"), 10);
+}
+
+#[test]
+fn box_drawing() {
+    assert_eq!(str_width("─"), 1);
+    assert_eq!(str_width("┌"), 1);
+}
+
+#[test]
+fn zero_width() {
+    assert_eq!(str_width("\u{200b}"), 0);
+    assert_eq!(str_width("\u{200d}"), 0);
+}
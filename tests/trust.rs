@@ -0,0 +1,70 @@
+mod common;
+
+use crate::common::{Env, MockGithub};
+
+static REMOTE_RS: &str = r#"//! ```cargo
+//! [package]
+//! name = "remote"
+//! version = "0.1.0"
+//! edition = "2018"
+//!
+//! [dependencies]
+//! itoa = "0.4.5"
+//! ```
+
+fn main() {}
+"#;
+
+#[test]
+fn trust() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    let mock = MockGithub::get();
+    mock.raw("/raw/7a570001/remote.rs", REMOTE_RS, None);
+    let url = mock.url("/raw/7a570001/remote.rs");
+    let not_trusted = format!(
+        "{} has not been trusted. Review it and run with `--trust`",
+        url,
+    );
+
+    let error = env
+        .bikecase(&["--no-input", "--list-targets", &url], "")
+        .unwrap_err();
+    assert_eq!(error, not_trusted);
+
+    // No TTY in tests.
+    let outcome = env.bikecase(&["--list-targets", &url], "");
+    assert!(outcome.events().contains("INFO   itoa 0.4.5"));
+    assert!(outcome.unwrap_err().starts_with(&not_trusted));
+
+    let outcome = env
+        .bikecase(&["--trust", "--list-targets", &url], "")
+        .unwrap();
+    assert_eq!(outcome.stdout, "bin remote\n");
+    env.bikecase(&["--no-input", "--list-targets", &url], "")
+        .unwrap();
+
+    let outcome = env
+        .cargo_bikecase(&["trust", "list", "--no-header"])
+        .unwrap();
+    assert!(outcome.stdout.starts_with(&format!("{}\t", url)));
+
+    // A changed script is not trusted.
+    mock.raw(
+        "/raw/7a570001/remote.rs",
+        &REMOTE_RS.replace("fn main() {}", "fn main() {\n    panic!();\n}"),
+        None,
+    );
+    let error = env
+        .bikecase(&["--no-input", "--list-targets", &url], "")
+        .unwrap_err();
+    assert_eq!(error, not_trusted);
+
+    env.cargo_bikecase(&["trust", "revoke", &url]).unwrap();
+    let error = env.cargo_bikecase(&["trust", "revoke", &url]).unwrap_err();
+    assert_eq!(error, format!("{} is not trusted", url));
+    let outcome = env
+        .cargo_bikecase(&["trust", "list", "--no-header"])
+        .unwrap();
+    assert_eq!(outcome.stdout, "");
+    Ok(())
+}
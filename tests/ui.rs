@@ -0,0 +1,108 @@
+mod common;
+
+use crate::common::Env;
+
+#[test]
+fn invalid_color() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.cargo_bikecase_with_config(&["ls"]).unwrap();
+    let config = env
+        .read("bikecase.toml")?
+        .replace("[ROOT]", &env.root().to_string_lossy());
+    env.write(
+        "bikecase.toml",
+        &format!("{}\n[ui.colors]\nadded = \"chartreuse\"\n", config),
+    )?;
+
+    let outcome = env.cargo_bikecase_with_config(&["ls", "--ascii"]);
+    insta::assert_snapshot!(env.redact(&outcome.unwrap_err()), @r###"invalid config: [ROOT]/bikecase.toml: `ui.colors.added`: invalid color "chartreuse": unrecognized color name 'chartreuse'. Choose from: black, blue, green, red, cyan, magenta, yellow, white"###);
+    Ok(())
+}
+
+#[test]
+fn keeps_ui_config() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.cargo_bikecase_with_config(&["ls"]).unwrap();
+    let config = env
+        .read("bikecase.toml")?
+        .replace("[ROOT]", &env.root().to_string_lossy());
+    env.write(
+        "bikecase.toml",
+        &format!(
+            "{}\n[ui]\nascii = true\n\n[ui.colors]\nadded = \"blue\"\naccent = \"208\"\n",
+            config,
+        ),
+    )?;
+    env.write(
+        "ws/tool/Cargo.toml",
+        "[package]\nname = \"tool\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+    )?;
+    env.write("ws/tool/src/main.rs", "fn main() {}\n")?;
+
+    env.cargo_bikecase(&["include", "tool"]).unwrap();
+    env.cargo_bikecase_with_config(&["set", "-p", "tool", "--release", "--ascii"])
+        .unwrap();
+    insta::assert_snapshot!(env.read("bikecase.toml")?, @r###"
+    default-workspace = '[ROOT]/ws'
+    template-package = '[ROOT]/template'
+    derive-package-names = false
+    check-for-updates = false
+    disable-self-update = false
+    auto-create = true
+
+    [ui]
+    ascii = true
+
+    [ui.colors]
+    added = 'blue'
+    accent = '208'
+    [workspaces."[ROOT]/ws".gist-ids]
+    [workspaces."[ROOT]/ws".packages.tool]
+    profile = 'release'
+    "###);
+    Ok(())
+}
+
+#[test]
+fn sort() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/Cargo.toml",
+        "[workspace]\nmembers = [\"b\", \"A\", \"c\"]\nexclude = []\n",
+    )?;
+    for (name, code) in &[
+        ("b", "fn main() {}\n"),
+        ("A", "fn main() {\n    println!(\"Hello, world!\");\n}\n"),
+        (
+            "c",
+            "fn main() {\n    let _ = std::io::stdin().read_line(&mut String::new());\n}\n",
+        ),
+    ] {
+        env.write(
+            &format!("ws/{}/Cargo.toml", name),
+            &format!(
+                "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2018\"\n",
+                name,
+            ),
+        )?;
+        env.write(&format!("ws/{}/src/main.rs", name), code)?;
+    }
+
+    let outcome = env
+        .cargo_bikecase(&["ls", "--no-header", "--ascii"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    A	0.0.0	A	-
+    b	0.0.0	b	-
+    c	0.0.0	c	-
+    "###);
+    let outcome = env
+        .cargo_bikecase(&["ls", "--no-header", "--ascii", "--sort", "size"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    c	0.0.0	c	-
+    A	0.0.0	A	-
+    b	0.0.0	b	-
+    "###);
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! `User-Agent` is set for the whole process, so this is the only test in the binary.
+
+mod common;
+
+use crate::common::{Env, MockGithub};
+
+#[test]
+fn user_agent_suffix() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "bikecase.toml",
+        &format!(
+            "default-workspace = {:?}\ntemplate-package = {:?}\nuser-agent-suffix = \"corp-proxy/1.0\"\n\n[workspaces]\n",
+            env.path("ws"),
+            env.path("template"),
+        ),
+    )?;
+    MockGithub::get().gist(
+        "a9e00001",
+        "",
+        &[("hello.rs", "//! ```cargo\n//! [package]\n//! name = \"hello\"\n//! version = \"0.1.0\"\n//! edition = \"2018\"\n//! ```\n\nfn main() {}\n")],
+    );
+
+    env.cargo_bikecase_with_config(&["gist", "clone", "a9e00001"])
+        .unwrap();
+    insta::assert_snapshot!(
+        MockGithub::get().user_agent("/gists/a9e00001").unwrap(),
+        @"bikecase <https://github.com/qryxip/bikecase> corp-proxy/1.0"
+    );
+    Ok(())
+}
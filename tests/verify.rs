@@ -0,0 +1,42 @@
+mod common;
+
+use crate::common::Env;
+
+#[test]
+fn members() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    for (name, main_rs) in &[
+        ("good", "fn main() {}\n"),
+        ("bad", "fn main() {\n    let _: u32 = \"\";\n}\n"),
+    ] {
+        env.write(
+            &format!("ws/{}/Cargo.toml", name),
+            &format!(
+                "[package]\nname = {:?}\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+                name,
+            ),
+        )?;
+        env.write(&format!("ws/{}/src/main.rs", name), main_rs)?;
+        env.cargo_bikecase(&["include", name]).unwrap();
+    }
+
+    let outcome = env.cargo_bikecase(&["verify", "--jobs", "2"]);
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    NAME	CHECK
+    bad	FAILED
+    good	ok
+    "###);
+    insta::assert_snapshot!(outcome.unwrap_err(), @"1 of 2 member(s) failed");
+
+    let outcome = env
+        .cargo_bikecase(&["verify", "--filter", "g*", "--test"])
+        .unwrap();
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    NAME	CHECK	TEST
+    good	ok	ok
+    "###);
+
+    let outcome = env.cargo_bikecase(&["verify", "--filter", "x*"]);
+    insta::assert_snapshot!(outcome.unwrap_err(), @"no members match `--filter`");
+    Ok(())
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use crate::common::Env;
+
+#[test]
+fn version() -> anyhow::Result<()> {
+    let env = Env::new()?;
+
+    let outcome = env.cargo_bikecase(&["version"]).unwrap();
+    let keys = outcome
+        .stdout
+        .lines()
+        .map(|l| l.split([':', ' ']).next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            "bikecase",
+            "commit-hash",
+            "build-date",
+            "features",
+            "target",
+            "cargo",
+            "rustc",
+        ],
+    );
+    assert!(outcome
+        .stdout
+        .starts_with(&format!("bikecase {}\n", env!("CARGO_PKG_VERSION"))));
+
+    let outcome = env.cargo_bikecase(&["version", "--json"]).unwrap();
+    let info = serde_json::from_str::<serde_json::Value>(&outcome.stdout)?;
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(
+        info["features"],
+        serde_json::json!(if cfg!(feature = "shims") {
+            vec!["shims"]
+        } else {
+            vec![]
+        }),
+    );
+    assert!(info["cargo"].as_str().unwrap().starts_with("cargo "));
+    assert!(info["build-date"].as_str().unwrap().len() == 10);
+    Ok(())
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use crate::common::Env;
+
+#[test]
+fn points_to_manifest_lines() -> anyhow::Result<()> {
+    let env = Env::new()?;
+    env.write(
+        "ws/Cargo.toml",
+        "[workspace]\nmembers = [\"hello\", \"mid\", \"leaf\"]\nexclude = []\n",
+    )?;
+    env.write(
+        "ws/hello/Cargo.toml",
+        r#"[package]
+name = "hello"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+m = { path = "../mid", package = "mid" }
+
+[dev-dependencies.leaf]
+path = "../leaf"
+"#,
+    )?;
+    env.write("ws/hello/src/main.rs", "fn main() {}\n")?;
+    env.write(
+        "ws/mid/Cargo.toml",
+        "[package]\nname = \"mid\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\n[dependencies]\nleaf = { path = \"../leaf\" }\n",
+    )?;
+    env.write("ws/mid/src/lib.rs", "")?;
+    env.write(
+        "ws/leaf/Cargo.toml",
+        "[package]\nname = \"leaf\"\nversion = \"0.1.0\"\nedition = \"2018\"\n",
+    )?;
+    env.write("ws/leaf/src/lib.rs", "")?;
+
+    let outcome = env
+        .cargo_bikecase(&[
+            "why",
+            "-p",
+            "hello",
+            "--offline",
+            "--color",
+            "never",
+            "leaf",
+        ])
+        .unwrap();
+    insta::assert_snapshot!(outcome.stdout, @r###"
+    leaf v0.1.0 ([ROOT]/ws/leaf)
+    └── mid v0.1.0 ([ROOT]/ws/mid)
+        └── hello v0.1.0 ([ROOT]/ws/hello)
+    [dev-dependencies]
+    └── hello v0.1.0 ([ROOT]/ws/hello)
+
+    `leaf` is pulled in by:
+        [ROOT]/ws/hello/Cargo.toml:7: m = { path = "../mid", package = "mid" }
+        [ROOT]/ws/hello/Cargo.toml:9: [dev-dependencies.leaf]
+    "###);
+    Ok(())
+}